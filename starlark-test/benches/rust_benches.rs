@@ -17,7 +17,45 @@
 #![feature(test)]
 
 extern crate test;
+use starlark::environment::Environment;
+use starlark::values::Value;
 use starlark_test::do_bench;
 use test::Bencher;
 
 include!(concat!(env!("OUT_DIR"), "/benches/rust-benches.rs"));
+
+const BINDINGS: usize = 2000;
+
+/// An environment with `BINDINGS` bindings in a chain of environments, as built by nested
+/// `child` calls, so that a lookup walks up the parents, and the names of its bindings.
+fn chained_environment() -> (Environment, Vec<String>) {
+    let mut env = Environment::new("root");
+    for i in 0..BINDINGS {
+        if i % 100 == 0 {
+            env = env.child(&format!("env{}", i));
+        }
+        env.set(&format!("v{}", i), Value::new(i as i64)).unwrap();
+    }
+    (env, (0..BINDINGS).map(|i| format!("v{}", i)).collect())
+}
+
+#[bench]
+fn bench_environment_lookup(bencher: &mut Bencher) {
+    let (env, names) = chained_environment();
+    bencher.iter(|| {
+        for name in &names {
+            test::black_box(env.get(name).unwrap());
+        }
+    });
+}
+
+#[bench]
+fn bench_precomputed_environment_lookup(bencher: &mut Bencher) {
+    let (env, names) = chained_environment();
+    let frozen = env.precompute_hash_table();
+    bencher.iter(|| {
+        for name in &names {
+            test::black_box(frozen.get(name).unwrap());
+        }
+    });
+}
@@ -21,13 +21,13 @@ extern crate test;
 
 use codemap::CodeMap;
 use codemap_diagnostic::{ColorConfig, Diagnostic, Emitter};
-use linked_hash_map::LinkedHashMap;
 use starlark::environment::TypeValues;
 use starlark::eval::call_stack::CallStack;
 use starlark::eval::simple::eval;
 use starlark::stdlib::global_environment_with_extensions;
 use starlark::syntax::dialect::Dialect;
 use starlark::values::error::ValueError;
+use starlark::values::OrderedMap;
 use std::fs::File;
 use std::io::prelude::*;
 use std::io::{self, Write};
@@ -196,7 +196,7 @@ def assert_(cond, msg="assertion failed"):
             &CallStack::default(),
             TypeValues::new(env),
             Vec::new(),
-            LinkedHashMap::new(),
+            OrderedMap::new(),
             None,
             None,
         ) {
@@ -205,6 +205,11 @@ def assert_(cond, msg="assertion failed"):
                 Emitter::stderr(ColorConfig::Always, Some(&map.lock().unwrap())).emit(&[e]);
                 panic!();
             }
+            Err(ValueError::TracedError(e, traceback)) => {
+                eprintln!("{}", traceback);
+                Emitter::stderr(ColorConfig::Always, Some(&map.lock().unwrap())).emit(&[e]);
+                panic!();
+            }
             Err(e) => {
                 panic!("{:?}", e);
             }
@@ -0,0 +1,689 @@
+// Copyright 2019 The Starlark in Rust Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Read-only snapshots of the bindings of a frozen environment, indexed with a minimal
+//! perfect hash table: [`FrozenEnvironment`] (see [`Environment::precompute_hash_table`]),
+//! and [`SharedEnvironment`] (see [`Environment::share`]) which can be shared between
+//! threads.
+
+use crate::environment::{Environment, EnvironmentError, SetConstructor};
+use crate::values::error::ValueError;
+use crate::values::int::IntegerOverflowMode;
+use crate::values::{FrozenValue, Value};
+use std::cell::RefCell;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::rc::Rc;
+use std::sync::{Arc, Weak};
+use std::thread::LocalKey;
+
+/// Average number of keys per bucket of the first level hash.
+const KEYS_PER_BUCKET: usize = 4;
+
+/// Bindings looked up in constant time.
+///
+/// The table is built with the "hash, displace and compress" scheme: keys are split in
+/// buckets by a first hash, then each bucket is assigned a seed such that the second hash of
+/// its keys, seeded with it, lands on distinct free slots. A lookup is thus two hashes and a
+/// single key comparison, whatever the number of bindings.
+#[derive(Clone)]
+struct HashTable<V> {
+    /// Seed of the second level hash for each bucket.
+    seeds: Vec<u64>,
+    /// One binding per slot, `slots.len()` being the number of bindings.
+    slots: Vec<(String, V)>,
+}
+
+/// The bindings of a frozen [`Environment`] and of its parents, looked up in constant time.
+///
+/// The bindings are indexed with a minimal perfect hash table, so a lookup never walks up
+/// the parents.
+#[derive(Clone)]
+pub struct FrozenEnvironment {
+    name: String,
+    table: HashTable<Value>,
+}
+
+/// The bindings of a frozen [`Environment`] and of its parents, with its type values and
+/// settings, which can be shared between threads.
+///
+/// The bindings are stored as [`FrozenValue`]s, so a `SharedEnvironment` is `Send + Sync`
+/// and cheap to clone: it can be computed once, e.g. from the standard library, and each
+/// thread evaluates scripts in children of its own
+/// [`to_environment`](SharedEnvironment::to_environment) copy.
+///
+/// # Examples
+///
+/// ```
+/// # use codemap::CodeMap;
+/// # use starlark::environment::TypeValues;
+/// # use starlark::eval::noload::eval;
+/// # use starlark::stdlib::global_environment;
+/// # use starlark::syntax::dialect::Dialect;
+/// # use std::sync::{Arc, Mutex};
+/// let globals = global_environment().share().unwrap();
+/// let threads: Vec<_> = (1..4)
+///     .map(|i| {
+///         let globals = globals.clone();
+///         std::thread::spawn(move || {
+///             let globals = globals.to_environment();
+///             let mut env = globals.child("script");
+///             let script = format!("len([x for x in range({})])", i);
+///             let map = Arc::new(Mutex::new(CodeMap::new()));
+///             let result = eval(&map, "script.star", &script, Dialect::Bzl, &mut env,
+///                               TypeValues::new(globals)).unwrap();
+///             result.to_int().unwrap()
+///         })
+///     })
+///     .collect();
+/// let results: Vec<i64> = threads.into_iter().map(|t| t.join().unwrap()).collect();
+/// assert_eq!(vec![1, 2, 3], results);
+/// ```
+#[derive(Clone)]
+pub struct SharedEnvironment(Arc<SharedEnvironmentContent>);
+
+pub(crate) struct SharedEnvironmentContent {
+    name: String,
+    table: HashTable<FrozenValue>,
+    /// Static values of an object per type, e.g. `list.index`.
+    type_objs: HashMap<String, HashMap<String, FrozenValue>>,
+    set_constructor: SetConstructor,
+    integer_overflow_mode: IntegerOverflowMode,
+    catch_native_panics: bool,
+}
+
+/// The module environment of a `def`, captured when the function is shared.
+///
+/// The environment of a module contains the functions defined in the module, which capture
+/// it: while the environment is shared, they only refer to it by the address of its content.
+/// Its bindings are only thawed by [`SharedEnvironment::get`] and
+/// [`SharedEnvironment::to_environment`], which register it in the thaw scope first.
+pub(crate) enum CapturedEnvironment {
+    Owned(SharedEnvironment),
+    Cyclic(usize),
+}
+
+/// An environment registered in the thaw scope.
+#[derive(Clone)]
+enum Thawed {
+    /// An environment from which a binding is thawed, converted if a function it defines is.
+    Borrowed(SharedEnvironment),
+    Converted(Environment),
+}
+
+// Environments shared, by address of the `Environment` content, or thawed, by address of
+// the `SharedEnvironment` content, since the outermost call to `with_share_scope` or
+// `with_thaw_scope`, so that an environment captured by several functions is only copied
+// once.
+thread_local!(static SHARED: RefCell<Option<HashMap<usize, Weak<SharedEnvironmentContent>>>> =
+    const { RefCell::new(None) });
+thread_local!(static THAWED: RefCell<Option<HashMap<usize, Thawed>>> =
+    const { RefCell::new(None) });
+
+/// Clears the environments memoized by the outermost scope, even on panic.
+struct ScopeGuard<V: 'static>(&'static LocalKey<RefCell<Option<HashMap<usize, V>>>>);
+
+impl<V> Drop for ScopeGuard<V> {
+    fn drop(&mut self) {
+        self.0.with(|m| *m.borrow_mut() = None);
+    }
+}
+
+fn with_scope<V, R>(
+    key: &'static LocalKey<RefCell<Option<HashMap<usize, V>>>>,
+    f: impl FnOnce(bool) -> R,
+) -> R {
+    let outermost = key.with(|m| {
+        let mut m = m.borrow_mut();
+        if m.is_none() {
+            *m = Some(HashMap::new());
+            true
+        } else {
+            false
+        }
+    });
+    let _guard = if outermost {
+        Some(ScopeGuard(key))
+    } else {
+        None
+    };
+    f(outermost)
+}
+
+/// Call `f`, which shares values, memoizing the environments it shares.
+pub(crate) fn with_share_scope<R>(f: impl FnOnce() -> R) -> R {
+    with_scope(&SHARED, |_| f())
+}
+
+/// Call `f`, which thaws values, memoizing the environments it thaws. `f` is given whether
+/// this is the outermost call.
+pub(crate) fn with_thaw_scope<R>(f: impl FnOnce(bool) -> R) -> R {
+    with_scope(&THAWED, f)
+}
+
+fn hash(seed: u64, name: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    seed.hash(&mut hasher);
+    name.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn environment_key(env: &Environment) -> usize {
+    Rc::as_ptr(&env.env) as usize
+}
+
+/// `env` and its parents, from the root.
+fn chain(env: &Environment) -> Vec<Environment> {
+    let mut chain = vec![env.clone()];
+    while let Some(parent) = chain.last().unwrap().get_parent() {
+        chain.push(parent);
+    }
+    chain.reverse();
+    chain
+}
+
+/// The bindings of `env` and of its parents.
+fn bindings(env: &Environment) -> HashMap<String, Value> {
+    // Walk the environments from the root so that a child binding replaces the binding
+    // of the same name in a parent, like `Environment::get` does.
+    let mut bindings = HashMap::new();
+    for e in chain(env) {
+        for (name, value) in &e.env.borrow().variables {
+            bindings.insert(name.clone(), value.clone());
+        }
+    }
+    bindings
+}
+
+impl<V> HashTable<V> {
+    /// Compute the seeds of the buckets and the slots of `bindings`.
+    fn new(bindings: Vec<(String, V)>) -> HashTable<V> {
+        let size = bindings.len();
+        let bucket_count = (size + KEYS_PER_BUCKET - 1) / KEYS_PER_BUCKET;
+        let mut buckets: Vec<Vec<usize>> = vec![Vec::new(); bucket_count];
+        for (i, (name, _)) in bindings.iter().enumerate() {
+            buckets[(hash(0, name) % bucket_count as u64) as usize].push(i);
+        }
+        // Place the largest buckets first, while most slots are still free.
+        let mut order: Vec<usize> = (0..bucket_count).collect();
+        order.sort_by_key(|&b| std::cmp::Reverse(buckets[b].len()));
+
+        let mut seeds = vec![0; bucket_count];
+        let mut placement: Vec<Option<usize>> = vec![None; size];
+        let mut candidate = Vec::new();
+        for b in order {
+            if buckets[b].is_empty() {
+                break;
+            }
+            let mut seed = 1;
+            loop {
+                candidate.clear();
+                let fits = buckets[b].iter().all(|&i| {
+                    let slot = (hash(seed, &bindings[i].0) % size as u64) as usize;
+                    let free = placement[slot].is_none() && !candidate.contains(&slot);
+                    candidate.push(slot);
+                    free
+                });
+                if fits {
+                    break;
+                }
+                seed += 1;
+            }
+            for (&slot, &i) in candidate.iter().zip(buckets[b].iter()) {
+                placement[slot] = Some(i);
+            }
+            seeds[b] = seed;
+        }
+
+        let mut bindings: Vec<Option<(String, V)>> = bindings.into_iter().map(Some).collect();
+        let slots = placement
+            .into_iter()
+            .map(|i| bindings[i.unwrap()].take().unwrap())
+            .collect();
+        HashTable { seeds, slots }
+    }
+
+    fn get(&self, name: &str) -> Option<&V> {
+        if self.slots.is_empty() {
+            return None;
+        }
+        let bucket = hash(0, name) % self.seeds.len() as u64;
+        let seed = self.seeds[bucket as usize];
+        let (ref key, ref value) =
+            self.slots[(hash(seed, name) % self.slots.len() as u64) as usize];
+        if key == name {
+            Some(value)
+        } else {
+            None
+        }
+    }
+
+    fn names(&self) -> impl Iterator<Item = &str> {
+        self.slots.iter().map(|(name, _)| name.as_str())
+    }
+}
+
+impl FrozenEnvironment {
+    pub(crate) fn new(env: &Environment) -> FrozenEnvironment {
+        FrozenEnvironment {
+            name: env.name(),
+            table: HashTable::new(bindings(env).into_iter().collect()),
+        }
+    }
+
+    /// Return the name of the environment this table was computed from.
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    /// Get the value of the variable `name`
+    pub fn get(&self, name: &str) -> Result<Value, EnvironmentError> {
+        match self.table.get(name) {
+            Some(value) => Ok(value.clone()),
+            None => Err(EnvironmentError::VariableNotFound(name.to_owned())),
+        }
+    }
+
+    /// Return the number of bindings.
+    pub fn len(&self) -> usize {
+        self.table.slots.len()
+    }
+
+    /// Return `true` if there is no binding.
+    pub fn is_empty(&self) -> bool {
+        self.table.slots.is_empty()
+    }
+
+    /// Iterate over the names of the bindings, in no particular order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.table.names()
+    }
+}
+
+impl fmt::Debug for FrozenEnvironment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FrozenEnvironment")
+            .field("name", &self.name)
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+impl SharedEnvironment {
+    pub(crate) fn new(env: &Environment) -> Result<SharedEnvironment, ValueError> {
+        with_share_scope(|| SharedEnvironmentContent::share(env).map(SharedEnvironment))
+    }
+
+    /// Return the name of the environment this table was computed from.
+    pub fn name(&self) -> String {
+        self.0.name.clone()
+    }
+
+    /// The key of this environment in the thaw scope.
+    fn key(&self) -> usize {
+        Arc::as_ptr(&self.0) as usize
+    }
+
+    /// Get the value of the variable `name`, converted with [`FrozenValue::to_value`].
+    ///
+    /// The value does not depend on this environment once converted, even if it is a
+    /// function defined in it. A thread reading many bindings should rather convert the
+    /// whole environment once with [`to_environment`](SharedEnvironment::to_environment).
+    pub fn get(&self, name: &str) -> Result<Value, EnvironmentError> {
+        let value = match self.0.table.get(name) {
+            Some(value) => value,
+            None => return Err(EnvironmentError::VariableNotFound(name.to_owned())),
+        };
+        Ok(with_thaw_scope(|outermost| {
+            THAWED.with(|m| {
+                m.borrow_mut()
+                    .as_mut()
+                    .unwrap()
+                    .entry(self.key())
+                    .or_insert_with(|| Thawed::Borrowed(self.clone()));
+            });
+            let mut value = value.to_value();
+            // `to_value` only freezes the outermost value it converts.
+            if outermost {
+                value.freeze();
+            }
+            value
+        }))
+    }
+
+    /// Return the number of bindings.
+    pub fn len(&self) -> usize {
+        self.0.table.slots.len()
+    }
+
+    /// Return `true` if there is no binding.
+    pub fn is_empty(&self) -> bool {
+        self.0.table.slots.is_empty()
+    }
+
+    /// Iterate over the names of the bindings, in no particular order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.0.table.names()
+    }
+
+    /// Create a frozen [`Environment`] with the bindings, type values, set constructor and
+    /// settings of this environment, to evaluate scripts in its children.
+    ///
+    /// The values are converted as explained in [`FrozenValue::to_value`], so a thread
+    /// should convert the environment once and reuse it for all its evaluations.
+    pub fn to_environment(&self) -> Environment {
+        with_thaw_scope(|_| {
+            let key = self.key();
+            let thawed = THAWED.with(|m| m.borrow().as_ref().unwrap().get(&key).cloned());
+            if let Some(Thawed::Converted(env)) = thawed {
+                return env;
+            }
+            let env = Environment::new(&self.0.name);
+            // Functions defined in the environment capture it: register it before thawing
+            // them.
+            let converted = Thawed::Converted(env.clone());
+            THAWED.with(|m| m.borrow_mut().as_mut().unwrap().insert(key, converted));
+            for (name, value) in &self.0.table.slots {
+                env.set(name, value.to_value()).unwrap();
+            }
+            for (obj, attrs) in &self.0.type_objs {
+                for (attr, value) in attrs {
+                    env.add_type_value(obj, attr, value.to_value());
+                }
+            }
+            env.env.borrow_mut().set_constructor = self.0.set_constructor.clone();
+            env.set_integer_overflow_mode(self.0.integer_overflow_mode);
+            env.set_catch_native_panics(self.0.catch_native_panics);
+            env.freeze();
+            env
+        })
+    }
+}
+
+impl fmt::Debug for SharedEnvironment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedEnvironment")
+            .field("name", &self.0.name)
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+impl SharedEnvironmentContent {
+    /// Share the bindings of `env` and of its parents, which may contain functions
+    /// capturing `env`.
+    fn share(env: &Environment) -> Result<Arc<SharedEnvironmentContent>, ValueError> {
+        let key = environment_key(env);
+        let mut error = None;
+        let content = Arc::new_cyclic(|weak| {
+            SHARED.with(|m| m.borrow_mut().as_mut().unwrap().insert(key, weak.clone()));
+            SharedEnvironmentContent::new(env).unwrap_or_else(|e| {
+                error = Some(e);
+                SharedEnvironmentContent::empty()
+            })
+        });
+        match error {
+            Some(e) => Err(e),
+            None => Ok(content),
+        }
+    }
+
+    fn empty() -> SharedEnvironmentContent {
+        SharedEnvironmentContent {
+            name: String::new(),
+            table: HashTable::new(Vec::new()),
+            type_objs: HashMap::new(),
+            set_constructor: SetConstructor(None),
+            integer_overflow_mode: IntegerOverflowMode::Error,
+            catch_native_panics: true,
+        }
+    }
+
+    fn new(env: &Environment) -> Result<SharedEnvironmentContent, ValueError> {
+        let mut type_objs: HashMap<String, HashMap<String, Value>> = HashMap::new();
+        let mut set_constructor = SetConstructor(None);
+        for e in chain(env) {
+            let e = e.env.borrow();
+            for (obj, attrs) in &e.type_objs {
+                let shared = type_objs.entry(obj.clone()).or_default();
+                for (attr, value) in attrs {
+                    shared.insert(attr.clone(), value.clone());
+                }
+            }
+            if e.set_constructor.0.is_some() {
+                set_constructor = e.set_constructor.clone();
+            }
+        }
+        let mut shared_type_objs = HashMap::new();
+        for (obj, attrs) in type_objs {
+            let mut shared = HashMap::new();
+            for (attr, value) in attrs {
+                shared.insert(attr, value.share()?);
+            }
+            shared_type_objs.insert(obj, shared);
+        }
+        let mut shared_bindings = Vec::new();
+        for (name, value) in bindings(env) {
+            let value = value.share()?;
+            shared_bindings.push((name, value));
+        }
+        Ok(SharedEnvironmentContent {
+            name: env.name(),
+            table: HashTable::new(shared_bindings),
+            type_objs: shared_type_objs,
+            set_constructor,
+            integer_overflow_mode: env.integer_overflow_mode(),
+            catch_native_panics: env.catch_native_panics(),
+        })
+    }
+}
+
+impl CapturedEnvironment {
+    /// Share `env`, or refer to it if it is being shared.
+    pub(crate) fn share(env: &Environment) -> Result<CapturedEnvironment, ValueError> {
+        with_share_scope(|| {
+            let key = environment_key(env);
+            let shared = SHARED.with(|m| m.borrow().as_ref().unwrap().get(&key).cloned());
+            match shared {
+                // The environment is still being shared if it cannot be upgraded.
+                Some(weak) => Ok(match weak.upgrade() {
+                    Some(content) => CapturedEnvironment::Owned(SharedEnvironment(content)),
+                    None => CapturedEnvironment::Cyclic(weak.as_ptr() as usize),
+                }),
+                None => Ok(CapturedEnvironment::Owned(SharedEnvironment(
+                    SharedEnvironmentContent::share(env)?,
+                ))),
+            }
+        })
+    }
+
+    /// The environment converted with [`SharedEnvironment::to_environment`].
+    pub(crate) fn to_environment(&self) -> Environment {
+        match self {
+            CapturedEnvironment::Owned(env) => env.to_environment(),
+            CapturedEnvironment::Cyclic(key) => {
+                let thawed = THAWED.with(|m| m.borrow().as_ref().unwrap().get(key).cloned());
+                match thawed.expect("environment thawed before the functions it defines") {
+                    Thawed::Borrowed(env) => env.to_environment(),
+                    Thawed::Converted(env) => env,
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::TypeValues;
+    use crate::eval::noload;
+    use crate::stdlib::global_environment_with_extensions;
+    use crate::syntax::dialect::Dialect;
+    use crate::values::{Immutable, TypedValue};
+    use codemap::CodeMap;
+    use std::iter;
+    use std::sync::Mutex;
+    use std::thread;
+
+    fn eval(env: &mut Environment, globals: &Environment, content: &str) -> Value {
+        noload::eval(
+            &Arc::new(Mutex::new(CodeMap::new())),
+            "test.star",
+            content,
+            Dialect::Bzl,
+            env,
+            TypeValues::new(globals.clone()),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn empty() {
+        let env = Environment::new("empty").precompute_hash_table();
+        assert!(env.is_empty());
+        assert!(env.get("a").is_err());
+    }
+
+    #[test]
+    fn child_shadows_parent() {
+        let parent = Environment::new("parent");
+        parent.set("a", Value::new(1)).unwrap();
+        parent.set("b", Value::new(2)).unwrap();
+        let child = parent.child("child");
+        child.set("a", Value::new(10)).unwrap();
+        let frozen = child.precompute_hash_table();
+        assert_eq!("child", frozen.name());
+        assert_eq!(2, frozen.len());
+        assert_eq!(Value::new(10), frozen.get("a").unwrap());
+        assert_eq!(Value::new(2), frozen.get("b").unwrap());
+        assert!(frozen.get("c").is_err());
+        // The environment is frozen by the precomputation.
+        assert!(child.set("c", Value::new(3)).is_err());
+    }
+
+    #[test]
+    fn unshareable_binding() {
+        struct Opaque;
+
+        impl TypedValue for Opaque {
+            type Holder = Immutable<Opaque>;
+            const TYPE: &'static str = "opaque";
+
+            fn values_for_descendant_check_and_freeze<'a>(
+                &'a self,
+            ) -> Box<dyn Iterator<Item = Value> + 'a> {
+                Box::new(iter::empty())
+            }
+        }
+
+        let env = Environment::new("env");
+        env.set("a", Value::new(Opaque)).unwrap();
+        match env.share() {
+            Err(ValueError::TypeNotX { object_type, .. }) => assert_eq!("opaque", object_type),
+            r => panic!("unexpected result {:?}", r),
+        }
+        // The values only need to be shared to be sent to other threads.
+        let frozen = env.precompute_hash_table();
+        assert_eq!("opaque", frozen.get("a").unwrap().get_type());
+    }
+
+    #[test]
+    fn shared_environment_is_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<SharedEnvironment>();
+    }
+
+    #[test]
+    fn scripts_on_several_threads() {
+        let globals = global_environment_with_extensions();
+        // A helper module whose functions call each other and read its constants.
+        let mut helpers = globals.child("helpers");
+        eval(
+            &mut helpers,
+            &globals,
+            "PREFIX = 'item'\n\
+             def label(x):\n  return '{}-{}'.format(PREFIX, x)\n\
+             def labels(n):\n  return [label(x) for x in range(n)]\n",
+        );
+        let shared = helpers.share().unwrap();
+        assert_eq!("helpers", shared.name());
+
+        let threads: Vec<_> = (0..8)
+            .map(|i| {
+                let shared = shared.clone();
+                thread::spawn(move || {
+                    let globals = shared.to_environment();
+                    (0..10)
+                        .map(|j| {
+                            let mut env = globals.child("script");
+                            let script = format!(
+                                "','.join(labels({})) + str({{1, 2}}.union([3])) + \
+                                 repr(struct(a = {}))",
+                                i, j
+                            );
+                            eval(&mut env, &globals, &script).to_str()
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        for (i, thread) in threads.into_iter().enumerate() {
+            let labels: Vec<_> = (0..i).map(|x| format!("item-{}", x)).collect();
+            let expected: Vec<_> = (0..10)
+                .map(|j| format!("{}{{1, 2, 3}}struct(a={})", labels.join(","), j))
+                .collect();
+            assert_eq!(expected, thread.join().unwrap());
+        }
+    }
+
+    #[test]
+    fn functions_share_their_module() {
+        let globals = global_environment_with_extensions();
+        let mut module = globals.child("module");
+        eval(
+            &mut module,
+            &globals,
+            "def f():\n  return g\ndef g():\n  return 1\nl = [f, g]\n",
+        );
+        let shared = module.share().unwrap();
+        let env = shared.to_environment();
+        assert!(env.set("h", Value::new(2)).is_err());
+        // The functions of a value are thawed with the environment they capture.
+        let f = shared.get("f").unwrap();
+        let mut script = globals.child("script");
+        script.set("f", f).unwrap();
+        assert_eq!("1", eval(&mut script, &globals, "f()()").to_str());
+        let l = shared.get("l").unwrap();
+        assert!(l.check_frozen().is_ok());
+        assert_eq!(
+            "[<function f from module>(), <function g from module>()]",
+            l.to_repr()
+        );
+    }
+
+    #[test]
+    fn functions_outlive_their_module() {
+        let globals = global_environment_with_extensions();
+        let mut module = globals.child("module");
+        eval(&mut module, &globals, "def f():\n  return 1\n");
+        let shared = module.share().unwrap();
+        let f = shared.get("f").unwrap();
+        drop(shared);
+        let mut script = globals.child("script");
+        script.set("f", f).unwrap();
+        assert_eq!("1", eval(&mut script, &globals, "f()").to_str());
+    }
+}
@@ -17,11 +17,15 @@
 //! is the list of variable in the current scope. It can be frozen, after which all values from
 //! this environment become immutable.
 
+pub use crate::environment::frozen::{FrozenEnvironment, SharedEnvironment};
 use crate::values::error::{RuntimeError, ValueError};
+use crate::values::function::{Deprecation, NativeFunction, SignatureHelp};
+use crate::values::int::IntegerOverflowMode;
 use crate::values::*;
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::rc::Rc;
+use std::sync::Arc;
 
 // TODO: move that code in some common error code list?
 // CM prefix = Critical Module
@@ -105,15 +109,23 @@ struct EnvironmentContent {
     ///
     /// These bindings include methods for native types, e.g. `string.isalnum`.
     variables: HashMap<String, Value>,
+    /// Name of the module each variable bound by `load()` was loaded from.
+    imports: HashMap<String, String>,
     /// List of static values of an object per type
     type_objs: HashMap<String, HashMap<String, Value>>,
     /// Optional function which can be used to construct set literals (i.e. `{foo, bar}`).
     /// If not set, attempts to use set literals will raise an error.
     set_constructor: SetConstructor,
+    /// What to do when `int` arithmetic overflows, inherited from the parent if not set.
+    integer_overflow_mode: Option<IntegerOverflowMode>,
+    /// Whether a panic of a native function is an error, inherited from the parent if not
+    /// set.
+    catch_native_panics: Option<bool>,
 }
 
 // Newtype so that EnvironmentContent can derive Debug.
-struct SetConstructor((Option<Box<dyn Fn(Vec<Value>) -> ValueResult>>));
+#[derive(Clone)]
+struct SetConstructor(Option<Arc<dyn Fn(Vec<Value>) -> ValueResult + Send + Sync>>);
 
 impl std::fmt::Debug for SetConstructor {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
@@ -134,8 +146,11 @@ impl Environment {
                 frozen: false,
                 parent: None,
                 variables: HashMap::new(),
+                imports: HashMap::new(),
                 type_objs: HashMap::new(),
                 set_constructor: SetConstructor(None),
+                integer_overflow_mode: None,
+                catch_native_panics: None,
             })),
         }
     }
@@ -159,14 +174,32 @@ impl Environment {
     /// Create a new child environment for this environment
     pub fn child(&self, name: &str) -> Environment {
         self.freeze();
+        self.new_child(name)
+    }
+
+    /// Create a child environment which shadows this one: lookups fall back to this
+    /// environment and `set` only binds names in the child, so the parent bindings are never
+    /// replaced.
+    ///
+    /// Unlike [`child`](Environment::child), this environment is not frozen: bindings added
+    /// to it later are visible from the fork, and its values stay mutable, even through the
+    /// fork.
+    pub fn fork(&self) -> Environment {
+        self.new_child(&self.name())
+    }
+
+    fn new_child(&self, name: &str) -> Environment {
         Environment {
             env: Rc::new(RefCell::new(EnvironmentContent {
                 name_: name.to_owned(),
                 frozen: false,
                 parent: Some(self.clone()),
                 variables: HashMap::new(),
+                imports: HashMap::new(),
                 type_objs: HashMap::new(),
                 set_constructor: SetConstructor(None),
+                integer_overflow_mode: None,
+                catch_native_panics: None,
             })),
         }
     }
@@ -178,6 +211,25 @@ impl Environment {
         self
     }
 
+    /// Freeze the environment and index the bindings of it and of its parents in a minimal
+    /// perfect hash table, for constant time lookups that never walk up the parents.
+    ///
+    /// Computing the table is much more expensive than a lookup, so it is only worth it for
+    /// an environment which is read many times, e.g. the global environment of a server.
+    pub fn precompute_hash_table(&self) -> FrozenEnvironment {
+        self.freeze();
+        FrozenEnvironment::new(self)
+    }
+
+    /// Freeze the environment and share the bindings of it and of its parents, its type
+    /// values and its settings with other threads, see [`SharedEnvironment`].
+    ///
+    /// Fails with the error of [`Value::share`] if one of the values cannot be shared.
+    pub fn share(&self) -> Result<SharedEnvironment, ValueError> {
+        self.freeze();
+        SharedEnvironment::new(self)
+    }
+
     /// Return the name of this module
     pub fn name(&self) -> String {
         self.env.borrow().name_.clone()
@@ -204,7 +256,28 @@ impl Environment {
             Some('_') | None => Err(EnvironmentError::CannotImportPrivateSymbol(
                 symbol.to_owned(),
             )),
-            _ => self.set(new_name, env.get(symbol)?),
+            _ => {
+                self.set(new_name, env.get(symbol)?)?;
+                // A symbol loaded again by another module is still frozen by the module
+                // defining it.
+                let module = env.frozen_in_module(symbol).unwrap_or_else(|| env.name());
+                self.env
+                    .borrow_mut()
+                    .imports
+                    .insert(new_name.to_owned(), module);
+                Ok(())
+            }
+        }
+    }
+
+    /// Return the name of the module which froze the value bound to `name` in this
+    /// environment, i.e. the module it was loaded from, or this module if it is frozen.
+    pub(crate) fn frozen_in_module(&self, name: &str) -> Option<String> {
+        let env = self.env.borrow();
+        match env.imports.get(name) {
+            Some(module) => Some(module.clone()),
+            None if env.frozen && env.variables.contains_key(name) => Some(env.name_.clone()),
+            None => None,
         }
     }
 
@@ -224,8 +297,57 @@ impl Environment {
     ///
     /// The `Value` returned by this function is expected to be a one-dimensional collection
     /// containing no duplicates.
-    pub fn with_set_constructor(&self, constructor: Box<dyn Fn(Vec<Value>) -> ValueResult>) {
-        self.env.borrow_mut().set_constructor = SetConstructor(Some(constructor));
+    ///
+    /// The function must be `Send + Sync` to be kept by a [`SharedEnvironment`].
+    pub fn with_set_constructor(
+        &self,
+        constructor: Box<dyn Fn(Vec<Value>) -> ValueResult + Send + Sync>,
+    ) {
+        self.env.borrow_mut().set_constructor = SetConstructor(Some(Arc::from(constructor)));
+    }
+
+    /// Set what `int` arithmetic evaluated in this environment and its children does on
+    /// overflow, e.g. `9223372036854775807 + 1`.
+    ///
+    /// If this function is not called on the `Environment`, its parent's mode is used, and
+    /// overflows are errors if no transitive parent sets a mode.
+    pub fn set_integer_overflow_mode(&self, mode: IntegerOverflowMode) {
+        self.env.borrow_mut().integer_overflow_mode = Some(mode);
+    }
+
+    /// What `int` arithmetic evaluated in this environment does on overflow, see
+    /// [`set_integer_overflow_mode`](Environment::set_integer_overflow_mode).
+    pub fn integer_overflow_mode(&self) -> IntegerOverflowMode {
+        match self.env.borrow().integer_overflow_mode {
+            Some(mode) => mode,
+            None => match self.get_parent() {
+                Some(parent) => parent.integer_overflow_mode(),
+                None => IntegerOverflowMode::Error,
+            },
+        }
+    }
+
+    /// Set whether a panic of a native function called with the type values of this
+    /// environment or of its children is caught and reported as an error of the call, with
+    /// the code `CF07`, or unwinds through the evaluation.
+    ///
+    /// If this function is not called on the `Environment`, its parent's setting is used,
+    /// and panics are caught if no transitive parent sets it. Embedders which prefer to
+    /// crash, e.g. to get a core dump, disable it on their global environment.
+    pub fn set_catch_native_panics(&self, catch: bool) {
+        self.env.borrow_mut().catch_native_panics = Some(catch);
+    }
+
+    /// Whether a panic of a native function is caught, see
+    /// [`set_catch_native_panics`](Environment::set_catch_native_panics).
+    pub fn catch_native_panics(&self) -> bool {
+        match self.env.borrow().catch_native_panics {
+            Some(catch) => catch,
+            None => match self.get_parent() {
+                Some(parent) => parent.catch_native_panics(),
+                None => true,
+            },
+        }
     }
 
     pub(crate) fn make_set(&self, values: Vec<Value>) -> ValueResult {
@@ -332,6 +454,9 @@ impl EnvironmentContent {
 /// Function implementations are only allowed to access
 /// type values from "type values" from the caller context,
 /// so this struct is passed instead of full `Environment`.
+///
+/// Cloning is cheap: like [`Environment`], clones share the same content, so it is
+/// passed by value to each call without copying the type values.
 #[derive(Clone)]
 pub struct TypeValues {
     env: Environment,
@@ -357,4 +482,55 @@ impl TypeValues {
     pub fn list_type_value(&self, obj: &Value) -> Vec<String> {
         self.env.list_type_value(obj)
     }
+
+    /// Whether a panic of a native function is caught, see
+    /// [`Environment::set_catch_native_panics`].
+    pub(crate) fn catch_native_panics(&self) -> bool {
+        self.env.catch_native_panics()
+    }
+
+    /// Get the parameters of a native type value (e.g. list.index) and their
+    /// documentation, if it exists.
+    pub fn get_signature_help(&self, obj: &Value, id: &str) -> Option<SignatureHelp> {
+        let function = self.get_type_value(obj, id)?;
+        let help = function.downcast_ref::<NativeFunction>()?.signature_help();
+        Some(help)
+    }
+
+    /// Get the deprecation of a native type value (e.g. `string.foo`), if it is deprecated.
+    pub fn get_deprecation(&self, obj: &Value, id: &str) -> Option<Deprecation> {
+        let function = self.get_type_value(obj, id)?;
+        let deprecation = function
+            .downcast_ref::<NativeFunction>()?
+            .deprecation()?
+            .clone();
+        Some(deprecation)
+    }
+}
+
+pub mod frozen;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fork_shadows_parent() {
+        let parent = Environment::new("parent");
+        parent.set("a", Value::new(1)).unwrap();
+        parent.set("b", Value::new(2)).unwrap();
+        let fork = parent.fork();
+        assert_eq!("parent", fork.name());
+        fork.set("a", Value::new(10)).unwrap();
+        fork.set("c", Value::new(30)).unwrap();
+        assert_eq!(Value::new(10), fork.get("a").unwrap());
+        assert_eq!(Value::new(2), fork.get("b").unwrap());
+        assert_eq!(Value::new(1), parent.get("a").unwrap());
+        assert!(parent.get("c").is_err());
+
+        // The parent is not frozen, its new bindings are visible from the fork.
+        parent.set("d", Value::new(4)).unwrap();
+        assert_eq!(Value::new(4), fork.get("d").unwrap());
+        assert_eq!(Value::new(4), fork.fork().get("d").unwrap());
+    }
 }
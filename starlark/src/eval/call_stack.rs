@@ -13,19 +13,36 @@
 // limitations under the License.
 //! Starlark call stack.
 
+use crate::eval::options::{EvaluationLimits, LineHandler};
+use crate::eval::profile::ProfilingContext;
 use crate::values::error::ValueError;
 use crate::values::{FunctionId, Value};
-use codemap::{CodeMap, Pos};
+use codemap::{CodeMap, Pos, Span};
+use codemap_diagnostic::{Diagnostic, SpanLabel, SpanStyle};
 use std::cell::Cell;
 use std::fmt;
+use std::ops::{Deref, DerefMut};
+use std::rc::Rc;
 use std::sync::{Arc, Mutex};
 
 #[derive(Clone)]
-struct Frame(Value, Arc<Mutex<CodeMap>>, Pos);
+enum Frame {
+    /// Call to a Starlark value, from the given position in the code map.
+    Call(Value, Arc<Mutex<CodeMap>>, Pos),
+    /// Frame pushed by [`CallStack::with_location`].
+    Location {
+        function: String,
+        file: String,
+        line: u32,
+    },
+}
 
 impl fmt::Debug for Frame {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        f.debug_tuple("Frame").field(&self.0).finish()
+        match self {
+            Frame::Call(function, ..) => f.debug_tuple("Frame").field(function).finish(),
+            Frame::Location { function, .. } => f.debug_tuple("Frame").field(function).finish(),
+        }
     }
 }
 
@@ -33,43 +50,359 @@ impl fmt::Debug for Frame {
 #[derive(Clone, Debug, Default)]
 pub struct CallStack {
     stack: Vec<Frame>,
+    limits: Option<Rc<EvaluationLimits>>,
 }
 
 impl CallStack {
+    /// An empty call stack for an evaluation bounded by `limits`.
+    pub(crate) fn with_limits(limits: EvaluationLimits) -> CallStack {
+        CallStack {
+            stack: Vec::new(),
+            limits: Some(Rc::new(limits)),
+        }
+    }
+
+    /// Check that the evaluation this call stack belongs to is within its limits.
+    pub(crate) fn check_limits(&self) -> Result<(), ValueError> {
+        match self.limits {
+            Some(ref limits) if limits.is_cancelled() => Err(ValueError::Interrupted {
+                call_stack: DisplayWithNewlineBefore {
+                    call_stack: self,
+                    max_frames: MAX_REPORTED_FRAMES,
+                }
+                .to_string(),
+            }),
+            Some(ref limits) => limits.check(),
+            None => Ok(()),
+        }
+    }
+
+    /// Charge `steps` steps to the budget of the evaluation, see
+    /// [`EvaluationOptions::max_steps`](crate::eval::options::EvaluationOptions::max_steps).
+    ///
+    /// Native functions performing expensive operations can call this to have them
+    /// accounted for, the error must then be returned to abort the evaluation.
+    pub fn charge_steps(&self, steps: u64) -> Result<(), ValueError> {
+        match self.limits {
+            Some(ref limits) => limits.charge_steps(steps),
+            None => Ok(()),
+        }
+    }
+
+    /// Where to report the conversion of the arguments of native functions, if enabled.
+    pub(crate) fn argument_trace(&self) -> Option<&LineHandler> {
+        self.limits.as_ref()?.argument_trace.as_ref()
+    }
+
+    /// Report a warning to the handler of the evaluation, see
+    /// [`EvaluationOptions::on_warning`](crate::eval::options::EvaluationOptions::on_warning).
+    pub fn warn(&self, warning: &str) {
+        if let Some(handler) = self
+            .limits
+            .as_ref()
+            .and_then(|limits| limits.warning_handler.as_ref())
+        {
+            handler.emit(warning);
+        }
+    }
+
+    /// Write a line of output of the evaluation, to the standard output unless the
+    /// evaluation has a handler, see
+    /// [`EvaluationOptions::on_print`](crate::eval::options::EvaluationOptions::on_print).
+    pub fn print(&self, line: &str) {
+        match self
+            .limits
+            .as_ref()
+            .and_then(|limits| limits.print_handler.as_ref())
+        {
+            Some(handler) => handler.emit(line),
+            None => println!("{}", line),
+        }
+    }
+
+    /// Where to report the function calls, if profiling is enabled.
+    pub(crate) fn profiling(&self) -> Option<&ProfilingContext> {
+        self.limits.as_ref()?.profiling.as_ref()
+    }
+
+    /// Check that one more call can be pushed without exceeding the maximum call depth of
+    /// the evaluation.
+    pub(crate) fn check_depth(&self) -> Result<(), ValueError> {
+        match self.limits {
+            Some(ref limits) if self.stack.len() >= limits.max_call_depth => {
+                Err(ValueError::CallDepthExceeded {
+                    max_depth: limits.max_call_depth,
+                    call_stack: DisplayWithNewlineBefore {
+                        call_stack: self,
+                        max_frames: MAX_REPORTED_FRAMES,
+                    }
+                    .to_string(),
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
     /// Push an element to the stack
     pub fn push(&mut self, function: Value, code_map: Arc<Mutex<CodeMap>>, pos: Pos) {
-        self.stack.push(Frame(function, code_map, pos));
+        self.stack.push(Frame::Call(function, code_map, pos));
+    }
+
+    /// Push a frame for a call to `function` at `file:line` (line numbers start at 1),
+    /// for callers which do not have a code map position, e.g. native code calling back
+    /// into Starlark.
+    ///
+    /// The frame is popped when the returned guard is dropped, including when leaving
+    /// the scope early on error. The guard dereferences to the call stack, so it can be
+    /// passed to the callee.
+    pub fn with_location(
+        &mut self,
+        function: String,
+        file: String,
+        line: u32,
+    ) -> CallStackGuard<'_> {
+        self.stack.push(Frame::Location {
+            function,
+            file,
+            line,
+        });
+        CallStackGuard { call_stack: self }
+    }
+
+    /// The file, line and column (starting at 1) of the innermost call, i.e. for a native
+    /// function, of the expression calling it. The column is 0 for frames pushed by
+    /// [`with_location`](CallStack::with_location), which only know the line.
+    pub fn current_location(&self) -> Option<(String, usize, usize)> {
+        Some(match self.stack.last()? {
+            Frame::Call(_, code_map, pos) => {
+                let loc = { code_map.lock().unwrap().look_up_pos(*pos) };
+                (
+                    loc.file.name().to_owned(),
+                    loc.position.line + 1,
+                    loc.position.column + 1,
+                )
+            }
+            Frame::Location { file, line, .. } => (file.clone(), *line as usize, 0),
+        })
+    }
+
+    /// Whether no function is being called, i.e. the code is evaluated at the top level of
+    /// a file.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.stack.is_empty()
+    }
+
+    /// The functions being executed, outermost first, for an error raised by the innermost
+    /// function at `location` (file and line starting at 1), or by its native code if
+    /// `location` is `None`.
+    ///
+    /// The calls made from `code_map` are located by a span, to be shown in the diagnostic
+    /// of the error.
+    pub fn traceback(
+        &self,
+        code_map: &Arc<Mutex<CodeMap>>,
+        location: Option<(String, usize)>,
+    ) -> Traceback {
+        let mut frames = Vec::with_capacity(self.stack.len() + 1);
+        let mut function = MODULE_FUNCTION.to_owned();
+        for frame in &self.stack {
+            let (callee, file, line, span) = match frame {
+                Frame::Call(callee, frame_code_map, pos) => {
+                    let same_code_map = Arc::ptr_eq(frame_code_map, code_map);
+                    let frame_code_map = frame_code_map.lock().unwrap();
+                    let loc = frame_code_map.look_up_pos(*pos);
+                    // From the call to the end of its line, only its position is known.
+                    let span = if same_code_map {
+                        let file = &loc.file;
+                        let line = file.line_span(loc.position.line);
+                        let call = line.subspan(*pos - line.low(), line.len());
+                        let len = file.source_slice(call).trim_end().len();
+                        Some(call.subspan(0, len as u64))
+                    } else {
+                        None
+                    };
+                    (
+                        callee.to_str(),
+                        loc.file.name().to_owned(),
+                        loc.position.line + 1,
+                        span,
+                    )
+                }
+                Frame::Location {
+                    function,
+                    file,
+                    line,
+                } => (function.clone(), file.clone(), *line as usize, None),
+            };
+            frames.push(TracebackFrame {
+                function: std::mem::replace(&mut function, callee),
+                location: Some((file, line)),
+                span,
+            });
+        }
+        frames.push(TracebackFrame {
+            function,
+            location,
+            span: None,
+        });
+        Traceback { frames }
     }
 
     /// Test if call stack contains a function with given id.
     pub fn contains(&self, function_id: FunctionId) -> bool {
-        self.stack
-            .iter()
-            .any(|&Frame(ref f, _, _)| f.function_id() == function_id)
+        self.stack.iter().any(|frame| match frame {
+            Frame::Call(f, ..) => f.function_id() == function_id,
+            Frame::Location { .. } => false,
+        })
     }
 
     /// Print call stack as multiline string
     /// with each line beginning with newline.
     pub fn print_with_newline_before<'a>(&'a self) -> impl fmt::Display + 'a {
-        DisplayWithNewlineBefore { call_stack: self }
+        DisplayWithNewlineBefore {
+            call_stack: self,
+            max_frames: usize::MAX,
+        }
+    }
+}
+
+/// Frame pushed by [`CallStack::with_location`], popped on drop.
+#[must_use]
+pub struct CallStackGuard<'a> {
+    call_stack: &'a mut CallStack,
+}
+
+impl<'a> Deref for CallStackGuard<'a> {
+    type Target = CallStack;
+
+    fn deref(&self) -> &CallStack {
+        self.call_stack
     }
 }
 
+impl<'a> DerefMut for CallStackGuard<'a> {
+    fn deref_mut(&mut self) -> &mut CallStack {
+        self.call_stack
+    }
+}
+
+impl<'a> Drop for CallStackGuard<'a> {
+    fn drop(&mut self) {
+        self.call_stack.stack.pop();
+    }
+}
+
+const TRACEBACK_HEADER: &str = "Traceback (most recent call last):";
+
+/// Location of the frames of native functions in a [`Traceback`].
+const NATIVE_LOCATION: &str = "<native>";
+
+/// Function of the outermost frame of a [`Traceback`], the top level of the file.
+const MODULE_FUNCTION: &str = "<module>";
+
+/// A frame of a [`Traceback`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TracebackFrame {
+    /// The function executed, named as in call stacks (e.g. `f(x)`), or `<module>` for the
+    /// top level of the file.
+    pub function: String,
+    /// The file and line (starting at 1) executed in the function, `None` for a native
+    /// function.
+    pub location: Option<(String, usize)>,
+    /// The position of the call made by the function in the code map of the error, if it
+    /// was made from that code map.
+    pub span: Option<Span>,
+}
+
+impl fmt::Display for TracebackFrame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.location {
+            Some((ref file, line)) => write!(f, "{}:{}: in {}", file, line, self.function),
+            None => write!(f, "{}: in {}", NATIVE_LOCATION, self.function),
+        }
+    }
+}
+
+/// The functions being executed when an error was raised, see [`CallStack::traceback`].
+///
+/// The evaluator attaches it to the errors raised inside a function, see
+/// [`ValueError::TracedError`], and embedders get it with
+/// [`eval_with_traceback`](crate::eval::eval_with_traceback). Its [`Display`](fmt::Display)
+/// is the traceback as printed by Python, which is appended to the message of their
+/// [`Diagnostic`], e.g.:
+///
+/// ```text
+/// Traceback (most recent call last):
+///   main.star:4: in <module>
+///   lib.star:2: in parse(x)
+///   <native>: in int($a, base)
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Traceback {
+    /// The frames, outermost first.
+    pub frames: Vec<TracebackFrame>,
+}
+
+impl Traceback {
+    /// List the frames of this traceback after the message of `diagnostic`, so that they
+    /// are rendered before its snippet, and label the calls made from its code map.
+    pub(crate) fn add_to(&self, diagnostic: &mut Diagnostic) {
+        diagnostic.message = format!("{}\n{}", diagnostic.message, self);
+        for frame in &self.frames {
+            if let Some(span) = frame.span {
+                // The innermost call is the source of the error, which is already shown.
+                if diagnostic.spans.iter().any(|l| l.span.low() == span.low()) {
+                    continue;
+                }
+                diagnostic.spans.push(SpanLabel {
+                    span,
+                    style: SpanStyle::Secondary,
+                    label: Some(format!("in {}", frame.function)),
+                });
+            }
+        }
+    }
+}
+
+impl fmt::Display for Traceback {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(TRACEBACK_HEADER)?;
+        for frame in &self.frames {
+            write!(f, "\n  {}", frame)?;
+        }
+        Ok(())
+    }
+}
+
+/// Number of innermost frames reported when the maximum call depth is exceeded or the
+/// evaluation is cancelled.
+const MAX_REPORTED_FRAMES: usize = 5;
+
 struct DisplayWithNewlineBefore<'a> {
     call_stack: &'a CallStack,
+    max_frames: usize,
 }
 
 impl<'a> fmt::Display for DisplayWithNewlineBefore<'a> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        for Frame(function, code_map, pos) in self.call_stack.stack.iter().rev() {
-            let loc = { code_map.lock().unwrap().look_up_pos(*pos) };
-            write!(
-                f,
-                "\n    call to {} at {}:{}",
-                function.to_str(),
-                loc.file.name(),
-                loc.position.line + 1, // line 1 is 0, so add 1 for human readable.
-            )?;
+        for frame in self.call_stack.stack.iter().rev().take(self.max_frames) {
+            match frame {
+                Frame::Call(function, code_map, pos) => {
+                    let loc = { code_map.lock().unwrap().look_up_pos(*pos) };
+                    write!(
+                        f,
+                        "\n    call to {} at {}:{}",
+                        function.to_str(),
+                        loc.file.name(),
+                        loc.position.line + 1, // line 1 is 0, so add 1 for human readable.
+                    )?;
+                }
+                Frame::Location {
+                    function,
+                    file,
+                    line,
+                } => write!(f, "\n    call to {} at {}:{}", function, file, line)?,
+            }
         }
         Ok(())
     }
@@ -94,7 +427,7 @@ const MAX_RECURSION: u32 = 3000;
 //   starlark function which calls to_str. We could change all evaluation stack
 //   signatures to accept some "context" parameters, but passing it as thread-local
 //   is easier.
-thread_local!(static STACK_DEPTH: Cell<u32> = Cell::new(0));
+thread_local!(static STACK_DEPTH: Cell<u32> = const { Cell::new(0) });
 
 /// Stored previous stack depth before calling `try_inc`.
 ///
@@ -138,3 +471,197 @@ pub fn try_inc() -> Result<StackGuard, ValueError> {
     check()?;
     Ok(inc())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eval::EvalException;
+    use crate::syntax::dialect::Dialect;
+
+    #[test]
+    fn with_location_pops_on_drop() {
+        let mut call_stack = CallStack::default();
+        {
+            let guard = call_stack.with_location("f".to_owned(), "a.star".to_owned(), 3);
+            assert_eq!(
+                "\n    call to f at a.star:3",
+                guard.print_with_newline_before().to_string()
+            );
+        }
+        assert_eq!("", call_stack.print_with_newline_before().to_string());
+    }
+
+    #[test]
+    fn with_location_nested() {
+        let mut call_stack = CallStack::default();
+        let mut outer = call_stack.with_location("f".to_owned(), "a.star".to_owned(), 3);
+        {
+            let inner = outer.with_location("g".to_owned(), "b.star".to_owned(), 7);
+            assert_eq!(
+                "\n    call to g at b.star:7\n    call to f at a.star:3",
+                inner.print_with_newline_before().to_string()
+            );
+        }
+        assert_eq!(
+            "\n    call to f at a.star:3",
+            outer.print_with_newline_before().to_string()
+        );
+    }
+
+    #[test]
+    fn current_location() {
+        let mut call_stack = CallStack::default();
+        assert_eq!(None, call_stack.current_location());
+        let mut outer = call_stack.with_location("f".to_owned(), "a.star".to_owned(), 3);
+        assert_eq!(Some(("a.star".to_owned(), 3, 0)), outer.current_location());
+        let code_map = Arc::new(Mutex::new(CodeMap::new()));
+        let span = {
+            let mut code_map = code_map.lock().unwrap();
+            let file = code_map.add_file("b.star".to_owned(), "x = 1\ny = f(x)\n".to_owned());
+            file.span
+        };
+        outer.push(Value::new(1), code_map, span.low() + 10);
+        assert_eq!(Some(("b.star".to_owned(), 2, 5)), outer.current_location());
+        outer.stack.pop();
+    }
+
+    #[test]
+    fn with_location_pops_on_error() {
+        fn fails(call_stack: &mut CallStack) -> Result<(), ValueError> {
+            let _guard = call_stack.with_location("f".to_owned(), "a.star".to_owned(), 3);
+            Err(ValueError::TooManyRecursionLevel)?;
+            unreachable!()
+        }
+
+        let mut call_stack = CallStack::default();
+        assert!(fails(&mut call_stack).is_err());
+        assert_eq!("", call_stack.print_with_newline_before().to_string());
+    }
+
+    fn eval_traceback(content: &str) -> (Diagnostic, Option<Traceback>) {
+        let env = crate::stdlib::global_environment();
+        env.freeze();
+        let map = Arc::new(Mutex::new(CodeMap::new()));
+        let context = crate::eval::EvaluationContext::new(
+            env.child("test"),
+            crate::environment::TypeValues::new(env),
+            crate::eval::noload::NoLoadFileLoader,
+            map.clone(),
+            &crate::eval::options::EvaluationOptions::default(),
+            Dialect::Bzl,
+        );
+        let stmt = crate::syntax::parser::parse(&map, "main.star", content, Dialect::Bzl).unwrap();
+        match crate::eval::eval_stmt(&stmt, &context).unwrap_err() {
+            EvalException::TracedError(d, traceback) => (d, Some(traceback)),
+            e => (e.into(), None),
+        }
+    }
+
+    fn frames(traceback: &Traceback) -> Vec<(&str, Option<(&str, usize)>)> {
+        traceback
+            .frames
+            .iter()
+            .map(|frame| {
+                let location = frame.location.as_ref();
+                (
+                    frame.function.as_str(),
+                    location.map(|(file, line)| (file.as_str(), *line)),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn traceback() {
+        let (d, traceback) =
+            eval_traceback("def f(x):\n  return g(x)\ndef g(x):\n  return int(x)\n\nf('a')\n");
+        let traceback = traceback.unwrap();
+        assert_eq!(
+            vec![
+                ("<module>", Some(("main.star", 6))),
+                ("f(x)", Some(("main.star", 2))),
+                ("g(x)", Some(("main.star", 4))),
+                ("int($a, base)", None),
+            ],
+            frames(&traceback)
+        );
+        assert_eq!(
+            concat!(
+                "Traceback (most recent call last):",
+                "\n  main.star:6: in <module>",
+                "\n  main.star:2: in f(x)",
+                "\n  main.star:4: in g(x)",
+                "\n  <native>: in int($a, base)",
+            ),
+            traceback.to_string()
+        );
+        // The traceback is listed after the message, and the calls leading to the error
+        // are labelled in the diagnostic.
+        assert!(!d.message.contains("Traceback"), "{}", d.message);
+        let message = format!("{}\n{}", d.message, traceback);
+        let d: Diagnostic = EvalException::TracedError(d, traceback).into();
+        assert_eq!(message, d.message);
+        let labels: Vec<_> = d.spans[1..]
+            .iter()
+            .map(|span| (span.style, span.label.as_deref()))
+            .collect();
+        assert_eq!(
+            vec![
+                (SpanStyle::Secondary, Some("in <module>")),
+                (SpanStyle::Secondary, Some("in f(x)")),
+            ],
+            labels
+        );
+    }
+
+    #[test]
+    fn traceback_of_expression() {
+        // Raised by the function itself, or by a call not matching its parameters.
+        let (_, traceback) = eval_traceback("def f():\n  return 1 + 'a'\nf()\n");
+        assert_eq!(
+            vec![
+                ("<module>", Some(("main.star", 3))),
+                ("f()", Some(("main.star", 2))),
+            ],
+            frames(&traceback.unwrap())
+        );
+        let (_, traceback) = eval_traceback("def f():\n  g(1)\ndef g():\n  pass\nf()\n");
+        assert_eq!(
+            vec![
+                ("<module>", Some(("main.star", 5))),
+                ("f()", Some(("main.star", 2))),
+            ],
+            frames(&traceback.unwrap())
+        );
+    }
+
+    #[test]
+    fn traceback_of_failure() {
+        // A message looking like a traceback is not mistaken for one.
+        let (d, traceback) = eval_traceback(
+            "def f():\n  fail('Traceback (most recent call last):\\n  main.star:1: in g()')\nf()\n",
+        );
+        assert_eq!(
+            vec![
+                ("<module>", Some(("main.star", 3))),
+                ("f()", Some(("main.star", 2))),
+                ("fail(*args, sep = \" \")", None),
+            ],
+            frames(&traceback.unwrap())
+        );
+        assert_eq!(
+            concat!(
+                "fail(): Traceback (most recent call last):\n  main.star:1: in g()",
+                "\n    call to fail(*args, sep = \" \") at main.star:2",
+                "\n    call to f() at main.star:3",
+            ),
+            d.message
+        );
+    }
+
+    #[test]
+    fn no_traceback_at_top_level() {
+        assert_eq!(None, eval_traceback("int('a')").1);
+        assert_eq!(None, eval_traceback("1 + 'a'").1);
+    }
+}
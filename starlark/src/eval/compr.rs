@@ -21,6 +21,7 @@ use crate::values::dict::Dictionary;
 use crate::values::{TypedValue, Value};
 use codemap::{Span, Spanned};
 use codemap_diagnostic::Diagnostic;
+use std::cmp;
 use std::collections::HashMap;
 use std::iter;
 
@@ -116,7 +117,10 @@ impl ComprehensionCompiled {
     ) -> Result<ComprehensionCompiled, Diagnostic> {
         let fors = ClauseForCompiled::compile_clauses(clauses)?;
         Ok(ComprehensionCompiled::List(
-            Expr::transform_locals_to_slots(expr, &fors.last().unwrap().local_names_to_indices),
+            Expr::compile(Expr::transform_locals_to_slots(
+                expr,
+                &fors.last().unwrap().local_names_to_indices,
+            ))?,
             fors,
         ))
     }
@@ -127,7 +131,10 @@ impl ComprehensionCompiled {
     ) -> Result<ComprehensionCompiled, Diagnostic> {
         let fors = ClauseForCompiled::compile_clauses(clauses)?;
         Ok(ComprehensionCompiled::Set(
-            Expr::transform_locals_to_slots(expr, &fors.last().unwrap().local_names_to_indices),
+            Expr::compile(Expr::transform_locals_to_slots(
+                expr,
+                &fors.last().unwrap().local_names_to_indices,
+            ))?,
             fors,
         ))
     }
@@ -139,8 +146,14 @@ impl ComprehensionCompiled {
     ) -> Result<ComprehensionCompiled, Diagnostic> {
         let fors = ClauseForCompiled::compile_clauses(clauses)?;
         Ok(ComprehensionCompiled::Dict(
-            Expr::transform_locals_to_slots(key, &fors.last().unwrap().local_names_to_indices),
-            Expr::transform_locals_to_slots(value, &fors.last().unwrap().local_names_to_indices),
+            Expr::compile(Expr::transform_locals_to_slots(
+                key,
+                &fors.last().unwrap().local_names_to_indices,
+            ))?,
+            Expr::compile(Expr::transform_locals_to_slots(
+                value,
+                &fors.last().unwrap().local_names_to_indices,
+            ))?,
             fors,
         ))
     }
@@ -192,6 +205,10 @@ impl ComprehensionCompiled {
     }
 }
 
+/// Maximum number of values reserved ahead by a comprehension, so that a huge one is
+/// stopped by the evaluation limits rather than by a failed allocation.
+const MAX_RESERVED_VALUES: usize = 1 << 16;
+
 fn eval_one_dimensional_comprehension<'a>(
     e: &AstExpr,
     clauses: &[ClauseForCompiled],
@@ -201,17 +218,26 @@ fn eval_one_dimensional_comprehension<'a>(
     if let Some((c, tl)) = clauses.split_first() {
         let mut iterable = eval_expr(&c.over, context)?;
         iterable.freeze_for_iteration();
-        'f: for i in &t(iterable.iter(), &c.over.span)? {
-            let context = context.child(&c.local_names_to_indices);
-            set_expr(&c.var, &context, i)?;
-
-            for ifc in &c.ifs {
-                if !eval_expr(ifc, &context)?.to_bool() {
-                    continue 'f;
-                }
+        {
+            let iter = t(iterable.iter(), &c.over.span)?;
+            let iter = iter.iter();
+            if tl.is_empty() && c.ifs.is_empty() {
+                // Each element of the innermost clause produces exactly one value.
+                collect.reserve(cmp::min(iter.size_hint().0, MAX_RESERVED_VALUES));
             }
+            'f: for i in iter {
+                t(context.call_stack.check_limits(), &c.over)?;
+                let context = context.child(&c.local_names_to_indices);
+                set_expr(&c.var, &context, i)?;
+
+                for ifc in &c.ifs {
+                    if !eval_expr(ifc, &context)?.to_bool() {
+                        continue 'f;
+                    }
+                }
 
-            eval_one_dimensional_comprehension(e, tl, &context, collect)?;
+                eval_one_dimensional_comprehension(e, tl, &context, collect)?;
+            }
         }
 
         iterable.unfreeze_for_iteration();
@@ -14,22 +14,26 @@
 
 //! Implementation of `def`.
 
+use crate::environment::frozen::CapturedEnvironment;
 use crate::environment::{Environment, TypeValues};
 use crate::eval::call_stack::CallStack;
 use crate::eval::{
-    eval_stmt, EvalException, EvaluationContext, EvaluationContextEnvironment, IndexedLocals,
+    eval_stmt, CapturedScope, EvalException, EvaluationContext, EvaluationContextEnvironment,
+    IndexedLocals,
 };
 use crate::syntax::ast::{AstParameter, AstStatement, AstString, Expr, Statement};
+use crate::syntax::dialect::Dialect;
 use crate::values::error::ValueError;
-use crate::values::function::{FunctionParameter, FunctionType};
+use crate::values::frozen::Thaw;
+use crate::values::function::{FrozenFunctionParameter, FunctionParameter, FunctionType};
 use crate::values::none::NoneType;
-use crate::values::{function, Immutable, TypedValue, Value, ValueResult};
+use crate::values::OrderedMap;
+use crate::values::{function, FrozenValue, Immutable, TypedValue, Value, ValueResult};
 use codemap::{CodeMap, Spanned};
-use codemap_diagnostic::Diagnostic;
-use linked_hash_map::LinkedHashMap;
+use codemap_diagnostic::{Diagnostic, SpanStyle};
+use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 use std::convert::TryInto;
-use std::iter;
 use std::sync::{Arc, Mutex};
 
 /// `def` AST with post-processing suitable for faster excecution
@@ -40,6 +44,8 @@ pub struct DefCompiled {
     pub(crate) params: Vec<AstParameter>,
     pub(crate) suite: AstStatement,
     local_names_to_indices: HashMap<String, usize>,
+    /// Slot of each parameter, in the order of `params`.
+    param_slots: Vec<usize>,
 }
 
 impl DefCompiled {
@@ -57,6 +63,11 @@ impl DefCompiled {
                 .or_insert(len);
         }
 
+        let param_slots = params
+            .iter()
+            .map(|p| local_names_to_indices[p.name()])
+            .collect();
+
         DefCompiled::collect_locals(&suite, &mut local_names_to_indices);
 
         let suite = DefCompiled::transform_locals(suite, &local_names_to_indices);
@@ -68,6 +79,7 @@ impl DefCompiled {
             params,
             suite,
             local_names_to_indices,
+            param_slots,
         })
     }
 
@@ -80,6 +92,9 @@ impl DefCompiled {
                 Expr::collect_locals_from_assign_expr(dest, local_names_to_indices);
                 DefCompiled::collect_locals(body, local_names_to_indices);
             }
+            Statement::While(_, ref body) => {
+                DefCompiled::collect_locals(body, local_names_to_indices);
+            }
             Statement::Statements(ref stmts) => {
                 for stmt in stmts {
                     DefCompiled::collect_locals(stmt, local_names_to_indices);
@@ -107,13 +122,17 @@ impl DefCompiled {
             span: stmts.span,
             node: match stmts.node {
                 Statement::Assign(left, op, right) => Statement::Assign(
-                    Expr::transform_locals_to_slots(left, locals),
+                    Expr::transform_function_locals_to_slots(left, locals),
                     op,
-                    Expr::transform_locals_to_slots(right, locals),
+                    Expr::transform_function_locals_to_slots(right, locals),
                 ),
                 Statement::For(var, collection, body) => Statement::For(
-                    Expr::transform_locals_to_slots(var, locals),
-                    Expr::transform_locals_to_slots(collection, locals),
+                    Expr::transform_function_locals_to_slots(var, locals),
+                    Expr::transform_function_locals_to_slots(collection, locals),
+                    DefCompiled::transform_locals(body, locals),
+                ),
+                Statement::While(cond, body) => Statement::While(
+                    Expr::transform_function_locals_to_slots(cond, locals),
                     DefCompiled::transform_locals(body, locals),
                 ),
                 Statement::Statements(stmts) => Statement::Statements(
@@ -123,21 +142,23 @@ impl DefCompiled {
                         .collect(),
                 ),
                 Statement::If(cond, then_block) => Statement::If(
-                    Expr::transform_locals_to_slots(cond, locals),
+                    Expr::transform_function_locals_to_slots(cond, locals),
                     DefCompiled::transform_locals(then_block, locals),
                 ),
                 Statement::IfElse(cond, then_block, else_block) => Statement::IfElse(
-                    Expr::transform_locals_to_slots(cond, locals),
+                    Expr::transform_function_locals_to_slots(cond, locals),
                     DefCompiled::transform_locals(then_block, locals),
                     DefCompiled::transform_locals(else_block, locals),
                 ),
                 s @ Statement::Break | s @ Statement::Continue | s @ Statement::Pass => s,
-                Statement::Def(..) | Statement::Load(..) | Statement::DefCompiled(..) => unreachable!(),
+                Statement::Def(..) | Statement::Load(..) | Statement::DefCompiled(..) => {
+                    unreachable!()
+                }
                 Statement::Expression(expr) => {
-                    Statement::Expression(Expr::transform_locals_to_slots(expr, locals))
+                    Statement::Expression(Expr::transform_function_locals_to_slots(expr, locals))
                 }
                 Statement::Return(expr) => Statement::Return(
-                    expr.map(|expr| Expr::transform_locals_to_slots(expr, locals)),
+                    expr.map(|expr| Expr::transform_function_locals_to_slots(expr, locals)),
                 ),
             },
         })
@@ -149,8 +170,12 @@ pub(crate) struct Def {
     signature: Vec<FunctionParameter>,
     function_type: FunctionType,
     captured_env: Environment,
+    /// Scopes of the enclosing functions, innermost first, for a `lambda` created in a
+    /// function.
+    captured_scopes: Vec<CapturedScope>,
+    dialect: Dialect,
     map: Arc<Mutex<CodeMap>>,
-    stmt: DefCompiled,
+    stmt: Arc<DefCompiled>,
 }
 
 impl Def {
@@ -160,6 +185,21 @@ impl Def {
         stmt: DefCompiled,
         map: Arc<Mutex<CodeMap>>,
         env: Environment,
+        dialect: Dialect,
+    ) -> Value {
+        Def::new_lambda(module, signature, stmt, map, env, Vec::new(), dialect)
+    }
+
+    /// Create a function which resolves the names not bound in its body first in
+    /// `captured_scopes`, then in the module environment `env`.
+    pub fn new_lambda(
+        module: String,
+        signature: Vec<FunctionParameter>,
+        stmt: DefCompiled,
+        map: Arc<Mutex<CodeMap>>,
+        env: Environment,
+        captured_scopes: Vec<CapturedScope>,
+        dialect: Dialect,
     ) -> Value {
         // This can be implemented by delegating to `Function::new`,
         // but having a separate type allows slight more efficient implementation
@@ -167,8 +207,10 @@ impl Def {
         Value::new(Def {
             function_type: FunctionType::Def(stmt.name.node.clone(), module),
             signature,
-            stmt,
+            stmt: Arc::new(stmt),
             captured_env: env,
+            captured_scopes,
+            dialect,
             map,
         })
     }
@@ -182,7 +224,13 @@ impl TypedValue for Def {
     fn values_for_descendant_check_and_freeze<'a>(
         &'a self,
     ) -> Box<dyn Iterator<Item = Value> + 'a> {
-        Box::new(iter::empty())
+        let values: Vec<Value> = self
+            .captured_scopes
+            .iter()
+            .flat_map(|scope| scope.bindings())
+            .map(|(_, value)| value)
+            .collect();
+        Box::new(values.into_iter())
     }
 
     fn to_str(&self) -> String {
@@ -193,24 +241,52 @@ impl TypedValue for Def {
         function::repr(&self.function_type, &self.signature)
     }
 
+    fn share(&self) -> Result<FrozenValue, ValueError> {
+        // The variables of the inner scopes shadow those of the outer ones.
+        let mut captured_locals = HashMap::new();
+        for scope in &self.captured_scopes {
+            for (name, value) in scope.bindings() {
+                if let Entry::Vacant(e) = captured_locals.entry(name) {
+                    e.insert(value.share()?);
+                }
+            }
+        }
+        Ok(FrozenValue::new(FrozenDef {
+            signature: self
+                .signature
+                .iter()
+                .map(FunctionParameter::share)
+                .collect::<Result<_, _>>()?,
+            function_type: self.function_type.clone(),
+            captured_env: CapturedEnvironment::share(&self.captured_env)?,
+            captured_locals,
+            dialect: self.dialect,
+            map: self.map.clone(),
+            stmt: self.stmt.clone(),
+        }))
+    }
+
     fn call(
         &self,
         call_stack: &CallStack,
         type_values: TypeValues,
         positional: Vec<Value>,
-        named: LinkedHashMap<String, Value>,
+        named: OrderedMap<String, Value>,
         args: Option<Value>,
         kwargs: Option<Value>,
     ) -> ValueResult {
         // argument binding
         let mut ctx = EvaluationContext {
             call_stack: call_stack.to_owned(),
+            integer_overflow_mode: self.captured_env.integer_overflow_mode(),
             env: EvaluationContextEnvironment::Function(
                 self.captured_env.clone(),
                 IndexedLocals::new(&self.stmt.local_names_to_indices),
+                &self.captured_scopes,
             ),
             type_values,
             map: self.map.clone(),
+            dialect: self.dialect,
         };
 
         let mut parser = function::ParameterParser::new(
@@ -222,7 +298,7 @@ impl TypedValue for Def {
             kwargs,
         )?;
 
-        for s in &self.signature {
+        for (s, &slot) in self.signature.iter().zip(&self.stmt.param_slots) {
             let (name, v) = match s {
                 FunctionParameter::Normal(ref name) => (name, parser.next_normal(name)?),
                 FunctionParameter::WithDefaultValue(ref name, ref default_value) => {
@@ -232,11 +308,11 @@ impl TypedValue for Def {
                 FunctionParameter::KWArgsDict(ref name) => {
                     (name, parser.next_kwargs_dict().try_into().unwrap())
                 }
-                FunctionParameter::Optional(..) => {
-                    unreachable!("optional parameters only exist in native functions")
+                FunctionParameter::Optional(..) | FunctionParameter::WithStarlarkDefault(..) => {
+                    unreachable!("parameter only exists in native functions")
                 }
             };
-            if let Err(x) = ctx.env.set(name, v) {
+            if let Err(x) = ctx.env.set_slot(slot, name, v) {
                 return Err(x.into());
             }
         }
@@ -245,8 +321,56 @@ impl TypedValue for Def {
 
         match eval_stmt(&self.stmt.suite, &mut ctx) {
             Err(EvalException::Return(_s, ret)) => Ok(ret),
-            Err(x) => Err(ValueError::DiagnosedError(x.into())),
+            Err(EvalException::TracedError(d, traceback)) => {
+                Err(ValueError::TracedError(d, traceback))
+            }
+            // Raised by an expression of this function rather than by a call.
+            Err(x) => {
+                let d: Diagnostic = x.into();
+                let primary = d.spans.iter().find(|l| l.style == SpanStyle::Primary);
+                let location = primary.or_else(|| d.spans.first()).map(|label| {
+                    let loc = { self.map.lock().unwrap().look_up_pos(label.span.low()) };
+                    (loc.file.name().to_owned(), loc.position.line + 1)
+                });
+                let traceback = call_stack.traceback(&self.map, location);
+                Err(ValueError::TracedError(d, traceback))
+            }
             Ok(..) => Ok(Value::new(NoneType::None)),
         }
     }
 }
+
+/// A shared [`Def`], see [`Value::share`]. The code of the function is shared, only its
+/// signature, module environment and captured values are copied when it is thawed.
+struct FrozenDef {
+    signature: Vec<FrozenFunctionParameter>,
+    function_type: FunctionType,
+    captured_env: CapturedEnvironment,
+    captured_locals: HashMap<String, FrozenValue>,
+    dialect: Dialect,
+    map: Arc<Mutex<CodeMap>>,
+    stmt: Arc<DefCompiled>,
+}
+
+impl Thaw for FrozenDef {
+    fn thaw(self: Arc<Self>) -> Value {
+        Value::new(Def {
+            signature: self
+                .signature
+                .iter()
+                .map(FrozenFunctionParameter::to_parameter)
+                .collect(),
+            function_type: self.function_type.clone(),
+            captured_env: self.captured_env.to_environment(),
+            captured_scopes: vec![CapturedScope::from_values(
+                self.captured_locals
+                    .iter()
+                    .map(|(name, value)| (name.clone(), value.to_value()))
+                    .collect(),
+            )],
+            dialect: self.dialect,
+            map: self.map.clone(),
+            stmt: self.stmt.clone(),
+        })
+    }
+}
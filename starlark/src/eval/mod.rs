@@ -18,26 +18,32 @@
 //!
 //! All evaluation function can evaluate the full Starlark language (i.e. Bazel's
 //! .bzl files) or the BUILD file dialect (i.e. used to interpret Bazel's BUILD file).
-//! The BUILD dialect does not allow `def` statements.
+//! The BUILD dialect does not allow `def` statements. Language extensions such as `while`
+//! loops or recursion can be enabled through the fields of
+//! [`Dialect`](crate::syntax::dialect::Dialect).
 use crate::environment::{Environment, EnvironmentError, TypeValues};
-use crate::eval::call_stack::CallStack;
-use crate::eval::def::Def;
+use crate::eval::call_stack::{CallStack, Traceback};
+use crate::eval::def::{Def, DefCompiled};
+use crate::eval::options::{EvaluationLimits, EvaluationOptions};
 use crate::syntax::ast::*;
 use crate::syntax::ast::{AstExpr, AstStatement};
 use crate::syntax::dialect::Dialect;
 use crate::syntax::errors::SyntaxError;
 use crate::syntax::lexer::{LexerIntoIter, LexerItem};
 use crate::syntax::parser::{parse, parse_file, parse_lexer};
+use crate::values::bytes::Bytes;
 use crate::values::error::ValueError;
 use crate::values::function::{FunctionParameter, WrappedMethod};
+use crate::values::int::IntegerOverflowMode;
 use crate::values::none::NoneType;
+use crate::values::OrderedMap;
 use crate::values::*;
 use codemap::{CodeMap, Span, Spanned};
 use codemap_diagnostic::{Diagnostic, Level, SpanLabel, SpanStyle};
-use linked_hash_map::LinkedHashMap;
 use std::cell::RefCell;
 use std::cmp::Ordering;
 use std::collections::HashMap;
+use std::iter;
 use std::rc::Rc;
 use std::sync::{Arc, Mutex};
 
@@ -65,6 +71,10 @@ pub const INCORRECT_LEFT_VALUE_ERROR_CODE: &str = "CE03";
 pub const INCORRECT_UNPACK_ERROR_CODE: &str = "CE04";
 #[doc(hidden)]
 pub const RECURSION_ERROR_CODE: &str = "CE05";
+#[doc(hidden)]
+pub const LOAD_CYCLE_ERROR_CODE: &str = "CE07";
+#[doc(hidden)]
+pub const BIG_INTEGER_ERROR_CODE: &str = "CE08";
 
 #[doc(hidden)]
 #[derive(Debug, Clone)]
@@ -75,12 +85,16 @@ pub enum EvalException {
     Return(Span, Value),
     // Error bubbling up as diagnostics
     DiagnosedError(Diagnostic),
+    // Error raised inside a function bubbling up, with the functions being executed
+    TracedError(Diagnostic, Traceback),
     // Expression used as left value cannot be assigned
     IncorrectLeftValue(Span),
     // Incorrect number of value to unpack (expected, got)
     IncorrectNumberOfValueToUnpack(Span, i64, i64),
     // Recursion
     Recursion(Span, String, CallStack),
+    // Module loaded while its own loads are in progress (load statement, chain of modules)
+    LoadCycle(Span, Vec<String>),
 }
 
 impl From<Diagnostic> for EvalException {
@@ -124,10 +138,59 @@ fn t<T, E: SyntaxError, S: AsSpan>(r: Result<T, E>, spanned: &S) -> Result<T, Ev
     }
 }
 
+/// Convert the error of mutating the value of `receiver` to a spanned evaluation exception,
+/// naming the module which froze that value if it is a global of another module, or a
+/// value reachable from one.
+fn t_mutate<T, S: AsSpan>(
+    r: Result<T, ValueError>,
+    receiver: &AstExpr,
+    context: &EvaluationContext,
+    spanned: &S,
+) -> Result<T, EvalException> {
+    fn frozen_in_module(expr: &AstExpr, context: &EvaluationContext) -> Option<String> {
+        match expr.node {
+            Expr::Identifier(ref name) => context.env.frozen_in_module(&name.node),
+            Expr::Dot(ref e, ..) | Expr::ArrayIndirection(ref e, ..) => {
+                frozen_in_module(e, context)
+            }
+            _ => None,
+        }
+    }
+
+    t(
+        r.map_err(|e| match e {
+            ValueError::CannotMutateImmutableValue => match frozen_in_module(receiver, context) {
+                Some(module) => ValueError::CannotMutateFrozenValue { module },
+                None => e,
+            },
+            e => e,
+        }),
+        spanned,
+    )
+}
+
+impl EvalException {
+    /// Convert this exception into a [`Diagnostic`], and return the [`Traceback`] of an
+    /// error raised inside a function, whose frames are also listed in the message.
+    pub fn into_diagnostic_with_traceback(self) -> (Diagnostic, Option<Traceback>) {
+        match self {
+            EvalException::TracedError(mut d, traceback) => {
+                traceback.add_to(&mut d);
+                (d, Some(traceback))
+            }
+            e => (e.into(), None),
+        }
+    }
+}
+
 impl Into<Diagnostic> for EvalException {
     fn into(self) -> Diagnostic {
         match self {
             EvalException::DiagnosedError(e) => e,
+            EvalException::TracedError(mut e, traceback) => {
+                traceback.add_to(&mut e);
+                e
+            }
             EvalException::Break(s) => Diagnostic {
                 level: Level::Error,
                 message: "Break statement used outside of a loop".to_owned(),
@@ -181,7 +244,7 @@ impl Into<Diagnostic> for EvalException {
             EvalException::Recursion(s, f, stack) => Diagnostic {
                 level: Level::Error,
                 message: format!(
-                    "Function {} recursed, call stack:{}",
+                    "Function {} recursed (see Dialect::allow_recursion), call stack:{}",
                     f,
                     stack.print_with_newline_before()
                 ),
@@ -192,16 +255,66 @@ impl Into<Diagnostic> for EvalException {
                     label: Some("Recursive call".to_owned()),
                 }],
             },
+            EvalException::LoadCycle(s, modules) => Diagnostic {
+                level: Level::Error,
+                message: format!("Cycle in load() statements: {}", modules.join(" -> ")),
+                code: Some(LOAD_CYCLE_ERROR_CODE.to_owned()),
+                spans: vec![SpanLabel {
+                    span: s,
+                    style: SpanStyle::Primary,
+                    label: Some("Load cycle".to_owned()),
+                }],
+            },
         }
     }
 }
 
 /// A trait for loading file using the load statement path.
+///
+/// The path is passed as written in the `load` statement, so embedders can resolve it with
+/// their own notion of labels, see [`SimpleFileLoader`](crate::eval::simple::SimpleFileLoader)
+/// for a loader reading files relative to the current directory.
+///
+/// The returned environment is frozen before its symbols are imported. A loader may return
+/// the same environment for several loads of the same path, to only evaluate it once.
+///
+/// Modules are identified by their file name in the [`CodeMap`], which is expected to be
+/// the path they are loaded with: a module loading a path while that path is still
+/// loading is reported as a cycle instead of calling the loader.
 pub trait FileLoader: 'static {
     /// Open the file given by the load statement `path`.
     fn load(&self, path: &str) -> Result<Environment, EvalException>;
 }
 
+// Modules of this thread with a `load()` in progress, outermost first.
+thread_local!(static LOADING_MODULES: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) });
+
+/// Removes the module pushed by [`start_load`] on drop.
+#[must_use]
+struct LoadGuard;
+
+impl Drop for LoadGuard {
+    fn drop(&mut self) {
+        LOADING_MODULES.with(|m| m.borrow_mut().pop());
+    }
+}
+
+/// Record that `module` loads `path`, or return the cycle `path -> ... -> module -> path`
+/// if `path` is `module` itself or one of the modules loading it.
+fn start_load(module: &str, path: &str) -> Result<LoadGuard, Vec<String>> {
+    LOADING_MODULES.with(|m| {
+        let mut m = m.borrow_mut();
+        m.push(module.to_owned());
+        if let Some(i) = m.iter().position(|x| x == path) {
+            let mut cycle = m[i..].to_vec();
+            cycle.push(path.to_owned());
+            m.pop();
+            return Err(cycle);
+        }
+        Ok(LoadGuard)
+    })
+}
+
 /// Starlark `def` or comprehension local variables
 pub(crate) struct IndexedLocals<'a> {
     // name to index is needed for nested contexts only
@@ -214,14 +327,15 @@ pub(crate) struct IndexedLocals<'a> {
     /// need it (the other one have been replaced during the parsing).
     /// `locals` is used to optimize access speed to local variable
     /// (versus looking into a hashmap).
-    locals: RefCell<Vec<Option<Value>>>,
+    /// They are shared with the `lambda`s created in this scope.
+    locals: Rc<RefCell<Vec<Option<Value>>>>,
 }
 
 impl<'a> IndexedLocals<'a> {
     fn new(name_to_index: &'a HashMap<String, usize>) -> IndexedLocals<'a> {
         IndexedLocals {
             name_to_index,
-            locals: RefCell::new(vec![None; name_to_index.len()]),
+            locals: Rc::new(RefCell::new(vec![None; name_to_index.len()])),
         }
     }
 
@@ -253,14 +367,67 @@ impl<'a> IndexedLocals<'a> {
     fn set_slot(&self, slot: usize, _name: &str, value: Value) {
         self.locals.borrow_mut()[slot] = Some(value);
     }
+
+    /// Capture this scope for a `lambda`, which sees the later assignments of its variables.
+    fn capture(&self) -> CapturedScope {
+        CapturedScope {
+            name_to_index: self.name_to_index.clone(),
+            locals: self.locals.clone(),
+        }
+    }
+}
+
+/// Local variables of an enclosing function or comprehension captured by a `lambda`.
+///
+/// The variables are shared with the scope: the `lambda` reads their value when it is called.
+#[derive(Clone)]
+pub(crate) struct CapturedScope {
+    name_to_index: HashMap<String, usize>,
+    locals: Rc<RefCell<Vec<Option<Value>>>>,
+}
+
+impl CapturedScope {
+    /// A scope binding `values`, e.g. the variables of a thawed function.
+    pub(crate) fn from_values(values: HashMap<String, Value>) -> CapturedScope {
+        let mut name_to_index = HashMap::with_capacity(values.len());
+        let mut locals = Vec::with_capacity(values.len());
+        for (name, value) in values {
+            name_to_index.insert(name, locals.len());
+            locals.push(Some(value));
+        }
+        CapturedScope {
+            name_to_index,
+            locals: Rc::new(RefCell::new(locals)),
+        }
+    }
+
+    /// The value of `name` if it is a variable of this scope.
+    fn get(&self, name: &str) -> Option<Result<Value, EnvironmentError>> {
+        let slot = *self.name_to_index.get(name)?;
+        Some(match self.locals.borrow()[slot].clone() {
+            Some(value) => Ok(value),
+            None => Err(EnvironmentError::LocalVariableReferencedBeforeAssignment(
+                name.to_owned(),
+            )),
+        })
+    }
+
+    /// The variables currently bound in this scope.
+    pub(crate) fn bindings(&self) -> Vec<(String, Value)> {
+        let locals = self.locals.borrow();
+        self.name_to_index
+            .iter()
+            .filter_map(|(name, slot)| Some((name.clone(), locals[*slot].clone()?)))
+            .collect()
+    }
 }
 
 /// Stacked environment for [`EvaluationContext`].
 pub(crate) enum EvaluationContextEnvironment<'a> {
     /// Module-level
     Module(Environment, Rc<dyn FileLoader>),
-    /// Function-level
-    Function(Environment, IndexedLocals<'a>),
+    /// Function-level, with the scopes captured by a `lambda`, innermost first
+    Function(Environment, IndexedLocals<'a>, &'a [CapturedScope]),
     /// Scope inside function, e. g. list comprenension
     Nested(&'a EvaluationContextEnvironment<'a>, IndexedLocals<'a>),
 }
@@ -291,10 +458,15 @@ impl<'a> EvaluationContextEnvironment<'a> {
     fn get(&self, name: &str) -> Result<Value, EnvironmentError> {
         match self {
             EvaluationContextEnvironment::Module(env, ..) => env.get(name),
-            EvaluationContextEnvironment::Function(env, locals) => match locals.get(name)? {
-                Some(v) => Ok(v),
-                None => env.get(name),
-            },
+            EvaluationContextEnvironment::Function(env, locals, captured) => {
+                if let Some(v) = locals.get(name)? {
+                    return Ok(v);
+                }
+                match captured.iter().find_map(|scope| scope.get(name)) {
+                    Some(v) => v,
+                    None => env.get(name),
+                }
+            }
             EvaluationContextEnvironment::Nested(parent, locals) => match locals.get(name)? {
                 Some(v) => Ok(v),
                 None => parent.get(name),
@@ -304,16 +476,25 @@ impl<'a> EvaluationContextEnvironment<'a> {
 
     fn get_slot(&self, _slot: usize, name: &str) -> Result<Value, EnvironmentError> {
         match self {
-            EvaluationContextEnvironment::Function(_, locals)
+            EvaluationContextEnvironment::Function(_, locals, _)
             | EvaluationContextEnvironment::Nested(_, locals) => locals.get_slot(_slot, name),
             _ => unreachable!("slot in non-indexed environment"),
         }
     }
 
+    /// Get a local variable of the enclosing function by its slot.
+    fn get_function_slot(&self, slot: usize, name: &str) -> Result<Value, EnvironmentError> {
+        match self {
+            EvaluationContextEnvironment::Function(_, locals, _) => locals.get_slot(slot, name),
+            EvaluationContextEnvironment::Nested(parent, _) => parent.get_function_slot(slot, name),
+            _ => unreachable!("slot in non-indexed environment"),
+        }
+    }
+
     fn set(&self, name: &str, value: Value) -> Result<(), EnvironmentError> {
         match self {
             EvaluationContextEnvironment::Module(env, ..) => env.set(name, value),
-            EvaluationContextEnvironment::Function(_, locals) => {
+            EvaluationContextEnvironment::Function(_, locals, _) => {
                 locals.set(name, value);
                 Ok(())
             }
@@ -326,7 +507,7 @@ impl<'a> EvaluationContextEnvironment<'a> {
 
     fn set_slot(&self, slot: usize, name: &str, value: Value) -> Result<(), EnvironmentError> {
         match self {
-            EvaluationContextEnvironment::Function(_, locals)
+            EvaluationContextEnvironment::Function(_, locals, _)
             | EvaluationContextEnvironment::Nested(_, locals) => {
                 locals.set_slot(slot, name, value);
                 Ok(())
@@ -335,6 +516,49 @@ impl<'a> EvaluationContextEnvironment<'a> {
         }
     }
 
+    /// Local scopes visible from this scope, innermost first, to be captured by a `lambda`.
+    fn captured_scopes(&self) -> Vec<CapturedScope> {
+        match self {
+            EvaluationContextEnvironment::Module(..) => Vec::new(),
+            EvaluationContextEnvironment::Function(_, locals, captured) => {
+                let mut r = vec![locals.capture()];
+                r.extend(captured.iter().cloned());
+                r
+            }
+            EvaluationContextEnvironment::Nested(parent, locals) => {
+                let mut r = vec![locals.capture()];
+                r.extend(parent.captured_scopes());
+                r
+            }
+        }
+    }
+
+    /// Name of the module which froze the value of the global `name`, if `name` is not
+    /// shadowed by a local, see [`Environment::frozen_in_module`].
+    fn frozen_in_module(&self, name: &str) -> Option<String> {
+        match self {
+            EvaluationContextEnvironment::Module(env, ..) => env.frozen_in_module(name),
+            EvaluationContextEnvironment::Function(env, locals, captured) => {
+                if locals.name_to_index.contains_key(name)
+                    || captured
+                        .iter()
+                        .any(|scope| scope.name_to_index.contains_key(name))
+                {
+                    None
+                } else {
+                    env.frozen_in_module(name)
+                }
+            }
+            EvaluationContextEnvironment::Nested(parent, locals) => {
+                if locals.name_to_index.contains_key(name) {
+                    None
+                } else {
+                    parent.frozen_in_module(name)
+                }
+            }
+        }
+    }
+
     fn assert_module_env(&self) -> &Environment {
         match self {
             EvaluationContextEnvironment::Module(env, ..) => env,
@@ -355,6 +579,10 @@ pub struct EvaluationContext<'a> {
     type_values: TypeValues,
     call_stack: CallStack,
     map: Arc<Mutex<CodeMap>>,
+    // Dialect of the code being evaluated.
+    dialect: Dialect,
+    // What `int` arithmetic does on overflow, from the environment.
+    integer_overflow_mode: IntegerOverflowMode,
 }
 
 impl<'a> EvaluationContext<'a> {
@@ -363,12 +591,16 @@ impl<'a> EvaluationContext<'a> {
         type_values: TypeValues,
         loader: T,
         map: Arc<Mutex<CodeMap>>,
+        options: &EvaluationOptions,
+        dialect: Dialect,
     ) -> Self {
         EvaluationContext {
-            call_stack: CallStack::default(),
+            call_stack: CallStack::with_limits(EvaluationLimits::new(options)),
+            integer_overflow_mode: env.integer_overflow_mode(),
             env: EvaluationContextEnvironment::Module(env, Rc::new(loader)),
             type_values,
             map,
+            dialect,
         }
     }
 
@@ -378,6 +610,8 @@ impl<'a> EvaluationContext<'a> {
             type_values: self.type_values.clone(),
             call_stack: self.call_stack.clone(),
             map: self.map.clone(),
+            dialect: self.dialect,
+            integer_overflow_mode: self.integer_overflow_mode,
         }
     }
 }
@@ -449,7 +683,7 @@ fn eval_call<'a>(
     context: &EvaluationContext,
 ) -> EvalResult {
     let npos = eval_vector!(pos, context);
-    let mut nnamed = LinkedHashMap::new();
+    let mut nnamed = OrderedMap::new();
     for &(ref k, ref v) in named.iter() {
         nnamed.insert(k.node.clone(), eval_expr(v, context)?);
     }
@@ -463,23 +697,69 @@ fn eval_call<'a>(
     } else {
         None
     };
-    let f = eval_expr(e, context)?;
+    // A method called right away is not wrapped with its object in a `WrappedMethod`, the
+    // object is inserted before the positional arguments instead. The method has the same
+    // `function_id` and representation as the wrapper.
+    let (f, self_obj) = match e.node {
+        Expr::Dot(ref receiver, ref name) => {
+            resolve_dot(e, eval_expr(receiver, context)?, name, context)?
+        }
+        _ => (eval_expr(e, context)?, None),
+    };
+    let npos = match self_obj {
+        Some(self_obj) => iter::once(self_obj).chain(npos.into_iter()).collect(),
+        None => npos,
+    };
+    t(context.call_stack.check_limits(), this)?;
+    t(context.call_stack.check_depth(), this)?;
     let mut new_stack = context.call_stack.clone();
-    if context.call_stack.contains(f.function_id()) {
+    if !context.dialect.allow_recursion && context.call_stack.contains(f.function_id()) {
         Err(EvalException::Recursion(this.span, f.to_repr(), new_stack))
     } else {
         new_stack.push(f.clone(), context.map.clone(), this.span.low());
-        t(
-            eval_expr(e, context)?.call(
+        let call = || {
+            f.call(
                 &new_stack,
                 context.type_values.clone(),
                 npos,
                 nnamed,
                 nargs,
                 nkwargs,
-            ),
-            this,
-        )
+            )
+        };
+        let r = match new_stack.profiling() {
+            Some(profiling) => profiling.profile(&f, &new_stack, call),
+            None => call(),
+        };
+        // Raised in a function, which already has its traceback
+        if let Err(ValueError::TracedError(d, traceback)) = r {
+            return Err(EvalException::TracedError(d, traceback));
+        }
+        let raised_here = match r {
+            Err(ValueError::DiagnosedError(..)) | Ok(..) => false,
+            Err(..) => !context.call_stack.is_empty(),
+        };
+        let r = match e.node {
+            // A method mutating the object it is called on
+            Expr::Dot(ref receiver, ..) => t_mutate(r, receiver, context, this),
+            _ => t(r, this),
+        };
+        match r {
+            Err(EvalException::DiagnosedError(d)) if raised_here => {
+                let traceback = if f.downcast_ref::<Def>().is_some() {
+                    // The arguments do not match the parameters of a Starlark function.
+                    let loc = { context.map.lock().unwrap().look_up_pos(this.span.low()) };
+                    context.call_stack.traceback(
+                        &context.map,
+                        Some((loc.file.name().to_owned(), loc.position.line + 1)),
+                    )
+                } else {
+                    new_stack.traceback(&context.map, None)
+                };
+                Err(EvalException::TracedError(d, traceback))
+            }
+            r => r,
+        }
     }
 }
 
@@ -490,15 +770,29 @@ fn eval_dot<'a>(
     context: &EvaluationContext,
 ) -> EvalResult {
     let left = eval_expr(e, context)?;
+    match resolve_dot(this, left, s, context)? {
+        // Insert self so the method see the object it is acting on
+        (method, Some(self_obj)) => Ok(WrappedMethod::new(self_obj, method)),
+        (v, None) => Ok(v),
+    }
+}
+
+/// Resolve `left.s`: a method of the type of `left` along with `left`, which must be
+/// inserted as the first argument of its calls, or an attribute of `left`.
+fn resolve_dot(
+    this: &AstExpr,
+    left: Value,
+    s: &AstString,
+    context: &EvaluationContext,
+) -> Result<(Value, Option<Value>), EvalException> {
     if let Some(v) = context.type_values.get_type_value(&left, &s.node) {
         if v.get_type() == "function" {
-            // Insert self so the method see the object it is acting on
-            Ok(WrappedMethod::new(left, v))
+            Ok((v, Some(left)))
         } else {
-            Ok(v)
+            Ok((v, None))
         }
     } else {
-        t(left.get_attr(&s.node), this)
+        Ok((t(left.get_attr(&s.node), this)?, None))
     }
 }
 
@@ -626,6 +920,23 @@ fn transform(
     }
 }
 
+/// The error of evaluating an integer literal overflowing i64 without the `bigint` feature,
+/// kept out of `eval_expr` so that its stack frame stays small.
+#[cfg(not(feature = "bigint"))]
+#[inline(never)]
+fn big_integer_unsupported(literal: &AstString) -> EvalException {
+    EvalException::DiagnosedError(Diagnostic {
+        level: Level::Error,
+        message: "big integers require the bigint feature".to_owned(),
+        code: Some(BIG_INTEGER_ERROR_CODE.to_owned()),
+        spans: vec![SpanLabel {
+            span: literal.span,
+            style: SpanStyle::Primary,
+            label: Some(format!("integer literal {} overflows int", literal.node)),
+        }],
+    })
+}
+
 // Evaluate the AST element, i.e. mutate the environment and return an evaluation result
 fn eval_expr(expr: &AstExpr, context: &EvaluationContext) -> EvalResult {
     match expr.node {
@@ -646,10 +957,21 @@ fn eval_expr(expr: &AstExpr, context: &EvaluationContext) -> EvalResult {
         }
         Expr::Identifier(ref i) => t(context.env.get(&i.node), i),
         Expr::Slot(slot, ref i) => t(context.env.get_slot(slot, &i.node), i),
+        Expr::FunctionSlot(slot, ref i) => t(context.env.get_function_slot(slot, &i.node), i),
         Expr::IntLiteral(ref i) => Ok(Value::new(i.node)),
+        // The lexer only produces valid digits.
+        #[cfg(feature = "bigint")]
+        Expr::BigIntLiteral(ref i) => Ok(Value::from(bigint::parse(&i.node, 10).unwrap())),
+        // Only built by hand: without the feature the lexer rejects integers overflowing i64.
+        #[cfg(not(feature = "bigint"))]
+        Expr::BigIntLiteral(ref i) => Err(big_integer_unsupported(i)),
         Expr::StringLiteral(ref s) => Ok(Value::new(s.node.clone())),
+        Expr::BytesLiteral(ref b) => Ok(Value::new(Bytes::new(b.node.clone()))),
         Expr::Not(ref s) => Ok(Value::new(!eval_expr(s, context)?.to_bool())),
-        Expr::Minus(ref s) => t(eval_expr(s, context)?.minus(), expr),
+        Expr::Minus(ref s) => t(
+            context.integer_overflow_mode.minus(&eval_expr(s, context)?),
+            expr,
+        ),
         Expr::Plus(ref s) => t(eval_expr(s, context)?.plus(), expr),
         Expr::Op(BinOp::Or, ref l, ref r) => {
             let l = eval_expr(l, context)?;
@@ -693,18 +1015,30 @@ fn eval_expr(expr: &AstExpr, context: &EvaluationContext) -> EvalResult {
                 .map(|r| Value::new(!r)),
             expr,
         ),
-        Expr::Op(BinOp::Substraction, ref l, ref r) => {
-            t(eval_expr(l, context)?.sub(eval_expr(r, context)?), expr)
-        }
-        Expr::Op(BinOp::Addition, ref l, ref r) => {
-            t(eval_expr(l, context)?.add(eval_expr(r, context)?), expr)
-        }
-        Expr::Op(BinOp::Multiplication, ref l, ref r) => {
-            t(eval_expr(l, context)?.mul(eval_expr(r, context)?), expr)
-        }
-        Expr::Op(BinOp::Percent, ref l, ref r) => {
-            t(eval_expr(l, context)?.percent(eval_expr(r, context)?), expr)
-        }
+        Expr::Op(BinOp::Substraction, ref l, ref r) => t(
+            context
+                .integer_overflow_mode
+                .sub(&eval_expr(l, context)?, eval_expr(r, context)?),
+            expr,
+        ),
+        Expr::Op(BinOp::Addition, ref l, ref r) => t(
+            context
+                .integer_overflow_mode
+                .add(&eval_expr(l, context)?, eval_expr(r, context)?),
+            expr,
+        ),
+        Expr::Op(BinOp::Multiplication, ref l, ref r) => t(
+            context
+                .integer_overflow_mode
+                .mul(&eval_expr(l, context)?, eval_expr(r, context)?),
+            expr,
+        ),
+        Expr::Op(BinOp::Percent, ref l, ref r) => t(
+            context
+                .integer_overflow_mode
+                .percent(&eval_expr(l, context)?, eval_expr(r, context)?),
+            expr,
+        ),
         Expr::Op(BinOp::Division, ref l, ref r) => {
             let l = eval_expr(l, context)?;
             let r = eval_expr(r, context)?;
@@ -717,7 +1051,9 @@ fn eval_expr(expr: &AstExpr, context: &EvaluationContext) -> EvalResult {
             Err(EvalException::DiagnosedError(err.to_diagnostic(expr.span)))
         }
         Expr::Op(BinOp::FloorDivision, ref l, ref r) => t(
-            eval_expr(l, context)?.floor_div(eval_expr(r, context)?),
+            context
+                .integer_overflow_mode
+                .floor_div(&eval_expr(l, context)?, eval_expr(r, context)?),
             expr,
         ),
         Expr::Op(BinOp::Pipe, ref l, ref r) => {
@@ -735,7 +1071,7 @@ fn eval_expr(expr: &AstExpr, context: &EvaluationContext) -> EvalResult {
             Ok(Value::from(r))
         }
         Expr::Dict(ref v) => {
-            let mut r = dict::Dictionary::new();
+            let mut r = dict::Dictionary::with_capacity(v.len());
             for s in v.iter() {
                 t(
                     r.set_at(eval_expr(&s.0, context)?, eval_expr(&s.1, context)?),
@@ -751,9 +1087,44 @@ fn eval_expr(expr: &AstExpr, context: &EvaluationContext) -> EvalResult {
             }
             make_set(values, context, expr.span)
         }
-        Expr::ListComprehension(..) | Expr::DictComprehension(..) | Expr::SetComprehension(..) => unreachable!(),
+        Expr::ListComprehension(..) | Expr::DictComprehension(..) | Expr::SetComprehension(..) => {
+            unreachable!()
+        }
         Expr::ComprehensionCompiled(ref e) => e.eval(expr.span, context),
+        Expr::Lambda(..) => unreachable!(),
+        Expr::LambdaCompiled(ref def) => {
+            // The enclosing function locals, like module variables, are looked up when the
+            // `lambda` is called.
+            Ok(Def::new_lambda(
+                context.env.env().name(),
+                eval_signature(def, context)?,
+                def.clone(),
+                context.map.clone(),
+                context.env.env().clone(),
+                context.env.captured_scopes(),
+                context.dialect,
+            ))
+        }
+    }
+}
+
+/// Evaluate the parameters of a `def` or `lambda`, i.e. their default values.
+fn eval_signature(
+    def: &DefCompiled,
+    context: &EvaluationContext,
+) -> Result<Vec<FunctionParameter>, EvalException> {
+    let mut p = Vec::new();
+    for x in &def.params {
+        p.push(match x.node {
+            Parameter::Normal(ref n) => FunctionParameter::Normal(n.node.clone()),
+            Parameter::WithDefaultValue(ref n, ref v) => {
+                FunctionParameter::WithDefaultValue(n.node.clone(), eval_expr(v, context)?)
+            }
+            Parameter::Args(ref n) => FunctionParameter::ArgsArray(n.node.clone()),
+            Parameter::KWArgs(ref n) => FunctionParameter::KWArgsDict(n.node.clone()),
+        })
     }
+    Ok(p)
 }
 
 // Perform an assignment on the LHS represented by this AST element
@@ -780,7 +1151,12 @@ fn set_expr(expr: &AstExpr, context: &EvaluationContext, new_value: Value) -> Ev
             }
         }
         Expr::Dot(ref e, ref s) => {
-            t(eval_expr(e, context)?.set_attr(&(s.node), new_value), expr)?;
+            t_mutate(
+                eval_expr(e, context)?.set_attr(&(s.node), new_value),
+                e,
+                context,
+                expr,
+            )?;
             ok
         }
         Expr::Identifier(ref i) => {
@@ -792,8 +1168,10 @@ fn set_expr(expr: &AstExpr, context: &EvaluationContext, new_value: Value) -> Ev
             ok
         }
         Expr::ArrayIndirection(ref e, ref idx) => {
-            t(
+            t_mutate(
                 eval_expr(e, context)?.set_at(eval_expr(idx, context)?, new_value),
+                e,
+                context,
                 expr,
             )?;
             ok
@@ -835,22 +1213,32 @@ fn eval_stmt(stmt: &AstStatement, context: &EvaluationContext) -> EvalResult {
             set_expr(lhs, context, rhs)
         }
         Statement::Assign(ref lhs, AssignOp::Increment, ref rhs) => {
-            eval_assign_modify(stmt, lhs, rhs, context, Value::add)
+            eval_assign_modify(stmt, lhs, rhs, context, |l, r| {
+                context.integer_overflow_mode.add(l, r)
+            })
         }
         Statement::Assign(ref lhs, AssignOp::Decrement, ref rhs) => {
-            eval_assign_modify(stmt, lhs, rhs, context, Value::sub)
+            eval_assign_modify(stmt, lhs, rhs, context, |l, r| {
+                context.integer_overflow_mode.sub(l, r)
+            })
         }
         Statement::Assign(ref lhs, AssignOp::Multiplier, ref rhs) => {
-            eval_assign_modify(stmt, lhs, rhs, context, Value::mul)
+            eval_assign_modify(stmt, lhs, rhs, context, |l, r| {
+                context.integer_overflow_mode.mul(l, r)
+            })
         }
         Statement::Assign(ref lhs, AssignOp::Divider, ref rhs) => {
             eval_assign_modify(stmt, lhs, rhs, context, Value::div)
         }
         Statement::Assign(ref lhs, AssignOp::FloorDivider, ref rhs) => {
-            eval_assign_modify(stmt, lhs, rhs, context, Value::floor_div)
+            eval_assign_modify(stmt, lhs, rhs, context, |l, r| {
+                context.integer_overflow_mode.floor_div(l, r)
+            })
         }
         Statement::Assign(ref lhs, AssignOp::Percent, ref rhs) => {
-            eval_assign_modify(stmt, lhs, rhs, context, Value::percent)
+            eval_assign_modify(stmt, lhs, rhs, context, |l, r| {
+                context.integer_overflow_mode.percent(l, r)
+            })
         }
         Statement::If(ref cond, ref st) => {
             if eval_expr(cond, context)?.to_bool() {
@@ -871,6 +1259,10 @@ fn eval_stmt(stmt: &AstStatement, context: &EvaluationContext) -> EvalResult {
             let mut result = Ok(Value::new(NoneType::None));
             iterable.freeze_for_iteration();
             for v in &t(iterable.iter(), &e2.span)? {
+                if let Err(e) = t(context.call_stack.check_limits(), stmt) {
+                    result = Err(e);
+                    break;
+                }
                 set_expr(e1, context, v)?;
                 match eval_stmt(st, context) {
                     Err(EvalException::Break(..)) => break,
@@ -885,31 +1277,44 @@ fn eval_stmt(stmt: &AstStatement, context: &EvaluationContext) -> EvalResult {
             iterable.unfreeze_for_iteration();
             result
         }
-        Statement::DefCompiled(ref stmt) => {
-            let mut p = Vec::new();
-            for x in &stmt.params {
-                p.push(match x.node {
-                    Parameter::Normal(ref n) => FunctionParameter::Normal(n.node.clone()),
-                    Parameter::WithDefaultValue(ref n, ref v) => {
-                        FunctionParameter::WithDefaultValue(n.node.clone(), eval_expr(v, context)?)
-                    }
-                    Parameter::Args(ref n) => FunctionParameter::ArgsArray(n.node.clone()),
-                    Parameter::KWArgs(ref n) => FunctionParameter::KWArgsDict(n.node.clone()),
-                })
+        Statement::While(ref cond, ref st) => {
+            while eval_expr(cond, context)?.to_bool() {
+                t(context.call_stack.check_limits(), stmt)?;
+                match eval_stmt(st, context) {
+                    Err(EvalException::Break(..)) => break,
+                    Err(EvalException::Continue(..)) | Ok(..) => (),
+                    Err(x) => return Err(x),
+                }
             }
+            Ok(Value::new(NoneType::None))
+        }
+        Statement::DefCompiled(ref stmt) => {
             let f = Def::new(
                 context.env.assert_module_env().name(),
-                p,
+                eval_signature(stmt, context)?,
                 stmt.clone(),
                 context.map.clone(),
                 context.env.assert_module_env().clone(),
+                context.dialect,
             );
             t(context.env.set(&stmt.name.node, f.clone()), &stmt.name)?;
             Ok(f)
         }
         Statement::Def(..) => unreachable!(),
         Statement::Load(ref name, ref v) => {
-            let loadenv = context.env.loader().load(name)?;
+            let module = context
+                .map
+                .lock()
+                .unwrap()
+                .look_up_span(stmt.span)
+                .file
+                .name()
+                .to_owned();
+            let loadenv = match start_load(&module, name) {
+                Ok(_guard) => context.env.loader().load(name)?,
+                Err(cycle) => return Err(EvalException::LoadCycle(stmt.span, cycle)),
+            };
+            loadenv.freeze();
             for &(ref new_name, ref orig_name) in v.iter() {
                 t(
                     context.env.assert_module_env().import_symbol(
@@ -958,7 +1363,14 @@ pub fn eval_lexer<
     type_values: TypeValues,
     file_loader: T3,
 ) -> Result<Value, Diagnostic> {
-    let context = EvaluationContext::new(env.clone(), type_values, file_loader, map.clone());
+    let context = EvaluationContext::new(
+        env.clone(),
+        type_values,
+        file_loader,
+        map.clone(),
+        &EvaluationOptions::default(),
+        dialect,
+    );
     match eval_stmt(
         &parse_lexer(map, filename, content, dialect, lexer)?,
         &context,
@@ -989,11 +1401,69 @@ pub fn eval<T: FileLoader + 'static>(
     type_values: TypeValues,
     file_loader: T,
 ) -> Result<Value, Diagnostic> {
-    let context = EvaluationContext::new(env.clone(), type_values, file_loader, map.clone());
-    match eval_stmt(&parse(map, path, content, build)?, &context) {
-        Ok(v) => Ok(v),
-        Err(p) => Err(p.into()),
-    }
+    eval_with_options(
+        map,
+        path,
+        content,
+        build,
+        env,
+        type_values,
+        file_loader,
+        &EvaluationOptions::default(),
+    )
+}
+
+/// Evaluate a string content like [`eval`], within the limits given by `options`.
+///
+/// Files loaded by `load()` statements are evaluated by the `file_loader` and are not
+/// bounded by these limits.
+#[allow(clippy::too_many_arguments)]
+pub fn eval_with_options<T: FileLoader + 'static>(
+    map: &Arc<Mutex<CodeMap>>,
+    path: &str,
+    content: &str,
+    build: Dialect,
+    env: &mut Environment,
+    type_values: TypeValues,
+    file_loader: T,
+    options: &EvaluationOptions,
+) -> Result<Value, Diagnostic> {
+    eval_with_traceback(
+        map,
+        path,
+        content,
+        build,
+        env,
+        type_values,
+        file_loader,
+        options,
+    )
+    .map_err(|(d, _)| d)
+}
+
+/// Evaluate a string content like [`eval_with_options`], and return the [`Traceback`] of an
+/// error raised inside a function with its [`Diagnostic`], to inspect its frames.
+#[allow(clippy::too_many_arguments)]
+pub fn eval_with_traceback<T: FileLoader + 'static>(
+    map: &Arc<Mutex<CodeMap>>,
+    path: &str,
+    content: &str,
+    build: Dialect,
+    env: &mut Environment,
+    type_values: TypeValues,
+    file_loader: T,
+    options: &EvaluationOptions,
+) -> Result<Value, (Diagnostic, Option<Traceback>)> {
+    let context = EvaluationContext::new(
+        env.clone(),
+        type_values,
+        file_loader,
+        map.clone(),
+        options,
+        build,
+    );
+    let stmt = parse(map, path, content, build).map_err(|d| (d, None))?;
+    eval_stmt(&stmt, &context).map_err(EvalException::into_diagnostic_with_traceback)
 }
 
 /// Evaluate a file, mutate the environment accordingly and return the evaluated value.
@@ -1015,7 +1485,14 @@ pub fn eval_file<T: FileLoader + 'static>(
     type_values: TypeValues,
     file_loader: T,
 ) -> Result<Value, Diagnostic> {
-    let context = EvaluationContext::new(env.clone(), type_values, file_loader, map.clone());
+    let context = EvaluationContext::new(
+        env.clone(),
+        type_values,
+        file_loader,
+        map.clone(),
+        &EvaluationOptions::default(),
+        build,
+    );
     match eval_stmt(&parse_file(map, path, build)?, &context) {
         Ok(v) => Ok(v),
         Err(p) => Err(p.into()),
@@ -1024,7 +1501,10 @@ pub fn eval_file<T: FileLoader + 'static>(
 
 pub mod interactive;
 pub mod noload;
+pub mod options;
+pub mod profile;
 pub mod simple;
+pub mod source_map;
 
 pub mod call_stack;
 
@@ -0,0 +1,688 @@
+// Copyright 2019 The Starlark in Rust Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Options bounding the resources used by an evaluation.
+
+use crate::eval::profile::ProfilingContext;
+use crate::values::error::{RuntimeError, ValueError};
+use std::cell::Cell;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Code of the error reported when an evaluation exceeds its
+/// [`timeout`](EvaluationOptions::timeout).
+// EV prefix = EValuation limit
+pub const TIMEOUT_ERROR_CODE: &str = "EV01";
+
+const DEFAULT_CHECK_INTERVAL: u32 = 1000;
+
+const DEFAULT_MAX_CALL_DEPTH: usize = 500;
+
+/// A handle to cancel an in-flight evaluation, e.g. from another thread when the user
+/// asked to stop it, see [`EvaluationOptions::cancellation_token`].
+///
+/// Clones share the same state: cancelling any of them cancels all the evaluations using
+/// one of them. The token is an [interrupt flag](EvaluationOptions::interrupt_flag), which
+/// it can be created from.
+///
+/// # Examples
+///
+/// ```
+/// # use starlark::eval::options::{CancellationToken, EvaluationOptions};
+/// let token = CancellationToken::new();
+/// let options = EvaluationOptions::default().cancellation_token(token.clone());
+/// // ... start the evaluation on another thread, then:
+/// token.cancel();
+/// assert!(token.is_cancelled());
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    /// A token which is not cancelled yet.
+    pub fn new() -> CancellationToken {
+        CancellationToken::default()
+    }
+
+    /// Cancel the evaluations using this token. They abort at their next check point.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether [`cancel`](CancellationToken::cancel) was called.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+impl From<Arc<AtomicBool>> for CancellationToken {
+    fn from(flag: Arc<AtomicBool>) -> CancellationToken {
+        CancellationToken { cancelled: flag }
+    }
+}
+
+/// Receiver of lines written by an evaluation, see [`EvaluationOptions::trace_arguments`],
+/// [`EvaluationOptions::on_warning`] and [`EvaluationOptions::on_print`].
+#[derive(Clone)]
+pub(crate) struct LineHandler(Arc<dyn Fn(&str) + Send + Sync>);
+
+impl LineHandler {
+    pub(crate) fn emit(&self, line: &str) {
+        (self.0)(line)
+    }
+}
+
+impl fmt::Debug for LineHandler {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("LineHandler")
+    }
+}
+
+/// Options of an evaluation, for
+/// [`eval_with_options`](crate::eval::eval_with_options).
+///
+/// The limits are cooperative: they are checked by the evaluator at each function call and
+/// at each iteration of a loop or comprehension, no signal is ever delivered. In particular a long running
+/// native function cannot be interrupted, the limit is only reported once it returns.
+///
+/// # Examples
+///
+/// ```
+/// # use starlark::eval::options::EvaluationOptions;
+/// # use std::time::Duration;
+/// let options = EvaluationOptions::default()
+///     .timeout(Duration::from_secs(10))
+///     .check_interval(100);
+/// ```
+#[derive(Clone, Debug)]
+pub struct EvaluationOptions {
+    timeout: Option<Duration>,
+    check_interval: u32,
+    cancellation_token: Option<CancellationToken>,
+    max_call_depth: usize,
+    max_steps: Option<u64>,
+    argument_trace: Option<LineHandler>,
+    warning_handler: Option<LineHandler>,
+    print_handler: Option<LineHandler>,
+    profiling: Option<ProfilingContext>,
+}
+
+impl Default for EvaluationOptions {
+    fn default() -> Self {
+        EvaluationOptions {
+            timeout: None,
+            check_interval: DEFAULT_CHECK_INTERVAL,
+            cancellation_token: None,
+            max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+            max_steps: None,
+            argument_trace: None,
+            warning_handler: None,
+            print_handler: None,
+            profiling: None,
+        }
+    }
+}
+
+impl EvaluationOptions {
+    /// Abort the evaluation once it ran for longer than `timeout`, with an error of code
+    /// [`TIMEOUT_ERROR_CODE`] (`EV01`).
+    ///
+    /// The deadline is computed when the evaluation starts.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Only read the clock once every `interval` check points (1000 by default), to avoid
+    /// its overhead on tight loops. The deadline may thus be overrun by that many steps.
+    pub fn check_interval(mut self, interval: u32) -> Self {
+        self.check_interval = interval.max(1);
+        self
+    }
+
+    /// Abort the evaluation with [`ValueError::Interrupted`] once `flag` is set, e.g. from
+    /// another thread.
+    ///
+    /// Unlike the timeout, the flag is read at every check point. The error records the
+    /// innermost calls at the time of the interruption.
+    pub fn interrupt_flag(self, flag: Arc<AtomicBool>) -> Self {
+        self.cancellation_token(CancellationToken::from(flag))
+    }
+
+    /// Abort the evaluation with [`ValueError::Interrupted`] once `token` is cancelled, like
+    /// [`interrupt_flag`](EvaluationOptions::interrupt_flag).
+    pub fn cancellation_token(mut self, token: CancellationToken) -> Self {
+        self.cancellation_token = Some(token);
+        self
+    }
+
+    /// Abort the evaluation with [`ValueError::CallDepthExceeded`] when a function call
+    /// would nest more than `depth` calls (500 by default).
+    ///
+    /// Each Starlark call also uses the native stack of the evaluating thread, embedders
+    /// raising this limit should make sure that thread has a large enough stack.
+    pub fn max_call_depth(mut self, depth: usize) -> Self {
+        self.max_call_depth = depth;
+        self
+    }
+
+    /// Abort the evaluation with [`ValueError::EvaluationBudgetExceeded`] once it ran more
+    /// than `steps` steps.
+    ///
+    /// A step is counted at each check point, and native functions can charge more steps
+    /// for expensive operations with
+    /// [`CallStack::charge_steps`](crate::eval::call_stack::CallStack::charge_steps).
+    pub fn max_steps(mut self, steps: u64) -> Self {
+        self.max_steps = Some(steps);
+        self
+    }
+
+    /// Report to `sink`, one line per parameter, how the arguments of calls to native
+    /// functions are converted: the type of the value received for each parameter and
+    /// whether it was accepted, e.g.
+    ///
+    /// ```text
+    /// hasattr(a): received int, accepted
+    /// hasattr(attr): received int, rejected: Type of parameters attr mismatch, expected string, got int
+    /// ```
+    ///
+    /// This is meant for debugging signature mismatches, and slows down native calls.
+    pub fn trace_arguments<F>(mut self, sink: F) -> Self
+    where
+        F: Fn(&str) + Send + Sync + 'static,
+    {
+        self.argument_trace = Some(LineHandler(Arc::new(sink)));
+        self
+    }
+
+    /// Report the warnings of the evaluation to `handler`, e.g. calls to native functions
+    /// marked as deprecated in their
+    /// [`starlark_module!`](crate::starlark_module) declaration:
+    ///
+    /// ```text
+    /// foo() was deprecated in 2.0; use bar() instead
+    /// ```
+    ///
+    /// Without a handler, warnings are discarded.
+    pub fn on_warning<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(&str) + Send + Sync + 'static,
+    {
+        self.warning_handler = Some(LineHandler(Arc::new(handler)));
+        self
+    }
+
+    /// Write the lines printed by the evaluation, e.g. with the `print` builtin, to
+    /// `handler` instead of the standard output, e.g. to capture them in a buffer.
+    pub fn on_print<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(&str) + Send + Sync + 'static,
+    {
+        self.print_handler = Some(LineHandler(Arc::new(handler)));
+        self
+    }
+
+    /// Report the function calls, native or defined in Starlark, to the hook of `context`
+    /// along with their duration, e.g. to build a
+    /// [flat profile](crate::eval::profile::FlatProfileHook) of the evaluation.
+    pub fn profiling(mut self, context: ProfilingContext) -> Self {
+        self.profiling = Some(context);
+        self
+    }
+}
+
+/// Limits of a running evaluation, shared by all the frames of its call stack.
+#[derive(Debug)]
+pub(crate) struct EvaluationLimits {
+    timeout: Option<(Duration, Instant)>,
+    check_interval: u32,
+    countdown: Cell<u32>,
+    cancellation_token: Option<CancellationToken>,
+    pub(crate) max_call_depth: usize,
+    max_steps: Option<u64>,
+    steps: Cell<u64>,
+    pub(crate) argument_trace: Option<LineHandler>,
+    pub(crate) warning_handler: Option<LineHandler>,
+    pub(crate) print_handler: Option<LineHandler>,
+    pub(crate) profiling: Option<ProfilingContext>,
+}
+
+impl EvaluationLimits {
+    /// Start the evaluation clock.
+    pub(crate) fn new(options: &EvaluationOptions) -> EvaluationLimits {
+        EvaluationLimits {
+            timeout: options.timeout.map(|t| (t, Instant::now() + t)),
+            check_interval: options.check_interval,
+            countdown: Cell::new(options.check_interval),
+            cancellation_token: options.cancellation_token.clone(),
+            max_call_depth: options.max_call_depth,
+            max_steps: options.max_steps,
+            steps: Cell::new(0),
+            argument_trace: options.argument_trace.clone(),
+            warning_handler: options.warning_handler.clone(),
+            print_handler: options.print_handler.clone(),
+            profiling: options.profiling.clone(),
+        }
+    }
+
+    /// Count `steps` more steps, returning an error if the budget is exhausted.
+    pub(crate) fn charge_steps(&self, steps: u64) -> Result<(), ValueError> {
+        let total = self.steps.get().saturating_add(steps);
+        self.steps.set(total);
+        match self.max_steps {
+            Some(max_steps) if total > max_steps => {
+                Err(ValueError::EvaluationBudgetExceeded { steps: total })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Whether the cancellation token of the evaluation was cancelled.
+    pub(crate) fn is_cancelled(&self) -> bool {
+        match self.cancellation_token {
+            Some(ref token) => token.is_cancelled(),
+            None => false,
+        }
+    }
+
+    /// Return an error if the evaluation exceeded its limits.
+    pub(crate) fn check(&self) -> Result<(), ValueError> {
+        self.charge_steps(1)?;
+        if let Some((timeout, deadline)) = self.timeout {
+            let countdown = self.countdown.get() - 1;
+            if countdown != 0 {
+                self.countdown.set(countdown);
+                return Ok(());
+            }
+            self.countdown.set(self.check_interval);
+            if Instant::now() > deadline {
+                return Err(RuntimeError {
+                    code: TIMEOUT_ERROR_CODE,
+                    message: format!("Evaluation exceeded its timeout of {:?}", timeout),
+                    label: "Timeout exceeded".to_owned(),
+                }
+                .into());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::environment::{Environment, TypeValues};
+    use crate::eval::call_stack::CallStack;
+    use crate::eval::eval_with_options;
+    use crate::eval::noload::NoLoadFileLoader;
+    use crate::eval::options::{CancellationToken, EvaluationOptions, TIMEOUT_ERROR_CODE};
+    use crate::stdlib::global_environment;
+    use crate::syntax::dialect::Dialect;
+    use crate::values::error::{
+        CALL_DEPTH_EXCEEDED_ERROR_CODE, EVALUATION_BUDGET_EXCEEDED_ERROR_CODE,
+        INTERRUPTED_ERROR_CODE,
+    };
+    use crate::values::function::{NativeFunction, ParameterParser};
+    use crate::values::none::NoneType;
+    use crate::values::{Value, ValueResult};
+    use codemap::CodeMap;
+    use codemap_diagnostic::Diagnostic;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{mpsc, Arc, Mutex};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    fn eval_with_timeout(content: &str, timeout: Duration) -> Result<String, Diagnostic> {
+        let env = global_environment();
+        env.freeze();
+        eval_with_options(
+            &Arc::new(Mutex::new(CodeMap::new())),
+            "timeout.star",
+            content,
+            Dialect::Bzl,
+            &mut env.child("test"),
+            TypeValues::new(env),
+            NoLoadFileLoader,
+            &EvaluationOptions::default()
+                .timeout(timeout)
+                .check_interval(10),
+        )
+        .map(|v| v.to_str())
+    }
+
+    #[test]
+    fn loop_timeout() {
+        let start = Instant::now();
+        let err = eval_with_timeout(
+            "def f():\n  for x in range(1000000000):\n    pass\nf()",
+            Duration::from_millis(50),
+        )
+        .unwrap_err();
+        assert_eq!(Some(TIMEOUT_ERROR_CODE.to_owned()), err.code);
+        assert!(start.elapsed() < Duration::from_secs(10));
+    }
+
+    #[test]
+    fn comprehension_timeout() {
+        let err = eval_with_timeout("[x for x in range(1000000000)]", Duration::from_millis(50))
+            .unwrap_err();
+        assert_eq!(Some(TIMEOUT_ERROR_CODE.to_owned()), err.code);
+    }
+
+    #[test]
+    fn function_timeout() {
+        let err = eval_with_timeout(
+            "def g(x):\n  return x\ndef f():\n  return [g(x) for x in range(1000000000)]\nf()",
+            Duration::from_millis(50),
+        )
+        .unwrap_err();
+        assert_eq!(Some(TIMEOUT_ERROR_CODE.to_owned()), err.code);
+    }
+
+    #[test]
+    fn assert_fails_does_not_catch_timeout() {
+        let err = eval_with_timeout(
+            "def f():\n  for x in range(1000000000):\n    pass\nassert_fails(f, '')",
+            Duration::from_millis(50),
+        )
+        .unwrap_err();
+        assert_eq!(Some(TIMEOUT_ERROR_CODE.to_owned()), err.code);
+    }
+
+    #[test]
+    fn interrupt_flag() {
+        let env = global_environment();
+        env.freeze();
+        let flag = Arc::new(AtomicBool::new(false));
+        let setter = {
+            let flag = flag.clone();
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(50));
+                flag.store(true, Ordering::Relaxed);
+            })
+        };
+        let start = Instant::now();
+        let err = eval_with_options(
+            &Arc::new(Mutex::new(CodeMap::new())),
+            "interrupt.star",
+            "def f():\n  for x in range(1000000000):\n    pass\nf()",
+            Dialect::Bzl,
+            &mut env.child("test"),
+            TypeValues::new(env),
+            NoLoadFileLoader,
+            &EvaluationOptions::default().interrupt_flag(flag),
+        )
+        .unwrap_err();
+        setter.join().unwrap();
+        assert_eq!(Some(INTERRUPTED_ERROR_CODE.to_owned()), err.code);
+        assert!(start.elapsed() < Duration::from_secs(10));
+        // The flag is a cancellation token.
+        assert!(
+            err.message.contains("call to f() at interrupt.star:4"),
+            "{}",
+            err.message
+        );
+    }
+
+    #[test]
+    fn cancellation_token() {
+        let map = Arc::new(Mutex::new(CodeMap::new()));
+        let token = CancellationToken::new();
+        let (sender, receiver) = mpsc::channel();
+        let evaluation = {
+            let map = map.clone();
+            let token = token.clone();
+            thread::spawn(move || {
+                let env = global_environment();
+                env.freeze();
+                let result = eval_with_options(
+                    &map,
+                    "cancel.star",
+                    "def f():\n  for x in range(1000000000):\n    pass\nf()",
+                    Dialect::Bzl,
+                    &mut env.child("test"),
+                    TypeValues::new(env),
+                    NoLoadFileLoader,
+                    &EvaluationOptions::default().cancellation_token(token),
+                );
+                sender.send(result.map(|v| v.to_str())).unwrap();
+            })
+        };
+        thread::sleep(Duration::from_millis(50));
+        token.cancel();
+        let err = receiver
+            .recv_timeout(Duration::from_secs(10))
+            .unwrap()
+            .unwrap_err();
+        evaluation.join().unwrap();
+        assert_eq!(Some(INTERRUPTED_ERROR_CODE.to_owned()), err.code);
+        // The error points to the loop within `f`, called from the top level.
+        assert!(
+            err.message.contains("call to f() at cancel.star:4"),
+            "{}",
+            err.message
+        );
+        let loc = map.lock().unwrap().look_up_span(err.spans[0].span);
+        assert_eq!((1, 2), (loc.begin.line, loc.begin.column));
+    }
+
+    #[test]
+    fn cancelled_before_evaluation() {
+        let env = global_environment();
+        env.freeze();
+        let token = CancellationToken::new();
+        token.cancel();
+        let err = eval_with_options(
+            &Arc::new(Mutex::new(CodeMap::new())),
+            "cancel.star",
+            "[x for x in range(10)]",
+            Dialect::Bzl,
+            &mut env.child("test"),
+            TypeValues::new(env),
+            NoLoadFileLoader,
+            &EvaluationOptions::default().cancellation_token(token),
+        )
+        .unwrap_err();
+        assert_eq!(Some(INTERRUPTED_ERROR_CODE.to_owned()), err.code);
+        assert_eq!("Evaluation was interrupted", err.message);
+    }
+
+    #[test]
+    fn no_timeout() {
+        assert_eq!(
+            "45",
+            eval_with_timeout(
+                "def f():\n  r = 0\n  for x in range(10):\n    r += x\n  return r\nf()",
+                Duration::from_secs(60),
+            )
+            .unwrap()
+        );
+    }
+
+    fn eval_with_max_call_depth(content: &str, depth: usize) -> Result<String, Diagnostic> {
+        let env = global_environment();
+        env.freeze();
+        eval_with_options(
+            &Arc::new(Mutex::new(CodeMap::new())),
+            "depth.star",
+            content,
+            Dialect {
+                allow_recursion: true,
+                ..Dialect::Bzl
+            },
+            &mut env.child("test"),
+            TypeValues::new(env),
+            NoLoadFileLoader,
+            &EvaluationOptions::default().max_call_depth(depth),
+        )
+        .map(|v| v.to_str())
+    }
+
+    #[test]
+    fn max_call_depth_direct_recursion() {
+        let content = "def f(n):\n  return 0 if n == 0 else 1 + f(n - 1)\n";
+        assert_eq!(
+            "19",
+            eval_with_max_call_depth(&format!("{}f(19)", content), 20).unwrap()
+        );
+        let err = eval_with_max_call_depth(&format!("{}f(20)", content), 20).unwrap_err();
+        assert_eq!(Some(CALL_DEPTH_EXCEEDED_ERROR_CODE.to_owned()), err.code);
+        assert!(err.message.contains("call depth of 20"), "{}", err.message);
+        // Only the innermost frames are reported.
+        assert_eq!(
+            5,
+            err.message.matches("call to f(n) at depth.star:2").count()
+        );
+    }
+
+    #[test]
+    fn max_call_depth_mutual_recursion() {
+        let content = concat!(
+            "def even(n):\n  return True if n == 0 else odd(n - 1)\n",
+            "def odd(n):\n  return False if n == 0 else even(n - 1)\n",
+        );
+        assert_eq!(
+            "True",
+            eval_with_max_call_depth(&format!("{}even(10)", content), 20).unwrap()
+        );
+        let err = eval_with_max_call_depth(&format!("{}even(100)", content), 20).unwrap_err();
+        assert_eq!(Some(CALL_DEPTH_EXCEEDED_ERROR_CODE.to_owned()), err.code);
+        assert!(err.message.contains("call to odd"), "{}", err.message);
+        assert!(err.message.contains("call to even"), "{}", err.message);
+    }
+
+    #[test]
+    fn max_call_depth_nested_calls() {
+        // f0() ... f20() each call the previous one, fN() nests N + 1 calls.
+        let mut content = "def f0():\n  return 0\n".to_owned();
+        for i in 1..=20 {
+            content.push_str(&format!("def f{}():\n  return f{}() + 1\n", i, i - 1));
+        }
+        assert_eq!(
+            "19",
+            eval_with_max_call_depth(&format!("{}f19()", content), 20).unwrap()
+        );
+        let err = eval_with_max_call_depth(&format!("{}f20()", content), 20).unwrap_err();
+        assert_eq!(Some(CALL_DEPTH_EXCEEDED_ERROR_CODE.to_owned()), err.code);
+    }
+
+    fn eval_with_max_steps(
+        env: Environment,
+        content: &str,
+        steps: u64,
+    ) -> Result<String, Diagnostic> {
+        env.freeze();
+        eval_with_options(
+            &Arc::new(Mutex::new(CodeMap::new())),
+            "steps.star",
+            content,
+            Dialect::Bzl,
+            &mut env.child("test"),
+            TypeValues::new(env),
+            NoLoadFileLoader,
+            &EvaluationOptions::default().max_steps(steps),
+        )
+        .map(|v| v.to_str())
+    }
+
+    #[test]
+    fn max_steps_loop() {
+        let map = Arc::new(Mutex::new(CodeMap::new()));
+        let env = global_environment();
+        env.freeze();
+        let err = eval_with_options(
+            &map,
+            "steps.star",
+            "def f():\n  for x in range(1000000000):\n    pass\nf()",
+            Dialect::Bzl,
+            &mut env.child("test"),
+            TypeValues::new(env),
+            NoLoadFileLoader,
+            &EvaluationOptions::default().max_steps(1000),
+        )
+        .unwrap_err();
+        assert_eq!(
+            Some(EVALUATION_BUDGET_EXCEEDED_ERROR_CODE.to_owned()),
+            err.code
+        );
+        assert!(err.message.contains("after 1001 steps"), "{}", err.message);
+        // The error points to the loop statement.
+        let loc = map.lock().unwrap().look_up_span(err.spans[0].span);
+        assert_eq!((1, 2), (loc.begin.line, loc.begin.column));
+    }
+
+    #[test]
+    fn max_steps_nested_comprehension() {
+        let err = eval_with_max_steps(
+            global_environment(),
+            "[[[z for z in range(100)] for y in range(100)] for x in range(100)]",
+            10000,
+        )
+        .unwrap_err();
+        assert_eq!(
+            Some(EVALUATION_BUDGET_EXCEEDED_ERROR_CODE.to_owned()),
+            err.code
+        );
+    }
+
+    #[test]
+    fn max_steps_generous_budget() {
+        let content = concat!(
+            "def fib(n):\n  a, b = 0, 1\n  for _ in range(n):\n    a, b = b, a + b\n  return a\n",
+            "[fib(x) for x in range(20)][-1] + len([y for y in range(100) if y % 3 == 0])",
+        );
+        let unbounded = eval_with_options(
+            &Arc::new(Mutex::new(CodeMap::new())),
+            "steps.star",
+            content,
+            Dialect::Bzl,
+            &mut global_environment().freeze().child("test"),
+            TypeValues::new(global_environment()),
+            NoLoadFileLoader,
+            &EvaluationOptions::default(),
+        )
+        .unwrap()
+        .to_str();
+        assert_eq!("4215", unbounded);
+        assert_eq!(
+            unbounded,
+            eval_with_max_steps(global_environment(), content, 1_000_000).unwrap()
+        );
+    }
+
+    #[test]
+    fn max_steps_charged_by_native_function() {
+        fn expensive(cs: &CallStack, _: TypeValues, _: ParameterParser) -> ValueResult {
+            cs.charge_steps(100)?;
+            Ok(Value::new(NoneType::None))
+        }
+
+        let content = "def f():\n  for x in range(5):\n    expensive()\nf()";
+        let env = || {
+            let env = global_environment();
+            let f = NativeFunction::new("expensive".to_owned(), expensive, Vec::new());
+            env.set("expensive", f).unwrap();
+            env
+        };
+        assert!(eval_with_max_steps(env(), content, 1000).is_ok());
+        let err = eval_with_max_steps(env(), content, 300).unwrap_err();
+        assert_eq!(
+            Some(EVALUATION_BUDGET_EXCEEDED_ERROR_CODE.to_owned()),
+            err.code
+        );
+    }
+}
@@ -0,0 +1,254 @@
+// Copyright 2019 The Starlark in Rust Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Profiling of the function calls of an evaluation, see
+//! [`EvaluationOptions::profiling`](crate::eval::options::EvaluationOptions::profiling).
+
+use crate::eval::call_stack::CallStack;
+use crate::values::Value;
+use std::collections::HashMap;
+use std::fmt;
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Position of a call in the source code, lines and columns starting at 1.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CodeLocation {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Receiver of the function calls of an evaluation.
+///
+/// Functions are named as in call stacks, i.e. with their parameters (e.g. `f(x)` or
+/// `len($a)`), so that the hook can tell apart functions of the same name.
+pub trait ProfilingHook: Send + Sync {
+    /// Called before calling `fn_name` from `location`.
+    fn on_call(&self, fn_name: &str, location: Option<&CodeLocation>);
+
+    /// Called once `fn_name` returned, successfully or not, after running for `duration`.
+    fn on_return(&self, fn_name: &str, duration: Duration);
+}
+
+/// A [`ProfilingHook`] attached to an evaluation.
+#[derive(Clone)]
+pub struct ProfilingContext {
+    hook: Arc<dyn ProfilingHook>,
+}
+
+impl fmt::Debug for ProfilingContext {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("ProfilingContext")
+    }
+}
+
+impl ProfilingContext {
+    /// Report the calls to `hook`, which the caller can keep a reference to in order to
+    /// read the profile once the evaluation is done.
+    pub fn new(hook: Arc<dyn ProfilingHook>) -> ProfilingContext {
+        ProfilingContext { hook }
+    }
+
+    /// Run `call`, a call to `function` whose frame is on top of `call_stack`, between
+    /// the callbacks of the hook.
+    pub(crate) fn profile<T>(
+        &self,
+        function: &Value,
+        call_stack: &CallStack,
+        call: impl FnOnce() -> T,
+    ) -> T {
+        let name = function.to_str();
+        let location = call_stack
+            .current_location()
+            .map(|(file, line, column)| CodeLocation { file, line, column });
+        self.hook.on_call(&name, location.as_ref());
+        let start = Instant::now();
+        let result = call();
+        self.hook.on_return(&name, start.elapsed());
+        result
+    }
+}
+
+/// Number of calls and total time spent in a function, see [`FlatProfileHook`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FunctionProfile {
+    pub calls: u64,
+    pub total: Duration,
+}
+
+/// A [`ProfilingHook`] accumulating the number of calls and the time spent per function.
+///
+/// The time of a function includes the time of the functions it calls, so that the time
+/// of recursive functions is counted once per nested call.
+///
+/// # Examples
+///
+/// ```
+/// # use starlark::eval::options::EvaluationOptions;
+/// # use starlark::eval::profile::{FlatProfileHook, ProfilingContext};
+/// # use std::sync::Arc;
+/// let profile = Arc::new(FlatProfileHook::new());
+/// let options = EvaluationOptions::default().profiling(ProfilingContext::new(profile.clone()));
+/// // ... evaluate with `options`, then:
+/// print!("{}", profile.report());
+/// ```
+#[derive(Debug, Default)]
+pub struct FlatProfileHook {
+    functions: Mutex<HashMap<String, FunctionProfile>>,
+}
+
+impl FlatProfileHook {
+    /// An empty profile.
+    pub fn new() -> FlatProfileHook {
+        FlatProfileHook::default()
+    }
+
+    /// The profile of each function called so far, the most expensive first.
+    pub fn entries(&self) -> Vec<(String, FunctionProfile)> {
+        let mut entries: Vec<(String, FunctionProfile)> = self
+            .functions
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(name, profile)| (name.clone(), *profile))
+            .collect();
+        entries.sort_by(|(a_name, a), (b_name, b)| {
+            (b.total, b.calls, a_name).cmp(&(a.total, a.calls, b_name))
+        });
+        entries
+    }
+
+    /// A table of the [`entries`](FlatProfileHook::entries), one function per line.
+    pub fn report(&self) -> String {
+        let mut report = format!("{:>10} {:>14}  {}\n", "calls", "total", "function");
+        for (name, profile) in self.entries() {
+            writeln!(
+                report,
+                "{:>10} {:>14}  {}",
+                profile.calls,
+                format!("{:?}", profile.total),
+                name
+            )
+            .unwrap();
+        }
+        report
+    }
+}
+
+impl ProfilingHook for FlatProfileHook {
+    fn on_call(&self, _fn_name: &str, _location: Option<&CodeLocation>) {}
+
+    fn on_return(&self, fn_name: &str, duration: Duration) {
+        let mut functions = self.functions.lock().unwrap();
+        let profile = functions.entry(fn_name.to_owned()).or_default();
+        profile.calls += 1;
+        profile.total += duration;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::TypeValues;
+    use crate::eval::eval_with_options;
+    use crate::eval::noload::NoLoadFileLoader;
+    use crate::eval::options::EvaluationOptions;
+    use crate::stdlib::global_environment;
+    use crate::syntax::dialect::Dialect;
+    use codemap::CodeMap;
+
+    fn eval_profiled(content: &str, hook: Arc<dyn ProfilingHook>) {
+        let env = global_environment();
+        env.freeze();
+        eval_with_options(
+            &Arc::new(Mutex::new(CodeMap::new())),
+            "profile.star",
+            content,
+            Dialect::Bzl,
+            &mut env.child("test"),
+            TypeValues::new(env),
+            NoLoadFileLoader,
+            &EvaluationOptions::default().profiling(ProfilingContext::new(hook)),
+        )
+        .unwrap();
+    }
+
+    #[derive(Default)]
+    struct Events(Mutex<Vec<String>>);
+
+    impl ProfilingHook for Events {
+        fn on_call(&self, fn_name: &str, location: Option<&CodeLocation>) {
+            let location = location.unwrap();
+            self.0.lock().unwrap().push(format!(
+                "call {} at {}:{}:{}",
+                fn_name, location.file, location.line, location.column
+            ));
+        }
+
+        fn on_return(&self, fn_name: &str, _duration: Duration) {
+            self.0.lock().unwrap().push(format!("return {}", fn_name));
+        }
+    }
+
+    #[test]
+    fn nested_calls() {
+        let events = Arc::new(Events::default());
+        eval_profiled("def f(x):\n  return len(x)\n\nf([1, 2])", events.clone());
+        assert_eq!(
+            vec![
+                "call f(x) at profile.star:4:1",
+                "call len($a) at profile.star:2:10",
+                "return len($a)",
+                "return f(x)",
+            ],
+            *events.0.lock().unwrap()
+        );
+    }
+
+    #[test]
+    fn flat_profile() {
+        let profile = Arc::new(FlatProfileHook::new());
+        eval_profiled(
+            "def g(x):\n  return str(x)\ndef f():\n  return [g(x) for x in range(10)]\nf()",
+            profile.clone(),
+        );
+        let calls: Vec<(String, u64)> = profile
+            .entries()
+            .into_iter()
+            .map(|(name, profile)| (name, profile.calls))
+            .collect();
+        // `f` includes the time of all the other calls.
+        assert_eq!(("f()".to_owned(), 1), calls[0]);
+        let mut calls = calls;
+        calls.sort();
+        assert_eq!(
+            vec![
+                ("f()".to_owned(), 1),
+                ("g(x)".to_owned(), 10),
+                ("range($a1, $a2, $a3)".to_owned(), 1),
+                ("str($a)".to_owned(), 10),
+            ],
+            calls
+        );
+        let report = profile.report();
+        assert_eq!(5, report.lines().count(), "{}", report);
+        assert!(
+            report.lines().nth(1).unwrap().ends_with("  f()"),
+            "{}",
+            report
+        );
+    }
+}
@@ -0,0 +1,269 @@
+// Copyright 2019 The Starlark in Rust Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Source lines of the code evaluated, to display errors without an [`Emitter`].
+//!
+//! [`Emitter`]: codemap_diagnostic::Emitter
+
+use codemap::{CodeMap, Span};
+use codemap_diagnostic::{Diagnostic, SpanStyle};
+use std::sync::{Arc, Mutex};
+
+/// Map from the positions in the evaluated files to their line, column and source text.
+///
+/// The evaluator adds each file to its [`CodeMap`] when parsing it, a `SourceMap` is a view
+/// of that code map, so all spans of the errors of an evaluation can be looked up in it.
+#[derive(Clone)]
+pub struct SourceMap {
+    codemap: Arc<Mutex<CodeMap>>,
+}
+
+/// The location of a position in a source file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SourceLocation {
+    /// Name of the file.
+    pub file: String,
+    /// Line number, starting at 1.
+    pub line: usize,
+    /// Column in the line, in characters starting at 1.
+    pub column: usize,
+    /// The whole line, without its terminator.
+    pub source_line: String,
+}
+
+impl SourceMap {
+    /// A view of the code map given to the evaluation.
+    pub fn new(codemap: &Arc<Mutex<CodeMap>>) -> SourceMap {
+        SourceMap {
+            codemap: codemap.clone(),
+        }
+    }
+
+    /// Location of the start of `span`, which must come from the code map.
+    pub fn look_up(&self, span: Span) -> SourceLocation {
+        let codemap = self.codemap.lock().unwrap();
+        let loc = codemap.look_up_pos(span.low());
+        SourceLocation {
+            file: loc.file.name().to_owned(),
+            line: loc.position.line + 1,
+            column: loc.position.column + 1,
+            source_line: loc.file.source_line(loc.position.line).to_owned(),
+        }
+    }
+
+    /// Display `diagnostic` with the source line of its primary span, underlined up to the
+    /// end of the span or of the line, e.g.:
+    ///
+    /// ```text
+    /// error[CR03]: "a" is not a valid number in base 10
+    ///  --> main.star:2:9
+    ///   |
+    /// 2 | y = x + int('a')
+    ///   |         ^^^^^^^^ Not a base 10 integer
+    /// ```
+    pub fn display(&self, diagnostic: &Diagnostic) -> String {
+        let mut s = diagnostic.level.to_string();
+        if let Some(ref code) = diagnostic.code {
+            s += &format!("[{}]", code);
+        }
+        s += &format!(": {}", diagnostic.message);
+        let label = diagnostic
+            .spans
+            .iter()
+            .find(|l| l.style == SpanStyle::Primary)
+            .or_else(|| diagnostic.spans.first());
+        if let Some(label) = label {
+            let loc = self.look_up(label.span);
+            let end = self.look_up(label.span.subspan(label.span.len(), label.span.len()));
+            let width = if end.line == loc.line {
+                end.column - loc.column
+            } else {
+                loc.source_line.chars().count() + 1 - loc.column
+            };
+            let margin = " ".repeat(loc.line.to_string().len());
+            s += &format!(
+                "\n{}--> {}:{}:{}\n{} |\n{} | {}\n{} | {}{}",
+                margin,
+                loc.file,
+                loc.line,
+                loc.column,
+                margin,
+                loc.line,
+                loc.source_line,
+                margin,
+                " ".repeat(loc.column - 1),
+                "^".repeat(width.max(1))
+            );
+            if let Some(ref text) = label.label {
+                s += &format!(" {}", text);
+            }
+        }
+        s
+    }
+}
+
+/// Name of the file in the location displayed by [`RenderDiagnostic::render`].
+const RENDERED_FILE_NAME: &str = "<source>";
+
+/// Display of a [`Diagnostic`] from the source of the file it was raised in, for callers
+/// which did not keep the [`CodeMap`] of the evaluation.
+pub trait RenderDiagnostic {
+    /// Display this diagnostic like [`SourceMap::display`], e.g.:
+    ///
+    /// ```text
+    /// error[CR03]: "a" is not a valid number in base 10
+    ///  --> <source>:2:9
+    ///   |
+    /// 2 | y = x + int('a')
+    ///   |         ^^^^^^^^ Not a base 10 integer
+    /// ```
+    ///
+    /// `source` must be the first file added to the code map of the evaluation, which is
+    /// the case when evaluating a file with a new code map. The spans in other files, e.g.
+    /// in loaded modules, are not displayed.
+    fn render(&self, source: &str) -> String;
+}
+
+impl RenderDiagnostic for Diagnostic {
+    fn render(&self, source: &str) -> String {
+        let codemap = Arc::new(Mutex::new(CodeMap::new()));
+        let file_span = codemap
+            .lock()
+            .unwrap()
+            .add_file(RENDERED_FILE_NAME.to_owned(), source.to_owned())
+            .span;
+        let mut diagnostic = self.clone();
+        diagnostic
+            .spans
+            .retain(|l| l.span.low() >= file_span.low() && l.span.high() <= file_span.high());
+        SourceMap::new(&codemap).display(&diagnostic)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::TypeValues;
+    use crate::eval::noload;
+    use crate::stdlib::global_environment;
+    use crate::syntax::dialect::Dialect;
+    use crate::syntax::errors::SyntaxError;
+    use crate::values::error::RuntimeError;
+
+    #[test]
+    fn display_evaluation_error() {
+        let codemap = Arc::new(Mutex::new(CodeMap::new()));
+        let env = global_environment();
+        let err = noload::eval(
+            &codemap,
+            "main.star",
+            "x = 1\ny = x + int('a')\n",
+            Dialect::Bzl,
+            &mut env.freeze().child("test"),
+            TypeValues::new(env),
+        )
+        .unwrap_err();
+        let source_map = SourceMap::new(&codemap);
+        let loc = source_map.look_up(err.spans[0].span);
+        assert_eq!(("main.star", 2, 9), (&loc.file[..], loc.line, loc.column));
+        assert_eq!("y = x + int('a')", loc.source_line);
+        let display = source_map.display(&err);
+        assert!(
+            display.ends_with(&format!(
+                "\n --> main.star:2:9\n  |\n2 | y = x + int('a')\n  |         ^^^^^^^^ {}",
+                err.spans[0].label.as_ref().unwrap()
+            )),
+            "{}",
+            display
+        );
+    }
+
+    #[test]
+    fn display_runtime_error() {
+        let codemap = Arc::new(Mutex::new(CodeMap::new()));
+        let file = codemap.lock().unwrap().add_file(
+            "lib.star".to_owned(),
+            "def f():\n  return g(\n    1)\n".to_owned(),
+        );
+        let source_map = SourceMap::new(&codemap);
+        let err = RuntimeError {
+            code: "UF00",
+            message: "g failed".to_owned(),
+            label: "Failed here".to_owned(),
+        };
+        // A span over several lines is underlined up to the end of its first line.
+        let span = file.span.subspan(18, 27);
+        assert_eq!(
+            concat!(
+                "error[UF00]: g failed\n",
+                " --> lib.star:2:10\n",
+                "  |\n",
+                "2 |   return g(\n",
+                "  |          ^^ Failed here"
+            ),
+            err.display_with_source_map(span, &source_map)
+        );
+    }
+    #[test]
+    fn render_evaluation_error() {
+        let content = "x = 1\ny = x + int('a')\n";
+        let env = global_environment();
+        let err = noload::eval(
+            &Arc::new(Mutex::new(CodeMap::new())),
+            "main.star",
+            content,
+            Dialect::Bzl,
+            &mut env.freeze().child("test"),
+            TypeValues::new(env),
+        )
+        .unwrap_err();
+        assert_eq!(
+            format!(
+                concat!(
+                    "error[CR03]: {}\n",
+                    " --> <source>:2:9\n",
+                    "  |\n",
+                    "2 | y = x + int('a')\n",
+                    "  |         ^^^^^^^^ {}"
+                ),
+                err.message,
+                err.spans[0].label.as_ref().unwrap()
+            ),
+            err.render(content)
+        );
+    }
+
+    #[test]
+    fn render_span_in_other_file() {
+        let codemap = Arc::new(Mutex::new(CodeMap::new()));
+        codemap
+            .lock()
+            .unwrap()
+            .add_file("main.star".to_owned(), "load('lib.star', 'f')\n".to_owned());
+        let lib = codemap
+            .lock()
+            .unwrap()
+            .add_file("lib.star".to_owned(), "def f():\n  fail()\n".to_owned());
+        let err = RuntimeError {
+            code: "UF00",
+            message: "f failed".to_owned(),
+            label: "Failed here".to_owned(),
+        }
+        .to_diagnostic(lib.span.subspan(11, 17));
+        assert_eq!(
+            "error[UF00]: f failed",
+            err.render("load('lib.star', 'f')\n")
+        );
+    }
+}
@@ -13,12 +13,18 @@
 // limitations under the License.
 
 use crate::environment::{Environment, TypeValues};
+use crate::eval::options::EvaluationOptions;
 use crate::eval::testutil::starlark_no_diagnostic;
-use crate::eval::{eval, testutil, EvalException, FileLoader};
-use crate::eval::{noload, RECURSION_ERROR_CODE};
+use crate::eval::{eval, eval_with_traceback, testutil, EvalException, FileLoader};
+use crate::eval::{noload, LOAD_CYCLE_ERROR_CODE, RECURSION_ERROR_CODE};
 use crate::syntax::dialect::Dialect;
+use crate::values::error::IMMUTABLE_ERROR_CODE;
 use crate::values::Value;
 use codemap::CodeMap;
+use codemap_diagnostic::Diagnostic;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
 use std::sync::{Arc, Mutex};
 
 #[test]
@@ -253,3 +259,693 @@ fn test_type_values_are_imported_from_caller() {
 
     assert_eq!("[\"ab\", \"de\"]", result.to_str());
 }
+
+/// Loads modules from in-memory sources, evaluating each of them once.
+#[derive(Clone)]
+struct MapFileLoader {
+    sources: Rc<HashMap<&'static str, &'static str>>,
+    loaded: Rc<RefCell<HashMap<String, Environment>>>,
+    evaluations: Rc<Cell<usize>>,
+    codemap: Arc<Mutex<CodeMap>>,
+}
+
+impl MapFileLoader {
+    fn new(sources: &[(&'static str, &'static str)]) -> MapFileLoader {
+        MapFileLoader {
+            sources: Rc::new(sources.iter().cloned().collect()),
+            loaded: Default::default(),
+            evaluations: Default::default(),
+            codemap: Default::default(),
+        }
+    }
+}
+
+impl FileLoader for MapFileLoader {
+    fn load(&self, path: &str) -> Result<Environment, EvalException> {
+        if let Some(env) = self.loaded.borrow().get(path) {
+            return Ok(env.clone());
+        }
+        let source = self.sources.get(path).unwrap_or_else(|| panic!("{}", path));
+        let mut env = crate::stdlib::global_environment().freeze().child(path);
+        eval(
+            &self.codemap,
+            path,
+            source,
+            Dialect::Bzl,
+            &mut env,
+            TypeValues::new(crate::stdlib::global_environment()),
+            self.clone(),
+        )?;
+        self.evaluations.set(self.evaluations.get() + 1);
+        self.loaded
+            .borrow_mut()
+            .insert(path.to_owned(), env.clone());
+        Ok(env)
+    }
+}
+
+fn eval_with_loader(content: &str, loader: MapFileLoader) -> Result<Value, Diagnostic> {
+    let codemap = loader.codemap.clone();
+    eval(
+        &codemap,
+        "main.bzl",
+        content,
+        Dialect::Bzl,
+        &mut crate::stdlib::global_environment()
+            .freeze()
+            .child("main.bzl"),
+        TypeValues::new(crate::stdlib::global_environment()),
+        loader,
+    )
+}
+
+#[test]
+fn load_from_map() {
+    let loader = MapFileLoader::new(&[
+        (
+            "//pkg:defs.bzl",
+            "load('//lib:math.bzl', 'double')\ndef helper(x): return double(x) + 1",
+        ),
+        ("//lib:math.bzl", "def double(x): return 2 * x"),
+    ]);
+    let evaluations = loader.evaluations.clone();
+    let content = concat!(
+        "load('//pkg:defs.bzl', 'helper')\n",
+        "load('//lib:math.bzl', 'double')\n",
+        "helper(double(5))"
+    );
+    assert_eq!(Value::new(21), eval_with_loader(content, loader).unwrap());
+    // `//lib:math.bzl` is loaded twice but only evaluated once.
+    assert_eq!(2, evaluations.get());
+}
+
+#[test]
+fn load_with_alias() {
+    let loader = || MapFileLoader::new(&[("//pkg:defs.bzl", "orig = 1\nother = 2\n_private = 3")]);
+    assert_eq!(
+        Value::new(21),
+        eval_with_loader(
+            "load('//pkg:defs.bzl', 'other', alias = 'orig')\nalias * 20 + other - 1",
+            loader()
+        )
+        .unwrap()
+    );
+    // Only the alias is defined.
+    assert!(eval_with_loader("load('//pkg:defs.bzl', alias = 'orig')\norig", loader()).is_err());
+    assert!(eval_with_loader("load('//pkg:defs.bzl', p = '_private')\np", loader()).is_err());
+}
+
+#[test]
+fn loaded_environment_is_frozen() {
+    // The loader does not freeze the environment itself.
+    let loader = || MapFileLoader::new(&[("//pkg:defs.bzl", "l = [1]")]);
+    assert!(eval_with_loader("load('//pkg:defs.bzl', 'l')\nl.append(2)", loader()).is_err());
+    assert_eq!(
+        "[1]",
+        eval_with_loader("load('//pkg:defs.bzl', 'l')\nl", loader())
+            .unwrap()
+            .to_repr()
+    );
+}
+
+#[test]
+fn mutate_loaded_value() {
+    fn assert_frozen(content: &str, module: &str) {
+        let sources = [
+            (
+                "//pkg:defs.bzl",
+                concat!(
+                    "l = [1]\n",
+                    "d = {'a': 1}\n",
+                    "nested = {'l': [], 't': ([],)}\n",
+                    // The module mutates its own globals until it is frozen.
+                    "l.append(2)\n",
+                    "d['b'] = 2\n",
+                    "nested['l'].append(1)\n",
+                    "def append(x): l.append(x)\n",
+                ),
+            ),
+            ("//pkg:reexport.bzl", "load('//pkg:defs.bzl', 'd')"),
+        ];
+        let err = eval_with_loader(content, MapFileLoader::new(&sources)).unwrap_err();
+        assert_eq!(
+            Some(IMMUTABLE_ERROR_CODE.to_owned()),
+            err.code,
+            "{}",
+            content
+        );
+        assert_eq!(
+            format!(
+                "Cannot mutate a frozen value defined in module '{}'",
+                module
+            ),
+            err.message.lines().next().unwrap()
+        );
+    }
+
+    assert_frozen(
+        "load('//pkg:defs.bzl', 'l')
+l.append(3)",
+        "//pkg:defs.bzl",
+    );
+    assert_frozen(
+        "load('//pkg:defs.bzl', x = 'l')
+x[0] = 3",
+        "//pkg:defs.bzl",
+    );
+    assert_frozen(
+        "load('//pkg:defs.bzl', 'd')
+d['c'] = 3",
+        "//pkg:defs.bzl",
+    );
+    assert_frozen(
+        "load('//pkg:defs.bzl', 'd')
+d.update(c = 3)",
+        "//pkg:defs.bzl",
+    );
+    assert_frozen(
+        "load('//pkg:defs.bzl', 'nested')
+nested['l'].append(2)",
+        "//pkg:defs.bzl",
+    );
+    assert_frozen(
+        "load('//pkg:defs.bzl', 'nested')
+nested['t'][0].append(2)",
+        "//pkg:defs.bzl",
+    );
+    assert_frozen(
+        "load('//pkg:defs.bzl', 'nested')
+def f():
+  nested['l'].append(2)
+f()",
+        "//pkg:defs.bzl",
+    );
+    // A function of the loaded module mutating its globals
+    assert_frozen(
+        "load('//pkg:defs.bzl', 'append')
+append(3)",
+        "//pkg:defs.bzl",
+    );
+    // The module defining a value is reported, not the module loaded
+    assert_frozen(
+        "load('//pkg:reexport.bzl', 'd')
+d['c'] = 3",
+        "//pkg:defs.bzl",
+    );
+
+    // The mutations of the loaded module itself are visible.
+    let loader = MapFileLoader::new(&[(
+        "//pkg:defs.bzl",
+        "l = [1]
+d = {'a': 1}
+l.append(2)
+d['b'] = 2",
+    )]);
+    assert_eq!(
+        "([1, 2], {\"a\": 1, \"b\": 2})",
+        eval_with_loader(
+            "load('//pkg:defs.bzl', 'l', 'd')
+(l, d)",
+            loader
+        )
+        .unwrap()
+        .to_repr()
+    );
+
+    // The main module remains mutable, including a copy of a loaded value.
+    let loader = MapFileLoader::new(&[("//pkg:defs.bzl", "l = [1]")]);
+    assert_eq!(
+        "[1, 2, 3]",
+        eval_with_loader(
+            "load('//pkg:defs.bzl', 'l')
+m = list(l)
+m.append(2)
+l = m
+l.append(3)
+l",
+            loader
+        )
+        .unwrap()
+        .to_repr()
+    );
+}
+
+#[test]
+fn traceback_through_loaded_module() {
+    let sources = [("//pkg:lib.bzl", "def parse(x):\n  return int(x)\n")];
+    let map = Arc::new(Mutex::new(CodeMap::new()));
+    let globals = crate::stdlib::global_environment();
+    let mut env = globals.freeze().child("main");
+    let (d, traceback) = eval_with_traceback(
+        &map,
+        "main.bzl",
+        "load('//pkg:lib.bzl', 'parse')\nparse('a')\n",
+        Dialect::Bzl,
+        &mut env,
+        TypeValues::new(globals),
+        MapFileLoader::new(&sources),
+        &EvaluationOptions::default(),
+    )
+    .unwrap_err();
+    let frames: Vec<_> = traceback
+        .unwrap()
+        .frames
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    assert_eq!(
+        vec![
+            "main.bzl:2: in <module>",
+            "//pkg:lib.bzl:2: in parse(x)",
+            "<native>: in int($a, base)",
+        ],
+        frames
+    );
+    // The frames of the loaded module and of the native function are rendered too.
+    assert!(d.message.ends_with(&frames.join("\n  ")), "{}", d.message);
+}
+
+#[test]
+fn load_cycle() {
+    fn assert_cycle(sources: &[(&'static str, &'static str)], content: &str, cycle: &str) {
+        let loader = MapFileLoader::new(sources);
+        let codemap = loader.codemap.clone();
+        let err = eval_with_loader(content, loader).unwrap_err();
+        assert_eq!(Some(LOAD_CYCLE_ERROR_CODE.to_owned()), err.code);
+        assert_eq!(
+            format!("Cycle in load() statements: {}", cycle),
+            err.message
+        );
+        // The span is the load statement closing the cycle, on the last line of the module.
+        let loc = codemap.lock().unwrap().look_up_span(err.spans[0].span);
+        let last = cycle.rsplit(" -> ").nth(1).unwrap();
+        assert_eq!(last, loc.file.name());
+        assert_eq!(1, loc.begin.line);
+    }
+
+    assert_cycle(
+        &[
+            ("a.bzl", "a = 1\nload('b.bzl', 'b')"),
+            ("b.bzl", "b = 2\nload('a.bzl', 'a')"),
+        ],
+        "load('a.bzl', 'a')",
+        "a.bzl -> b.bzl -> a.bzl",
+    );
+    assert_cycle(
+        &[
+            ("a.bzl", "a = 1\nload('b.bzl', 'b')"),
+            ("b.bzl", "b = 2\nload('c.bzl', 'c')"),
+            ("c.bzl", "c = 3\nload('a.bzl', 'a')"),
+        ],
+        "load('a.bzl', 'a')",
+        "a.bzl -> b.bzl -> c.bzl -> a.bzl",
+    );
+    assert_cycle(
+        &[("a.bzl", "a = 1\nload('main.bzl', 'x')")],
+        "x = 1\nload('a.bzl', 'a')",
+        "main.bzl -> a.bzl -> main.bzl",
+    );
+    assert_cycle(
+        &[("a.bzl", "a = 1\nload('a.bzl', 'a')")],
+        "load('a.bzl', 'a')",
+        "a.bzl -> a.bzl",
+    );
+}
+
+#[test]
+fn load_diamond() {
+    let loader = MapFileLoader::new(&[
+        ("b.bzl", "load('d.bzl', 'd')\nb = d + 1"),
+        ("c.bzl", "load('d.bzl', 'd')\nc = d + 2"),
+        ("d.bzl", "d = 10"),
+    ]);
+    let evaluations = loader.evaluations.clone();
+    // `d.bzl` is loaded three times, which is not a cycle.
+    let content = "load('b.bzl', 'b')\nload('c.bzl', 'c')\nload('d.bzl', 'd')\nb + c + d";
+    assert_eq!(Value::new(33), eval_with_loader(content, loader).unwrap());
+    assert_eq!(3, evaluations.get());
+    // Nothing is left loading after a cycle was reported.
+    let cycle = MapFileLoader::new(&[("a.bzl", "load('a.bzl', 'a')")]);
+    assert!(eval_with_loader("load('a.bzl', 'a')", cycle).is_err());
+    let loader = MapFileLoader::new(&[("a.bzl", "a = 1")]);
+    assert_eq!(
+        Value::new(1),
+        eval_with_loader("load('a.bzl', 'a')\na", loader).unwrap()
+    );
+}
+
+fn eval_with_dialect(dialect: Dialect, content: &str) -> Result<Value, Diagnostic> {
+    let mut env = crate::stdlib::global_environment().child("test");
+    noload::eval(
+        &Arc::new(Mutex::new(CodeMap::new())),
+        "test.bzl",
+        content,
+        dialect,
+        &mut env,
+        TypeValues::new(crate::stdlib::global_environment()),
+    )
+}
+
+#[test]
+fn dialect_disabled_features() {
+    fn assert_not_allowed(dialect: Dialect, content: &str, flag: &str) {
+        let err = eval_with_dialect(dialect, content).unwrap_err();
+        assert_eq!(Some("CS10".to_owned()), err.code, "{}", content);
+        assert!(
+            err.message.contains(&format!("Dialect::{}", flag)),
+            "{}",
+            err.message
+        );
+    }
+
+    assert_not_allowed(Dialect::Build, "def f(): pass", "allow_def");
+    assert_not_allowed(
+        Dialect::Bzl,
+        "for x in []: pass",
+        "allow_toplevel_statements",
+    );
+    assert_not_allowed(Dialect::Bzl, "if True: pass", "allow_toplevel_statements");
+    assert_not_allowed(Dialect::Bzl, "def f():\n  while False: pass", "allow_while");
+    assert_not_allowed(Dialect::Bzl, "f = lambda: 1", "allow_lambda");
+    assert_not_allowed(Dialect::Bzl, "def f(x = lambda: 1): pass", "allow_lambda");
+    let no_sets = Dialect {
+        allow_set_literals: false,
+        ..Dialect::Bzl
+    };
+    assert_not_allowed(no_sets, "[{1, 2}]", "allow_set_literals");
+    assert_not_allowed(no_sets, "{x for x in []}", "allow_set_literals");
+    // `while` outside of a `def` needs both flags.
+    let with_while = Dialect {
+        allow_while: true,
+        ..Dialect::Bzl
+    };
+    assert_not_allowed(with_while, "while False: pass", "allow_toplevel_statements");
+
+    let err = eval_with_dialect(Dialect::Bzl, "def f(n): return f(n)\nf(1)").unwrap_err();
+    assert_eq!(Some(RECURSION_ERROR_CODE.to_owned()), err.code);
+    assert!(err.message.contains("Dialect::allow_recursion"));
+}
+
+#[test]
+fn dialect_toplevel_statements() {
+    let dialect = Dialect {
+        allow_toplevel_statements: true,
+        ..Dialect::Bzl
+    };
+    let r = eval_with_dialect(
+        dialect,
+        "r = []\nfor x in range(3):\n  if x != 1:\n    r.append(x)\nr",
+    );
+    assert_eq!("[0, 2]", r.unwrap().to_repr());
+    // Still no `def` in BUILD files.
+    assert!(eval_with_dialect(
+        Dialect {
+            allow_toplevel_statements: true,
+            ..Dialect::Build
+        },
+        "def f(): pass"
+    )
+    .is_err());
+}
+
+#[test]
+fn pass_break_continue() {
+    let eval = |content| eval_with_dialect(Dialect::Bzl, content).unwrap().to_repr();
+    assert_eq!("None", eval("def f():\n  pass\nf()"));
+    assert_eq!(
+        "2",
+        eval("def f(x):\n  if x:\n    pass\n  else:\n    return 1\n  return 2\nf(True)")
+    );
+    assert_eq!(
+        "[1, 3]",
+        eval(
+            r#"
+def f():
+  r = []
+  for x in [1, 2, 3, 4, 5]:
+    if x == 2:
+      continue
+    if x == 4:
+      break
+    r.append(x)
+  return r
+f()"#
+        )
+    );
+    // `break` only exits the innermost loop.
+    assert_eq!(
+        "[(1, 1), (2, 1)]",
+        eval(
+            r#"
+def f():
+  r = []
+  for x in [1, 2]:
+    for y in [1, 2, 3]:
+      if y == 2:
+        break
+      r.append((x, y))
+  return r
+f()"#
+        )
+    );
+}
+
+#[test]
+fn break_continue_outside_loop() {
+    for content in &[
+        "def f():\n  break\n",
+        "def f():\n  if True:\n    continue\n",
+    ] {
+        let err = eval_with_dialect(Dialect::Bzl, content).unwrap_err();
+        assert_eq!(Some("CS09".to_owned()), err.code, "{}", err.message);
+    }
+}
+
+#[test]
+fn dialect_while() {
+    let dialect = Dialect {
+        allow_while: true,
+        ..Dialect::Bzl
+    };
+    let r = eval_with_dialect(
+        dialect,
+        r#"
+def f(n):
+  r = []
+  i = 0
+  while True:
+    i += 1
+    if i > n:
+      break
+    if i % 2 == 0:
+      continue
+    r.append(i)
+  return r
+f(6)
+"#,
+    );
+    assert_eq!("[1, 3, 5]", r.unwrap().to_repr());
+    assert_eq!(
+        "None",
+        eval_with_dialect(dialect, "def f():\n  while []: fail()\nf()")
+            .unwrap()
+            .to_repr()
+    );
+}
+
+#[test]
+fn dialect_lambda() {
+    let dialect = Dialect {
+        allow_lambda: true,
+        ..Dialect::Bzl
+    };
+    let eval = |content| eval_with_dialect(dialect, content).unwrap().to_repr();
+    assert_eq!("3", eval("f = lambda x, y = 2: x + y\nf(1)"));
+    assert_eq!("7", eval("(lambda: 7)()"));
+    assert_eq!(
+        "6",
+        eval("def apply(f, x): return f(x)\napply(lambda x: x * 2, 3)")
+    );
+    // Module variables are looked up when the lambda is called.
+    assert_eq!("2", eval("f = lambda: x\nx = 2\nf()"));
+    // So are the enclosing function locals.
+    assert_eq!(
+        "[10, 10]",
+        eval("def f(n):\n  g = lambda: n\n  n = 10\n  return [g(), (lambda: n)()]\nf(1)")
+    );
+    assert_eq!(
+        "3",
+        eval("def f():\n  g = lambda: h()\n  h = lambda: 3\n  return g()\nf()")
+    );
+    assert_eq!(
+        "10",
+        eval("def f():\n  g = lambda: n\n  n = 10\n  return g\nf()()")
+    );
+    // Local variable referenced before assignment.
+    let err = eval_with_dialect(dialect, "def f():\n  g = lambda: n\n  g()\n  n = 1\nf()");
+    assert_eq!(Some("CM03"), err.unwrap_err().code.as_deref());
+    assert_eq!(
+        "[2, 4]",
+        eval("def f(y):\n  return [(lambda: x * y)() for x in [1, 2]]\nf(2)")
+    );
+}
+
+#[test]
+fn dialect_recursion() {
+    let dialect = Dialect {
+        allow_recursion: true,
+        ..Dialect::Bzl
+    };
+    let r = eval_with_dialect(
+        dialect,
+        "def fact(n):\n  return 1 if n <= 1 else n * fact(n - 1)\nfact(10)",
+    );
+    assert_eq!("3628800", r.unwrap().to_repr());
+    // Unbounded recursion is stopped by the maximum call depth, which is larger than what
+    // the default test thread stack fits in debug builds.
+    let err = std::thread::Builder::new()
+        .stack_size(256 << 20)
+        .spawn(move || {
+            eval_with_dialect(dialect, "def f(n): return f(n)\nf(1)")
+                .unwrap_err()
+                .code
+        })
+        .unwrap()
+        .join()
+        .unwrap();
+    assert_eq!(
+        Some(crate::values::error::CALL_DEPTH_EXCEEDED_ERROR_CODE.to_owned()),
+        err
+    );
+}
+
+#[test]
+fn function_locals() {
+    let eval = |content| eval_with_dialect(Dialect::Bzl, content).unwrap().to_repr();
+    // Parameters, including `*args` and `**kwargs`, are bound to slots.
+    assert_eq!(
+        "(3, [5, 6], {\"k\": 9}, [3])",
+        eval(concat!(
+            "def f(a, b = 2, *args, **kwargs):\n",
+            "  c = a + b\n",
+            "  l = [c + x for x in args if x != a]\n",
+            "  return (c, l, {k: v * c for k, v in kwargs.items()}, args[1:])\n",
+            "f(1, 2, 2, 3, k = 3)"
+        ))
+    );
+    // Comprehension variables shadow the function locals, except in the first iterable.
+    assert_eq!(
+        "(1, [2, 3])",
+        eval("def f():\n  x = 1\n  l = [x for x in [2, 3]]\n  return (x, l)\nf()")
+    );
+    assert_eq!(
+        "[1, 2]",
+        eval("def f(x):\n  return [x for x in x]\nf([1, 2])")
+    );
+    assert_eq!(
+        "[[], [10], [20, 21]]",
+        eval("def f(n):\n  return [[n * i + j for j in range(i)] for i in range(3)]\nf(10)")
+    );
+    // Names which are not locals are resolved in the module.
+    assert_eq!(
+        "[3, 4]",
+        eval("y = 2\ndef f(l):\n  return [len(l) + x + y for x in [0, 1]]\nf([1])")
+    );
+
+    for content in &[
+        "def f():\n  z = y\n  y = 1\nf()",
+        "def f():\n  l = [y for _ in [1]]\n  y = 1\n  return l\nf()",
+        "def f():\n  return [[y for _ in [1]] for _ in [1]]\n  y = 1\nf()",
+    ] {
+        let err = eval_with_dialect(Dialect::Bzl, content).unwrap_err();
+        assert!(
+            err.message
+                .contains("Local variable 'y' referenced before assignment"),
+            "{}",
+            err.message
+        );
+    }
+}
+
+#[test]
+fn bound_methods() {
+    // A method bound to its object outside of a call.
+    assert_eq!(
+        "(True, False)",
+        eval_with_dialect(Dialect::Bzl, "f = 'x'.startswith\n(f('x'), f('y'))")
+            .unwrap()
+            .to_repr()
+    );
+    assert_eq!(
+        "[1, 2]",
+        eval_with_dialect(
+            Dialect::Bzl,
+            "l = []\nappend = l.append\nappend(1)\nappend(2)\nl"
+        )
+        .unwrap()
+        .to_repr()
+    );
+    assert_eq!(
+        "[True, True]",
+        eval_with_dialect(
+            Dialect::Bzl,
+            "[f('a') for f in ['ab'.startswith, 'ba'.endswith]]"
+        )
+        .unwrap()
+        .to_repr()
+    );
+}
+
+#[test]
+fn set_attr() {
+    use crate::testing::{eval_in_env, setup_test_env};
+    use crate::values::error::ValueError;
+    use crate::values::{Mutable, TypedValue};
+
+    // A Bazel-style provider with writable fields.
+    struct Provider(HashMap<String, Value>);
+
+    impl TypedValue for Provider {
+        type Holder = Mutable<Provider>;
+        const TYPE: &'static str = "provider";
+
+        fn values_for_descendant_check_and_freeze<'a>(
+            &'a self,
+        ) -> Box<dyn Iterator<Item = Value> + 'a> {
+            Box::new(self.0.values().cloned())
+        }
+
+        fn get_attr(&self, attribute: &str) -> Result<Value, ValueError> {
+            self.0
+                .get(attribute)
+                .cloned()
+                .ok_or_else(|| ValueError::OperationNotSupported {
+                    op: format!(".{}", attribute),
+                    left: Self::TYPE.to_owned(),
+                    right: None,
+                })
+        }
+
+        fn set_attr(&mut self, attribute: &str, new_value: Value) -> Result<(), ValueError> {
+            self.0.insert(attribute.to_owned(), new_value);
+            Ok(())
+        }
+    }
+
+    let env = setup_test_env();
+    env.set("p", Value::new(Provider(HashMap::new()))).unwrap();
+    let result = eval_in_env(
+        &env,
+        "p.name = 'x'\np.count = 1\np.count += 2\ndef f():\n  p.name += 'y'\nf()\n(p.name, p.count)",
+    )
+    .unwrap();
+    assert_eq!("(\"xy\", 3)", result.to_repr());
+
+    // Attributes of a frozen value cannot be set, nor those of types without the hook.
+    env.get("p").unwrap().freeze();
+    assert_eq!(
+        Some(IMMUTABLE_ERROR_CODE.to_owned()),
+        eval_in_env(&env, "p.name = 'z'").unwrap_err().code
+    );
+    assert!(eval_in_env(&env, "d = {}\nd.name = 'z'").is_err());
+}
@@ -73,8 +73,10 @@
 
 #![deny(intra_doc_link_resolution_failure)]
 
+#[macro_use]
+pub mod testing;
+
 pub mod environment;
-#[doc(hidden)]
 pub mod syntax;
 #[macro_use]
 pub mod values;
@@ -83,3 +85,5 @@ pub mod eval;
 #[macro_use]
 pub mod stdlib;
 pub mod linked_hash_set;
+#[cfg(feature = "msgpack")]
+pub mod msgpack;
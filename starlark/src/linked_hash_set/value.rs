@@ -15,10 +15,11 @@
 //! Define the set type of Starlark
 use crate::linked_hash_set::set_impl::LinkedHashSet;
 use crate::values::error::ValueError;
+use crate::values::frozen::Thaw;
 use crate::values::hashed_value::HashedValue;
 use crate::values::iter::TypedIterable;
 use crate::values::*;
-use std::num::Wrapping;
+use std::sync::Arc;
 
 #[derive(Default, Clone)]
 pub(crate) struct Set {
@@ -151,7 +152,19 @@ impl TypedValue for Set {
         )
     }
 
+    fn share(&self) -> Result<FrozenValue, ValueError> {
+        let content = self
+            .content
+            .iter()
+            .map(|v| v.get_value().share())
+            .collect::<Result<_, _>>()?;
+        Ok(FrozenValue::new(FrozenSet(content)))
+    }
+
     const TYPE: &'static str = "set";
+    fn python_type_name(&self) -> &'static str {
+        "set"
+    }
     fn to_bool(&self) -> bool {
         !self.content.is_empty()
     }
@@ -237,16 +250,6 @@ impl TypedValue for Set {
         }
         Ok(result)
     }
-
-    fn get_hash(&self) -> Result<u64, ValueError> {
-        Ok(self
-            .content
-            .iter()
-            .map(HashedValue::get_hash)
-            .map(Wrapping)
-            .fold(Wrapping(0_u64), |acc, v| acc + v)
-            .0)
-    }
 }
 
 impl TypedIterable for Set {
@@ -255,6 +258,22 @@ impl TypedIterable for Set {
     }
 }
 
+/// A shared set, see [`Value::share`].
+struct FrozenSet(Vec<FrozenValue>);
+
+impl Thaw for FrozenSet {
+    fn thaw(self: Arc<Self>) -> Value {
+        let mut result = Set::default();
+        for v in &self.0 {
+            // The value was hashable when it was shared.
+            result
+                .content
+                .insert_if_absent(HashedValue::new(v.to_value()).unwrap());
+        }
+        Value::new(result)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -263,8 +282,8 @@ mod tests {
     fn test_to_str() {
         assert_eq!("{1, 2, 3}", Set::from(vec![1, 2, 3]).unwrap().to_str());
         assert_eq!(
-            "{1, {2, 3}}",
-            Set::from(vec![Value::from(1), Set::from(vec![2, 3]).unwrap()])
+            "{1, (2, 3)}",
+            Set::from(vec![Value::from(1), Value::from((2, 3))])
                 .unwrap()
                 .to_str()
         );
@@ -295,19 +314,21 @@ mod tests {
 
     #[test]
     fn test_is_descendant() {
-        let v1 = Set::from(vec![1, 2, 3]).unwrap();
-        let v2 = Set::from(vec![Value::new(1), Value::new(2), v1.clone()]).unwrap();
-        let v3 = Set::from(vec![Value::new(1), Value::new(2), v2.clone()]).unwrap();
-        assert!(v3.is_descendant_value(&v2));
-        assert!(v3.is_descendant_value(&v1));
-        assert!(v3.is_descendant_value(&v3));
-
+        let v1 = Value::from((1, 2));
+        let v2 = Set::from(vec![Value::new(1), v1.clone()]).unwrap();
+        let v3 = Set::from(vec![1, 2]).unwrap();
         assert!(v2.is_descendant_value(&v1));
         assert!(v2.is_descendant_value(&v2));
         assert!(!v2.is_descendant_value(&v3));
 
-        assert!(v1.is_descendant_value(&v1));
         assert!(!v1.is_descendant_value(&v2));
-        assert!(!v1.is_descendant_value(&v3));
+        assert!(!v3.is_descendant_value(&v2));
+    }
+
+    #[test]
+    fn test_not_hashable() {
+        let set = Set::from(vec![1, 2]).unwrap();
+        assert_eq!(Err(ValueError::NotHashableValue), set.get_hash());
+        assert!(Set::from(vec![Value::new(1), set]).is_err());
     }
 }
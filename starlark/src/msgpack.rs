@@ -0,0 +1,525 @@
+// Copyright 2019 The Starlark in Rust Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Conversion of values from and to [MessagePack](https://msgpack.org/).
+//!
+//! | Starlark          | MessagePack |
+//! |-------------------|-------------|
+//! | `None`            | nil         |
+//! | `bool`            | boolean     |
+//! | `int`             | integer     |
+//! | `string`          | string      |
+//! | `bytes`           | binary      |
+//! | `list`, `tuple`   | array       |
+//! | `dict`            | map         |
+//!
+//! Other values cannot be serialized. Integers are encoded in their most compact form.
+//! There is no Starlark type for MessagePack floats and extension types, they cannot be
+//! deserialized.
+
+use crate::eval::call_stack;
+use crate::values::bytes::Bytes;
+use crate::values::dict::Dictionary;
+use crate::values::error::{RuntimeError, ValueError};
+use crate::values::none::NoneType;
+use crate::values::Value;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+
+// VC prefix = Value Conversion, from or to a serialization format
+pub const MSGPACK_ERROR_CODE: &str = "VC02";
+
+/// Error of the serialization or deserialization of a value.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MsgPackError {
+    /// A value cannot be serialized or deserialized, e.g. a function or a float.
+    Value(ValueError),
+    /// A string, binary, array or map has more than `u32::MAX` elements.
+    TooLarge(usize),
+    /// The data ends in the middle of a value.
+    UnexpectedEof,
+    /// A value starts with a byte which is not a supported MessagePack type.
+    UnsupportedMarker { marker: u8, offset: usize },
+    /// The string at `offset` is not valid UTF-8.
+    InvalidUtf8 { offset: usize },
+    /// The data continues after the end of the value.
+    TrailingData { offset: usize },
+}
+
+impl fmt::Display for MsgPackError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MsgPackError::Value(e) => fmt::Display::fmt(e, f),
+            MsgPackError::TooLarge(len) => {
+                write!(f, "Length {} is too large for MessagePack", len)
+            }
+            MsgPackError::UnexpectedEof => write!(f, "Unexpected end of MessagePack data"),
+            MsgPackError::UnsupportedMarker { marker, offset } => write!(
+                f,
+                "Unsupported MessagePack type 0x{:02x} at offset {}",
+                marker, offset
+            ),
+            MsgPackError::InvalidUtf8 { offset } => {
+                write!(f, "Invalid UTF-8 in the string at offset {}", offset)
+            }
+            MsgPackError::TrailingData { offset } => {
+                write!(f, "Unexpected data after the value at offset {}", offset)
+            }
+        }
+    }
+}
+
+impl Error for MsgPackError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            MsgPackError::Value(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<ValueError> for MsgPackError {
+    fn from(e: ValueError) -> Self {
+        MsgPackError::Value(e)
+    }
+}
+
+impl From<MsgPackError> for ValueError {
+    fn from(e: MsgPackError) -> Self {
+        match e {
+            MsgPackError::Value(e) => e,
+            e => RuntimeError {
+                code: MSGPACK_ERROR_CODE,
+                message: e.to_string(),
+                label: "Invalid MessagePack".to_owned(),
+            }
+            .into(),
+        }
+    }
+}
+
+/// Write the header of a string, binary, array or map of `len` elements: `fixed` (a
+/// marker and the exclusive limit of the lengths it can hold) or-ed with the length if
+/// possible, otherwise the marker of the smallest of the 8 (if any), 16 or 32 bits
+/// lengths followed by the length.
+fn encode_header(
+    out: &mut Vec<u8>,
+    len: usize,
+    fixed: Option<(u8, usize)>,
+    markers: (Option<u8>, u8, u8),
+) -> Result<(), MsgPackError> {
+    match (fixed, markers.0) {
+        (Some((marker, limit)), _) if len < limit => out.push(marker | len as u8),
+        (_, Some(marker)) if len <= 0xff => {
+            out.push(marker);
+            out.push(len as u8);
+        }
+        _ if len <= 0xffff => {
+            out.push(markers.1);
+            out.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        _ => {
+            let len = u32::try_from(len).map_err(|_| MsgPackError::TooLarge(len))?;
+            out.push(markers.2);
+            out.extend_from_slice(&len.to_be_bytes());
+        }
+    }
+    Ok(())
+}
+
+fn encode_int(out: &mut Vec<u8>, i: i64) {
+    if (-32..=0x7f).contains(&i) {
+        // Positive and negative fixint
+        out.push(i as u8);
+    } else if i > 0 {
+        if i <= 0xff {
+            out.push(0xcc);
+            out.push(i as u8);
+        } else if i <= 0xffff {
+            out.push(0xcd);
+            out.extend_from_slice(&(i as u16).to_be_bytes());
+        } else if i <= 0xffff_ffff {
+            out.push(0xce);
+            out.extend_from_slice(&(i as u32).to_be_bytes());
+        } else {
+            out.push(0xcf);
+            out.extend_from_slice(&(i as u64).to_be_bytes());
+        }
+    } else if i >= i64::from(i8::MIN) {
+        out.push(0xd0);
+        out.push(i as u8);
+    } else if i >= i64::from(i16::MIN) {
+        out.push(0xd1);
+        out.extend_from_slice(&(i as i16).to_be_bytes());
+    } else if i >= i64::from(i32::MIN) {
+        out.push(0xd2);
+        out.extend_from_slice(&(i as i32).to_be_bytes());
+    } else {
+        out.push(0xd3);
+        out.extend_from_slice(&i.to_be_bytes());
+    }
+}
+
+fn encode(out: &mut Vec<u8>, v: &Value) -> Result<(), MsgPackError> {
+    let _stack_depth_guard = call_stack::try_inc()?;
+    match v.get_type() {
+        "NoneType" => out.push(0xc0),
+        "bool" => out.push(if v.to_bool() { 0xc3 } else { 0xc2 }),
+        "int" => encode_int(out, v.to_int()?),
+        "string" => {
+            let s = v.to_str();
+            encode_header(out, s.len(), Some((0xa0, 32)), (Some(0xd9), 0xda, 0xdb))?;
+            out.extend_from_slice(s.as_bytes());
+        }
+        "bytes" => {
+            let b = v.downcast_ref::<Bytes>().unwrap();
+            let b = b.as_bytes();
+            encode_header(out, b.len(), None, (Some(0xc4), 0xc5, 0xc6))?;
+            out.extend_from_slice(b);
+        }
+        "list" | "tuple" => {
+            encode_header(
+                out,
+                v.length()? as usize,
+                Some((0x90, 16)),
+                (None, 0xdc, 0xdd),
+            )?;
+            for x in &v.iter()? {
+                encode(out, &x)?;
+            }
+        }
+        "dict" => {
+            encode_header(
+                out,
+                v.length()? as usize,
+                Some((0x80, 16)),
+                (None, 0xde, 0xdf),
+            )?;
+            for k in &v.iter()? {
+                encode(out, &k)?;
+                encode(out, &v.at(k)?)?;
+            }
+        }
+        t => {
+            return Err(ValueError::TypeNotX {
+                object_type: t.to_owned(),
+                op: "MessagePack serializable".to_owned(),
+            }
+            .into())
+        }
+    }
+    Ok(())
+}
+
+/// A reader of MessagePack values.
+struct Decoder<'a> {
+    data: &'a [u8],
+    /// Offset of the next byte to read in `data`.
+    pos: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], MsgPackError> {
+        if self.data.len() - self.pos < len {
+            return Err(MsgPackError::UnexpectedEof);
+        }
+        let r = &self.data[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(r)
+    }
+
+    /// A big-endian unsigned integer of `len` bytes.
+    fn uint(&mut self, len: usize) -> Result<u64, MsgPackError> {
+        Ok(self
+            .take(len)?
+            .iter()
+            .fold(0, |r, &b| (r << 8) | u64::from(b)))
+    }
+
+    /// A length of `len` bytes.
+    fn len(&mut self, len: usize) -> Result<usize, MsgPackError> {
+        Ok(self.uint(len)? as usize)
+    }
+
+    fn string(&mut self, len: usize) -> Result<Value, MsgPackError> {
+        let offset = self.pos;
+        match std::str::from_utf8(self.take(len)?) {
+            Ok(s) => Ok(Value::from(s)),
+            Err(..) => Err(MsgPackError::InvalidUtf8 { offset }),
+        }
+    }
+
+    fn binary(&mut self, len: usize) -> Result<Value, MsgPackError> {
+        Ok(Value::new(Bytes::new(self.take(len)?.to_vec())))
+    }
+
+    fn array(&mut self, len: usize) -> Result<Value, MsgPackError> {
+        // The length is not trusted to preallocate the list.
+        let mut values = Vec::new();
+        for _ in 0..len {
+            values.push(self.decode()?);
+        }
+        Ok(Value::from(values))
+    }
+
+    fn map(&mut self, len: usize) -> Result<Value, MsgPackError> {
+        let mut dict = Dictionary::new();
+        for _ in 0..len {
+            let k = self.decode()?;
+            let v = self.decode()?;
+            dict.set_at(k, v)?;
+        }
+        Ok(dict)
+    }
+
+    fn decode(&mut self) -> Result<Value, MsgPackError> {
+        let _stack_depth_guard = call_stack::try_inc()?;
+        let offset = self.pos;
+        let marker = self.take(1)?[0];
+        Ok(match marker {
+            0x00..=0x7f => Value::new(i64::from(marker)),
+            0x80..=0x8f => self.map(usize::from(marker & 0x0f))?,
+            0x90..=0x9f => self.array(usize::from(marker & 0x0f))?,
+            0xa0..=0xbf => self.string(usize::from(marker & 0x1f))?,
+            0xc0 => Value::new(NoneType::None),
+            0xc2 => Value::new(false),
+            0xc3 => Value::new(true),
+            0xc4 => {
+                let len = self.len(1)?;
+                self.binary(len)?
+            }
+            0xc5 => {
+                let len = self.len(2)?;
+                self.binary(len)?
+            }
+            0xc6 => {
+                let len = self.len(4)?;
+                self.binary(len)?
+            }
+            0xca | 0xcb => return Err(ValueError::TypeNotSupported("float".to_owned()).into()),
+            0xcc => Value::new(self.uint(1)? as i64),
+            0xcd => Value::new(self.uint(2)? as i64),
+            0xce => Value::new(self.uint(4)? as i64),
+            0xcf => match i64::try_from(self.uint(8)?) {
+                Ok(i) => Value::new(i),
+                Err(..) => return Err(ValueError::IntegerOverflow.into()),
+            },
+            0xd0 => Value::new(i64::from(self.uint(1)? as u8 as i8)),
+            0xd1 => Value::new(i64::from(self.uint(2)? as u16 as i16)),
+            0xd2 => Value::new(i64::from(self.uint(4)? as u32 as i32)),
+            0xd3 => Value::new(self.uint(8)? as i64),
+            0xd9 => {
+                let len = self.len(1)?;
+                self.string(len)?
+            }
+            0xda => {
+                let len = self.len(2)?;
+                self.string(len)?
+            }
+            0xdb => {
+                let len = self.len(4)?;
+                self.string(len)?
+            }
+            0xdc => {
+                let len = self.len(2)?;
+                self.array(len)?
+            }
+            0xdd => {
+                let len = self.len(4)?;
+                self.array(len)?
+            }
+            0xde => {
+                let len = self.len(2)?;
+                self.map(len)?
+            }
+            0xdf => {
+                let len = self.len(4)?;
+                self.map(len)?
+            }
+            0xe0..=0xff => Value::new(i64::from(marker as i8)),
+            // Never used (0xc1) and extension types
+            _ => return Err(MsgPackError::UnsupportedMarker { marker, offset }),
+        })
+    }
+}
+
+/// Encode `v` as MessagePack.
+///
+/// ```
+/// # use starlark::msgpack::{deserialize_msgpack, serialize_msgpack};
+/// # use starlark::values::Value;
+/// let v = Value::from(vec![Value::from(1), Value::from("a")]);
+/// assert_eq!(vec![0x92, 0x01, 0xa1, b'a'], serialize_msgpack(&v).unwrap());
+/// assert_eq!(v, deserialize_msgpack(&serialize_msgpack(&v).unwrap()).unwrap());
+/// ```
+pub fn serialize_msgpack(v: &Value) -> Result<Vec<u8>, MsgPackError> {
+    let mut out = Vec::new();
+    encode(&mut out, v)?;
+    Ok(out)
+}
+
+/// Decode the MessagePack value `data`, which must not contain anything else.
+pub fn deserialize_msgpack(data: &[u8]) -> Result<Value, MsgPackError> {
+    let mut decoder = Decoder { data, pos: 0 };
+    let v = decoder.decode()?;
+    if decoder.pos != data.len() {
+        return Err(MsgPackError::TrailingData {
+            offset: decoder.pos,
+        });
+    }
+    Ok(v)
+}
+
+impl Value {
+    /// Encode this value as MessagePack, see [`serialize_msgpack`].
+    pub fn serialize_msgpack(&self) -> Result<Vec<u8>, MsgPackError> {
+        serialize_msgpack(self)
+    }
+
+    /// Decode a MessagePack value, see [`deserialize_msgpack`].
+    pub fn deserialize_msgpack(data: &[u8]) -> Result<Value, MsgPackError> {
+        deserialize_msgpack(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::setup_test_env;
+
+    fn eval(content: &str) -> Value {
+        eval_in_env!(setup_test_env(), content)
+    }
+
+    #[test]
+    fn round_trip() {
+        for content in &[
+            "None",
+            "True",
+            "False",
+            "[0, 1, -1, 127, 128, -32, -33, 255, 256, -128, -129]",
+            "[65535, 65536, -32768, -32769, 4294967295, 4294967296, -2147483648, -2147483649]",
+            "[9223372036854775807, -9223372036854775807 - 1]",
+            "''",
+            "'a' * 31",
+            "'a' * 32",
+            "'a' * 256",
+            "'a' * 65536",
+            "'世界'",
+            "b''",
+            "bytes(list(range(256)) * 2)",
+            "[]",
+            "list(range(15))",
+            "list(range(16))",
+            "list(range(70000))",
+            "{}",
+            "{'a': 1, 2: 'b', None: [True, {}], b'k': (1, 2)}",
+            "{i: str(i) for i in range(100)}",
+        ] {
+            let v = eval(content);
+            let data = v.serialize_msgpack().unwrap();
+            // Tuples are deserialized as lists.
+            let v = eval(&format!("repr({})", content.replace("(1, 2)", "[1, 2]")));
+            assert_eq!(
+                v.to_str(),
+                Value::deserialize_msgpack(&data).unwrap().to_repr(),
+                "{}",
+                content
+            );
+        }
+    }
+
+    #[test]
+    fn encoding() {
+        assert_eq!(vec![0xc0], serialize_msgpack(&eval("None")).unwrap());
+        assert_eq!(vec![0xe0], serialize_msgpack(&eval("-32")).unwrap());
+        assert_eq!(vec![0xd0, 0xdf], serialize_msgpack(&eval("-33")).unwrap());
+        assert_eq!(
+            vec![0xcd, 0x01, 0x00],
+            serialize_msgpack(&eval("256")).unwrap()
+        );
+        assert_eq!(
+            vec![0x81, 0xa1, b'a', 0x92, 0xc3, 0xc4, 0x01, 0xff],
+            serialize_msgpack(&eval("{'a': (True, b'\\xff')}")).unwrap()
+        );
+        assert_eq!(
+            vec![0xdc, 0x00, 0x10],
+            serialize_msgpack(&eval("[None] * 16")).unwrap()[..3].to_vec()
+        );
+        match serialize_msgpack(&eval("[len]")) {
+            Err(MsgPackError::Value(ValueError::TypeNotX { .. })) => {}
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn more_compact_than_json() {
+        let v = eval("[i * 1000 for i in range(1000)]");
+        let msgpack = serialize_msgpack(&v).unwrap();
+        let json = v.to_json().unwrap();
+        assert!(
+            msgpack.len() < json.len(),
+            "{} bytes of MessagePack, {} bytes of JSON",
+            msgpack.len(),
+            json.len()
+        );
+    }
+
+    #[test]
+    fn decoding_errors() {
+        assert_eq!(Err(MsgPackError::UnexpectedEof), deserialize_msgpack(&[]));
+        assert_eq!(
+            Err(MsgPackError::UnexpectedEof),
+            deserialize_msgpack(&[0x92, 0x01])
+        );
+        assert_eq!(
+            Err(MsgPackError::UnexpectedEof),
+            deserialize_msgpack(&[0xdb, 0xff, 0xff, 0xff, 0xff, b'a'])
+        );
+        assert_eq!(
+            Err(MsgPackError::TrailingData { offset: 1 }),
+            deserialize_msgpack(&[0x01, 0x02])
+        );
+        assert_eq!(
+            Err(MsgPackError::UnsupportedMarker {
+                marker: 0xc1,
+                offset: 1
+            }),
+            deserialize_msgpack(&[0x91, 0xc1])
+        );
+        assert_eq!(
+            Err(MsgPackError::InvalidUtf8 { offset: 1 }),
+            deserialize_msgpack(&[0xa1, 0xff])
+        );
+        match deserialize_msgpack(&[0xca, 0, 0, 0, 0]) {
+            Err(MsgPackError::Value(ValueError::TypeNotSupported(t))) => assert_eq!("float", t),
+            r => panic!("unexpected result {:?}", r),
+        }
+        assert_eq!(
+            Err(MsgPackError::Value(ValueError::IntegerOverflow)),
+            deserialize_msgpack(&[0xcf, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff])
+        );
+        // Lists are not hashable
+        assert_eq!(
+            Err(MsgPackError::Value(ValueError::NotHashableValue)),
+            deserialize_msgpack(&[0x81, 0x90, 0xc0])
+        );
+        let e: ValueError = MsgPackError::UnexpectedEof.into();
+        match e {
+            ValueError::Runtime(e) => assert_eq!(MSGPACK_ERROR_CODE, e.code),
+            e => panic!("unexpected error {:?}", e),
+        }
+    }
+}
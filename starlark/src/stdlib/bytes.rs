@@ -0,0 +1,288 @@
+// Copyright 2019 The Starlark in Rust Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The `bytes()` function and the methods of the `bytes` type.
+//!
+//! The supported encodings are `utf-8`, `ascii` and `latin-1`.
+
+use crate::values::bytes::Bytes;
+use crate::values::error::*;
+use crate::values::*;
+use std::fmt::Write;
+
+// Errors -- UF = User Failure -- Failure that should be expected by the user (e.g. from a fail()).
+pub const BYTES_DECODE_ERROR_CODE: &str = "UF60";
+pub const BYTES_ENCODE_ERROR_CODE: &str = "UF61";
+pub const UNKNOWN_ENCODING_ERROR_CODE: &str = "UF62";
+pub const BYTE_OUT_OF_RANGE_ERROR_CODE: &str = "UF63";
+
+macro_rules! ok {
+    ($e:expr) => {
+        return Ok(Value::from($e));
+    };
+}
+
+enum Encoding {
+    Utf8,
+    Ascii,
+    Latin1,
+}
+
+impl Encoding {
+    fn from_name(name: &str) -> Result<Encoding, ValueError> {
+        match name.to_lowercase().replace('_', "-").as_str() {
+            "utf-8" | "utf8" => Ok(Encoding::Utf8),
+            "ascii" | "us-ascii" => Ok(Encoding::Ascii),
+            "latin-1" | "latin1" | "iso-8859-1" => Ok(Encoding::Latin1),
+            _ => starlark_err!(
+                UNKNOWN_ENCODING_ERROR_CODE,
+                format!(
+                    "Unknown encoding {:?}, supported encodings are utf-8, ascii and latin-1",
+                    name
+                ),
+                "Unknown encoding".to_owned()
+            ),
+        }
+    }
+
+    /// The highest code point which can be encoded.
+    fn max_char(&self) -> char {
+        match self {
+            Encoding::Utf8 => std::char::MAX,
+            Encoding::Ascii => '\x7f',
+            Encoding::Latin1 => '\u{ff}',
+        }
+    }
+}
+
+/// Encode `s` with the encoding named `encoding`, for `string.encode`.
+pub(crate) fn encode_string(s: &str, encoding: &str) -> Result<Bytes, ValueError> {
+    let encoding = Encoding::from_name(encoding)?;
+    if let Encoding::Utf8 = encoding {
+        return Ok(Bytes::new(s.as_bytes().to_vec()));
+    }
+    let max = encoding.max_char();
+    let mut content = Vec::with_capacity(s.len());
+    for (i, c) in s.chars().enumerate() {
+        if c > max {
+            starlark_err!(
+                BYTES_ENCODE_ERROR_CODE,
+                format!(
+                    "Character {:?} at index {} cannot be encoded in {}",
+                    c,
+                    i,
+                    encoding_name(&encoding)
+                ),
+                "Cannot encode".to_owned()
+            );
+        }
+        content.push(c as u8);
+    }
+    Ok(Bytes::new(content))
+}
+
+/// Decode `b` with the encoding named `encoding`, for `bytes.decode`.
+fn decode_bytes(b: &[u8], encoding: &str) -> Result<String, ValueError> {
+    let encoding = Encoding::from_name(encoding)?;
+    let invalid = match encoding {
+        Encoding::Utf8 => match std::str::from_utf8(b) {
+            Ok(s) => return Ok(s.to_owned()),
+            Err(e) => e.valid_up_to(),
+        },
+        Encoding::Ascii => match b.iter().position(|&c| c > 0x7f) {
+            Some(i) => i,
+            None => return Ok(b.iter().map(|&c| c as char).collect()),
+        },
+        Encoding::Latin1 => return Ok(b.iter().map(|&c| c as char).collect()),
+    };
+    starlark_err!(
+        BYTES_DECODE_ERROR_CODE,
+        format!(
+            "Byte 0x{:02x} at index {} is not valid {}",
+            b[invalid],
+            invalid,
+            encoding_name(&encoding)
+        ),
+        "Cannot decode".to_owned()
+    )
+}
+
+fn encoding_name(encoding: &Encoding) -> &'static str {
+    match encoding {
+        Encoding::Utf8 => "utf-8",
+        Encoding::Ascii => "ascii",
+        Encoding::Latin1 => "latin-1",
+    }
+}
+
+starlark_module! {global =>
+    /// [bytes](
+    /// https://github.com/bazelbuild/starlark/blob/master/spec.md#bytes
+    /// ): construct a bytes value.
+    ///
+    /// `bytes(x)` returns `x` if it is a `bytes` value, the UTF-8 encoding of `x` if it
+    /// is a string, and the bytes of the elements of `x` if it is an iterable of integers
+    /// between 0 and 255.
+    ///
+    /// Examples:
+    ///
+    /// ```
+    /// # use starlark::stdlib::starlark_default;
+    /// # assert!(starlark_default(r#"(
+    /// bytes("hé") == b"h\xc3\xa9"
+    /// # and
+    /// bytes([104, 105]) == b"hi"
+    /// # )"#).unwrap());
+    /// ```
+    bytes(#x) {
+        if x.get_type() == "bytes" {
+            return Ok(x);
+        }
+        if let Some(s) = x.as_str() {
+            ok!(Bytes::new(s.as_bytes().to_vec()))
+        }
+        let mut content = Vec::new();
+        for b in &x.iter()? {
+            let b = b.to_int()?;
+            if !(0..=255).contains(&b) {
+                starlark_err!(
+                    BYTE_OUT_OF_RANGE_ERROR_CODE,
+                    format!("bytes() elements must be between 0 and 255, got {}", b),
+                    "Not a byte".to_owned()
+                );
+            }
+            content.push(b as u8);
+        }
+        ok!(Bytes::new(content))
+    }
+
+    /// bytes.decode: decode bytes into a string.
+    ///
+    /// `B.decode(encoding="utf-8")` returns the string encoded by `B` in `encoding`, and
+    /// fails if `B` is not a valid encoding.
+    ///
+    /// Examples:
+    ///
+    /// ```
+    /// # use starlark::stdlib::starlark_default;
+    /// # assert!(starlark_default(r#"(
+    /// b"h\xc3\xa9".decode() == "hé"
+    /// # and
+    /// b"h\xe9".decode("latin-1") == "hé"
+    /// # )"#).unwrap());
+    /// ```
+    bytes.decode(this: Bytes, encoding: String = "utf-8".to_owned()) {
+        ok!(decode_bytes(this.as_bytes(), &encoding)?)
+    }
+
+    /// bytes.hex: returns the hexadecimal representation of bytes.
+    ///
+    /// `B.hex()` returns a string with two lowercase hexadecimal digits per byte of `B`.
+    ///
+    /// Examples:
+    ///
+    /// ```
+    /// # use starlark::stdlib::starlark_default;
+    /// # assert!(starlark_default(r#"(
+    /// b"\x00\xffa".hex() == "00ff61"
+    /// # )"#).unwrap());
+    /// ```
+    bytes.hex(this: Bytes) {
+        let mut r = String::with_capacity(this.as_bytes().len() * 2);
+        for b in this.as_bytes() {
+            write!(r, "{:02x}", b).unwrap();
+        }
+        ok!(r)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::setup_test_env;
+
+    fn b(content: &[u8]) -> Value {
+        Value::new(Bytes::new(content.to_vec()))
+    }
+
+    #[test]
+    fn test_literals() {
+        let env = setup_test_env();
+        assert_starlark_eq!(env, "b'abc'", b(b"abc"));
+        assert_starlark_eq!(env, "b\"\\x00\\xff\\n\"", b(b"\x00\xff\n"));
+        assert_starlark_eq!(env, "b'\\x01a'", b(b"\x01a"));
+        assert_starlark_eq!(env, "b'\\0101'", b(b"A"));
+        assert_starlark_eq!(env, "b'é'", b("é".as_bytes()));
+        assert_starlark_eq!(env, "rb'\\x00'", b(b"\\x00"));
+        assert_starlark_eq!(env, "br'\\x00'", b(b"\\x00"));
+        assert_starlark_eq!(env, "b'''a\nb'''", b(b"a\nb"));
+        // Identifiers starting with string prefixes are unaffected.
+        eval_in_env!(env, "b = 1\nbr = 2\nrb = 3\nbrb = 4");
+        assert_starlark_eq!(env, "b + br + rb + brb", 10);
+        assert_starlark_eq!(env, "type(b'')", "bytes");
+        assert_starlark_eq!(env, "repr(b'a\\xff')", "b\"a\\xff\"");
+    }
+
+    #[test]
+    fn test_operations() {
+        let env = setup_test_env();
+        assert_starlark_eq!(env, "b'abc'[0]", 97);
+        assert_starlark_eq!(env, "b'abc'[-1]", 99);
+        assert_starlark_eq!(env, "b'abcde'[1:3]", b(b"bc"));
+        assert_starlark_eq!(env, "b'abcde'[::-2]", b(b"eca"));
+        assert_starlark_eq!(env, "b'abc' + b'\\xff'", b(b"abc\xff"));
+        assert_starlark_eq!(env, "len(b'\\xff\\x00')", 2);
+        assert_starlark_eq!(env, "b'bc' in b'abcd'", true);
+        assert_starlark_eq!(env, "98 in b'abc'", true);
+        assert_starlark_eq!(env, "b'ac' in b'abc'", false);
+        assert_starlark_eq!(env, "{b'a': 1}[b'a']", 1);
+        assert_starlark_eq!(env, "b'a' < b'b'", true);
+        assert_starlark_error!(env, "b'a' + 'a'", NOT_SUPPORTED_ERROR_CODE);
+        assert_starlark_error!(env, "b'a'[1]", OUT_OF_BOUND_ERROR_CODE);
+    }
+
+    #[test]
+    fn test_bytes() {
+        let env = setup_test_env();
+        assert_starlark_eq!(env, "bytes(b'a')", b(b"a"));
+        assert_starlark_eq!(env, "bytes('hé')", b("hé".as_bytes()));
+        assert_starlark_eq!(env, "bytes([0, 255])", b(b"\x00\xff"));
+        assert_starlark_eq!(env, "bytes(())", b(b""));
+        assert_starlark_error!(env, "bytes([256])", BYTE_OUT_OF_RANGE_ERROR_CODE);
+        assert_starlark_error!(env, "bytes(1)", NOT_SUPPORTED_ERROR_CODE);
+    }
+
+    #[test]
+    fn test_decode_encode() {
+        let env = setup_test_env();
+        assert_starlark_eq!(env, "b'h\\xc3\\xa9'.decode()", "hé");
+        assert_starlark_eq!(env, "b'h\\xe9'.decode('latin-1')", "hé");
+        assert_starlark_eq!(env, "b'hi'.decode(encoding = 'ASCII')", "hi");
+        assert_starlark_eq!(env, "'hé'.encode()", b("hé".as_bytes()));
+        assert_starlark_eq!(env, "'hé'.encode(encoding = 'latin1')", b(b"h\xe9"));
+        assert_starlark_eq!(env, "'hé'.encode().decode()", "hé");
+        assert_starlark_error!(env, "b'\\xff'.decode()", BYTES_DECODE_ERROR_CODE);
+        assert_starlark_error!(env, "b'\\xe9'.decode('ascii')", BYTES_DECODE_ERROR_CODE);
+        assert_starlark_error!(env, "'é'.encode('ascii')", BYTES_ENCODE_ERROR_CODE);
+        assert_starlark_error!(env, "'a'.encode('utf-16')", UNKNOWN_ENCODING_ERROR_CODE);
+        assert_starlark_error!(env, "b'a'.decode('utf-16')", UNKNOWN_ENCODING_ERROR_CODE);
+    }
+
+    #[test]
+    fn test_hex() {
+        let env = setup_test_env();
+        assert_starlark_eq!(env, "b''.hex()", "");
+        assert_starlark_eq!(env, "b'\\x00\\x0f\\xff'.hex()", "000fff");
+    }
+}
@@ -288,33 +288,30 @@ starlark_module! {global =>
     dict.update(this, ?#pairs, **kwargs) {
         if let Some(pairs) = pairs {
             match pairs.get_type() {
-                "list" => for v in &pairs.iter()? {
-                    if v.length()? != 2 {
+                "NoneType" => (),
+                "dict" => for k in &pairs.iter()? {
+                    this.set_at(k.clone(), pairs.at(k)?)?
+                },
+                _ => for v in &pairs.iter()? {
+                    let pair: Vec<Value> = match v.iter() {
+                        Ok(items) => items.iter().collect(),
+                        Err(..) => Vec::new(),
+                    };
+                    if pair.len() != 2 {
                         starlark_err!(
                             INCORRECT_PARAMETER_TYPE_ERROR_CODE,
-                            concat!(
-                                "dict.update expect a list of pairsor a dictionary as first ",
-                                "argument, got a list of non-pairs."
-                            ).to_owned(),
-                            "list of non-pairs".to_owned()
+                            format!(
+                                concat!(
+                                    "dict.update expect an iterable of pairs or a dictionary ",
+                                    "as first argument, got an element {} which is not a pair."
+                                ),
+                                v.to_repr()
+                            ),
+                            "iterable of non-pairs".to_owned()
                         )
                     }
-                    this.set_at(v.at(Value::new(0))?, v.at(Value::new(1))?)?;
-                },
-                "dict" => for k in &pairs.iter()? {
-                    this.set_at(k.clone(), pairs.at(k)?)?
+                    this.set_at(pair[0].clone(), pair[1].clone())?;
                 },
-                x => starlark_err!(
-                    INCORRECT_PARAMETER_TYPE_ERROR_CODE,
-                    format!(
-                        concat!(
-                            "dict.update expect a list or a dictionary as first argument, ",
-                            "got a value of type {}."
-                        ),
-                        x
-                    ),
-                    format!("type {} while expected list or dict", x)
-                )
             }
         }
 
@@ -353,6 +350,15 @@ mod tests {
     use super::super::starlark_default;
     use super::super::tests::starlark_default_fail;
     use super::*;
+    use crate::environment::Environment;
+    use crate::testing::{eval_in_env, setup_test_env};
+
+    /// An environment whose parent defines the frozen dictionary `d`.
+    fn frozen_dict_env() -> Environment {
+        let parent = setup_test_env();
+        eval_in_env(&parent, r#"d = {"one": 1}"#).unwrap();
+        parent.freeze().child("test")
+    }
 
     macro_rules! starlark_ok {
         ($($t:expr),+) => (starlark_ok_fn!(starlark_default, $($t),+))
@@ -405,6 +411,21 @@ mod tests {
         starlark_fail!(r#"x = {}; x.popitem()"#, POP_ON_EMPTY_DICT_ERROR_CODE);
     }
 
+    #[test]
+    fn test_insertion_order() {
+        // Removing a key keeps the order of the others.
+        starlark_ok!(
+            r#"x = {"a": 1, "b": 2, "c": 3, "d": 4}; x.pop("b"); x["a"] = 5; (
+            x.keys() == ["a", "c", "d"] and x.popitem() == ("a", 5) and x.keys() == ["c", "d"])"#
+        );
+        // Named arguments are collected in call order, **kwargs last.
+        starlark_ok!(
+            r#"def f(**kwargs): return kwargs.keys()
+(f(b = 1, a = 2, **{"d": 3, "c": 4}) == ["b", "a", "d", "c"] and
+ dict([("z", 0)], y = 1, x = 2).keys() == ["z", "y", "x"])"#
+        );
+    }
+
     #[test]
     fn test_setdefault() {
         starlark_ok!(
@@ -415,6 +436,11 @@ mod tests {
             x.setdefault("four") == None and
             x == {"one": 1, "two": 2, "three": 0, "four": None })"#
         );
+        assert_starlark_error!(
+            frozen_dict_env(),
+            r#"d.setdefault("two", 2)"#,
+            IMMUTABLE_ERROR_CODE
+        );
     }
 
     #[test]
@@ -427,6 +453,19 @@ x.update({"d": 4})
 x.update(e=5)
 (x == {"a": 1, "b": 2, "c": 3, "d": 4, "e": 5})"#
         );
+        // Existing keys keep their position, the keyword arguments are applied last.
+        starlark_ok!(
+            r#"
+x = {"a": 1, "b": 2}
+x.update((("c", 3), ["a", 4]), c=5, b=6)
+x.update(None)
+(x.items() == [("a", 4), ("b", 6), ("c", 5)])"#
+        );
+        starlark_fail!(
+            r#"x = {}; x.update([("a", 1, 2)])"#,
+            INCORRECT_PARAMETER_TYPE_ERROR_CODE
+        );
+        assert_starlark_error!(frozen_dict_env(), "d.update(a=1)", IMMUTABLE_ERROR_CODE);
     }
 
     #[test]
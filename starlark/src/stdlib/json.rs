@@ -0,0 +1,427 @@
+// Copyright 2019 The Starlark in Rust Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Implementation of the `json` module, modeled after the
+//! [Go `starlark-json` module](https://github.com/google/starlark-go/blob/master/lib/json/json.go).
+//!
+//! Values are encoded with [`Value::to_json`](crate::values::Value::to_json), so types defined
+//! outside of this crate can be encoded by implementing
+//! [`TypedValue::to_json`](crate::values::TypedValue::to_json). JSON texts are decoded with
+//! `serde_json`, so this module is only available with the `json` feature.
+
+use crate::values::dict::Dictionary;
+use crate::values::error::*;
+use crate::values::frozen::Thaw;
+use crate::values::none::NoneType;
+use crate::values::*;
+use serde::de::{self, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor};
+use std::convert::TryFrom;
+use std::fmt;
+use std::iter;
+use std::sync::Arc;
+
+// Errors -- UF = User Failure -- Failure that should be expected by the user (e.g. from a fail()).
+pub const JSON_DECODE_ERROR_CODE: &str = "UF41";
+
+macro_rules! ok {
+    ($e:expr) => {
+        return Ok(Value::from($e));
+    };
+}
+
+/// The `json` value, whose attributes are the functions of the module.
+struct JsonModule;
+
+impl TypedValue for JsonModule {
+    type Holder = Immutable<JsonModule>;
+
+    fn values_for_descendant_check_and_freeze<'a>(
+        &'a self,
+    ) -> Box<dyn Iterator<Item = Value> + 'a> {
+        Box::new(iter::empty())
+    }
+
+    fn to_repr(&self) -> String {
+        "<module json>".to_owned()
+    }
+
+    fn share(&self) -> Result<FrozenValue, ValueError> {
+        Ok(FrozenValue::new(JsonModule))
+    }
+
+    const TYPE: &'static str = "json";
+}
+
+impl Thaw for JsonModule {
+    fn thaw(self: Arc<Self>) -> Value {
+        Value::new(JsonModule)
+    }
+}
+
+/// Builds the Starlark value of a JSON text, keeping the keys of objects in their order.
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a JSON value")
+    }
+
+    fn visit_unit<E>(self) -> Result<Value, E> {
+        Ok(Value::new(NoneType::None))
+    }
+
+    fn visit_bool<E>(self, b: bool) -> Result<Value, E> {
+        Ok(Value::new(b))
+    }
+
+    fn visit_i64<E>(self, i: i64) -> Result<Value, E> {
+        Ok(Value::new(i))
+    }
+
+    fn visit_u64<E: de::Error>(self, u: u64) -> Result<Value, E> {
+        match i64::try_from(u) {
+            Ok(i) => Ok(Value::new(i)),
+            Err(..) => Err(E::custom(format!("integer {} is out of range", u))),
+        }
+    }
+
+    fn visit_f64<E: de::Error>(self, f: f64) -> Result<Value, E> {
+        // `-0` is decoded as a float, and is not distinguished from `-0.0`.
+        if f == 0.0 && f.is_sign_negative() {
+            return Ok(Value::new(0));
+        }
+        Err(E::custom(format!(
+            "floating point number {} is not supported",
+            f
+        )))
+    }
+
+    fn visit_str<E>(self, s: &str) -> Result<Value, E> {
+        Ok(Value::from(s))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let mut content = Vec::new();
+        while let Some(v) = seq.next_element_seed(ValueVisitor)? {
+            content.push(v);
+        }
+        Ok(Value::from(content))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+        let mut dict = Dictionary::new();
+        while let Some(key) = map.next_key::<String>()? {
+            let value = map.next_value_seed(ValueVisitor)?;
+            dict.set_at(Value::from(key), value)
+                .map_err(de::Error::custom)?;
+        }
+        Ok(dict)
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+/// Decode the JSON text `s`, which must consist of a single JSON value. `function` is
+/// reported in error messages.
+fn decode_json(function: &str, s: &str) -> ValueResult {
+    let mut deserializer = serde_json::Deserializer::from_str(s);
+    let value = ValueVisitor
+        .deserialize(&mut deserializer)
+        .and_then(|v| deserializer.end().map(|()| v));
+    value.map_err(|e| {
+        RuntimeError {
+            code: JSON_DECODE_ERROR_CODE,
+            message: format!("{}: {}", function, e),
+            label: "Invalid JSON".to_owned(),
+        }
+        .into()
+    })
+}
+
+/// Reformat valid JSON text with one element per line.
+fn indent_json(s: &str, indent: &str) -> String {
+    fn newline(r: &mut String, indent: &str, depth: usize) {
+        r.push('\n');
+        for _ in 0..depth {
+            r.push_str(indent);
+        }
+    }
+
+    let mut r = String::with_capacity(s.len());
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+    // Whether an array or object was just opened, to keep empty ones on a single line.
+    let mut opened = false;
+    for c in s.chars() {
+        if in_string {
+            r.push(c);
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '"' {
+                in_string = false;
+            }
+            continue;
+        }
+        if c.is_whitespace() {
+            continue;
+        }
+        if opened {
+            opened = false;
+            if c != ']' && c != '}' {
+                newline(&mut r, indent, depth);
+            } else {
+                depth -= 1;
+                r.push(c);
+                continue;
+            }
+        }
+        match c {
+            '"' => {
+                in_string = true;
+                r.push(c);
+            }
+            '[' | '{' => {
+                r.push(c);
+                depth += 1;
+                opened = true;
+            }
+            ']' | '}' => {
+                depth -= 1;
+                newline(&mut r, indent, depth);
+                r.push(c);
+            }
+            ',' => {
+                r.push(c);
+                newline(&mut r, indent, depth);
+            }
+            ':' => r.push_str(": "),
+            c => r.push(c),
+        }
+    }
+    r
+}
+
+starlark_module! {global =>
+    const json = Value::new(JsonModule);
+
+    /// json.encode: encode a value as JSON.
+    ///
+    /// `json.encode(x)` returns the compact JSON encoding of `x`, which may be `None`, a
+    /// boolean, an integer, a string, a list or tuple (encoded as arrays), a dict with
+    /// string keys or a struct (encoded as objects). Values containing themselves cannot
+    /// be encoded.
+    ///
+    /// Examples:
+    ///
+    /// ```
+    /// # use starlark::stdlib::starlark_default;
+    /// # assert!(starlark_default(r#"(
+    /// json.encode({"a": [1, True, None]}) == '{"a":[1,true,null]}'
+    /// # )"#).unwrap());
+    /// ```
+    json.encode(_this, #x) {
+        ok!(x.to_json()?)
+    }
+
+    /// json.decode: decode a JSON text.
+    ///
+    /// `json.decode(x)` returns the value described by the JSON text `x`: `null`, booleans,
+    /// strings and arrays are decoded as `None`, booleans, strings and lists, objects as
+    /// dicts with the keys in the order of the text. Integral numbers are decoded as
+    /// integers, other numbers are not supported yet.
+    ///
+    /// Examples:
+    ///
+    /// ```
+    /// # use starlark::stdlib::starlark_default;
+    /// # assert!(starlark_default(r#"(
+    /// json.decode('{"a": [1, true, null]}') == {"a": [1, True, None]}
+    /// # )"#).unwrap());
+    /// ```
+    json.decode(_this, #x: String) {
+        decode_json("json.decode", &x)
+    }
+
+    /// json.indent: reformat a JSON text.
+    ///
+    /// `json.indent(x, indent=" ")` returns the JSON text `x` with each element of an array
+    /// or object on its own line, prefixed by `indent` repeated once per level of nesting.
+    /// It fails if `x` is not valid JSON.
+    ///
+    /// Examples:
+    ///
+    /// ```
+    /// # use starlark::stdlib::starlark_default;
+    /// # assert!(starlark_default(r#"(
+    /// json.indent('{"a": [1, 2], "b": {}}', indent="  ") == '''{
+    ///   "a": [
+    ///     1,
+    ///     2
+    ///   ],
+    ///   "b": {}
+    /// }'''
+    /// # )"#).unwrap());
+    /// ```
+    json.indent(_this, #x: String, indent: String = " ".to_owned()) {
+        decode_json("json.indent", &x)?;
+        ok!(indent_json(&x, &indent))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::starlark_default;
+    use super::super::tests::starlark_default_fail;
+    use super::*;
+
+    macro_rules! starlark_ok {
+        ($($t:expr),+) => (starlark_ok_fn!(starlark_default, $($t),+))
+    }
+
+    macro_rules! starlark_fail {
+        ($($t:expr),+) => (starlark_fail_fn!(starlark_default_fail, $($t),+))
+    }
+
+    fn decode_error(s: &str) -> String {
+        match decode_json("json.decode", s) {
+            Err(ValueError::Runtime(e)) => {
+                assert_eq!(JSON_DECODE_ERROR_CODE, e.code);
+                e.message
+            }
+            Err(e) => panic!("unexpected error {:?}", e),
+            Ok(v) => panic!("unexpected value {}", v.to_repr()),
+        }
+    }
+
+    #[test]
+    fn test_encode() {
+        starlark_ok!(r#"(json.encode(None) == "null" and json.encode(True) == "true")"#);
+        starlark_ok!(r#"(json.encode(-12) == "-12")"#);
+        starlark_ok!(r#"(json.encode("a\"b\\c\nd\té") == '"a\\"b\\\\c\\nd\\té"')"#);
+        starlark_ok!(r#"(json.encode([1, (2, 3), []]) == "[1,[2,3],[]]")"#);
+        starlark_ok!(r#"(json.encode({"b": 1, "a": {}}) == '{"b":1,"a":{}}')"#);
+        starlark_ok!(r#"(json.encode(struct(x=1, y=[])) == '{"x":1,"y":[]}')"#);
+        starlark_fail!(r#"json.encode({1: 2})"#, NOT_SUPPORTED_ERROR_CODE);
+        starlark_fail!(r#"json.encode(len)"#, NOT_SUPPORTED_ERROR_CODE);
+        starlark_fail!(r#"json.encode([range(3)])"#, NOT_SUPPORTED_ERROR_CODE);
+    }
+
+    #[test]
+    fn test_encode_control_characters() {
+        assert_eq!(
+            Ok("\"\\u0000\\u001f\"".to_owned()),
+            Value::from("\u{0}\u{1f}").to_json().map_err(|_| ())
+        );
+    }
+
+    #[test]
+    fn test_decode() {
+        starlark_ok!(r#"(json.decode(" null ") == None and json.decode("false") == False)"#);
+        starlark_ok!(r#"(json.decode("-0") == 0 and json.decode("123") == 123)"#);
+        starlark_ok!(r#"(json.decode("-9223372036854775808") == -9223372036854775807 - 1)"#);
+        starlark_ok!(r#"(json.decode('"a\\"\\\\\\/\\n\\r\\t"') == 'a"\\/\n\r\t')"#);
+        starlark_ok!(r#"(json.decode('"\\u00e9\\u4e16\\ud83d\\ude00"') == "é世😀")"#);
+        starlark_ok!(r#"(json.decode('[1, [], [[2]], {}]') == [1, [], [[2]], {}])"#);
+        starlark_ok!(
+            r#"(list(json.decode('{"b": 1, "a": 2, "c": 3}').keys()) == ["b", "a", "c"])"#
+        );
+        starlark_ok!(r#"(json.decode('{"a": 1, "a": 2}') == {"a": 2})"#);
+        starlark_fail!(r#"json.decode("[1,]")"#, JSON_DECODE_ERROR_CODE);
+        starlark_fail!(r#"json.decode(1)"#, INCORRECT_PARAMETER_TYPE_ERROR_CODE);
+    }
+
+    #[test]
+    fn test_decode_backspace_form_feed() {
+        assert_eq!(
+            "\u{8}\u{c}",
+            decode_json("json.decode", "\"\\b\\f\"").unwrap().to_str()
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        starlark_ok!(
+            r#"
+x = {"a": [1, -2, "three", None, True, False], "b": {"c": {}, "d": []}, "é": "\"\\\n"}
+json.decode(json.encode(x)) == x"#
+        );
+        starlark_ok!(
+            r#"(json.encode(json.decode('{"a":[1,{"b":null}]}')) == '{"a":[1,{"b":null}]}')"#
+        );
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        assert_eq!(
+            "json.decode: EOF while parsing a value at line 1 column 0",
+            decode_error("")
+        );
+        assert_eq!(
+            "json.decode: trailing comma at line 1 column 4",
+            decode_error("[1,]")
+        );
+        assert_eq!(
+            "json.decode: trailing characters at line 1 column 5",
+            decode_error("[1] x")
+        );
+        assert_eq!(
+            "json.decode: key must be a string at line 1 column 2",
+            decode_error("{1: 2}")
+        );
+        assert_eq!(
+            "json.decode: floating point number 1.5 is not supported at line 1 column 4",
+            decode_error("[1.5]")
+        );
+        assert_eq!(
+            "json.decode: integer 9223372036854775808 is out of range at line 1 column 19",
+            decode_error("9223372036854775808")
+        );
+        for s in &[
+            "[1 2]",
+            "{\"a\" 2}",
+            "\"a",
+            "\"a\\x\"",
+            "\"\\u12\"",
+            "\"\n\"",
+            "nul",
+            "-01",
+            "-",
+        ] {
+            decode_error(s);
+        }
+    }
+
+    #[test]
+    fn test_indent() {
+        starlark_ok!(r#"(json.indent('[]') == "[]" and json.indent(' { } ') == "{}")"#);
+        starlark_ok!(r#"(json.indent('[1,2]') == "[\n 1,\n 2\n]")"#);
+        starlark_ok!(
+            r#"(json.indent('{"a":{"b":[]}}', indent="\t") == '{\n\t"a": {\n\t\t"b": []\n\t}\n}')"#
+        );
+        starlark_ok!(r#"(json.indent('["a, [b]: {c}"]') == '[\n "a, [b]: {c}"\n]')"#);
+        starlark_ok!(r#"(json.indent('["\\"[", 1]') == '[\n "\\"[",\n 1\n]')"#);
+        starlark_fail!(r#"json.indent('[1')"#, JSON_DECODE_ERROR_CODE);
+    }
+}
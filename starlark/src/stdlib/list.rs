@@ -14,6 +14,7 @@
 
 //! Methods for the `list` type.
 
+use crate::stdlib::sort_values;
 use crate::values::list::List;
 use crate::values::none::NoneType;
 use crate::values::*;
@@ -247,6 +248,57 @@ starlark_module! {global =>
         this.remove(needle)?;
         Ok(Value::new(NoneType::None))
     }
+
+    /// list.sort: sort a list in place.
+    ///
+    /// `L.sort()` sorts the elements of the list L, like [`sorted`](#sorted), and returns
+    /// `None`. The sort algorithm is stable.
+    ///
+    /// The optional named parameter `reverse`, if true, sorts the list in reverse order.
+    ///
+    /// The optional named parameter `key` specifies a function of one
+    /// argument to apply to obtain the element's sort key.
+    ///
+    /// `sort` fails if the elements, or their keys, cannot be compared, or if the list is
+    /// frozen or has active iterators.
+    ///
+    /// Examples:
+    ///
+    /// ```
+    /// # use starlark::stdlib::starlark_default;
+    /// # assert!(starlark_default(r#"
+    /// x = ["two", "three", "four"]
+    /// # (
+    /// x.sort() == None
+    /// # and
+    /// x == ["four", "three", "two"]
+    /// # and
+    /// x.sort(key=len) == None
+    /// # and
+    /// x == ["two", "four", "three"]  # the sort is stable
+    /// # and
+    /// x.sort(key=len, reverse=True) == None
+    /// # and
+    /// x == ["three", "four", "two"]
+    /// # )"#).unwrap());
+    /// ```
+    list.sort(call_stack cs, env e, this, ?key, reverse = false) {
+        // Fail early if the list cannot be mutated.
+        this.downcast_mut::<List>()?.unwrap();
+        let values: Vec<Value> = this.iter()?.iter().collect();
+        // The key function must not modify the list while it is being sorted.
+        let mut list = this.clone();
+        list.freeze_for_iteration();
+        let sorted = sort_values(cs, e, values.into_iter(), key, reverse.to_bool());
+        list.unfreeze_for_iteration();
+        let sorted = sorted?;
+        let mut this = this.downcast_mut::<List>()?.unwrap();
+        this.clear();
+        for el in sorted {
+            this.push(el)?;
+        }
+        Ok(Value::new(NoneType::None))
+    }
 }
 
 #[cfg(test)]
@@ -313,4 +365,33 @@ x.remove(2); t2 = x == [1, 3]
             LIST_REMOVE_ELEMENT_NOT_FOUND_ERROR_CODE
         );
     }
+
+    #[test]
+    fn test_sort() {
+        starlark_ok!(r#"x = [3, 1, 4, 1, 5, 9]; x.sort(); (x == [1, 1, 3, 4, 5, 9])"#);
+        starlark_ok!(r#"x = [3, 1, 4, 1, 5, 9]; x.sort(reverse=True); (x == [9, 5, 4, 3, 1, 1])"#);
+        starlark_ok!(
+            r#"x = ["bb", "a", "cc", "b", "aa"]; x.sort(key=len); (
+            x == ["a", "b", "bb", "cc", "aa"])"#
+        );
+        starlark_ok!(
+            r#"x = ["bb", "a", "cc", "b", "aa"]; x.sort(key=len, reverse=True); (
+            x == ["bb", "cc", "aa", "a", "b"])"#
+        );
+        starlark_ok!(r#"x = []; x.sort(); (x == [])"#);
+        starlark_ok!(r#"x = [2, 1]; (x.sort() == None)"#);
+    }
+
+    #[test]
+    fn test_sort_fails() {
+        starlark_fail!(r#"x = [1, "a"]; x.sort()"#);
+        starlark_fail!(r#"x = [2, 1]; [x.sort() for _ in x]"#);
+        starlark_fail!(
+            r#"x = [2, 1]
+def key(e):
+    x.append(e)
+    return e
+x.sort(key=key)"#
+        );
+    }
 }
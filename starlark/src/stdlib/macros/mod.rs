@@ -48,6 +48,17 @@ macro_rules! starlark_signature {
         $( starlark_signature!($signature $($rest)+) )?;
     };
 
+    // tuple destructuring param, e.g. `(a, b): (i64, String)`
+    ($signature:ident ($($t:ident),+) : ($($pt:ty),+) $(,$($rest:tt)+)?) => {
+        $signature.push($crate::values::function::FunctionParameter::Normal(
+            stringify!(($($t),+)).to_owned()
+        ));
+        $( starlark_signature!($signature $($rest)+) )?;
+    };
+    ($signature:ident ($($t:ident),+) $(,$($rest:tt)+)?) => {
+        starlark_signature!($signature ($($t),+) : ($(starlark_parse_param_type!(tuple item $t)),+) $(,$($rest)+)?);
+    };
+
     // insert `(named)` tt if param is not unnamed
     ($signature:ident $t:ident $($rest:tt)*) => {
         starlark_signature!($signature (named) $t $($rest)*)
@@ -66,6 +77,17 @@ macro_rules! starlark_signature {
         $( starlark_signature!($signature $($rest)+) )?;
     };
 
+    // handle params with a Starlark default value (both named and unnamed)
+    ($signature:ident $is_named:tt $t:ident $(: $pt:ty)? = starlark $lit:literal $(,$($rest:tt)+)?) => {
+        $signature.push(
+            $crate::values::function::FunctionParameter::with_starlark_default(
+                starlark_param_name!($is_named $t).to_owned(),
+                $lit,
+            )
+        );
+        $( starlark_signature!($signature $($rest)+) )?;
+    };
+
     // handle params with default value (both named and unnamed)
     ($signature:ident $is_named:tt $t:ident : $pt:ty = $e:expr $(,$($rest:tt)+)?) => {
         $signature.push(
@@ -74,7 +96,11 @@ macro_rules! starlark_signature {
                 // explicitly specify parameter type to:
                 // * verify that default value is convertible to required type
                 // * help type inference find type parameters
-                ::std::convert::From::<starlark_parse_param_type!(1 : $pt)>::from($e)
+                // * accept values convertible to the parameter type, e.g. a `&str` const
+                //   for a `String` parameter
+                ::std::convert::From::<starlark_parse_param_type!(1 : $pt)>::from(
+                    <starlark_parse_param_type!(1 : $pt) as ::std::convert::From<_>>::from($e)
+                )
             )
         );
         $( starlark_signature!($signature $($rest)+) )?;
@@ -100,6 +126,9 @@ macro_rules! starlark_parse_param_type {
     (1) => {
         $crate::values::Value
     };
+    (tuple item $t:ident) => {
+        $crate::values::Value
+    };
     (?) => {
         ::std::option::Option<$crate::values::Value>
     };
@@ -107,7 +136,7 @@ macro_rules! starlark_parse_param_type {
         ::std::vec::Vec<$crate::values::Value>
     };
     (**) => {
-        ::linked_hash_map::LinkedHashMap<::std::string::String, $crate::values::Value>
+        $crate::values::OrderedMap<::std::string::String, $crate::values::Value>
     };
 }
 
@@ -126,15 +155,25 @@ macro_rules! starlark_signature_extraction {
     ($args:ident $call_stack:ident $env:ident * $t:ident $(: $pt:ty)? $(,$($rest:tt)+)?) => {
         #[allow(unused_mut)]
         let mut $t: starlark_parse_param_type!(* $(: $pt)?) =
-            $args.next_arg()?.into_args_array(stringify!($t))?;
+            $args.next_arg_into(|arg| arg.into_args_array(stringify!($t)))?;
         $( starlark_signature_extraction!($args $call_stack $env $($rest)+) )?;
     };
     ($args:ident $call_stack:ident $env:ident ** $t:ident $(: $pt:ty)? $(,$($rest:tt)+)?) => {
         #[allow(unused_mut)]
         let mut $t: starlark_parse_param_type!(** $(: $pt)?) =
-            $args.next_arg()?.into_kw_args_dict(stringify!($t))?;
+            $args.next_arg_into(|arg| arg.into_kw_args_dict(stringify!($t)))?;
+        $( starlark_signature_extraction!($args $call_stack $env $($rest)+) )?;
+    };
+
+    ($args:ident $call_stack:ident $env:ident ($($t:ident),+) : ($($pt:ty),+) $(,$($rest:tt)+)?) => {
+        #[allow(unused_mut)]
+        let ($(mut $t),+): ($($pt),+) =
+            $args.next_arg_into(|arg| arg.into_normal(stringify!(($($t),+))))?;
         $( starlark_signature_extraction!($args $call_stack $env $($rest)+) )?;
     };
+    ($args:ident $call_stack:ident $env:ident ($($t:ident),+) $(,$($rest:tt)+)?) => {
+        starlark_signature_extraction!($args $call_stack $env ($($t),+) : ($(starlark_parse_param_type!(tuple item $t)),+) $(,$($rest)+)?);
+    };
 
     // insert `(named)` tt if param is not unnamed
     ($args:ident $call_stack:ident $env:ident $t:ident $($rest:tt)*) => {
@@ -147,13 +186,16 @@ macro_rules! starlark_signature_extraction {
     ($args:ident $call_stack:ident $env:ident ? $is_named:tt $t:ident $(: $pt:ty)? $(,$($rest:tt)+)?) => {
         #[allow(unused_mut)]
         let mut $t: starlark_parse_param_type!(? $(: $pt)?) =
-            $args.next_arg()?.into_optional(starlark_param_name!(# $t))?;
+            $args.next_arg_into(|arg| arg.into_optional(starlark_param_name!(# $t)))?;
         $( starlark_signature_extraction!($args $call_stack $env $($rest)+) )?;
     };
+    ($args:ident $call_stack:ident $env:ident $is_named:tt $t:ident $(: $pt:ty)? = starlark $lit:literal $(,$($rest:tt)+)?) => {
+        starlark_signature_extraction!($args $call_stack $env $is_named $t $(: $pt)? $(,$($rest)+)?);
+    };
     ($args:ident $call_stack:ident $env:ident $is_named:tt $t:ident $(: $pt:ty)? $(= $e:expr)? $(,$($rest:tt)+)?) => {
         #[allow(unused_mut)]
         let mut $t: starlark_parse_param_type!(1 $(: $pt)?) =
-            $args.next_arg()?.into_normal(starlark_param_name!($is_named $t))?;
+            $args.next_arg_into(|arg| arg.into_normal(starlark_param_name!($is_named $t)))?;
         $( starlark_signature_extraction!($args $call_stack $env $($rest)+) )?;
     };
 }
@@ -161,8 +203,25 @@ macro_rules! starlark_signature_extraction {
 #[doc(hidden)]
 #[macro_export]
 macro_rules! starlark_fun {
-    ($(#[$attr:meta])* $fn:ident ( $($signature:tt)* ) { $($content:tt)* } $($($rest:tt)+)?) => {
-        $(#[$attr])*
+    (@item $(#[$($attr:tt)*])* $fn:ident ( $($signature:tt)* ) { $($content:tt)* }) => {
+        starlark_fun! {
+            @attrs [] $(#[$($attr)*])* $fn ( $($signature)* ) { $($content)* }
+        }
+    };
+    (@item $(#[$($attr:tt)*])* $ty:ident . $fn:ident ( $($signature:tt)* ) { $($content:tt)* }) => {
+        starlark_fun! {
+            @item $(#[$($attr)*])* $fn ( $($signature)* ) { $($content)* }
+        }
+    };
+    // `#[starlark(...)]` attributes are only read by the registration, drop them.
+    (@attrs [$($kept:tt)*] #[starlark $($options:tt)*] $($rest:tt)*) => {
+        starlark_fun! { @attrs [$($kept)*] $($rest)* }
+    };
+    (@attrs [$($kept:tt)*] #[$($attr:tt)*] $($rest:tt)*) => {
+        starlark_fun! { @attrs [$($kept)* #[$($attr)*]] $($rest)* }
+    };
+    (@attrs [$($kept:tt)*] $fn:ident ( $($signature:tt)* ) { $($content:tt)* }) => {
+        $($kept)*
         fn $fn(
             __call_stack: &$crate::eval::call_stack::CallStack,
             __env: $crate::environment::TypeValues,
@@ -172,21 +231,33 @@ macro_rules! starlark_fun {
             args.check_no_more_args()?;
             $($content)*
         }
+    };
+    // Without constants, there is no need to recurse over the functions, which would
+    // reach the `recursion_limit` for large modules.
+    ($(
+        $(#[$($attr:tt)*])* $first:ident $(. $method:ident)? ( $($signature:tt)* ) { $($content:tt)* }
+    )+) => {
+        $(starlark_fun! {
+            @item $(#[$($attr)*])* $first $(. $method)? ( $($signature)* ) { $($content)* }
+        })+
+    };
+    (const $name:ident = $e:expr; $($($rest:tt)+)?) => {
+        $(starlark_fun! {
+            $($rest)+
+        })?
+    };
+    ($(#[$($attr:tt)*])* $fn:ident ( $($signature:tt)* ) { $($content:tt)* } $($($rest:tt)+)?) => {
+        starlark_fun! {
+            @item $(#[$($attr)*])* $fn ( $($signature)* ) { $($content)* }
+        }
         $(starlark_fun! {
             $($rest)+
         })?
     };
-    ($(#[$attr:meta])* $ty:ident . $fn:ident ( $($signature:tt)* ) { $($content:tt)* }
+    ($(#[$($attr:tt)*])* $ty:ident . $fn:ident ( $($signature:tt)* ) { $($content:tt)* }
             $($($rest:tt)+)?) => {
-        $(#[$attr])*
-        fn $fn(
-            __call_stack: &$crate::eval::call_stack::CallStack,
-            __env: $crate::environment::TypeValues,
-            mut args: $crate::values::function::ParameterParser,
-        ) -> $crate::values::ValueResult {
-            starlark_signature_extraction!(args __call_stack __env $($signature)*);
-            args.check_no_more_args()?;
-            $($content)*
+        starlark_fun! {
+            @item $(#[$($attr)*])* $fn ( $($signature)* ) { $($content)* }
         }
         $(starlark_fun! {
             $($rest)+
@@ -194,30 +265,121 @@ macro_rules! starlark_fun {
     };
 }
 
+/// The text of a doc comment line given as a `doc` attribute, empty for other attributes.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! starlark_doc_line {
+    (doc = $doc:literal) => {
+        $doc
+    };
+    ($($attr:tt)*) => {
+        ""
+    };
+}
+
+/// The deprecation declared by a `#[starlark(deprecated_in = "version")]` attribute, `None`
+/// for other attributes.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! starlark_deprecation {
+    (starlark(deprecated_in = $version:literal $(, note = $note:literal)? $(,)?)) => {
+        Some($crate::values::function::Deprecation {
+            version: $version.to_owned(),
+            note: None $(.or(Some($note.to_owned())))?,
+        })
+    };
+    (starlark $($options:tt)*) => {
+        compile_error!(concat!(
+            "unsupported attribute #[starlark",
+            stringify!($($options)*),
+            "], expected #[starlark(deprecated_in = \"version\", note = \"...\")]"
+        ))
+    };
+    ($($attr:tt)*) => {
+        None
+    };
+}
+
 #[doc(hidden)]
 #[macro_export]
 macro_rules! starlark_signatures {
-    ($env:expr, $(#[$attr:meta])* $name:ident ( $($signature:tt)* ) { $($content:tt)* }
-            $($($rest:tt)+)?) => {
+    ($env:expr, @item $(#[$($attr:tt)*])* $first:ident $(. $method:ident)? ( $($signature:tt)* )
+            { $($content:tt)* }) => {
+        starlark_signatures!{ $env,
+            @attrs [] [$(#[$($attr)*])*] $(#[$($attr)*])* $first $(. $method)? ( $($signature)* )
+        }
+    };
+    // Attributes are applied to the registration like to the function definition, so
+    // that a function disabled with `#[cfg]` is not registered, except for the
+    // `#[starlark(...)]` attributes which are not Rust attributes.
+    ($env:expr, @attrs [$($kept:tt)*] [$($all:tt)*] #[starlark $($options:tt)*] $($rest:tt)*) => {
+        starlark_signatures!{ $env, @attrs [$($kept)*] [$($all)*] $($rest)* }
+    };
+    ($env:expr, @attrs [$($kept:tt)*] [$($all:tt)*] #[$($attr:tt)*] $($rest:tt)*) => {
+        starlark_signatures!{ $env, @attrs [$($kept)* #[$($attr)*]] [$($all)*] $($rest)* }
+    };
+    ($env:expr, @attrs [$($kept:tt)*] [$(#[$($attr:tt)*])*] $name:ident ( $($signature:tt)* )) => {
+        $($kept)*
+        #[allow(unused_doc_comments)]
         {
             let name = stringify!($name).trim_matches('_');
             #[allow(unused_mut)]
             let mut signature = Vec::new();
             starlark_signature!(signature $($signature)*);
-            $env.set(name, $crate::values::function::NativeFunction::new(name.to_owned(), $name, signature)).unwrap();
+            let doc: &[&str] = &[$(starlark_doc_line!($($attr)*)),*];
+            let deprecation: Option<$crate::values::function::Deprecation> =
+                None $(.or(starlark_deprecation!($($attr)*)))*;
+            $env.set(
+                name,
+                $crate::values::function::NativeFunction::new_documented(
+                    name.to_owned(), $name, signature, doc, deprecation,
+                ),
+            ).unwrap();
         }
-        $(starlark_signatures!{ $env,
-            $($rest)+
-        })?
     };
-    ($env:expr, $(#[$attr:meta])* $ty:ident . $name:ident ( $($signature:tt)* ) { $($content:tt)* }
-            $($($rest:tt)+)?) => {
+    ($env:expr, @attrs [$($kept:tt)*] [$(#[$($attr:tt)*])*] $ty:ident . $name:ident
+            ( $($signature:tt)* )) => {
+        $($kept)*
+        #[allow(unused_doc_comments)]
         {
             let name = stringify!($name).trim_matches('_');
             let mut signature = Vec::new();
             starlark_signature!(signature $($signature)*);
+            let doc: &[&str] = &[$(starlark_doc_line!($($attr)*)),*];
+            let deprecation: Option<$crate::values::function::Deprecation> =
+                None $(.or(starlark_deprecation!($($attr)*)))*;
             $env.add_type_value(stringify!($ty), name,
-                $crate::values::function::NativeFunction::new(name.to_owned(), $name, signature));
+                $crate::values::function::NativeFunction::new_documented(
+                    name.to_owned(), $name, signature, doc, deprecation,
+                ));
+        }
+    };
+    ($env:expr, $(
+        $(#[$($attr:tt)*])* $first:ident $(. $method:ident)? ( $($signature:tt)* ) { $($content:tt)* }
+    )+) => {
+        $(starlark_signatures!{ $env,
+            @item $(#[$($attr)*])* $first $(. $method)? ( $($signature)* ) { $($content)* }
+        })+
+    };
+    ($env:expr, const $name:ident = $e:expr; $($($rest:tt)+)?) => {
+        $env.set(stringify!($name), $crate::values::Value::from($e)).unwrap();
+        $(starlark_signatures!{ $env,
+            $($rest)+
+        })?
+    };
+    ($env:expr, $(#[$($attr:tt)*])* $name:ident ( $($signature:tt)* ) { $($content:tt)* }
+            $($($rest:tt)+)?) => {
+        starlark_signatures!{ $env,
+            @item $(#[$($attr)*])* $name ( $($signature)* ) { $($content)* }
+        }
+        $(starlark_signatures!{ $env,
+            $($rest)+
+        })?
+    };
+    ($env:expr, $(#[$($attr:tt)*])* $ty:ident . $name:ident ( $($signature:tt)* ) { $($content:tt)* }
+            $($($rest:tt)+)?) => {
+        starlark_signatures!{ $env,
+            @item $(#[$($attr)*])* $ty . $name ( $($signature)* ) { $($content)* }
         }
         $(starlark_signatures!{ $env,
             $($rest)+
@@ -243,6 +405,8 @@ macro_rules! starlark_signatures {
 /// # use starlark::values::*;
 /// # use starlark::values::none::NoneType;
 /// # use starlark::environment::Environment;
+/// const DEFAULT_NAME: &str = "world";
+///
 /// starlark_module!{ my_starlark_module =>
 ///     // Declare a 'str' function (_ are trimmed away and just here to avoid collision with
 ///     // reserved keyword)
@@ -263,19 +427,43 @@ macro_rules! starlark_signatures {
 ///     // Parameter can be any type which implements `TryParamConvertFromValue`.
 ///     // When parameter type is not specified, it is defaulted to `Value`
 ///     // for regular parameters, `Vec<Value>` for `*args`
-///     // and `LinkedHashMap<String, Value>` for `**kwargs`.
+///     // and `OrderedMap<String, Value>` for `**kwargs`.
 ///     sqr(x: i64) {
 ///         Ok(Value::new(x * x))
 ///     }
 ///
+///     // Default values are Rust expressions evaluated when the module is registered,
+///     // so they can refer to constants. A typed default may be any value convertible
+///     // to the parameter type.
+///     greet(name: String = DEFAULT_NAME) {
+///         Ok(Value::new(format!("Hello, {}", name)))
+///     }
+///
+///     // A default value can also be a Starlark expression, given as a string literal
+///     // after `starlark`. It is evaluated once and each call gets a copy, so a mutable
+///     // default like an empty list is not shared between calls.
+///     new_list(items = starlark "[]") {
+///         Ok(items)
+///     }
+///
+///     // A list or tuple parameter can be destructured in the signature,
+///     // in which case each element is converted to the corresponding type,
+///     // or kept as a `Value` if the types are omitted.
+///     pair((a, b): (i64, String)) {
+///         Ok(Value::new(format!("{} {}", a, b)))
+///     }
+///     swap((a, b)) {
+///         Ok(Value::from((b, a)))
+///     }
+///
 ///     // It is also possible to capture the call stack with
-///     // `call_stack name` (type `Vec<String>`). For example a `dbg` function that print the
-///     // the call stack:
+///     // `call_stack name` (type `&CallStack`). For example a `dbg` function that print the
+///     // the call stack to the output of the evaluation:
 ///     dbg(call_stack cs) {
-///        println!(
+///        cs.print(&format!(
 ///            "In:{}",
 ///            cs.print_with_newline_before()
-///        );
+///        ));
 ///        Ok(Value::from(NoneType::None))
 ///     }
 /// }
@@ -285,6 +473,9 @@ macro_rules! starlark_signatures {
 /// #    assert_eq!(env.get("str").unwrap().get_type(), "function");
 /// #    assert_eq!(env.get("my_fun").unwrap().get_type(), "function");
 /// #    assert_eq!(env.get("sqr").unwrap().get_type(), "function");
+/// #    assert_eq!(env.get("pair").unwrap().get_type(), "function");
+/// #    assert_eq!(env.get("swap").unwrap().get_type(), "function");
+/// #    assert_eq!(env.get("new_list").unwrap().get_type(), "function");
 /// # }
 /// ```
 ///
@@ -308,6 +499,24 @@ macro_rules! starlark_signatures {
 /// # }
 /// ```
 ///
+/// Constants can be declared alongside the functions with `const NAME = expr;`, the
+/// expression is converted with `Value::from` when the module is registered:
+///
+/// ```rust
+/// # #[macro_use] extern crate starlark;
+/// # use starlark::values::*;
+/// # use starlark::environment::Environment;
+/// starlark_module!{ my_starlark_module =>
+///     const MAX_INT = i64::max_value();
+///     const VERSION = "1.0";
+/// }
+/// #
+/// # fn main() {
+/// #    let env = my_starlark_module(Environment::new("test"));
+/// #    assert_eq!(env.get("VERSION").unwrap().to_str(), "1.0");
+/// # }
+/// ```
+///
 /// Additionally function might be declared for a type by prefixing them by `type.`, e.g the
 /// definition of a `hello` function for the `string` type would look like:
 ///
@@ -330,6 +539,67 @@ macro_rules! starlark_signatures {
 /// #    assert_eq!(env.get_type_value(&Value::from(""), "hello").unwrap().get_type(), "function");
 /// # }
 /// ```
+///
+/// The attributes of a function, such as `#[cfg(feature = "network")]`, apply to both its
+/// definition and its registration, so a function disabled by `#[cfg]` is absent from the
+/// environment.
+///
+/// Parameters can be described in the doc comment of their function by lines of the form
+/// `@param name: description`, made available by
+/// [`TypeValues::get_signature_help`](crate::environment::TypeValues::get_signature_help)
+/// for methods and
+/// [`NativeFunction::signature_help`](crate::values::function::NativeFunction::signature_help):
+///
+/// ```rust
+/// # #[macro_use] extern crate starlark;
+/// # use starlark::values::*;
+/// # use starlark::environment::{Environment, TypeValues};
+/// starlark_module!{ my_starlark_module =>
+///     /// Repeat a string.
+///     ///
+///     /// @param this: the string to repeat
+///     /// @param times: how many times to repeat it
+///     string.repeat(this: String, times: i64) {
+///        Ok(Value::new(this.repeat(times as usize)))
+///     }
+/// }
+/// #
+/// # fn main() {
+/// let env = TypeValues::new(my_starlark_module(Environment::new("test")));
+/// let help = env.get_signature_help(&Value::from(""), "repeat").unwrap();
+/// assert_eq!("repeat(this, times)", help.label);
+/// assert_eq!(Some("how many times to repeat it"), help.parameters[1].documentation.as_deref());
+/// # }
+/// ```
+///
+/// A function can be marked as deprecated since a version with a
+/// `#[starlark(deprecated_in = "version")]` attribute, optionally followed by a note on how
+/// to upgrade. The deprecation is available from
+/// [`TypeValues::get_deprecation`](crate::environment::TypeValues::get_deprecation) for
+/// methods and
+/// [`NativeFunction::deprecation`](crate::values::function::NativeFunction::deprecation),
+/// and each call is reported to the
+/// [warning handler](crate::eval::options::EvaluationOptions::on_warning) of the evaluation:
+///
+/// ```rust
+/// # #[macro_use] extern crate starlark;
+/// # use starlark::values::*;
+/// # use starlark::environment::Environment;
+/// starlark_module!{ my_starlark_module =>
+///     // Calls are reported as "foo() was deprecated in 2.0; use bar() instead".
+///     #[starlark(deprecated_in = "2.0", note = "use bar() instead")]
+///     foo() {
+///         Ok(Value::new(1))
+///     }
+/// }
+/// # fn main() {
+/// #    my_starlark_module(Environment::new("test"));
+/// # }
+/// ```
+///
+/// Functions are expanded without recursing over the module, so a module of any size can
+/// be declared without raising the `recursion_limit`, as long as its constants come before
+/// its functions. Each attribute of a function adds a level of recursion though.
 #[macro_export]
 macro_rules! starlark_module {
     ($name:ident => $($t:tt)*) => (
@@ -347,6 +617,34 @@ macro_rules! starlark_module {
     )
 }
 
+/// Register a group of constants in an environment.
+///
+/// Each value is converted with `Value::from` and frozen before being set, the macro panics
+/// if the environment is frozen.
+///
+/// ```rust
+/// # #[macro_use] extern crate starlark;
+/// # use starlark::environment::Environment;
+/// # fn main() {
+/// let env = Environment::new("test");
+/// starlark_consts! { env =>
+///     MAX_INT = i64::max_value(),
+///     VERSION = "1.0",
+/// }
+/// assert_eq!(env.get("VERSION").unwrap().to_str(), "1.0");
+/// # }
+/// ```
+#[macro_export]
+macro_rules! starlark_consts {
+    ($env:expr => $($name:ident = $e:expr),* $(,)?) => {
+        $({
+            let mut value = $crate::values::Value::from($e);
+            value.freeze();
+            $env.set(stringify!($name), value).unwrap();
+        })*
+    };
+}
+
 /// Shortcut for returning an error from the code, message and label.
 ///
 /// # Parameters:
@@ -354,6 +652,10 @@ macro_rules! starlark_module {
 /// * $code is a short code to uniquely identify the error.
 /// * $message is the long explanation for the user of the error.
 /// * $label is a a short description of the error to be put next to the code.
+///
+/// When a call stack is given as first parameter, the location of the innermost call (see
+/// [`CallStack::current_location`](crate::eval::call_stack::CallStack::current_location))
+/// is appended to the label, so that errors from native functions point to their caller.
 #[macro_export]
 macro_rules! starlark_err {
     ($code:expr, $message:expr, $label:expr) => {
@@ -364,6 +666,17 @@ macro_rules! starlark_err {
         }
         .into());
     };
+    ($call_stack:expr, $code:expr, $message:expr, $label:expr) => {
+        return Err($crate::values::error::RuntimeError {
+            code: $code,
+            message: $message,
+            label: match $call_stack.current_location() {
+                Some((file, line, column)) => format!("{} at {}:{}:{}", $label, file, line, column),
+                None => $label,
+            },
+        }
+        .into());
+    };
 }
 
 /// A shortcut to assert the type of a value
@@ -461,9 +774,18 @@ macro_rules! convert_indices {
 
 #[cfg(test)]
 mod tests {
-    use crate::environment::Environment;
+    use crate::environment::{Environment, TypeValues};
+    use crate::eval::eval_with_options;
+    use crate::eval::noload::{eval, NoLoadFileLoader};
+    use crate::eval::options::EvaluationOptions;
+    use crate::syntax::dialect::Dialect;
+    use crate::values::error::INCORRECT_PARAMETER_TYPE_ERROR_CODE;
+    use crate::values::function::{Deprecation, NativeFunction, ParameterHelp};
+    use crate::values::list::List;
     use crate::values::none::NoneType;
     use crate::values::Value;
+    use codemap::CodeMap;
+    use std::sync::{Arc, Mutex};
 
     #[test]
     fn no_arg() {
@@ -476,4 +798,508 @@ mod tests {
         let env = global(Environment::new("root"));
         env.get("nop").unwrap();
     }
+
+    #[test]
+    fn const_item() {
+        starlark_module! { global =>
+            const ANSWER = 42;
+            incr(#x: i64) {
+                Ok(Value::new(x + 1))
+            }
+            const NAME = "starlark";
+        }
+
+        let env = global(Environment::new("root"));
+        env.freeze();
+        let r = eval(
+            &Arc::new(Mutex::new(CodeMap::new())),
+            "const_item.star",
+            "'%s %d %d' % (NAME, ANSWER, incr(ANSWER))",
+            Dialect::Bzl,
+            &mut env.child("test"),
+            TypeValues::new(env.clone()),
+        )
+        .unwrap();
+        assert_eq!("starlark 42 43", r.to_str());
+    }
+
+    #[test]
+    fn cfg_attributes() {
+        starlark_module! { global =>
+            /// Always compiled.
+            #[cfg(all())]
+            enabled() {
+                Ok(Value::new(NoneType::None))
+            }
+
+            #[cfg(any())]
+            disabled() {
+                Ok(Value::new(NoneType::None))
+            }
+
+            #[cfg(any())]
+            string.disabled(_this) {
+                Ok(Value::new(NoneType::None))
+            }
+        }
+
+        let env = global(Environment::new("root"));
+        env.get("enabled").unwrap();
+        assert!(env.get("disabled").is_err());
+        assert!(TypeValues::new(env)
+            .get_type_value(&Value::from(""), "disabled")
+            .is_none());
+    }
+
+    #[test]
+    fn many_functions() {
+        // Enough functions to exceed the default `recursion_limit` if the macro recursed
+        // once per function.
+        starlark_module! { global =>
+            const BASE = 1000;
+            f000() { Ok(Value::new(0)) } f001() { Ok(Value::new(1)) } f002() { Ok(Value::new(2)) }
+            f003() { Ok(Value::new(3)) } f004() { Ok(Value::new(4)) } f005() { Ok(Value::new(5)) }
+            f006() { Ok(Value::new(6)) } f007() { Ok(Value::new(7)) } f008() { Ok(Value::new(8)) }
+            f009() { Ok(Value::new(9)) } f010() { Ok(Value::new(10)) } f011() { Ok(Value::new(11)) }
+            f012() { Ok(Value::new(12)) } f013() { Ok(Value::new(13)) } f014() { Ok(Value::new(14)) }
+            f015() { Ok(Value::new(15)) } f016() { Ok(Value::new(16)) } f017() { Ok(Value::new(17)) }
+            f018() { Ok(Value::new(18)) } f019() { Ok(Value::new(19)) } f020() { Ok(Value::new(20)) }
+            f021() { Ok(Value::new(21)) } f022() { Ok(Value::new(22)) } f023() { Ok(Value::new(23)) }
+            f024() { Ok(Value::new(24)) } f025() { Ok(Value::new(25)) } f026() { Ok(Value::new(26)) }
+            f027() { Ok(Value::new(27)) } f028() { Ok(Value::new(28)) } f029() { Ok(Value::new(29)) }
+            f030() { Ok(Value::new(30)) } f031() { Ok(Value::new(31)) } f032() { Ok(Value::new(32)) }
+            f033() { Ok(Value::new(33)) } f034() { Ok(Value::new(34)) } f035() { Ok(Value::new(35)) }
+            f036() { Ok(Value::new(36)) } f037() { Ok(Value::new(37)) } f038() { Ok(Value::new(38)) }
+            f039() { Ok(Value::new(39)) } f040() { Ok(Value::new(40)) } f041() { Ok(Value::new(41)) }
+            f042() { Ok(Value::new(42)) } f043() { Ok(Value::new(43)) } f044() { Ok(Value::new(44)) }
+            f045() { Ok(Value::new(45)) } f046() { Ok(Value::new(46)) } f047() { Ok(Value::new(47)) }
+            f048() { Ok(Value::new(48)) } f049() { Ok(Value::new(49)) } f050() { Ok(Value::new(50)) }
+            f051() { Ok(Value::new(51)) } f052() { Ok(Value::new(52)) } f053() { Ok(Value::new(53)) }
+            f054() { Ok(Value::new(54)) } f055() { Ok(Value::new(55)) } f056() { Ok(Value::new(56)) }
+            f057() { Ok(Value::new(57)) } f058() { Ok(Value::new(58)) } f059() { Ok(Value::new(59)) }
+            f060() { Ok(Value::new(60)) } f061() { Ok(Value::new(61)) } f062() { Ok(Value::new(62)) }
+            f063() { Ok(Value::new(63)) } f064() { Ok(Value::new(64)) } f065() { Ok(Value::new(65)) }
+            f066() { Ok(Value::new(66)) } f067() { Ok(Value::new(67)) } f068() { Ok(Value::new(68)) }
+            f069() { Ok(Value::new(69)) } f070() { Ok(Value::new(70)) } f071() { Ok(Value::new(71)) }
+            f072() { Ok(Value::new(72)) } f073() { Ok(Value::new(73)) } f074() { Ok(Value::new(74)) }
+            f075() { Ok(Value::new(75)) } f076() { Ok(Value::new(76)) } f077() { Ok(Value::new(77)) }
+            f078() { Ok(Value::new(78)) } f079() { Ok(Value::new(79)) } f080() { Ok(Value::new(80)) }
+            f081() { Ok(Value::new(81)) } f082() { Ok(Value::new(82)) } f083() { Ok(Value::new(83)) }
+            f084() { Ok(Value::new(84)) } f085() { Ok(Value::new(85)) } f086() { Ok(Value::new(86)) }
+            f087() { Ok(Value::new(87)) } f088() { Ok(Value::new(88)) } f089() { Ok(Value::new(89)) }
+            f090() { Ok(Value::new(90)) } f091() { Ok(Value::new(91)) } f092() { Ok(Value::new(92)) }
+            f093() { Ok(Value::new(93)) } f094() { Ok(Value::new(94)) } f095() { Ok(Value::new(95)) }
+            f096() { Ok(Value::new(96)) } f097() { Ok(Value::new(97)) } f098() { Ok(Value::new(98)) }
+            f099() { Ok(Value::new(99)) } f100() { Ok(Value::new(100)) } f101() { Ok(Value::new(101)) }
+            f102() { Ok(Value::new(102)) } f103() { Ok(Value::new(103)) } f104() { Ok(Value::new(104)) }
+            f105() { Ok(Value::new(105)) } f106() { Ok(Value::new(106)) } f107() { Ok(Value::new(107)) }
+            f108() { Ok(Value::new(108)) } f109() { Ok(Value::new(109)) } f110() { Ok(Value::new(110)) }
+            f111() { Ok(Value::new(111)) } f112() { Ok(Value::new(112)) } f113() { Ok(Value::new(113)) }
+            f114() { Ok(Value::new(114)) } f115() { Ok(Value::new(115)) } f116() { Ok(Value::new(116)) }
+            f117() { Ok(Value::new(117)) } f118() { Ok(Value::new(118)) } f119() { Ok(Value::new(119)) }
+            f120() { Ok(Value::new(120)) } f121() { Ok(Value::new(121)) } f122() { Ok(Value::new(122)) }
+            f123() { Ok(Value::new(123)) } f124() { Ok(Value::new(124)) } f125() { Ok(Value::new(125)) }
+            f126() { Ok(Value::new(126)) } f127() { Ok(Value::new(127)) } f128() { Ok(Value::new(128)) }
+            f129() { Ok(Value::new(129)) } f130() { Ok(Value::new(130)) } f131() { Ok(Value::new(131)) }
+            f132() { Ok(Value::new(132)) } f133() { Ok(Value::new(133)) } f134() { Ok(Value::new(134)) }
+            f135() { Ok(Value::new(135)) } f136() { Ok(Value::new(136)) } f137() { Ok(Value::new(137)) }
+            f138() { Ok(Value::new(138)) } f139() { Ok(Value::new(139)) } f140() { Ok(Value::new(140)) }
+            string.last(this) { Ok(this) }
+        }
+
+        let env = global(Environment::new("root"));
+        env.freeze();
+        let r = eval(
+            &Arc::new(Mutex::new(CodeMap::new())),
+            "many_functions.star",
+            "(f000(), f070(), BASE + f140(), 'abc'.last())",
+            Dialect::Bzl,
+            &mut env.child("test"),
+            TypeValues::new(env.clone()),
+        )
+        .unwrap();
+        assert_eq!("(0, 70, 1140, \"abc\")", r.to_repr());
+    }
+
+    #[test]
+    fn consts() {
+        let env = Environment::new("root");
+        starlark_consts! { env =>
+            ANSWER = 42,
+            NAMES = vec!["a", "b"],
+        }
+        assert_eq!("42", env.get("ANSWER").unwrap().to_str());
+        let mut names = env.get("NAMES").unwrap();
+        assert!(names.set_at(Value::from(0), Value::from("c")).is_err());
+    }
+
+    #[test]
+    fn const_default() {
+        const DEFAULT_COUNT: i64 = 3;
+        const DEFAULT_SEP: &str = ", ";
+        // Consts converted to the declared parameter type
+        const SMALL_COUNT: i32 = 2;
+
+        starlark_module! { global =>
+            repeat(#s, count = DEFAULT_COUNT, sep: String = DEFAULT_SEP, n: i64 = SMALL_COUNT) {
+                Ok(Value::new(format!("{}{}{}{}", s, count, sep, n)))
+            }
+        }
+
+        let env = global(Environment::new("root"));
+        let f = env.get("repeat").unwrap();
+        assert_eq!(
+            "<native function repeat>($s, count = 3, sep = \", \", n = 2)",
+            f.to_repr()
+        );
+
+        env.freeze();
+        let r = eval(
+            &Arc::new(Mutex::new(CodeMap::new())),
+            "const_default.star",
+            "repeat('x') + ' ' + repeat('y', 1, '-')",
+            Dialect::Bzl,
+            &mut env.child("test"),
+            TypeValues::new(env.clone()),
+        )
+        .unwrap();
+        assert_eq!("x3, 2 y1-2", r.to_str());
+    }
+
+    #[test]
+    fn starlark_default() {
+        starlark_module! { global =>
+            append_to(#x, items = starlark "[]", options: Value = starlark "{'sep': None}") {
+                items.downcast_mut::<List>()?.unwrap().push(x)?;
+                Ok(Value::from((items, options)))
+            }
+        }
+
+        let env = global(Environment::new("root"));
+        let f = env.get("append_to").unwrap();
+        assert_eq!(
+            "<native function append_to>($x, items = [], options = {'sep': None})",
+            f.to_repr()
+        );
+
+        env.freeze();
+        let r = eval(
+            &Arc::new(Mutex::new(CodeMap::new())),
+            "starlark_default.star",
+            "a = append_to(1)[0]\na[0] = 2\n(a, append_to(3), append_to(4, [5]))",
+            Dialect::Bzl,
+            &mut env.child("test"),
+            TypeValues::new(env.clone()),
+        )
+        .unwrap();
+        // Each call gets a fresh list, not the one mutated by the previous call.
+        assert_eq!(
+            "([2], ([3], {\"sep\": None}), ([5, 4], {\"sep\": None}))",
+            r.to_repr()
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid default value [ for parameter list")]
+    fn invalid_starlark_default() {
+        starlark_module! { global =>
+            f(list = starlark "[") {
+                Ok(list)
+            }
+        }
+
+        global(Environment::new("root"));
+    }
+
+    #[test]
+    fn tuple_parameter() {
+        starlark_module! { global =>
+            dist((x, y): (i64, i64)) {
+                Ok(Value::new(x * x + y * y))
+            }
+            swap((a, b), #c) {
+                Ok(Value::from((b, a, c)))
+            }
+        }
+
+        let env = global(Environment::new("root"));
+        assert_eq!(
+            "<native function dist>((x, y))",
+            env.get("dist").unwrap().to_repr()
+        );
+        env.freeze();
+        let eval_tuple = |content: &str| {
+            eval(
+                &Arc::new(Mutex::new(CodeMap::new())),
+                "tuple_parameter.star",
+                content,
+                Dialect::Bzl,
+                &mut env.child("test"),
+                TypeValues::new(env.clone()),
+            )
+        };
+        assert_eq!("25", eval_tuple("dist((3, 4))").unwrap().to_str());
+        assert_eq!("25", eval_tuple("dist([3, 4])").unwrap().to_str());
+        assert_eq!(
+            "(\"b\", 1, [])",
+            eval_tuple("swap((1, 'b'), [])").unwrap().to_repr()
+        );
+        for content in &[
+            "dist((3, 4, 5))",
+            "dist((3,))",
+            "dist(('3', 4))",
+            "swap([1], 2)",
+        ] {
+            let err = eval_tuple(content).unwrap_err();
+            assert_eq!(
+                Some(INCORRECT_PARAMETER_TYPE_ERROR_CODE.to_owned()),
+                err.code,
+                "{}",
+                content
+            );
+        }
+    }
+
+    #[test]
+    fn trace_arguments() {
+        starlark_module! { global =>
+            greet(#name: String, times: i64 = 1, *rest) {
+                Ok(Value::new(format!("{} {} {}", name, times, rest.len())))
+            }
+        }
+
+        let env = global(Environment::new("root"));
+        env.freeze();
+        let lines = Arc::new(Mutex::new(Vec::new()));
+        let options = {
+            let lines = lines.clone();
+            EvaluationOptions::default()
+                .trace_arguments(move |line| lines.lock().unwrap().push(line.to_owned()))
+        };
+        let eval_traced = |content: &str| {
+            lines.lock().unwrap().clear();
+            let result = eval_with_options(
+                &Arc::new(Mutex::new(CodeMap::new())),
+                "trace.star",
+                content,
+                Dialect::Bzl,
+                &mut env.child("test"),
+                TypeValues::new(env.clone()),
+                NoLoadFileLoader,
+                &options,
+            );
+            (result.is_ok(), lines.lock().unwrap().clone())
+        };
+
+        assert_eq!(
+            (
+                true,
+                vec![
+                    "greet(name): received string, accepted".to_owned(),
+                    "greet(times): received int, accepted".to_owned(),
+                    "greet(rest): received (), accepted".to_owned(),
+                ]
+            ),
+            eval_traced("greet('x')")
+        );
+        assert_eq!(
+            (
+                false,
+                vec![
+                    "greet(name): received string, accepted".to_owned(),
+                    concat!(
+                        "greet(times): received string, ",
+                        "rejected: Type of parameters times mismatch, expected int, got string"
+                    )
+                    .to_owned(),
+                ]
+            ),
+            eval_traced("greet('x', '2', 3, 4)")
+        );
+        assert_eq!(
+            (false, vec!["greet(name): missing".to_owned()]),
+            eval_traced("greet(times = 2)")
+        );
+    }
+
+    #[test]
+    fn error_location() {
+        starlark_module! { global =>
+            check_positive(call_stack cs, #x: i64) {
+                if x <= 0 {
+                    starlark_err!(
+                        cs,
+                        INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                        format!("expected a positive number, got {}", x),
+                        "not positive".to_owned()
+                    )
+                }
+                Ok(Value::new(x))
+            }
+        }
+
+        let env = global(Environment::new("root"));
+        env.freeze();
+        let err = eval(
+            &Arc::new(Mutex::new(CodeMap::new())),
+            "location.star",
+            "def f(x):\n  return check_positive(x)\n\nf(1)\nf(-1)\n",
+            Dialect::Bzl,
+            &mut env.child("test"),
+            TypeValues::new(env.clone()),
+        )
+        .unwrap_err();
+        assert_eq!(
+            Some(INCORRECT_PARAMETER_TYPE_ERROR_CODE.to_owned()),
+            err.code
+        );
+        assert_eq!(
+            Some("not positive at location.star:2:10".to_owned()),
+            err.spans[0].label
+        );
+    }
+
+    #[test]
+    fn parameter_docs() {
+        starlark_module! { global =>
+            /// Join two strings.
+            ///
+            /// @param a: the first string
+            /// @param b : the second string, after `a`
+            #[cfg(all())]
+            join(#a: String, b: String = "", *rest) {
+                Ok(Value::new(format!("{}{}{}", a, b, rest.len())))
+            }
+
+            /// @param this: the list to grow
+            list.grow(this, #n) {
+                Ok(Value::from((this, n)))
+            }
+        }
+
+        let env = global(Environment::new("root"));
+        let join = env.get("join").unwrap();
+        let help = join
+            .downcast_ref::<NativeFunction>()
+            .unwrap()
+            .signature_help();
+        assert_eq!("join($a, b = \"\", *rest)", help.label);
+        assert_eq!(
+            vec![
+                ParameterHelp {
+                    name: "a".to_owned(),
+                    documentation: Some("the first string".to_owned()),
+                },
+                ParameterHelp {
+                    name: "b".to_owned(),
+                    documentation: Some("the second string, after `a`".to_owned()),
+                },
+                ParameterHelp {
+                    name: "rest".to_owned(),
+                    documentation: None,
+                },
+            ],
+            help.parameters
+        );
+
+        let type_values = TypeValues::new(env);
+        let help = type_values
+            .get_signature_help(&Value::from(vec![1]), "grow")
+            .unwrap();
+        assert_eq!("grow(this, $n)", help.label);
+        assert_eq!(
+            vec![Some("the list to grow"), None],
+            help.parameters
+                .iter()
+                .map(|p| p.documentation.as_deref())
+                .collect::<Vec<_>>()
+        );
+        assert!(type_values
+            .get_signature_help(&Value::from(vec![1]), "shrink")
+            .is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "documented parameter c is not a parameter of native function f")]
+    fn unknown_documented_parameter() {
+        starlark_module! { global =>
+            /// @param c: not a parameter
+            f(a, b) {
+                Ok(Value::from((a, b)))
+            }
+        }
+
+        global(Environment::new("root"));
+    }
+
+    #[test]
+    fn deprecated_in() {
+        starlark_module! { global =>
+            /// Superseded by `bar`.
+            #[starlark(deprecated_in = "2.0", note = "use bar() instead")]
+            foo() {
+                Ok(Value::new(1))
+            }
+
+            bar() {
+                Ok(Value::new(2))
+            }
+
+            #[starlark(deprecated_in = "1.5")]
+            string.shout(this: String) {
+                Ok(Value::new(this.to_uppercase()))
+            }
+        }
+
+        let env = global(Environment::new("root"));
+        env.freeze();
+        let warnings = Arc::new(Mutex::new(Vec::new()));
+        let options = {
+            let warnings = warnings.clone();
+            EvaluationOptions::default()
+                .on_warning(move |w| warnings.lock().unwrap().push(w.to_owned()))
+        };
+        let result = eval_with_options(
+            &Arc::new(Mutex::new(CodeMap::new())),
+            "deprecated.star",
+            "'%d %d %s' % (foo(), bar(), 'a'.shout())",
+            Dialect::Bzl,
+            &mut env.child("test"),
+            TypeValues::new(env.clone()),
+            NoLoadFileLoader,
+            &options,
+        )
+        .unwrap();
+        assert_eq!("1 2 A", result.to_str());
+        assert_eq!(
+            vec![
+                "foo() was deprecated in 2.0; use bar() instead".to_owned(),
+                "shout() was deprecated in 1.5".to_owned(),
+            ],
+            *warnings.lock().unwrap()
+        );
+
+        let foo = env.get("foo").unwrap();
+        assert_eq!(
+            Some(&Deprecation {
+                version: "2.0".to_owned(),
+                note: Some("use bar() instead".to_owned()),
+            }),
+            foo.downcast_ref::<NativeFunction>().unwrap().deprecation()
+        );
+        let bar = env.get("bar").unwrap();
+        assert!(bar
+            .downcast_ref::<NativeFunction>()
+            .unwrap()
+            .deprecation()
+            .is_none());
+        let type_values = TypeValues::new(env);
+        assert_eq!(
+            Some(Deprecation {
+                version: "1.5".to_owned(),
+                note: None,
+            }),
+            type_values.get_deprecation(&Value::from(""), "shout")
+        );
+    }
 }
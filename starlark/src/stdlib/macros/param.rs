@@ -16,36 +16,73 @@
 
 use crate::values::dict::Dictionary;
 use crate::values::error::ValueError;
+use crate::values::OrderedMap;
 use crate::values::{TypedValue, Value};
-use linked_hash_map::LinkedHashMap;
 use std::convert::TryInto;
 use std::hash::Hash;
 
 /// Types implementing this type may appear in function parameter types
 /// in `starlark_module!` macro function signatures.
 pub trait TryParamConvertFromValue: Sized {
+    /// The Starlark type a value is converted from, named in the error reported when the
+    /// conversion of a parameter fails.
+    const EXPECTED_TYPE: &'static str = "value";
+
     fn try_from(source: Value) -> Result<Self, ValueError>;
 }
 
+/// Convert the element at `path` (e.g. `[1]`) of a container, with an error naming the
+/// position, the expected type and the type received when the conversion fails.
+fn try_element_from<T: TryParamConvertFromValue>(
+    source: Value,
+    path: impl FnOnce() -> String,
+) -> Result<T, ValueError> {
+    let got = source.get_type();
+    T::try_from(source).map_err(|e| match e {
+        ValueError::IncorrectElementType {
+            path: inner,
+            expected,
+            got,
+        } => ValueError::IncorrectElementType {
+            path: format!("{}{}", path(), inner),
+            expected,
+            got,
+        },
+        _ => ValueError::IncorrectElementType {
+            path: path(),
+            expected: T::EXPECTED_TYPE,
+            got: got.to_owned(),
+        },
+    })
+}
+
 impl<T: TryParamConvertFromValue> TryParamConvertFromValue for Vec<T> {
+    const EXPECTED_TYPE: &'static str = "list";
+
     fn try_from(source: Value) -> Result<Self, ValueError> {
         let mut r = Vec::new();
-        for item in &source.iter()? {
-            r.push(T::try_from(item)?);
+        for (i, item) in source.iter()?.iter().enumerate() {
+            r.push(try_element_from(item, || format!("[{}]", i))?);
         }
         Ok(r)
     }
 }
 
 impl<K: TryParamConvertFromValue + Hash + Eq, V: TryParamConvertFromValue> TryParamConvertFromValue
-    for LinkedHashMap<K, V>
+    for OrderedMap<K, V>
 {
+    const EXPECTED_TYPE: &'static str = "dict";
+
     fn try_from(source: Value) -> Result<Self, ValueError> {
         match source.downcast_ref::<Dictionary>() {
             Some(dict) => {
-                let mut r = LinkedHashMap::new();
+                let mut r = OrderedMap::new();
                 for (k, v) in dict.get_content() {
-                    r.insert(K::try_from(k.get_value().clone())?, V::try_from(v.clone())?);
+                    let k = k.get_value();
+                    r.insert(
+                        try_element_from(k.clone(), || format!(" key {}", k.to_repr()))?,
+                        try_element_from(v.clone(), || format!("[{}]", k.to_repr()))?,
+                    );
                 }
                 Ok(r)
             }
@@ -61,6 +98,8 @@ impl TryParamConvertFromValue for Value {
 }
 
 impl<T: TypedValue + Clone + 'static> TryParamConvertFromValue for T {
+    const EXPECTED_TYPE: &'static str = T::TYPE;
+
     fn try_from(source: Value) -> Result<Self, ValueError> {
         match source.downcast_ref::<T>() {
             Some(t) => Ok(t.clone()),
@@ -70,6 +109,8 @@ impl<T: TypedValue + Clone + 'static> TryParamConvertFromValue for T {
 }
 
 impl TryParamConvertFromValue for i32 {
+    const EXPECTED_TYPE: &'static str = "int";
+
     fn try_from(source: Value) -> Result<Self, ValueError> {
         let source = i64::try_from(source)?;
         source
@@ -79,6 +120,8 @@ impl TryParamConvertFromValue for i32 {
 }
 
 impl TryParamConvertFromValue for u32 {
+    const EXPECTED_TYPE: &'static str = "int";
+
     fn try_from(source: Value) -> Result<Self, ValueError> {
         let source = i64::try_from(source)?;
         source
@@ -88,6 +131,8 @@ impl TryParamConvertFromValue for u32 {
 }
 
 impl TryParamConvertFromValue for u64 {
+    const EXPECTED_TYPE: &'static str = "int";
+
     fn try_from(source: Value) -> Result<Self, ValueError> {
         let source = i64::try_from(source)?;
         source
@@ -97,6 +142,8 @@ impl TryParamConvertFromValue for u64 {
 }
 
 impl TryParamConvertFromValue for usize {
+    const EXPECTED_TYPE: &'static str = "int";
+
     fn try_from(source: Value) -> Result<Self, ValueError> {
         let source = i64::try_from(source)?;
         source
@@ -105,6 +152,31 @@ impl TryParamConvertFromValue for usize {
     }
 }
 
+macro_rules! try_param_convert_tuple {
+    ($len:expr; $($t:ident),+) => {
+        /// Destructure a list or a tuple of exactly the same length.
+        impl<$($t: TryParamConvertFromValue),+> TryParamConvertFromValue for ($($t,)+) {
+            const EXPECTED_TYPE: &'static str = "list or tuple";
+
+            fn try_from(source: Value) -> Result<Self, ValueError> {
+                let items: Vec<Value> = source.iter()?.iter().collect();
+                if items.len() != $len {
+                    return Err(ValueError::IncorrectParameterType);
+                }
+                let mut items = items.into_iter().enumerate();
+                Ok(($({
+                    let (i, item) = items.next().unwrap();
+                    try_element_from::<$t>(item, || format!("[{}]", i))?
+                },)+))
+            }
+        }
+    };
+}
+
+try_param_convert_tuple!(2; A, B);
+try_param_convert_tuple!(3; A, B, C);
+try_param_convert_tuple!(4; A, B, C, D);
+
 /// Starlark `None` or another value.
 pub enum EitherValueOrNone<T> {
     None,
@@ -112,6 +184,8 @@ pub enum EitherValueOrNone<T> {
 }
 
 impl<T: TryParamConvertFromValue> TryParamConvertFromValue for EitherValueOrNone<T> {
+    const EXPECTED_TYPE: &'static str = T::EXPECTED_TYPE;
+
     fn try_from(source: Value) -> Result<Self, ValueError> {
         if source.get_type() == "NoneType" {
             Ok(EitherValueOrNone::None)
@@ -135,6 +209,7 @@ mod test {
     use crate::eval::noload::eval;
     use crate::stdlib::global_environment;
     use crate::syntax::dialect::Dialect;
+    use crate::values::OrderedMap;
     use crate::values::Value;
     use codemap::CodeMap;
     use std::sync::{Arc, Mutex};
@@ -144,6 +219,28 @@ mod test {
             // real implementation may write it to a global variable
             Ok(Value::new(format!("{:?} {:?}", name, srcs)))
         }
+
+        pair((x, s): (i64, String)) {
+            Ok(Value::new(format!("{} {}", x + 1, s)))
+        }
+
+        tags(tags: OrderedMap<String, String>) {
+            Ok(Value::new(format!("{:?}", tags)))
+        }
+    }
+
+    fn eval_in_global(content: &str) -> Result<Value, codemap_diagnostic::Diagnostic> {
+        let env = global(global_environment());
+        env.freeze();
+        let mut child = env.child("my");
+        eval(
+            &Arc::new(Mutex::new(CodeMap::new())),
+            "test.star",
+            content,
+            Dialect::Build,
+            &mut child,
+            TypeValues::new(env),
+        )
     }
 
     #[test]
@@ -166,4 +263,46 @@ mod test {
 
         assert_eq!(r#""star" ["a.cc", "b.cc"]"#, r.to_str());
     }
+
+    #[test]
+    fn test_tuple_destructuring() {
+        assert_eq!("2 a", eval_in_global("pair([1, 'a'])").unwrap().to_str());
+        assert_eq!("3 b", eval_in_global("pair((2, 'b'))").unwrap().to_str());
+        assert!(eval_in_global("pair([1])").is_err());
+        assert!(eval_in_global("pair([1, 'a', 2])").is_err());
+        assert!(eval_in_global("pair(['a', 1])").is_err());
+    }
+
+    #[test]
+    fn test_type_mismatch() {
+        let message = |content| eval_in_global(content).unwrap_err().message;
+        assert_eq!(
+            "Type of parameters name mismatch, expected string, got list",
+            message("cc_binary(name=['star'])")
+        );
+        assert_eq!(
+            "Type of parameters srcs mismatch, expected list, got string",
+            message("cc_binary(name='star', srcs='a.cc')")
+        );
+        assert_eq!(
+            "Type of parameters (x, s) mismatch, expected list or tuple, got int",
+            message("pair(1)")
+        );
+        assert_eq!(
+            "Type of parameters srcs[1] mismatch, expected string, got int",
+            message("cc_binary(name='star', srcs=['a.cc', 1])")
+        );
+        assert_eq!(
+            "Type of parameters (x, s)[0] mismatch, expected int, got string",
+            message("pair(['a', 1])")
+        );
+        assert_eq!(
+            "Type of parameters tags[\"a\"] mismatch, expected string, got list",
+            message("tags({'a': []})")
+        );
+        assert_eq!(
+            "Type of parameters tags key 1 mismatch, expected string, got int",
+            message("tags({1: 'a'})")
+        );
+    }
 }
@@ -0,0 +1,223 @@
+// Copyright 2019 The Starlark in Rust Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Implementation of the `math` module, enabled by the `math` feature.
+//!
+//! Starlark in Rust has no floating point type, so only the functions which are defined on
+//! integers are provided: `sqrt`, `log`, `exp`, the trigonometric functions and the `pi`
+//! and `e` constants will be added along with the `float` type.
+
+use crate::values::error::*;
+use crate::values::frozen::Thaw;
+use crate::values::*;
+use std::convert::TryFrom;
+use std::iter;
+use std::sync::Arc;
+
+// Errors -- UF = User Failure -- Failure that should be expected by the user (e.g. from a fail()).
+pub const NEGATIVE_EXPONENT_ERROR_CODE: &str = "UF40";
+
+macro_rules! ok {
+    ($e:expr) => {
+        return Ok(Value::from($e));
+    };
+}
+
+/// The `math` value, whose attributes are the functions of the module.
+struct MathModule;
+
+impl TypedValue for MathModule {
+    type Holder = Immutable<MathModule>;
+
+    fn values_for_descendant_check_and_freeze<'a>(
+        &'a self,
+    ) -> Box<dyn Iterator<Item = Value> + 'a> {
+        Box::new(iter::empty())
+    }
+
+    fn to_repr(&self) -> String {
+        "<module math>".to_owned()
+    }
+
+    fn share(&self) -> Result<FrozenValue, ValueError> {
+        Ok(FrozenValue::new(MathModule))
+    }
+
+    const TYPE: &'static str = "math";
+}
+
+impl Thaw for MathModule {
+    fn thaw(self: Arc<Self>) -> Value {
+        Value::new(MathModule)
+    }
+}
+
+starlark_module! {global =>
+    const math = Value::new(MathModule);
+
+    /// math.abs: absolute value of a number.
+    ///
+    /// Examples:
+    ///
+    /// ```
+    /// # use starlark::stdlib::starlark_default;
+    /// # assert!(starlark_default(r#"(
+    /// math.abs(-3) == 3
+    /// # )"#).unwrap());
+    /// ```
+    math.abs(_this, #x: i64) {
+        ok!(x.checked_abs().ok_or(ValueError::IntegerOverflow)?)
+    }
+
+    /// math.ceil: smallest integer greater than or equal to a number.
+    ///
+    /// Integers are returned unchanged.
+    math.ceil(_this, #x: i64) {
+        ok!(x)
+    }
+
+    /// math.floor: largest integer less than or equal to a number.
+    ///
+    /// Integers are returned unchanged.
+    math.floor(_this, #x: i64) {
+        ok!(x)
+    }
+
+    /// math.pow: raise a number to a power.
+    ///
+    /// `math.pow(x, y)` returns `x` to the power of `y`, `y` must not be negative.
+    ///
+    /// Examples:
+    ///
+    /// ```
+    /// # use starlark::stdlib::starlark_default;
+    /// # assert!(starlark_default(r#"(
+    /// math.pow(2, 10) == 1024
+    /// # )"#).unwrap());
+    /// ```
+    math.pow(_this, #x: i64, #y: i64) {
+        if y < 0 {
+            starlark_err!(
+                NEGATIVE_EXPONENT_ERROR_CODE,
+                format!("Negative exponent {} is not supported for integers", y),
+                "Negative exponent".to_owned()
+            );
+        }
+        let r = match u32::try_from(y) {
+            Ok(y) => x.checked_pow(y),
+            // Only 0, 1 and -1 do not overflow with such exponents.
+            Err(..) => match x {
+                0 | 1 => Some(x),
+                -1 => Some(if y % 2 == 0 { 1 } else { -1 }),
+                _ => None,
+            },
+        };
+        ok!(r.ok_or(ValueError::IntegerOverflow)?)
+    }
+
+    /// math.round: round a number to a given precision in decimal digits.
+    ///
+    /// `math.round(x, ndigits=0)` rounds `x` to a multiple of `10 ** -ndigits`, ties
+    /// being rounded to the even multiple. Integers are thus only changed by a negative
+    /// `ndigits`.
+    ///
+    /// Examples:
+    ///
+    /// ```
+    /// # use starlark::stdlib::starlark_default;
+    /// # assert!(starlark_default(r#"(
+    /// math.round(1234, -2) == 1200
+    /// # )"#).unwrap());
+    /// # assert!(starlark_default(r#"(
+    /// math.round(25, -1) == 20
+    /// # )"#).unwrap());
+    /// ```
+    math.round(_this, #x: i64, #ndigits: i64 = 0) {
+        if ndigits >= 0 {
+            ok!(x);
+        }
+        let p = match u32::try_from(-ndigits).ok().and_then(|n| 10i64.checked_pow(n)) {
+            Some(p) => p,
+            // Every integer is closer to 0 than to any larger power of 10.
+            None => ok!(0),
+        };
+        let (q, r) = (x.div_euclid(p), x.rem_euclid(p));
+        let q = if r > p - r || (r == p - r && q % 2 != 0) {
+            q + 1
+        } else {
+            q
+        };
+        ok!(q.checked_mul(p).ok_or(ValueError::IntegerOverflow)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::starlark_default;
+    use super::super::tests::starlark_default_fail;
+    use super::*;
+
+    macro_rules! starlark_ok {
+        ($($t:expr),+) => (starlark_ok_fn!(starlark_default, $($t),+))
+    }
+
+    macro_rules! starlark_fail {
+        ($($t:expr),+) => (starlark_fail_fn!(starlark_default_fail, $($t),+))
+    }
+
+    #[test]
+    fn test_repr() {
+        starlark_ok!(r#"(repr(math) == "<module math>" and type(math) == "math")"#);
+    }
+
+    #[test]
+    fn test_abs() {
+        starlark_ok!(r#"(math.abs(-3) == 3 and math.abs(3) == 3 and math.abs(0) == 0)"#);
+        starlark_fail!(
+            r#"math.abs(-9223372036854775807 - 1)"#,
+            INTEGER_OVERFLOW_ERROR_CODE
+        );
+        starlark_fail!(r#"math.abs("1")"#, INCORRECT_PARAMETER_TYPE_ERROR_CODE);
+    }
+
+    #[test]
+    fn test_ceil_floor() {
+        starlark_ok!(r#"(math.ceil(-3) == -3 and math.floor(7) == 7)"#);
+    }
+
+    #[test]
+    fn test_pow() {
+        starlark_ok!(r#"(math.pow(2, 10) == 1024)"#);
+        starlark_ok!(r#"(math.pow(-3, 3) == -27)"#);
+        starlark_ok!(r#"(math.pow(5, 0) == 1)"#);
+        starlark_ok!(r#"(math.pow(-1, 5000000001) == -1 and math.pow(1, 5000000001) == 1)"#);
+        starlark_fail!(r#"math.pow(2, 63)"#, INTEGER_OVERFLOW_ERROR_CODE);
+        starlark_fail!(r#"math.pow(2, 5000000000)"#, INTEGER_OVERFLOW_ERROR_CODE);
+        starlark_fail!(r#"math.pow(2, -1)"#, NEGATIVE_EXPONENT_ERROR_CODE);
+    }
+
+    #[test]
+    fn test_round() {
+        starlark_ok!(r#"(math.round(1234) == 1234 and math.round(1234, 2) == 1234)"#);
+        starlark_ok!(r#"(math.round(1234, -2) == 1200 and math.round(1250, -2) == 1200)"#);
+        starlark_ok!(r#"(math.round(1350, -2) == 1400 and math.round(1251, -2) == 1300)"#);
+        starlark_ok!(r#"(math.round(-15, -1) == -20 and math.round(-25, -1) == -20)"#);
+        starlark_ok!(r#"(math.round(-14, -1) == -10 and math.round(-16, -1) == -20)"#);
+        starlark_ok!(r#"(math.round(123, -30) == 0)"#);
+        starlark_fail!(
+            r#"math.round(9223372036854775807, -1)"#,
+            INTEGER_OVERFLOW_ERROR_CODE
+        );
+    }
+}
@@ -13,19 +13,26 @@
 // limitations under the License.
 
 //! A module with the standard function and constants that are by default in all dialect of Starlark
+use crate::values::OrderedMap;
 use codemap::CodeMap;
 use codemap_diagnostic::{ColorConfig, Diagnostic, Emitter};
-use linked_hash_map::LinkedHashMap;
 use std;
 use std::cmp::Ordering;
 use std::error::Error;
 use std::num::NonZeroI64;
+use std::panic;
 use std::sync;
 
 use crate::environment::{Environment, TypeValues};
+use crate::eval::call_stack::CallStack;
 use crate::eval::noload::eval;
+use crate::eval::options::TIMEOUT_ERROR_CODE;
 use crate::syntax::dialect::Dialect;
 use crate::values::dict::Dictionary;
+use crate::values::error::{
+    ValueError, CALL_DEPTH_EXCEEDED_ERROR_CODE, EVALUATION_BUDGET_EXCEEDED_ERROR_CODE,
+    INTERRUPTED_ERROR_CODE, TOO_MANY_RECURSION_LEVEL_ERROR_CODE,
+};
 use crate::values::function::WrappedMethod;
 use crate::values::none::NoneType;
 use crate::values::range::Range;
@@ -38,37 +45,115 @@ const INT_CONVERSION_FAILED_ERROR_CODE: &str = "CR03";
 const ORD_EXPECT_ONE_CHAR_ERROR_CODE: &str = "CR04";
 const EMPTY_ITERABLE_ERROR_CODE: &str = "CR05";
 const NUL_RANGE_STEP_ERROR_CODE: &str = "CR06";
-const USER_FAILURE_ERROR_CODE: &str = "CR99";
+const ASSERT_FAILS_ERROR_CODE: &str = "CR07";
 
 #[macro_use]
 pub mod macros;
+pub mod bytes;
 pub mod dict;
+#[cfg(feature = "json")]
+pub mod json;
 pub mod list;
+#[cfg(feature = "math")]
+pub mod math;
+pub mod partial;
 pub mod string;
 pub mod structs;
 
+/// Whether `err` is an exceeded evaluation limit, which must abort the evaluation.
+fn is_evaluation_limit(err: &ValueError) -> bool {
+    let code = match err {
+        ValueError::TooManyRecursionLevel
+        | ValueError::Interrupted { .. }
+        | ValueError::CallDepthExceeded { .. }
+        | ValueError::EvaluationBudgetExceeded { .. } => return true,
+        ValueError::Runtime(ref e) => Some(e.code),
+        ValueError::DiagnosedError(ref d) | ValueError::TracedError(ref d, _) => d.code.as_deref(),
+        _ => None,
+    };
+    code == Some(TIMEOUT_ERROR_CODE)
+        || code == Some(TOO_MANY_RECURSION_LEVEL_ERROR_CODE)
+        || code == Some(INTERRUPTED_ERROR_CODE)
+        || code == Some(CALL_DEPTH_EXCEEDED_ERROR_CODE)
+        || code == Some(EVALUATION_BUDGET_EXCEEDED_ERROR_CODE)
+}
+
 starlark_module! {global_functions =>
     /// fail: fail the execution
     ///
+    /// `fail(*args, sep=" ")` fails with the `str()` of all its arguments separated by
+    /// `sep` as message. The error reports the call stack at the time of the failure.
+    ///
     /// Examples:
     /// ```python
     /// fail("this is an error")  # Will fail with "this is an error"
+    /// fail("expected", 1, "got", 2)  # Will fail with "expected 1 got 2"
+    /// fail("a", "b", sep=", ")  # Will fail with "a, b"
+    /// ```
+    fail(call_stack st, *args, sep: String = " ".to_owned()) {
+        let message = args.iter().map(Value::to_str).collect::<Vec<_>>().join(&sep);
+        Err(ValueError::UserFailure {
+            message,
+            call_stack: st.print_with_newline_before().to_string(),
+        })
+    }
+
+    /// assert_fails: check that a function fails
+    ///
+    /// `assert_fails(f, expected)` calls `f` without arguments and fails unless the call
+    /// fails with an error whose message contains `expected` (case-sensitive). Errors
+    /// aborting the whole evaluation, like a timeout, are not caught, and neither is `f`
+    /// not being callable.
+    ///
+    /// Examples:
+    ///
+    /// ```
+    /// # use starlark::stdlib::starlark_default;
+    /// # assert!(starlark_default(r#"
+    /// def f():
+    ///   return {}["a"]
+    ///
+    /// assert_fails(f, "was not found")
+    /// # True"#).unwrap());
     /// ```
-    fail(call_stack st, msg) {
-        starlark_err!(
-            USER_FAILURE_ERROR_CODE,
-            format!(
-                "fail(): {}{}",
-                msg.to_str(),
-                st.print_with_newline_before(),
+    assert_fails(call_stack cs, env e, #f, #expected: String) {
+        let err = match f.call(cs, e, Vec::new(), OrderedMap::new(), None, None) {
+            Ok(v) => starlark_err!(
+                ASSERT_FAILS_ERROR_CODE,
+                format!(
+                    "assert_fails: expected an error containing {:?}, but the call returned {}",
+                    expected,
+                    v.to_repr()
+                ),
+                "Call did not fail".to_owned()
             ),
-            msg.to_str()
-        )
+            Err(err) => err,
+        };
+        let not_callable = match err {
+            ValueError::OperationNotSupported { ref op, .. } => op == "call()",
+            _ => false,
+        };
+        if not_callable || is_evaluation_limit(&err) {
+            return Err(err);
+        }
+        let message = err.to_string();
+        if !message.contains(&expected) {
+            starlark_err!(
+                ASSERT_FAILS_ERROR_CODE,
+                format!(
+                    "assert_fails: expected an error containing {:?}, got: {}",
+                    expected, message
+                ),
+                "Unexpected error".to_owned()
+            )
+        }
+        Ok(Value::new(NoneType::None))
     }
 
     /// [any](
     /// https://github.com/google/skylark/blob/a0e5de7e63b47e716cca7226662a4c95d47bf873/doc/spec.md#any
-    /// ): returns true if any value in the iterable object have a truth value of true.
+    /// ): returns true if any value in the iterable object have a truth value of true,
+    /// without evaluating the values after the first one which does.
     ///
     /// Examples:
     ///
@@ -89,6 +174,9 @@ starlark_module! {global_functions =>
     /// # assert!(starlark_default("(
     /// any([0, False]) == False
     /// # )").unwrap());
+    /// # assert!(starlark_default("(
+    /// any([]) == False
+    /// # )").unwrap());
     /// ```
     any(#x) {
         for i in &x.iter()? {
@@ -101,7 +189,8 @@ starlark_module! {global_functions =>
 
     /// [all](
     /// https://github.com/google/skylark/blob/a0e5de7e63b47e716cca7226662a4c95d47bf873/doc/spec.md#all
-    /// ): returns true if all values in the iterable object have a truth value of true.
+    /// ): returns true if all values in the iterable object have a truth value of true,
+    /// without evaluating the values after the first one which does not.
     ///
     /// Examples:
     ///
@@ -122,6 +211,9 @@ starlark_module! {global_functions =>
     /// # assert!(starlark_default("(
     /// all([0, False]) == False
     /// # )").unwrap());
+    /// # assert!(starlark_default("(
+    /// all([]) == True
+    /// # )").unwrap());
     /// ```
     all(#x) {
         for i in &x.iter()? {
@@ -234,7 +326,8 @@ starlark_module! {global_functions =>
     /// # )").unwrap());
     /// ```
     dict(?#a, **kwargs) {
-        let mut map = Dictionary::new();
+        let len = a.as_ref().and_then(|a| a.length().ok()).unwrap_or(0) as usize;
+        let mut map = Dictionary::with_capacity(len + kwargs.len());
         if let Some(a) = a {
             match a.get_type() {
                 "dict" => {
@@ -315,6 +408,7 @@ starlark_module! {global_functions =>
     /// the iterable sequence and the index of the value within the sequence.
     ///
     /// The optional second parameter, `start`, specifies an integer value to add to each index.
+    /// It can also be given by name.
     ///
     /// Examples:
     ///
@@ -326,14 +420,17 @@ starlark_module! {global_functions =>
     /// # assert!(starlark_default(r#"(
     /// enumerate(["one", "two"], 1) == [(1, "one"), (2, "two")]
     /// # )"#).unwrap());
+    /// # assert!(starlark_default(r#"(
+    /// enumerate(["one", "two"], start=-1) == [(-1, "one"), (0, "two")]
+    /// # )"#).unwrap());
     /// ```
-    enumerate(#it, #offset: i64 = 0) {
+    enumerate(#it, start: i64 = 0) {
         let v : Vec<Value> =
             it
             .iter()?
             .iter()
             .enumerate()
-            .map(|(k, v)| Value::from((Value::new(k as i64 + offset), v)))
+            .map(|(k, v)| Value::from((Value::new(k as i64 + start), v)))
             .collect();
         Ok(Value::from(v))
     }
@@ -347,23 +444,31 @@ starlark_module! {global_functions =>
     ///
     /// `getattr(x, "f")` is equivalent to `x.f`.
     ///
+    /// `getattr(x, name, default)` returns `default` instead of failing if x has no such
+    /// attribute.
+    ///
     /// ```
     /// # use starlark::stdlib::starlark_default;
     /// # assert!(starlark_default(r#"
     /// getattr("banana", "split")("a") == ["b", "n", "n", ""] # equivalent to "banana".split("a")
     /// # "#).unwrap());
+    /// # assert!(starlark_default(r#"
+    /// getattr("banana", "size", None) == None
+    /// # "#).unwrap());
     /// ```
-    getattr(env env, #a, #attr: String, #default = NoneType::None) {
-        match a.get_attr(&attr) {
-            Ok(v) => Ok(v),
-            x => match env.get_type_value(&a, &attr) {
-                Some(v) => if v.get_type() == "function" {
-                    // Insert self so the method see the object it is acting on
-                    Ok(WrappedMethod::new(a.clone(), v))
-                } else {
-                    Ok(v)
-                }
-                None => if default.get_type() == "NoneType" { x } else { Ok(default) }
+    getattr(env env, #a, #attr: String, ?#default) {
+        // Same lookup order as for `a.attr`.
+        match env.get_type_value(&a, &attr) {
+            Some(v) => if v.get_type() == "function" {
+                // Insert self so the method see the object it is acting on
+                Ok(WrappedMethod::new(a.clone(), v))
+            } else {
+                Ok(v)
+            }
+            None => match (a.get_attr(&attr), default) {
+                (Ok(v), _) => Ok(v),
+                (Err(..), Some(default)) => Ok(default),
+                (Err(e), None) => Err(e),
             }
         }
     }
@@ -412,7 +517,8 @@ starlark_module! {global_functions =>
     /// If x is a string, it is interpreted like a string literal;
     /// an optional base prefix (`0`, `0b`, `0B`, `0x`, `0X`) determines which base to use.
     /// The string may specify an arbitrarily large integer,
-    /// whereas true integer literals are restricted to 64 bits.
+    /// whereas true integer literals are restricted to 64 bits
+    /// unless the `bigint` feature is enabled.
     /// If a non-zero `base` argument is provided, the string is interpreted
     /// in that base and no base prefix is permitted; the base argument may
     /// specified by name.
@@ -464,8 +570,15 @@ starlark_module! {global_functions =>
                 } else { s },
                 _ => s
             };
-            match i64::from_str_radix(&s, base) {
-                Ok(i) => Ok(Value::new(sign * i)),
+            let parsed = i64::from_str_radix(&s, base).map(|i| Value::new(sign * i));
+            #[cfg(feature = "bigint")]
+            let parsed = parsed.or_else(|e| match bigint::parse(&s, base) {
+                Some(b) if sign < 0 => Ok(Value::from(-b)),
+                Some(b) => Ok(Value::from(b)),
+                None => Err(e),
+            });
+            match parsed {
+                Ok(v) => Ok(v),
                 Err(x) => starlark_err!(
                     INT_CONVERSION_FAILED_ERROR_CODE,
                     format!(
@@ -486,6 +599,8 @@ starlark_module! {global_functions =>
                         format!("Explict base '{}' provided with non-string", base.to_repr())
                     )
                 }
+                // Also keeps big integers with the `bigint` feature.
+                None if a.get_type() == "int" => Ok(a),
                 None => Ok(Value::new(a.to_int()?)),
             }
         }
@@ -511,15 +626,34 @@ starlark_module! {global_functions =>
     ///
     /// With no argument, `list()` returns a new empty list.
     list(?#a) {
-        let mut l = Vec::new();
-        if let Some(a) = a {
-            for x in &a.iter()? {
-                l.push(x.clone())
-            }
-        }
+        let l: Vec<Value> = match a {
+            Some(a) => a.iter()?.iter().collect(),
+            None => Vec::new(),
+        };
         Ok(Value::from(l))
     }
 
+    /// log: write its arguments to the output of the evaluation, separated by a space.
+    ///
+    /// The line goes to the same sink as [`print`](#print), see
+    /// [`EvaluationOptions::on_print`](crate::eval::options::EvaluationOptions::on_print).
+    /// Unlike `print`, which is built on `str()`, `log` never fails: an argument
+    /// that cannot be converted to a string is written as `<unreprable T>` where `T` is
+    /// its type, so that logging in an error path does not hide the original error.
+    ///
+    /// Examples:
+    ///
+    /// ```
+    /// # use starlark::stdlib::starlark_default;
+    /// # assert!(starlark_default(r#"(
+    /// log("x =", 1, [1, "x"]) == None
+    /// # )"#).unwrap());
+    /// ```
+    log(call_stack cs, *args) {
+        cs.print(&log_line(&args));
+        Ok(Value::new(NoneType::None))
+    }
+
     /// [max](
     /// https://github.com/google/skylark/blob/a0e5de7e63b47e716cca7226662a4c95d47bf873/doc/spec.md#max
     /// ): returns the maximum of a sequence.
@@ -567,9 +701,9 @@ starlark_module! {global_functions =>
                 }
             }
             Some(key) => {
-                let mut cached = key.call(cs, e.clone(), vec![max.clone()], LinkedHashMap::new(), None, None)?;
+                let mut cached = key.call(cs, e.clone(), vec![max.clone()], OrderedMap::new(), None, None)?;
                 for i in it {
-                    let keyi = key.call(cs, e.clone(), vec![i.clone()], LinkedHashMap::new(), None, None)?;
+                    let keyi = key.call(cs, e.clone(), vec![i.clone()], OrderedMap::new(), None, None)?;
                     if cached.compare(&keyi)? == Ordering::Less {
                         max = i;
                         cached = keyi;
@@ -624,9 +758,9 @@ starlark_module! {global_functions =>
                 }
             }
             Some(key) => {
-                let mut cached = key.call(cs, e.clone(), vec![min.clone()], LinkedHashMap::new(), None, None)?;
+                let mut cached = key.call(cs, e.clone(), vec![min.clone()], OrderedMap::new(), None, None)?;
                 for i in it {
-                    let keyi = key.call(cs, e.clone(), vec![i.clone()], LinkedHashMap::new(), None, None)?;
+                    let keyi = key.call(cs, e.clone(), vec![i.clone()], OrderedMap::new(), None, None)?;
                     if cached.compare(&keyi)? == Ordering::Greater {
                         min = i;
                         cached = keyi;
@@ -676,6 +810,27 @@ starlark_module! {global_functions =>
         }
     }
 
+    /// [print](
+    /// https://github.com/google/skylark/blob/a0e5de7e63b47e716cca7226662a4c95d47bf873/doc/spec.md#print
+    /// ): write the `str()` of its arguments separated by `sep` as a line to the output of
+    /// the evaluation.
+    ///
+    /// The output is the standard output, unless the embedder captures it with
+    /// [`EvaluationOptions::on_print`](crate::eval::options::EvaluationOptions::on_print).
+    ///
+    /// Examples:
+    ///
+    /// ```
+    /// # use starlark::stdlib::starlark_default;
+    /// # assert!(starlark_default(r#"(
+    /// print("x =", 1, sep=" ") == None
+    /// # )"#).unwrap());
+    /// ```
+    print(call_stack cs, *args, sep: String = " ".to_owned()) {
+        cs.print(&args.iter().map(Value::to_str).collect::<Vec<_>>().join(&sep));
+        Ok(Value::new(NoneType::None))
+    }
+
     /// [range](
     /// https://github.com/google/skylark/blob/a0e5de7e63b47e716cca7226662a4c95d47bf873/doc/spec.md#range
     /// ): return a range of integers
@@ -807,46 +962,7 @@ starlark_module! {global_functions =>
     /// ```
     sorted(call_stack cs, env e, #x, ?key, reverse = false) {
         let it = x.iter()?;
-        let x = it.iter();
-        let mut it = match key {
-            None => {
-                x.map(|x| (x.clone(), x)).collect()
-            }
-            Some(key) => {
-                let mut v = Vec::new();
-                for el in x {
-                    v.push((
-                        el.clone(),
-                        key.call(cs, e.clone(), vec![el], LinkedHashMap::new(), None, None)?
-                    ));
-                }
-                v
-            }
-        };
-
-        let mut compare_ok = Ok(());
-
-        let reverse = reverse.to_bool();
-        it.sort_by(
-            |x : &(Value, Value), y : &(Value, Value)| {
-                let ord_or_err = if reverse {
-                    x.1.compare(&y.1).map(Ordering::reverse)
-                } else {
-                    x.1.compare(&y.1)
-                };
-                match ord_or_err {
-                    Ok(r) => r,
-                    Err(e) => {
-                        compare_ok = Err(e);
-                        Ordering::Equal // does not matter
-                    }
-                }
-            }
-        );
-
-        compare_ok?;
-
-        let result : Vec<Value> = it.into_iter().map(|x| x.0).collect();
+        let result = sort_values(cs, e, it.iter(), key, reverse.to_bool())?;
         Ok(Value::from(result))
     }
 
@@ -877,12 +993,10 @@ starlark_module! {global_functions =>
     ///
     /// With no arguments, `tuple()` returns the empty tuple.
     tuple(?#a) {
-        let mut l = Vec::new();
-        if let Some(a) = a {
-            for x in &a.iter()? {
-                l.push(x.clone())
-            }
-        }
+        let l = match a {
+            Some(a) => a.iter()?.iter().collect(),
+            None => Vec::new(),
+        };
         Ok(Value::new(tuple::Tuple::new(l)))
     }
 
@@ -896,6 +1010,8 @@ starlark_module! {global_functions =>
     /// type(None)              == "NoneType"
     /// # and
     /// type(0)                 == "int"
+    /// # and
+    /// type([])                == "list"
     /// # )"#).unwrap());
     /// ```
     _type(#a) {
@@ -926,17 +1042,17 @@ starlark_module! {global_functions =>
     zip(*args) {
         let mut v = Vec::new();
 
-        for arg in args {
-            let first = v.is_empty();
+        for (i, arg) in args.into_iter().enumerate() {
             let mut idx = 0;
             for e in &arg.iter()? {
-                if first {
+                if i == 0 {
                     v.push(Value::from((e.clone(),)));
-                    idx += 1;
                 } else if idx < v.len() {
                     v[idx] = v[idx].add(Value::from((e.clone(),)))?;
-                    idx += 1;
+                } else {
+                    break;
                 }
+                idx += 1;
             }
             v.truncate(idx);
         }
@@ -944,22 +1060,102 @@ starlark_module! {global_functions =>
     }
 }
 
+/// Stable sort of `values` for `sorted()` and `list.sort()`, by the result of the `key`
+/// function if given.
+pub(crate) fn sort_values(
+    call_stack: &CallStack,
+    type_values: TypeValues,
+    values: impl Iterator<Item = Value>,
+    key: Option<Value>,
+    reverse: bool,
+) -> Result<Vec<Value>, ValueError> {
+    let mut it: Vec<(Value, Value)> = match key {
+        None => values.map(|x| (x.clone(), x)).collect(),
+        Some(key) => {
+            let mut v = Vec::with_capacity(values.size_hint().0);
+            for el in values {
+                v.push((
+                    el.clone(),
+                    key.call(
+                        call_stack,
+                        type_values.clone(),
+                        vec![el],
+                        OrderedMap::new(),
+                        None,
+                        None,
+                    )?,
+                ));
+            }
+            v
+        }
+    };
+
+    let mut compare_ok = Ok(());
+
+    it.sort_by(|x, y| {
+        let ord_or_err = if reverse {
+            x.1.compare(&y.1).map(Ordering::reverse)
+        } else {
+            x.1.compare(&y.1)
+        };
+        match ord_or_err {
+            Ok(r) => r,
+            Err(e) => {
+                compare_ok = Err(e);
+                Ordering::Equal // does not matter
+            }
+        }
+    });
+
+    compare_ok?;
+
+    Ok(it.into_iter().map(|x| x.0).collect())
+}
+
+/// Format the arguments of `log()`, replacing values whose conversion to string panics by a
+/// placeholder.
+fn log_line(args: &[Value]) -> String {
+    let v: Vec<String> = args
+        .iter()
+        .map(|a| {
+            panic::catch_unwind(panic::AssertUnwindSafe(|| a.to_str()))
+                .unwrap_or_else(|_| format!("<unreprable {}>", a.get_type()))
+        })
+        .collect();
+    v.join(" ")
+}
+
 /// Return the default global environment, it is not yet frozen so that a caller can refine it.
 ///
 /// For example `stdlib::global_environment().freeze().child("test")` create a child environment
 /// of this global environment that have been frozen.
+///
+/// The `json` and `math` modules are only registered when the crate is built with the
+/// `json` and `math` features, which are not enabled by default.
+///
+/// To evaluate scripts on several threads, share it once with [`Environment::share`], and
+/// convert the result in each thread with
+/// [`SharedEnvironment::to_environment`](crate::environment::SharedEnvironment::to_environment).
 pub fn global_environment() -> Environment {
     let env = Environment::new("global");
     env.set("None", Value::new(NoneType::None)).unwrap();
     env.set("True", Value::new(true)).unwrap();
     env.set("False", Value::new(false)).unwrap();
+    // Opt-in modules, see the `[features]` of the crate.
+    #[cfg(feature = "math")]
+    let env = math::global(env);
+    #[cfg(feature = "json")]
+    let env = json::global(env);
+    let env = bytes::global(env);
     dict::global(list::global(string::global(global_functions(env))))
 }
 
-/// Default global environment with added non-standard `struct` and `set` extensions.
+/// Default global environment with added non-standard `struct`, `set` and `partial`
+/// extensions.
 pub fn global_environment_with_extensions() -> Environment {
     let env = global_environment();
     let env = structs::global(env);
+    let env = partial::global(env);
     crate::linked_hash_set::global(env)
 }
 
@@ -991,10 +1187,14 @@ pub mod tests {
     use super::global_environment;
     use super::starlark_default;
     use super::Dialect;
+    use super::{is_evaluation_limit, ASSERT_FAILS_ERROR_CODE, TIMEOUT_ERROR_CODE};
     use crate::environment::TypeValues;
     use crate::eval::noload::eval;
+    use crate::eval::LOAD_CYCLE_ERROR_CODE;
+    use crate::values::error::{ValueError, NOT_SUPPORTED_ERROR_CODE, USER_FAILURE_ERROR_CODE};
+    use crate::values::Value;
     use codemap::CodeMap;
-    use codemap_diagnostic::Diagnostic;
+    use codemap_diagnostic::{Diagnostic, Level};
     use std::sync;
 
     pub fn starlark_default_fail(snippet: &str) -> Result<bool, Diagnostic> {
@@ -1030,6 +1230,48 @@ pub mod tests {
         starlark_ok!("True");
     }
 
+    #[test]
+    fn test_assert_fails() {
+        starlark_ok!(
+            r#"
+def f():
+  fail("boom", 1)
+def g():
+  return {}["b"]
+assert_fails(f, "boom 1") == None and assert_fails(g, "not found") == None"#
+        );
+        starlark_ok!("def f(): 1 // 0\nassert_fails(f, '') == None");
+
+        let d = starlark_default_fail("def f(): return 1\nassert_fails(f, 'boom')").unwrap_err();
+        assert_eq!(Some(ASSERT_FAILS_ERROR_CODE), d.code.as_deref());
+        assert!(
+            d.message.contains("but the call returned 1"),
+            "{}",
+            d.message
+        );
+
+        let d =
+            starlark_default_fail("def f(): fail('Boom')\nassert_fails(f, 'boom')").unwrap_err();
+        assert_eq!(Some(ASSERT_FAILS_ERROR_CODE), d.code.as_deref());
+        assert!(d.message.contains("got: fail(): Boom"), "{}", d.message);
+
+        starlark_fail!("assert_fails(1, '')");
+    }
+
+    #[test]
+    fn load_cycle_is_not_an_evaluation_limit() {
+        let diagnosed = |code: &str| {
+            ValueError::DiagnosedError(Diagnostic {
+                level: Level::Error,
+                message: String::new(),
+                code: Some(code.to_owned()),
+                spans: Vec::new(),
+            })
+        };
+        assert!(!is_evaluation_limit(&diagnosed(LOAD_CYCLE_ERROR_CODE)));
+        assert!(is_evaluation_limit(&diagnosed(TIMEOUT_ERROR_CODE)));
+    }
+
     #[test]
     fn test_any() {
         starlark_ok!("any([0, True])");
@@ -1048,6 +1290,45 @@ pub mod tests {
         starlark_ok!("(not all([1, False]))");
     }
 
+    #[test]
+    fn test_any_all_empty() {
+        starlark_ok!("(not any([]))");
+        starlark_ok!("all([])");
+        starlark_ok!("(not any(range(0)))");
+        starlark_ok!("all({})");
+    }
+
+    #[test]
+    fn test_any_all_short_circuit() {
+        use crate::testing::{eval_in_env, setup_test_env};
+        use crate::values::{Immutable, TypedValue, Value};
+        use std::iter;
+
+        // A value whose truth must not be evaluated.
+        struct Unreachable;
+
+        impl TypedValue for Unreachable {
+            type Holder = Immutable<Unreachable>;
+            const TYPE: &'static str = "unreachable";
+
+            fn values_for_descendant_check_and_freeze<'a>(
+                &'a self,
+            ) -> Box<dyn Iterator<Item = Value> + 'a> {
+                Box::new(iter::empty())
+            }
+
+            fn to_bool(&self) -> bool {
+                panic!("iterated past the short-circuit point")
+            }
+        }
+
+        let env = setup_test_env();
+        env.set("u", Value::new(Unreachable)).unwrap();
+        let eval = |content| eval_in_env(&env, content).unwrap().to_bool();
+        assert!(eval("any([0, 1, u])"));
+        assert!(!eval("all([1, 0, u])"));
+    }
+
     #[test]
     fn test_bool() {
         // bool
@@ -1104,6 +1385,128 @@ pub mod tests {
             "(enumerate(['zero', 'one', 'two']) == [(0, 'zero'), (1, 'one'), (2, 'two')])"
         );
         starlark_ok!("(enumerate(['one', 'two'], 1) == [(1, 'one'), (2, 'two')])");
+        starlark_ok!("(enumerate(('a', 'b'), start=10) == [(10, 'a'), (11, 'b')])");
+        starlark_ok!("(enumerate({'k': 1}) == [(0, 'k')] and enumerate([]) == [])");
+    }
+
+    /// Evaluate `content` with `globals` set, returning the lines written with `print()`
+    /// and `log()`.
+    fn printed_lines(content: &str, globals: Vec<(&str, Value)>) -> Vec<String> {
+        use crate::eval::eval_with_options;
+        use crate::eval::noload::NoLoadFileLoader;
+        use crate::eval::options::EvaluationOptions;
+        use std::sync::{Arc, Mutex};
+
+        let printed = Arc::new(Mutex::new(Vec::new()));
+        let options = {
+            let printed = printed.clone();
+            EvaluationOptions::default()
+                .on_print(move |line| printed.lock().unwrap().push(line.to_owned()))
+        };
+        let env = global_environment();
+        env.freeze();
+        let mut child = env.child("test");
+        for (name, value) in globals {
+            child.set(name, value).unwrap();
+        }
+        let result = eval_with_options(
+            &Arc::new(Mutex::new(CodeMap::new())),
+            "print.star",
+            content,
+            Dialect::Bzl,
+            &mut child,
+            TypeValues::new(env.clone()),
+            NoLoadFileLoader,
+            &options,
+        )
+        .unwrap();
+        assert_eq!("None", result.to_repr());
+        let printed = printed.lock().unwrap();
+        printed.clone()
+    }
+
+    #[test]
+    fn test_print() {
+        assert_eq!(
+            vec!["x = [1, \"a\"]", "1, 2", ""],
+            printed_lines(
+                "def f(x):\n  print('x =', x)\nf([1, 'a'])\nprint(1, 2, sep=', ')\nprint()",
+                Vec::new()
+            )
+        );
+    }
+
+    #[test]
+    fn test_fail() {
+        starlark_fail!(r#"fail("x")"#, USER_FAILURE_ERROR_CODE);
+        starlark_fail!(r#"fail()"#, USER_FAILURE_ERROR_CODE);
+
+        let d = starlark_default_fail(
+            "def f(x):\n  fail('expected', x, 'got', [2])\ndef g():\n  f(1)\ng()",
+        )
+        .unwrap_err();
+        assert_eq!(
+            Some(USER_FAILURE_ERROR_CODE),
+            d.code.as_ref().map(String::as_str)
+        );
+        assert_eq!(
+            concat!(
+                "fail(): expected 1 got [2]",
+                "\n    call to fail(*args, sep = \" \") at <test>:2",
+                "\n    call to f(x) at <test>:4",
+                "\n    call to g() at <test>:5",
+                "\nTraceback (most recent call last):",
+                "\n  <test>:5: in <module>",
+                "\n  <test>:4: in g()",
+                "\n  <test>:2: in f(x)",
+                "\n  <native>: in fail(*args, sep = \" \")",
+            ),
+            d.message
+        );
+        // The calls are also labelled in the snippet.
+        assert_eq!(
+            vec![
+                Some("expected 1 got [2]"),
+                Some("in <module>"),
+                Some("in g()")
+            ],
+            d.spans
+                .iter()
+                .map(|span| span.label.as_deref())
+                .collect::<Vec<_>>()
+        );
+
+        let d = starlark_default_fail(r#"fail("a", 1, sep=", ")"#).unwrap_err();
+        assert!(d.message.starts_with("fail(): a, 1\n"), "{}", d.message);
+    }
+
+    #[test]
+    fn test_getattr() {
+        starlark_ok!(r#"getattr("banana", "split")("a") == ["b", "n", "n", ""]"#);
+        starlark_ok!(r#"getattr({1: 2}, "get")(1) == 2"#);
+        starlark_ok!(r#"getattr(struct(a = 1), "a") == 1"#);
+        starlark_ok!(r#"getattr(struct(a = 1), "b", 2) == 2"#);
+        starlark_ok!(r#"getattr("", "size", None) == None"#);
+        starlark_ok!(r#"getattr(len, "a", "default") == "default""#);
+        starlark_ok!("x = [1]\ngetattr(x, 'append')(2)\nx == [1, 2]");
+        starlark_fail!(r#"getattr("", "size")"#, NOT_SUPPORTED_ERROR_CODE);
+        starlark_fail!(r#"getattr({}, "size")"#, NOT_SUPPORTED_ERROR_CODE);
+    }
+
+    #[test]
+    fn test_hasattr() {
+        starlark_ok!(r#"hasattr("", "split") and not hasattr("", "size")"#);
+        starlark_ok!(r#"hasattr({}, "keys") and not hasattr({}, "split")"#);
+        starlark_ok!(r#"hasattr(struct(a = 1), "a") and not hasattr(struct(a = 1), "b")"#);
+        starlark_ok!(r#"not hasattr(len, "a")"#);
+    }
+
+    #[test]
+    fn test_dir() {
+        starlark_ok!(r#""split" in dir("") and "keys" not in dir("")"#);
+        starlark_ok!(r#""keys" in dir({}) and "append" in dir([])"#);
+        starlark_ok!(r#"dir(struct(b = 1, a = 2)) == ["a", "b"]"#);
+        starlark_ok!(r#"dir(len) == []"#);
     }
 
     #[test]
@@ -1168,6 +1571,36 @@ pub mod tests {
         starlark_ok!("(type(None) == 'NoneType')");
     }
 
+    #[test]
+    fn test_repr_str_type_per_type() {
+        // (value, repr, str, type) for each builtin type.
+        for (value, repr, s, t) in &[
+            ("None", "None", "None", "NoneType"),
+            ("True", "True", "True", "bool"),
+            ("-3", "-3", "-3", "int"),
+            ("'a\\\"b'", "\"a\\\"b\"", "a\"b", "string"),
+            ("[1, 'x']", "[1, \"x\"]", "[1, \"x\"]", "list"),
+            ("('x',)", "(\"x\",)", "(\"x\",)", "tuple"),
+            ("{'k': None}", "{\"k\": None}", "{\"k\": None}", "dict"),
+            ("{1, 2}", "{1, 2}", "{1, 2}", "set"),
+            ("range(3)", "range(3)", "range(3)", "range"),
+        ] {
+            let snippet = format!(
+                "v = {}\n(repr(v), str(v), type(v)) == ({:?}, {:?}, {:?})",
+                value, repr, s, t
+            );
+            assert!(starlark_default(&snippet).unwrap(), "{}", snippet);
+        }
+        assert!(starlark_default(
+            r#"
+def f(x):
+  return x
+(type(f) == "function" and type(len) == "function" and str(f) == "f(x)"
+ and repr(len).startswith("<native function len>"))"#
+        )
+        .unwrap());
+    }
+
     #[test]
     fn test_min() {
         starlark_ok!("(min([3, 1, 4, 1, 5, 9]) == 1)");
@@ -1175,6 +1608,43 @@ pub mod tests {
         starlark_ok!("(min('two', 'three', 'four', key=len) == 'two')");
     }
 
+    #[test]
+    fn test_log() {
+        starlark_ok!(r#"(log() == None)"#);
+        starlark_ok!(r#"(log("x", 1, [1, "x"], {"a": None}) == None)"#);
+    }
+
+    #[test]
+    fn test_log_unreprable() {
+        use crate::values::{Immutable, TypedValue};
+        use std::iter;
+
+        struct Unreprable;
+
+        impl TypedValue for Unreprable {
+            type Holder = Immutable<Unreprable>;
+            const TYPE: &'static str = "unreprable_type";
+
+            fn to_repr(&self) -> String {
+                panic!("cannot repr")
+            }
+
+            fn values_for_descendant_check_and_freeze<'a>(
+                &'a self,
+            ) -> Box<dyn Iterator<Item = Value> + 'a> {
+                Box::new(iter::empty())
+            }
+        }
+
+        assert_eq!(
+            vec!["a 1 <unreprable unreprable_type> [\"b\"]", ""],
+            printed_lines(
+                "log('a', 1, u, ['b'])\nlog()",
+                vec![("u", Value::new(Unreprable))]
+            )
+        );
+    }
+
     #[test]
     fn test_max() {
         starlark_ok!("(max([3, 1, 4, 1, 5, 9]) == 9)");
@@ -1213,5 +1683,8 @@ pub mod tests {
         starlark_ok!("(zip() == [])");
         starlark_ok!("(zip(range(5)) == [(0,), (1,), (2,), (3,), (4,)])");
         starlark_ok!("(zip(range(5), 'abc'.split_codepoints()) == [(0, 'a'), (1, 'b'), (2, 'c')])");
+        starlark_ok!("(zip([1, 2], [3, 4, 5], (6, 7, 8)) == [(1, 3, 6), (2, 4, 7)])");
+        starlark_ok!("(zip([], [1, 2]) == [] and zip([1, 2], []) == [])");
+        starlark_ok!("(zip([1, 2], [], [3]) == [])");
     }
 }
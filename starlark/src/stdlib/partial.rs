@@ -0,0 +1,230 @@
+// Copyright 2019 The Starlark in Rust Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Implementation of `partial` function.
+
+use crate::environment::TypeValues;
+use crate::eval::call_stack::CallStack;
+use crate::values::error::{ValueError, INCORRECT_PARAMETER_TYPE_ERROR_CODE};
+use crate::values::frozen::Thaw;
+use crate::values::OrderedMap;
+use crate::values::*;
+use std::iter;
+use std::sync::Arc;
+
+/// `partial()` implementation: a function with some of its arguments already bound.
+///
+/// Like `self` for methods, the bound positional arguments are inserted before the
+/// positional arguments of the call. Keyword arguments of the call replace the bound
+/// keyword arguments of the same name.
+pub struct Partial {
+    func: Value,
+    args: Vec<Value>,
+    kwargs: OrderedMap<String, Value>,
+}
+
+impl TypedValue for Partial {
+    type Holder = Immutable<Partial>;
+
+    fn values_for_descendant_check_and_freeze<'a>(
+        &'a self,
+    ) -> Box<dyn Iterator<Item = Value> + 'a> {
+        Box::new(
+            iter::once(self.func.clone())
+                .chain(self.args.iter().cloned())
+                .chain(self.kwargs.values().cloned()),
+        )
+    }
+
+    fn function_id(&self) -> Option<FunctionId> {
+        Some(self.func.function_id())
+    }
+
+    fn to_repr(&self) -> String {
+        let mut r = "partial(".to_owned();
+        r.push_str(&self.func.to_repr());
+        for arg in &self.args {
+            r.push_str(", ");
+            r.push_str(&arg.to_repr());
+        }
+        for (name, value) in &self.kwargs {
+            r.push_str(", ");
+            r.push_str(name);
+            r.push_str("=");
+            r.push_str(&value.to_repr());
+        }
+        r.push_str(")");
+        r
+    }
+
+    fn share(&self) -> Result<FrozenValue, ValueError> {
+        let mut kwargs = Vec::with_capacity(self.kwargs.len());
+        for (name, value) in &self.kwargs {
+            kwargs.push((name.clone(), value.share()?));
+        }
+        Ok(FrozenValue::new(FrozenPartial {
+            func: self.func.share()?,
+            args: self
+                .args
+                .iter()
+                .map(Value::share)
+                .collect::<Result<_, _>>()?,
+            kwargs,
+        }))
+    }
+
+    const TYPE: &'static str = "function";
+
+    fn call(
+        &self,
+        call_stack: &CallStack,
+        type_values: TypeValues,
+        positional: Vec<Value>,
+        named: OrderedMap<String, Value>,
+        args: Option<Value>,
+        kwargs: Option<Value>,
+    ) -> ValueResult {
+        let positional: Vec<Value> = self
+            .args
+            .iter()
+            .cloned()
+            .chain(positional.into_iter())
+            .collect();
+        let mut merged = OrderedMap::new();
+        for (name, value) in &self.kwargs {
+            // A bound argument is also replaced by the `**kwargs` of the call.
+            let overridden = match kwargs {
+                Some(ref kwargs) => kwargs.is_in(&Value::from(name.as_str())).unwrap_or(false),
+                None => false,
+            };
+            if !overridden {
+                merged.insert(name.clone(), value.clone());
+            }
+        }
+        for (name, value) in named {
+            merged.insert(name, value);
+        }
+        self.func
+            .call(call_stack, type_values, positional, merged, args, kwargs)
+    }
+}
+
+starlark_module! { global =>
+    /// Creates a function with some arguments bound.
+    ///
+    /// `partial(func, *args, **kwargs)` returns a function which calls `func` with `args`
+    /// followed by its own positional arguments, and with `kwargs` updated with its own
+    /// keyword arguments. The bound arguments are frozen with the function.
+    ///
+    /// Examples:
+    ///
+    /// ```
+    /// # use starlark::stdlib::starlark_default;
+    /// # assert!(starlark_default(r#"(
+    /// partial(int, base = 16)("ff") == 255
+    /// # )"#).unwrap());
+    /// # assert!(starlark_default(r#"(
+    /// partial("{}-{}".format, "a")("b") == "a-b"
+    /// # )"#).unwrap());
+    /// ```
+    partial(#func, *args, **kwargs) {
+        check_type!(func, "partial", function);
+        Ok(Value::new(Partial {
+            func,
+            args,
+            kwargs,
+        }))
+    }
+}
+
+/// A shared `partial()` result, see [`Value::share`].
+struct FrozenPartial {
+    func: FrozenValue,
+    args: Vec<FrozenValue>,
+    kwargs: Vec<(String, FrozenValue)>,
+}
+
+impl Thaw for FrozenPartial {
+    fn thaw(self: Arc<Self>) -> Value {
+        Value::new(Partial {
+            func: self.func.to_value(),
+            args: self.args.iter().map(FrozenValue::to_value).collect(),
+            kwargs: self
+                .kwargs
+                .iter()
+                .map(|(name, value)| (name.clone(), value.to_value()))
+                .collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::{eval_in_env, setup_test_env};
+    use crate::values::error::{IMMUTABLE_ERROR_CODE, INCORRECT_PARAMETER_TYPE_ERROR_CODE};
+
+    #[test]
+    fn test_positional_order() {
+        let env = setup_test_env();
+        eval_in_env!(env, "def f(*args): return args");
+        assert_starlark_eq!(env, "partial(f)(1, 2)", vec![1, 2]);
+        assert_starlark_eq!(env, "partial(f, 1, 2)(3, 4)", vec![1, 2, 3, 4]);
+        assert_starlark_eq!(env, "partial(partial(f, 1), 2)(3)", vec![1, 2, 3]);
+        assert_starlark_eq!(env, "partial(f, 1)(*[2, 3])", vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_kwargs_override() {
+        let env = setup_test_env();
+        eval_in_env!(env, "def f(a, b = 'b', c = 'c'): return a + b + c");
+        assert_starlark_eq!(env, "partial(f, b = 'x')('a')", "axc");
+        assert_starlark_eq!(env, "partial(f, b = 'x')('a', b = 'y')", "ayc");
+        assert_starlark_eq!(
+            env,
+            "partial(f, b = 'x', c = 'z')('a', **{'b': 'y'})",
+            "ayz"
+        );
+        assert_starlark_eq!(env, "partial(f, 'a', c = 'z')(b = 'y')", "ayz");
+        // Positional arguments are not replaced by keywords.
+        let err = eval_in_env(&env, "partial(f, 'a')(a = 'x')").unwrap_err();
+        assert!(
+            err.message.contains("Unexpected keyword argument a"),
+            "{}",
+            err.message
+        );
+    }
+
+    #[test]
+    fn test_freeze() {
+        let env = setup_test_env();
+        eval_in_env!(
+            env,
+            "def append(l, x):\n  l.append(x)\n  return l\nadd_to_l = partial(append, [1])"
+        );
+        assert_starlark_eq!(env, "add_to_l(2)", vec![1, 2]);
+        let child = env.freeze().child("child");
+        assert_starlark_error!(child, "add_to_l(3)", IMMUTABLE_ERROR_CODE);
+    }
+
+    #[test]
+    fn test_repr() {
+        let env = setup_test_env();
+        assert_starlark_eq!(
+            env,
+            "repr(partial(len, [1], x = None))",
+            "partial(<native function len>($a), [1], x=None)"
+        );
+        assert_starlark_error!(env, "partial(1)", INCORRECT_PARAMETER_TYPE_ERROR_CODE);
+    }
+}
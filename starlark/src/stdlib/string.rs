@@ -47,11 +47,24 @@ fn format_capture<T: Iterator<Item = Value>>(
     args: &Value,
     kwargs: &Value,
 ) -> Result<String, ValueError> {
+    let capture = capture.get(1..).unwrap();
+    let capture = match capture.find(':') {
+        Some(x) if x + 1 == capture.len() => capture.get(..x).unwrap(),
+        Some(x) => starlark_err!(
+            FORMAT_STRING_INVALID_SPECIFIER_ERROR_CODE,
+            format!(
+                "Format specifier '{}' is not supported, format specifiers must be empty",
+                capture.get(x + 1..).unwrap()
+            ),
+            "Unsupported format specifier".to_owned()
+        ),
+        None => capture,
+    };
     let (n, conv) = {
         if let Some(x) = capture.find('!') {
-            (capture.get(1..x).unwrap(), capture.get(x + 1..).unwrap())
+            (capture.get(..x).unwrap(), capture.get(x + 1..).unwrap())
         } else {
-            (capture.get(1..).unwrap(), "s")
+            (capture, "s")
         }
     };
     let conv_s = |x: Value| x.to_str();
@@ -107,7 +120,14 @@ fn format_capture<T: Iterator<Item = Value>>(
             )
         } else {
             *captured_by_index = true;
-            Ok(conv(args.at(Value::from(i64::from_str(n).unwrap()))?))
+            match i64::from_str(n) {
+                Ok(i) => Ok(conv(args.at(Value::from(i))?)),
+                Err(..) => starlark_err!(
+                    OUT_OF_BOUND_ERROR_CODE,
+                    format!("Index {} is out of range in format string", n),
+                    "Index out of range".to_owned()
+                ),
+            }
         }
     } else {
         if let Some(x) = n.chars().find(|c| match c {
@@ -179,6 +199,56 @@ fn rsplitn_whitespace(s: &str, maxsplit: usize) -> Vec<String> {
     v
 }
 
+// The part of `s` between the code point indices `start` and `end`, as computed by
+// `convert_indices!`.
+fn slice_codepoints(s: &str, start: usize, end: usize) -> &str {
+    if start >= end {
+        return "";
+    }
+    let byte_offset = |i: usize| s.char_indices().nth(i).map_or(s.len(), |(o, _)| o);
+    &s[byte_offset(start)..byte_offset(end)]
+}
+
+// The prefixes or suffixes accepted by `startswith` and `endswith`: either a string or a
+// tuple of strings.
+fn affixes(fn_name: &str, affix: &Value) -> Result<Vec<String>, ValueError> {
+    match affix.get_type() {
+        "string" => Ok(vec![affix.to_str()]),
+        "tuple" => {
+            let mut r = Vec::new();
+            for (i, x) in affix.iter()?.iter().enumerate() {
+                if x.get_type() != "string" {
+                    starlark_err!(
+                        INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                        format!(
+                            "{}() expect a tuple of strings while element {} is of type {}.",
+                            fn_name,
+                            i,
+                            x.get_type()
+                        ),
+                        format!(
+                            "element {} of type {} while expected string",
+                            i,
+                            x.get_type()
+                        )
+                    )
+                }
+                r.push(x.to_str());
+            }
+            Ok(r)
+        }
+        t => starlark_err!(
+            INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+            format!(
+                "{}() expect a string or a tuple of strings as first parameter while got a \
+                 value of type {}.",
+                fn_name, t
+            ),
+            format!("type {} while expected string or tuple", t)
+        ),
+    }
+}
+
 starlark_module! {global =>
     /// [string.elems](
     /// https://github.com/google/skylark/blob/3705afa472e466b8b061cce44b47c9ddc6db696d/doc/spec.md#string·elems
@@ -270,6 +340,27 @@ starlark_module! {global =>
         ok!(v)
     }
 
+    /// [string.codepoint_ords](
+    /// https://github.com/bazelbuild/starlark/blob/master/spec.md#string·codepoint_ords
+    /// ): returns the unicode codepoints of a string.
+    ///
+    /// `S.codepoint_ords()` is the name newer versions of the specification use for
+    /// `S.codepoints()`, both return the sequence of integer Unicode code points
+    /// encoded by the string S.
+    ///
+    /// Examples:
+    ///
+    /// ```
+    /// # use starlark::stdlib::starlark_default;
+    /// # assert!(starlark_default(r#"(
+    /// list("a😿".codepoint_ords()) == [97, 128575]
+    /// # )"#).unwrap());
+    /// ```
+    string.codepoint_ords(this: String) {
+        let v : Vec<i64> = this.chars().map(|x| i64::from(u32::from(x))).collect();
+        ok!(v)
+    }
+
     /// [string.count](
     /// https://github.com/google/skylark/blob/3705afa472e466b8b061cce44b47c9ddc6db696d/doc/spec.md#string·count
     /// ): count the number of occurrences of a string in another string.
@@ -314,11 +405,35 @@ starlark_module! {global =>
         }
     }
 
+    /// string.encode: encode a string into bytes.
+    ///
+    /// `S.encode(encoding="utf-8")` returns the encoding of the string S in `encoding`,
+    /// which can be `utf-8`, `ascii` or `latin-1`, and fails if S contains characters
+    /// which cannot be encoded.
+    ///
+    /// Examples:
+    ///
+    /// ```
+    /// # use starlark::stdlib::starlark_default;
+    /// # assert!(starlark_default(r#"(
+    /// "hé".encode() == b"h\xc3\xa9"
+    /// # )"#).unwrap());
+    /// # assert!(starlark_default(r#"(
+    /// "hé".encode("latin-1") == b"h\xe9"
+    /// # )"#).unwrap());
+    /// ```
+    string.encode(this: String, encoding: String = "utf-8".to_owned()) {
+        ok!(super::bytes::encode_string(&this, &encoding)?)
+    }
+
     /// [string.endswith](
     /// https://github.com/google/skylark/blob/3705afa472e466b8b061cce44b47c9ddc6db696d/doc/spec.md#string·endswith
     /// ): determine if a string ends with a given suffix.
     ///
-    /// `S.endswith(suffix)` reports whether the string S has the specified suffix.
+    /// `S.endswith(suffix[, start[, end]])` reports whether the string S has the specified
+    /// suffix. `suffix` may also be a tuple of strings, in which case the result is true if
+    /// any of them is a suffix of S. If `start` or `end` are given, the test is done on the
+    /// substring `S[start:end]`.
     ///
     /// Examples:
     ///
@@ -327,9 +442,18 @@ starlark_module! {global =>
     /// # assert!(starlark_default(r#"(
     /// "filename.sky".endswith(".sky") == True
     /// # )"#).unwrap());
+    /// # assert!(starlark_default(r#"(
+    /// "filename.bzl".endswith((".bzl", ".star")) == True
+    /// # )"#).unwrap());
+    /// # assert!(starlark_default(r#"(
+    /// "filename.sky".endswith("name", 0, -4) == True
+    /// # )"#).unwrap());
     /// ```
-    string.endswith(this: String, #suffix: String) {
-        ok!(this.ends_with(suffix.as_str()))
+    string.endswith(this: String, #suffix, #start = 0, #end = NoneType::None) {
+        let suffixes = affixes("string.endswith", &suffix)?;
+        convert_indices!(this, start, end);
+        let this = slice_codepoints(&this, start, end);
+        ok!(suffixes.iter().any(|x| this.ends_with(x.as_str())))
     }
 
     /// [string.find](
@@ -408,7 +532,8 @@ starlark_module! {global =>
     ///
     /// The *format specifier*, after a colon, specifies field width,
     /// alignment, padding, and numeric precision.
-    /// Currently it must be empty, but it is reserved for future use.
+    /// Currently it must be empty (`{0:}` is the same as `{0}`), but it is reserved for
+    /// future use.
     ///
     /// Examples:
     ///
@@ -821,7 +946,9 @@ starlark_module! {global =>
     /// https://github.com/google/skylark/blob/3705afa472e466b8b061cce44b47c9ddc6db696d/doc/spec.md#string·lstrip
     /// ): trim leading whitespaces.
     ///
-    /// `S.lstrip()` returns a copy of the string S with leading whitespace removed.
+    /// `S.lstrip([chars])` returns a copy of the string S with leading whitespace removed.
+    /// If `chars` is given, it is a string specifying the set of characters to remove
+    /// instead of whitespaces.
     ///
     /// Examples:
     ///
@@ -830,9 +957,18 @@ starlark_module! {global =>
     /// # assert!(starlark_default(r#"(
     /// "  hello  ".lstrip() == "hello  "
     /// # )"#).unwrap());
+    /// # assert!(starlark_default(r#"(
+    /// "abcbaa".lstrip("ab") == "cbaa"
+    /// # )"#).unwrap());
     /// ```
-    string.lstrip(this: String) {
-        ok!(this.trim_start())
+    string.lstrip(this: String, #chars = NoneType::None) {
+        if chars.get_type() == "NoneType" {
+            ok!(this.trim_start())
+        } else {
+            check_string!(chars, lstrip);
+            let chars: Vec<char> = chars.to_str().chars().collect();
+            ok!(this.trim_start_matches(|c| chars.contains(&c)))
+        }
     }
 
     /// [string.partition](
@@ -1049,8 +1185,15 @@ starlark_module! {global =>
                 ok!(rsplitn_whitespace(&this, maxsplit.unwrap()))
             }
         } else {
-            check_string!(sep, split);
+            check_string!(sep, rsplit);
             let sep = sep.to_str();
+            if sep.is_empty() {
+                starlark_err!(
+                    INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                    "Empty separator cannot be used for splitting".to_owned(),
+                    "Empty separator".to_owned()
+                )
+            }
             let mut v : Vec<&str> = if maxsplit.is_none() {
                 this.rsplit(sep.as_str()).collect()
             } else {
@@ -1065,7 +1208,9 @@ starlark_module! {global =>
     /// https://github.com/google/skylark/blob/3705afa472e466b8b061cce44b47c9ddc6db696d/doc/spec.md#string·rstrip
     /// ): trim trailing whitespace.
     ///
-    /// `S.rstrip()` returns a copy of the string S with trailing whitespace removed.
+    /// `S.rstrip([chars])` returns a copy of the string S with trailing whitespace removed.
+    /// If `chars` is given, it is a string specifying the set of characters to remove
+    /// instead of whitespaces.
     ///
     /// Examples:
     ///
@@ -1074,9 +1219,18 @@ starlark_module! {global =>
     /// # assert!(starlark_default(r#"(
     /// "  hello  ".rstrip() == "  hello"
     /// # )"#).unwrap());
+    /// # assert!(starlark_default(r#"(
+    /// "abcbaa".rstrip("ab") == "abc"
+    /// # )"#).unwrap());
     /// ```
-    string.rstrip(this: String) {
-        ok!(this.to_str().trim_end())
+    string.rstrip(this: String, #chars = NoneType::None) {
+        if chars.get_type() == "NoneType" {
+            ok!(this.to_str().trim_end())
+        } else {
+            check_string!(chars, rstrip);
+            let chars: Vec<char> = chars.to_str().chars().collect();
+            ok!(this.to_str().trim_end_matches(|c| chars.contains(&c)))
+        }
     }
 
     /// [string.split](
@@ -1143,6 +1297,13 @@ starlark_module! {global =>
             } else {
                 check_string!(sep, split);
                 let sep = sep.to_str();
+                if sep.is_empty() {
+                    starlark_err!(
+                        INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                        "Empty separator cannot be used for splitting".to_owned(),
+                        "Empty separator".to_owned()
+                    )
+                }
                 if maxsplit.is_none() {
                     this.split(sep.as_str()).collect()
                 } else {
@@ -1238,7 +1399,9 @@ starlark_module! {global =>
     /// https://github.com/google/skylark/blob/3705afa472e466b8b061cce44b47c9ddc6db696d/doc/spec.md#string·startswith
     /// ): test wether a string starts with a given prefix.
     ///
-    /// `S.startswith(suffix)` reports whether the string S has the specified prefix.
+    /// `S.startswith(prefix[, start[, end]])` reports whether the string S has the
+    /// specified prefix. Like for `endswith`, `prefix` may be a tuple of strings, and the
+    /// test is done on `S[start:end]` if `start` or `end` are given.
     ///
     /// Examples:
     ///
@@ -1247,17 +1410,27 @@ starlark_module! {global =>
     /// # assert!(starlark_default(r#"(
     /// "filename.sky".startswith("filename") == True
     /// # )"#).unwrap());
+    /// # assert!(starlark_default(r#"(
+    /// "filename.sky".startswith(("file", "dir")) == True
+    /// # )"#).unwrap());
+    /// # assert!(starlark_default(r#"(
+    /// "filename.sky".startswith("name", 4) == True
+    /// # )"#).unwrap());
     /// ```
-    string.startswith(this: String, #prefix) {
-        check_string!(prefix, startswith);
-        ok!(this.to_str().starts_with(prefix.to_str().as_str()))
+    string.startswith(this: String, #prefix, #start = 0, #end = NoneType::None) {
+        let prefixes = affixes("string.startswith", &prefix)?;
+        convert_indices!(this, start, end);
+        let this = slice_codepoints(&this, start, end);
+        ok!(prefixes.iter().any(|x| this.starts_with(x.as_str())))
     }
 
     /// [string.strip](
     /// https://github.com/google/skylark/blob/3705afa472e466b8b061cce44b47c9ddc6db696d/doc/spec.md#string·strip
     /// ): trim leading and trailing whitespaces.
     ///
-    /// `S.strip()` returns a copy of the string S with leading and trailing whitespace removed.
+    /// `S.strip([chars])` returns a copy of the string S with leading and trailing whitespace removed.
+    /// If `chars` is given, it is a string specifying the set of characters to remove
+    /// instead of whitespaces.
     ///
     /// Examples:
     ///
@@ -1266,9 +1439,18 @@ starlark_module! {global =>
     /// # assert!(starlark_default(r#"(
     /// "  hello  ".strip() == "hello"
     /// # )"#).unwrap());
+    /// # assert!(starlark_default(r#"(
+    /// "abcbaa".strip("ab") == "c"
+    /// # )"#).unwrap());
     /// ```
-    string.strip(this: String) {
-        ok!(this.trim())
+    string.strip(this: String, #chars = NoneType::None) {
+        if chars.get_type() == "NoneType" {
+            ok!(this.trim())
+        } else {
+            check_string!(chars, strip);
+            let chars: Vec<char> = chars.to_str().chars().collect();
+            ok!(this.trim_matches(|c| chars.contains(&c)))
+        }
     }
 
     /// [string.title](
@@ -1474,6 +1656,12 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_codepoint_ords() {
+        starlark_ok!(r#"(list("".codepoint_ords()) == [])"#);
+        starlark_ok!(r#"(list("é😿".codepoint_ords()) == [101, 769, 128575])"#);
+    }
+
     #[test]
     fn test_count() {
         starlark_ok!(r#"("hello, world!".count("o") == 2)"#);
@@ -1484,6 +1672,21 @@ mod tests {
     #[test]
     fn test_endswith() {
         starlark_ok!(r#"("filename.sky".endswith(".sky") == True)"#);
+        starlark_ok!(r#"("filename.bzl".endswith((".bzl", ".star")) == True)"#);
+        starlark_ok!(r#"("filename.sky".endswith((".bzl", ".star")) == False)"#);
+        starlark_ok!(r#"("filename.sky".endswith(()) == False)"#);
+        starlark_ok!(r#"("filename.sky".endswith("name", 0, -4) == True)"#);
+        starlark_ok!(r#"("filename.sky".endswith("file", -12, -8) == True)"#);
+        starlark_ok!(r#"("filename.sky".endswith("sky", 0, 100) == True)"#);
+        starlark_ok!(r#"("héllo".endswith("hé", 0, 2) == True)"#);
+        starlark_fail!(
+            r#""filename.sky".endswith(None)"#,
+            INCORRECT_PARAMETER_TYPE_ERROR_CODE
+        );
+        starlark_fail!(
+            r#""filename.sky".endswith((".sky", None))"#,
+            INCORRECT_PARAMETER_TYPE_ERROR_CODE
+        );
     }
 
     #[test]
@@ -1502,6 +1705,58 @@ mod tests {
             r#"("Is {0!r} {0!s}?".format('heterological') ==
                     "Is \"heterological\" heterological?")"#
         );
+        starlark_ok!(r#"("{}, {}!".format("Hello", [1]) == "Hello, [1]!")"#);
+        starlark_ok!(r#"("{0}{1}{0}".format("a", "b") == "aba")"#);
+        starlark_ok!(r#"("{name!r} is {age}".format(name="x", age=3) == "\"x\" is 3")"#);
+        starlark_ok!(r#"("{{}} {{{}}}".format(1) == "{} {1}")"#);
+        starlark_ok!(r#"("{:} {!r:}".format("a", "b") == "a \"b\"")"#);
+        starlark_ok!(r#"("{0:} {x:}".format(1, x=2) == "1 2")"#);
+        starlark_fail!(
+            r#""{".format(1)"#,
+            FORMAT_STRING_UNMATCHED_BRACKET_ERROR_CODE
+        );
+        starlark_fail!(
+            r#""}".format(1)"#,
+            FORMAT_STRING_UNMATCHED_BRACKET_ERROR_CODE
+        );
+        starlark_fail!(
+            r#""{{}".format(1)"#,
+            FORMAT_STRING_UNMATCHED_BRACKET_ERROR_CODE
+        );
+        starlark_fail!(r#""{} {}".format(1)"#, OUT_OF_BOUND_ERROR_CODE);
+        starlark_fail!(r#""{2}".format(1)"#, OUT_OF_BOUND_ERROR_CODE);
+        starlark_fail!(r#""{x}".format(y=1)"#, KEY_NOT_FOUND_ERROR_CODE);
+        starlark_fail!(
+            r#""{0!x}".format(1)"#,
+            FORMAT_STRING_INVALID_SPECIFIER_ERROR_CODE
+        );
+        starlark_fail!(
+            r#""{0:>4}".format(1)"#,
+            FORMAT_STRING_INVALID_SPECIFIER_ERROR_CODE
+        );
+        starlark_fail!(
+            r#""{} {0}".format(1)"#,
+            FORMAT_STRING_ORDER_INDEX_MIX_ERROR_CODE
+        );
+        // Behaviors of CPython's `str.format`
+        starlark_ok!(r#"("{x} {} {x}".format(1, x=2) == "2 1 2")"#);
+        starlark_ok!(r#"("{00}{01}".format(1, 2) == "12")"#);
+        starlark_ok!(r#"("{{{0}}}".format(1) == "{1}")"#);
+        starlark_ok!(r#"("}}{{".format() == "}{")"#);
+        starlark_ok!(r#"("{!s}".format("a") == "a")"#);
+        starlark_fail!(
+            r#""{0} {}".format(1, 2)"#,
+            FORMAT_STRING_ORDER_INDEX_MIX_ERROR_CODE
+        );
+        starlark_fail!(
+            r#""{99999999999999999999}".format(1)"#,
+            OUT_OF_BOUND_ERROR_CODE
+        );
+        starlark_fail!(r#""{-1}".format(1)"#, KEY_NOT_FOUND_ERROR_CODE);
+        starlark_fail!(
+            r#""{0[0]}".format([1])"#,
+            FORMAT_STRING_INVALID_CHARACTER_ERROR_CODE
+        );
     }
 
     #[test]
@@ -1577,11 +1832,20 @@ mod tests {
     #[test]
     fn test_lstrip() {
         starlark_ok!(r#"("  hello  ".lstrip() == "hello  ")"#);
+        starlark_ok!(r#"("abcbaa".lstrip("ab") == "cbaa")"#);
+        starlark_ok!(r#"("".lstrip("ab") == "")"#);
     }
 
     #[test]
     fn test_partition() {
         starlark_ok!(r#"("one/two/three".partition("/")	 == ("one", "/", "two/three"))"#);
+        starlark_ok!(r#"("one two".partition() == ("one", " ", "two"))"#);
+        starlark_ok!(r#"("one".partition("/") == ("one", "", ""))"#);
+        starlark_ok!(r#"("".partition("/") == ("", "", ""))"#);
+        starlark_fail!(
+            r#""one".partition("")"#,
+            INCORRECT_PARAMETER_TYPE_ERROR_CODE
+        );
     }
 
     #[test]
@@ -1610,6 +1874,12 @@ mod tests {
     #[test]
     fn test_rpartition() {
         starlark_ok!(r#"("one/two/three".rpartition("/") == ("one/two", "/", "three"))"#);
+        starlark_ok!(r#"("one".rpartition("/") == ("", "", "one"))"#);
+        starlark_ok!(r#"("".rpartition("/") == ("", "", ""))"#);
+        starlark_fail!(
+            r#""one".rpartition("")"#,
+            INCORRECT_PARAMETER_TYPE_ERROR_CODE
+        );
     }
 
     #[test]
@@ -1617,11 +1887,26 @@ mod tests {
         starlark_ok!(r#"("banana".rsplit("n") == ["ba", "a", "a"])"#);
         starlark_ok!(r#"("banana".rsplit("n", 1) == ["bana", "a"])"#);
         starlark_ok!(r#"("one two  three".rsplit(None, 1) == ["one two", "three"])"#);
+        starlark_ok!(r#"("  a b  c  ".rsplit() == ["a", "b", "c"])"#);
+        starlark_ok!(r#"("  a b  c  ".rsplit(None, 0) == ["  a b  c"])"#);
+        starlark_ok!(r#"("  a b  c  ".rsplit(None, 2) == ["  a", "b", "c"])"#);
+        starlark_ok!(r#"("a,b,c".rsplit(",", 0) == ["a,b,c"])"#);
+        starlark_ok!(r#"("a,b,c".rsplit(",", 2) == ["a", "b", "c"])"#);
+        starlark_ok!(r#"("a,b,c".rsplit(",", 5) == ["a", "b", "c"])"#);
+        starlark_ok!(r#"("a,b,c".rsplit(",", -1) == ["a", "b", "c"])"#);
+        starlark_ok!(r#"("   ".rsplit() == [] and "".rsplit(",") == [""])"#);
+        starlark_fail!(
+            r#""banana".rsplit("")"#,
+            INCORRECT_PARAMETER_TYPE_ERROR_CODE
+        );
+        starlark_fail!(r#""banana".rsplit(1)"#, INCORRECT_PARAMETER_TYPE_ERROR_CODE);
     }
 
     #[test]
     fn test_rstrip() {
         starlark_ok!(r#"("  hello  ".rstrip() == "  hello")"#);
+        starlark_ok!(r#"("abcbaa".rstrip("ab") == "abc")"#);
+        starlark_ok!(r#"("abcbaa".rstrip("") == "abcbaa")"#);
     }
 
     #[test]
@@ -1631,11 +1916,14 @@ mod tests {
         starlark_ok!(r#"("one two  three".split(None, 1) == ["one", "two  three"])"#);
         starlark_ok!(r#"("banana".split("n") == ["ba", "a", "a"])"#);
         starlark_ok!(r#"("banana".split("n", 1) == ["ba", "ana"])"#);
+        starlark_fail!(r#""banana".split("")"#, INCORRECT_PARAMETER_TYPE_ERROR_CODE);
     }
 
     #[test]
     fn test_split_codepoints() {
-        starlark_ok!(r#"(list('Hello, 世界'.split_codepoints()) == ['H', 'e', 'l', 'l', 'o', ',', ' ', '世', '界'])"#);
+        starlark_ok!(
+            r#"(list('Hello, 世界'.split_codepoints()) == ['H', 'e', 'l', 'l', 'o', ',', ' ', '世', '界'])"#
+        );
     }
 
     #[test]
@@ -1643,16 +1931,44 @@ mod tests {
         starlark_ok!(r#"("one\n\ntwo".splitlines() == ["one", "", "two"])"#);
         starlark_ok!(r#"("one\n\ntwo".splitlines(True) == ["one\n", "\n", "two"])"#);
         starlark_ok!(r#"("a\nb".splitlines() == ["a", "b"])"#);
+        starlark_ok!(r#"("".splitlines() == [])"#);
+        starlark_ok!(r#"("a\rb\r\nc\n\rd".splitlines() == ["a", "b", "c", "", "d"])"#);
+        starlark_ok!(
+            r#"("a\rb\r\nc\n\rd\n".splitlines(True) == ["a\r", "b\r\n", "c\n", "\r", "d\n"])"#
+        );
     }
 
     #[test]
     fn test_startswith() {
         starlark_ok!(r#"("filename.sky".startswith("filename") == True)"#);
+        starlark_ok!(r#"("filename.sky".startswith(("dir", "file")) == True)"#);
+        starlark_ok!(r#"("filename.sky".startswith(("dir", "name")) == False)"#);
+        starlark_ok!(r#"("filename.sky".startswith(()) == False)"#);
+        starlark_ok!(r#"("".startswith(()) == False and "".startswith("") == True)"#);
+        starlark_ok!(r#"("filename.sky".startswith("name", 4) == True)"#);
+        starlark_ok!(r#"("filename.sky".startswith("sky", -3) == True)"#);
+        starlark_ok!(r#"("filename.sky".startswith("file", 0, 3) == False)"#);
+        starlark_ok!(r#"("héllo".startswith("llo", 2) == True)"#);
+        starlark_fail!(
+            r#""filename.sky".startswith(1)"#,
+            INCORRECT_PARAMETER_TYPE_ERROR_CODE
+        );
+        starlark_fail!(
+            r#""filename.sky".startswith(("file", 1))"#,
+            INCORRECT_PARAMETER_TYPE_ERROR_CODE
+        );
     }
 
     #[test]
     fn test_strip() {
         starlark_ok!(r#"("  hello  ".strip() == "hello")"#);
+        // Trailing U+3000 ideographic space, the lexer has no \u escapes.
+        starlark_ok!(r#"("\t\n hello 　".strip() == "hello")"#);
+        starlark_ok!(r#"("  hello  ".strip(None) == "hello")"#);
+        starlark_ok!(r#"("abcbaa".strip("ab") == "c")"#);
+        starlark_ok!(r#"("aaa".strip("a") == "")"#);
+        starlark_ok!(r#"("".strip() == "")"#);
+        starlark_fail!(r#""abc".strip(1)"#, INCORRECT_PARAMETER_TYPE_ERROR_CODE);
     }
 
     #[test]
@@ -15,12 +15,17 @@
 //! Implementation of `struct` function.
 
 use crate::values::error::ValueError;
+use crate::values::frozen::Thaw;
+use crate::values::OrderedMap;
 use crate::values::*;
-use linked_hash_map::LinkedHashMap;
+use std::sync::Arc;
 
 /// `struct()` implementation.
+///
+/// Structs are immutable: no field can be added or replaced. The field values are not
+/// frozen, so a mutable value like a list can still be modified through its field.
 pub struct StarlarkStruct {
-    fields: LinkedHashMap<String, Value>,
+    fields: OrderedMap<String, Value>,
 }
 
 impl TypedValue for StarlarkStruct {
@@ -46,6 +51,37 @@ impl TypedValue for StarlarkStruct {
         r
     }
 
+    fn to_json(&self) -> Result<String, ValueError> {
+        let mut r = "{".to_owned();
+        for (i, (name, value)) in self.fields.iter().enumerate() {
+            if i != 0 {
+                r.push(',');
+            }
+            r.push_str(&Value::from(name.as_str()).to_json()?);
+            r.push(':');
+            r.push_str(&value.to_json()?);
+        }
+        r.push('}');
+        Ok(r)
+    }
+
+    fn share(&self) -> Result<FrozenValue, ValueError> {
+        let mut fields = Vec::with_capacity(self.fields.len());
+        for (name, value) in &self.fields {
+            fields.push((name.clone(), value.share()?));
+        }
+        Ok(FrozenValue::new(FrozenStruct(fields)))
+    }
+
+    #[cfg(feature = "json")]
+    fn to_json_value(&self) -> Result<serde_json::Value, ValueError> {
+        let mut object = serde_json::Map::new();
+        for (name, value) in self.fields.iter() {
+            object.insert(name.clone(), value.to_json_value()?);
+        }
+        Ok(serde_json::Value::Object(object))
+    }
+
     const TYPE: &'static str = "struct";
 
     fn equals(&self, other: &StarlarkStruct) -> Result<bool, ValueError> {
@@ -70,10 +106,9 @@ impl TypedValue for StarlarkStruct {
     fn get_attr(&self, attribute: &str) -> Result<Value, ValueError> {
         match self.fields.get(attribute) {
             Some(v) => Ok(v.clone()),
-            None => Err(ValueError::OperationNotSupported {
-                op: attribute.to_owned(),
+            None => Err(ValueError::NoAttrLeft {
+                attribute: attribute.to_owned(),
                 left: self.to_repr(),
-                right: None,
             }),
         }
     }
@@ -82,6 +117,14 @@ impl TypedValue for StarlarkStruct {
         Ok(self.fields.contains_key(attribute))
     }
 
+    fn set_attr(&mut self, attribute: &str, _new_value: Value) -> Result<(), ValueError> {
+        Err(ValueError::OperationNotSupported {
+            op: format!(".{} =", attribute),
+            left: Self::TYPE.to_owned(),
+            right: None,
+        })
+    }
+
     fn dir_attr(&self) -> Result<Vec<String>, ValueError> {
         Ok(self.fields.keys().cloned().collect())
     }
@@ -113,3 +156,91 @@ starlark_module! { global =>
         }))
     }
 }
+
+/// A shared struct, see [`Value::share`].
+struct FrozenStruct(Vec<(String, FrozenValue)>);
+
+impl Thaw for FrozenStruct {
+    fn thaw(self: Arc<Self>) -> Value {
+        Value::new(StarlarkStruct {
+            fields: self
+                .0
+                .iter()
+                .map(|(name, value)| (name.clone(), value.to_value()))
+                .collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::environment::TypeValues;
+    use crate::eval::noload::eval;
+    use crate::stdlib::{global_environment_with_extensions, starlark_default};
+    use crate::syntax::dialect::Dialect;
+    use crate::values::error::{NOT_SUPPORTED_ERROR_CODE, NO_ATTR_ERROR_CODE};
+    use codemap::CodeMap;
+    use codemap_diagnostic::Diagnostic;
+    use std::sync::{Arc, Mutex};
+
+    fn eval_fail(snippet: &str) -> Diagnostic {
+        let env = global_environment_with_extensions();
+        eval(
+            &Arc::new(Mutex::new(CodeMap::new())),
+            "<test>",
+            snippet,
+            Dialect::Bzl,
+            &mut env.freeze().child("test"),
+            TypeValues::new(env),
+        )
+        .unwrap_err()
+    }
+
+    #[test]
+    fn test_repr() {
+        assert!(starlark_default(
+            r#"(repr(struct(b = "x", a = [1, None])) == 'struct(b="x", a=[1, None])'
+ and repr(struct()) == "struct()")"#
+        )
+        .unwrap());
+    }
+
+    #[test]
+    fn test_equality() {
+        assert!(starlark_default(
+            r#"(struct(a = 1, b = [2]) == struct(b = [2], a = 1)
+ and struct(a = 1) != struct(a = 2)
+ and struct(a = 1) != struct(a = 1, b = 2)
+ and struct(a = 1) != struct(b = 1))"#
+        )
+        .unwrap());
+    }
+
+    #[test]
+    fn test_missing_field() {
+        let err = eval_fail("struct(a = 1).b");
+        assert_eq!(Some(NO_ATTR_ERROR_CODE.to_owned()), err.code);
+        assert!(
+            err.message
+                .contains("struct(a=1) has no field or method 'b'"),
+            "{}",
+            err.message
+        );
+    }
+
+    #[test]
+    fn test_set_attr() {
+        let err = eval_fail("s = struct(a = 1)\ns.a = 2");
+        assert_eq!(Some(NOT_SUPPORTED_ERROR_CODE.to_owned()), err.code);
+        let err = eval_fail("s = struct(a = 1)\ns.b = 2");
+        assert_eq!(Some(NOT_SUPPORTED_ERROR_CODE.to_owned()), err.code);
+    }
+
+    #[test]
+    fn test_fields_are_aliased() {
+        assert!(starlark_default(
+            "l = []\ns = struct(l = l)\nl.append(1)\ns.l.append(2)\ns.l == [1, 2] and l == [1, 2]"
+        )
+        .unwrap());
+    }
+}
@@ -25,21 +25,40 @@ use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::fmt::{Display, Formatter};
 
+/// Error for a language feature which is disabled in the current [`Dialect`].
+fn feature_not_allowed(span: Span, feature: &str, flag: &str) -> Diagnostic {
+    Diagnostic {
+        level: Level::Error,
+        message: format!(
+            "{} are not allowed in this dialect (see Dialect::{})",
+            feature, flag
+        ),
+        code: Some(FEATURE_NOT_ALLOWED_ERROR_CODE.to_owned()),
+        spans: vec![SpanLabel {
+            span,
+            label: Some("not allowed in this dialect".to_owned()),
+            style: SpanStyle::Primary,
+        }],
+    }
+}
+
 // Boxed types used for storing information from the parsing will be used especially for the
 // location of the AST item
-#[doc(hidden)]
+/// An expression and its span in the source.
 pub type AstExpr = Box<Spanned<Expr>>;
-#[doc(hidden)]
+/// An argument of a call and its span in the source.
 pub type AstArgument = Spanned<Argument>;
-#[doc(hidden)]
+/// An identifier or a string literal and its span in the source.
 pub type AstString = Spanned<String>;
-#[doc(hidden)]
+/// A bytes literal and its span in the source.
+pub type AstBytes = Spanned<Vec<u8>>;
+/// A parameter of a `def` or a `lambda` and its span in the source.
 pub type AstParameter = Spanned<Parameter>;
-#[doc(hidden)]
+/// A `for` or `if` clause of a comprehension and its span in the source.
 pub type AstClause = Spanned<Clause>;
-#[doc(hidden)]
+/// An integer literal and its span in the source.
 pub type AstInt = Spanned<i64>;
-#[doc(hidden)]
+/// A statement and its span in the source.
 pub type AstStatement = Box<Spanned<Statement>>;
 
 // Critical Semantic
@@ -53,6 +72,7 @@ const ARGS_AFTER_ARGS_OR_KWARGS_ERROR_CODE: &str = "CS06";
 const MULTIPLE_KWARGS_DICTS_IN_PARAMS_ERROR_CODE: &str = "CS07";
 const DUPLICATED_PARAM_NAME_ERROR_CODE: &str = "CS08";
 const BREAK_OR_CONTINUE_OUTSIDE_OF_LOOP_ERROR_CODE: &str = "CS09";
+const FEATURE_NOT_ALLOWED_ERROR_CODE: &str = "CS10";
 
 #[doc(hidden)]
 pub trait ToAst<T> {
@@ -78,28 +98,38 @@ macro_rules! to_ast_trait {
 
 to_ast_trait!(i64, AstInt);
 to_ast_trait!(String, AstString);
+to_ast_trait!(Vec<u8>, AstBytes);
 
-#[doc(hidden)]
+/// Argument of a call, e.g. `x`, `a = x`, `*args` or `**kwargs`.
 #[derive(Debug, Clone)]
 pub enum Argument {
+    /// `x`
     Positional(AstExpr),
+    /// `a = x`
     Named(AstString, AstExpr),
+    /// `*args`
     ArgsArray(AstExpr),
+    /// `**kwargs`
     KWArgsDict(AstExpr),
 }
 to_ast_trait!(Argument, AstArgument);
 
-#[doc(hidden)]
+/// Parameter of a `def` or a `lambda`, e.g. `x`, `x = 1`, `*args` or `**kwargs`.
 #[derive(Debug, Clone)]
 pub enum Parameter {
+    /// `x`
     Normal(AstString),
+    /// `x = 1`
     WithDefaultValue(AstString, AstExpr),
+    /// `*args`
     Args(AstString),
+    /// `**kwargs`
     KWArgs(AstString),
 }
 to_ast_trait!(Parameter, AstParameter);
 
 impl Parameter {
+    /// The name of the parameter, without the `*` or `**`.
     pub fn name(&self) -> &str {
         match self {
             Parameter::Normal(n) => &n.node,
@@ -108,13 +138,48 @@ impl Parameter {
             Parameter::KWArgs(n) => &n.node,
         }
     }
+
+    fn validate_dialect(params: &[AstParameter], dialect: Dialect) -> Result<(), Diagnostic> {
+        for p in params {
+            if let Parameter::WithDefaultValue(_, ref value) = p.node {
+                Expr::validate_dialect(value, dialect)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn transform_locals(
+        param: AstParameter,
+        locals: &HashMap<String, usize>,
+        slot: fn(usize, AstString) -> Expr,
+        into_comprehensions: bool,
+    ) -> AstParameter {
+        Spanned {
+            span: param.span,
+            node: match param.node {
+                Parameter::WithDefaultValue(name, value) => Parameter::WithDefaultValue(
+                    name,
+                    Expr::transform_locals(value, locals, slot, into_comprehensions),
+                ),
+                p => p,
+            },
+        }
+    }
 }
 
-#[doc(hidden)]
+/// An expression.
+///
+/// The variants hidden from the documentation are only produced when preparing the
+/// evaluation, never by [`parse`](crate::syntax::parse), and may change in any release.
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub enum Expr {
+    /// `(a, b)`
     Tuple(Vec<AstExpr>),
+    /// `a.b`
     Dot(AstExpr, AstString),
+    /// `f(a, b = c, *args, **kwargs)`: the function, the positional arguments, the named
+    /// arguments, `*args` and `**kwargs`.
     Call(
         AstExpr,
         Vec<AstExpr>,
@@ -122,29 +187,61 @@ pub enum Expr {
         Option<AstExpr>,
         Option<AstExpr>,
     ),
+    /// `a[i]`
     ArrayIndirection(AstExpr, AstExpr),
+    /// `a[start:stop:stride]`
     Slice(AstExpr, Option<AstExpr>, Option<AstExpr>, Option<AstExpr>),
+    /// `a`
     Identifier(AstString),
     // local variable index
+    #[doc(hidden)]
     Slot(usize, AstString),
+    // local variable index of the enclosing `def`, accessed from one of its comprehensions
+    #[doc(hidden)]
+    FunctionSlot(usize, AstString),
+    /// `1`
     IntLiteral(AstInt),
+    /// An integer literal which does not fit in an `i64`, in base 10, only produced with the
+    /// `bigint` feature.
+    BigIntLiteral(AstString),
+    /// `"a"`
     StringLiteral(AstString),
+    /// `b"a"`
+    BytesLiteral(AstBytes),
+    /// `not a`
     Not(AstExpr),
+    /// `-a`
     Minus(AstExpr),
+    /// `+a`
     Plus(AstExpr),
+    /// `a + b` and the other binary operations, including comparisons and `and`/`or`.
     Op(BinOp, AstExpr, AstExpr),
-    If(AstExpr, AstExpr, AstExpr), // Order: condition, v1, v2 <=> v1 if condition else v2
+    /// `a if condition else b`, in the order condition, `a`, `b`.
+    If(AstExpr, AstExpr, AstExpr),
+    /// `[a, b]`
     List(Vec<AstExpr>),
+    /// `{a, b}`
     Set(Vec<AstExpr>),
+    /// `{a: b}`
     Dict(Vec<(AstExpr, AstExpr)>),
+    /// `[x for x in a if x]`, the first `for` being the first clause.
     ListComprehension(AstExpr, Vec<AstClause>),
+    /// `{x for x in a if x}`
     SetComprehension(AstExpr, Vec<AstClause>),
+    /// `{k: v for k, v in a}`
     DictComprehension((AstExpr, AstExpr), Vec<AstClause>),
+    #[doc(hidden)]
     ComprehensionCompiled(ComprehensionCompiled),
+    /// `lambda x: x`
+    Lambda(Vec<AstParameter>, AstExpr),
+    /// Post-processed `lambda` expression
+    #[doc(hidden)]
+    LambdaCompiled(DefCompiled),
 }
 to_ast_trait!(Expr, AstExpr, Box);
 
 impl Expr {
+    #[doc(hidden)]
     pub fn check_call(
         f: AstExpr,
         args: Vec<AstArgument>,
@@ -218,6 +315,15 @@ impl Expr {
         Ok(Expr::Call(f, pos_args, named_args, args_array, kwargs_dict))
     }
 
+    #[doc(hidden)]
+    pub fn check_lambda(
+        parameters: Vec<AstParameter>,
+        body: AstExpr,
+    ) -> Result<Expr, lalrpop_util::ParseError<u64, lexer::Token, lexer::LexerError>> {
+        Statement::check_parameters(&parameters)?;
+        Ok(Expr::Lambda(parameters, body))
+    }
+
     pub(crate) fn collect_locals_from_assign_expr(
         expr: &AstExpr,
         local_names_to_indices: &mut HashMap<String, usize>,
@@ -238,97 +344,283 @@ impl Expr {
         }
     }
 
+    /// Validate that only the language features enabled in `dialect` are used
+    fn validate_dialect(expr: &AstExpr, dialect: Dialect) -> Result<(), Diagnostic> {
+        let all = |exprs: &[AstExpr]| -> Result<(), Diagnostic> {
+            for e in exprs {
+                Expr::validate_dialect(e, dialect)?;
+            }
+            Ok(())
+        };
+        let clauses = |clauses: &[AstClause]| -> Result<(), Diagnostic> {
+            for c in clauses {
+                match c.node {
+                    Clause::For(ref var, ref over) => {
+                        Expr::validate_dialect(var, dialect)?;
+                        Expr::validate_dialect(over, dialect)?;
+                    }
+                    Clause::If(ref cond) => Expr::validate_dialect(cond, dialect)?,
+                }
+            }
+            Ok(())
+        };
+        match expr.node {
+            Expr::Tuple(ref exprs) | Expr::List(ref exprs) => all(exprs),
+            Expr::Set(ref exprs) => {
+                if !dialect.allow_set_literals {
+                    return Err(feature_not_allowed(
+                        expr.span,
+                        "set literals",
+                        "allow_set_literals",
+                    ));
+                }
+                all(exprs)
+            }
+            Expr::Dict(ref pairs) => {
+                for (k, v) in pairs {
+                    Expr::validate_dialect(k, dialect)?;
+                    Expr::validate_dialect(v, dialect)?;
+                }
+                Ok(())
+            }
+            Expr::Dot(ref e, _) | Expr::Not(ref e) | Expr::Minus(ref e) | Expr::Plus(ref e) => {
+                Expr::validate_dialect(e, dialect)
+            }
+            Expr::Call(ref f, ref pos, ref named, ref args, ref kwargs) => {
+                Expr::validate_dialect(f, dialect)?;
+                all(pos)?;
+                for (_, v) in named {
+                    Expr::validate_dialect(v, dialect)?;
+                }
+                for e in args.iter().chain(kwargs.iter()) {
+                    Expr::validate_dialect(e, dialect)?;
+                }
+                Ok(())
+            }
+            Expr::ArrayIndirection(ref a, ref b) | Expr::Op(_, ref a, ref b) => {
+                Expr::validate_dialect(a, dialect)?;
+                Expr::validate_dialect(b, dialect)
+            }
+            Expr::Slice(ref a, ref p1, ref p2, ref p3) => {
+                Expr::validate_dialect(a, dialect)?;
+                for e in p1.iter().chain(p2.iter()).chain(p3.iter()) {
+                    Expr::validate_dialect(e, dialect)?;
+                }
+                Ok(())
+            }
+            Expr::If(ref cond, ref v1, ref v2) => {
+                Expr::validate_dialect(cond, dialect)?;
+                Expr::validate_dialect(v1, dialect)?;
+                Expr::validate_dialect(v2, dialect)
+            }
+            Expr::ListComprehension(ref e, ref c) => {
+                Expr::validate_dialect(e, dialect)?;
+                clauses(c)
+            }
+            Expr::SetComprehension(ref e, ref c) => {
+                if !dialect.allow_set_literals {
+                    return Err(feature_not_allowed(
+                        expr.span,
+                        "set comprehensions",
+                        "allow_set_literals",
+                    ));
+                }
+                Expr::validate_dialect(e, dialect)?;
+                clauses(c)
+            }
+            Expr::DictComprehension((ref k, ref v), ref c) => {
+                Expr::validate_dialect(k, dialect)?;
+                Expr::validate_dialect(v, dialect)?;
+                clauses(c)
+            }
+            Expr::Lambda(ref params, ref body) => {
+                if !dialect.allow_lambda {
+                    return Err(feature_not_allowed(
+                        expr.span,
+                        "`lambda` expressions",
+                        "allow_lambda",
+                    ));
+                }
+                Parameter::validate_dialect(params, dialect)?;
+                Expr::validate_dialect(body, dialect)
+            }
+            Expr::Identifier(..)
+            | Expr::IntLiteral(..)
+            | Expr::BigIntLiteral(..)
+            | Expr::StringLiteral(..)
+            | Expr::BytesLiteral(..) => Ok(()),
+            Expr::Slot(..)
+            | Expr::FunctionSlot(..)
+            | Expr::ComprehensionCompiled(..)
+            | Expr::LambdaCompiled(..) => unreachable!(),
+        }
+    }
+
+    /// Replace the accesses to the variables of `locals` by accesses to their slot in the
+    /// scope of `expr`, e.g. a comprehension. Nested comprehensions are not transformed.
     pub(crate) fn transform_locals_to_slots(
         expr: AstExpr,
         locals: &HashMap<String, usize>,
     ) -> AstExpr {
+        Expr::transform_locals(expr, locals, Expr::Slot, false)
+    }
+
+    /// Replace the accesses to the local variables of a `def` by accesses to their slot,
+    /// including from the comprehensions of its body.
+    pub(crate) fn transform_function_locals_to_slots(
+        expr: AstExpr,
+        locals: &HashMap<String, usize>,
+    ) -> AstExpr {
+        Expr::transform_locals(expr, locals, Expr::Slot, true)
+    }
+
+    /// Replace the accesses to the variables of `locals` with `slot(index, name)`,
+    /// descending into comprehensions if `into_comprehensions`, where the variables of the
+    /// function are then accessed with `Expr::FunctionSlot`.
+    fn transform_locals(
+        expr: AstExpr,
+        locals: &HashMap<String, usize>,
+        slot: fn(usize, AstString) -> Expr,
+        into_comprehensions: bool,
+    ) -> AstExpr {
+        let transform = |expr| Expr::transform_locals(expr, locals, slot, into_comprehensions);
         Box::new(Spanned {
             span: expr.span,
             node: match expr.node {
-                Expr::Tuple(exprs) => Expr::Tuple(
-                    exprs
-                        .into_iter()
-                        .map(|expr| Expr::transform_locals_to_slots(expr, locals))
-                        .collect(),
-                ),
-                Expr::List(exprs) => Expr::List(
-                    exprs
-                        .into_iter()
-                        .map(|expr| Expr::transform_locals_to_slots(expr, locals))
-                        .collect(),
-                ),
-                Expr::Set(exprs) => Expr::Set(
-                    exprs
-                        .into_iter()
-                        .map(|expr| Expr::transform_locals_to_slots(expr, locals))
-                        .collect(),
-                ),
+                Expr::Tuple(exprs) => Expr::Tuple(exprs.into_iter().map(transform).collect()),
+                Expr::List(exprs) => Expr::List(exprs.into_iter().map(transform).collect()),
+                Expr::Set(exprs) => Expr::Set(exprs.into_iter().map(transform).collect()),
                 Expr::Dict(pairs) => Expr::Dict(
                     pairs
                         .into_iter()
-                        .map(|(key, value)| {
-                            (
-                                Expr::transform_locals_to_slots(key, locals),
-                                Expr::transform_locals_to_slots(value, locals),
-                            )
-                        })
+                        .map(|(key, value)| (transform(key), transform(value)))
                         .collect(),
                 ),
                 Expr::Identifier(ident) => match locals.get(&ident.node) {
-                    Some(&slot) => Expr::Slot(slot, ident),
+                    Some(&index) => slot(index, ident),
                     None => Expr::Identifier(ident),
                 },
                 Expr::Slot(..) => unreachable!(),
-                Expr::Dot(left, right) => {
-                    Expr::Dot(Expr::transform_locals_to_slots(left, locals), right)
-                }
+                // Already transformed by the enclosing `def`.
+                n @ Expr::FunctionSlot(..) => n,
+                Expr::Dot(left, right) => Expr::Dot(transform(left), right),
                 Expr::Call(function, args, kwargs, star_args, star_star_kwargs) => Expr::Call(
-                    Expr::transform_locals_to_slots(function, locals),
-                    args.into_iter()
-                        .map(|expr| Expr::transform_locals_to_slots(expr, locals))
-                        .collect(),
+                    transform(function),
+                    args.into_iter().map(transform).collect(),
                     kwargs
                         .into_iter()
-                        .map(|(name, value)| (name, Expr::transform_locals_to_slots(value, locals)))
+                        .map(|(name, value)| (name, transform(value)))
                         .collect(),
-                    star_args.map(|expr| Expr::transform_locals_to_slots(expr, locals)),
-                    star_star_kwargs.map(|expr| Expr::transform_locals_to_slots(expr, locals)),
-                ),
-                Expr::ArrayIndirection(array, index) => Expr::ArrayIndirection(
-                    Expr::transform_locals_to_slots(array, locals),
-                    Expr::transform_locals_to_slots(index, locals),
+                    star_args.map(transform),
+                    star_star_kwargs.map(transform),
                 ),
+                Expr::ArrayIndirection(array, index) => {
+                    Expr::ArrayIndirection(transform(array), transform(index))
+                }
                 Expr::Slice(array, p1, p2, p3) => Expr::Slice(
-                    array,
-                    p1.map(|expr| Expr::transform_locals_to_slots(expr, locals)),
-                    p2.map(|expr| Expr::transform_locals_to_slots(expr, locals)),
-                    p3.map(|expr| Expr::transform_locals_to_slots(expr, locals)),
-                ),
-                Expr::Not(expr) => Expr::Not(Expr::transform_locals_to_slots(expr, locals)),
-                Expr::Minus(expr) => Expr::Minus(Expr::transform_locals_to_slots(expr, locals)),
-                Expr::Plus(expr) => Expr::Plus(Expr::transform_locals_to_slots(expr, locals)),
-                Expr::Op(op, left, right) => Expr::Op(
-                    op,
-                    Expr::transform_locals_to_slots(left, locals),
-                    Expr::transform_locals_to_slots(right, locals),
+                    transform(array),
+                    p1.map(transform),
+                    p2.map(transform),
+                    p3.map(transform),
                 ),
-                Expr::If(cond, then_expr, else_expr) => Expr::If(
-                    Expr::transform_locals_to_slots(cond, locals),
-                    Expr::transform_locals_to_slots(then_expr, locals),
-                    Expr::transform_locals_to_slots(else_expr, locals),
+                Expr::Not(expr) => Expr::Not(transform(expr)),
+                Expr::Minus(expr) => Expr::Minus(transform(expr)),
+                Expr::Plus(expr) => Expr::Plus(transform(expr)),
+                Expr::Op(op, left, right) => Expr::Op(op, transform(left), transform(right)),
+                Expr::If(cond, then_expr, else_expr) => {
+                    Expr::If(transform(cond), transform(then_expr), transform(else_expr))
+                }
+                n @ Expr::IntLiteral(..)
+                | n @ Expr::BigIntLiteral(..)
+                | n @ Expr::StringLiteral(..)
+                | n @ Expr::BytesLiteral(..) => n,
+                Expr::Lambda(params, body) => Expr::Lambda(
+                    params
+                        .into_iter()
+                        .map(|p| Parameter::transform_locals(p, locals, slot, into_comprehensions))
+                        .collect(),
+                    // The body is evaluated in its own scope, where the enclosing function
+                    // locals are captured by name.
+                    body,
                 ),
-                n @ Expr::IntLiteral(..) | n @ Expr::StringLiteral(..) => n,
+                Expr::LambdaCompiled(..) => unreachable!(),
+                Expr::ListComprehension(expr, clauses) if into_comprehensions => {
+                    let (locals, clauses) = Expr::transform_clauses(clauses, locals, slot);
+                    Expr::ListComprehension(
+                        Expr::transform_in_comprehension(expr, &locals),
+                        clauses,
+                    )
+                }
+                Expr::SetComprehension(expr, clauses) if into_comprehensions => {
+                    let (locals, clauses) = Expr::transform_clauses(clauses, locals, slot);
+                    Expr::SetComprehension(Expr::transform_in_comprehension(expr, &locals), clauses)
+                }
+                Expr::DictComprehension((key, value), clauses) if into_comprehensions => {
+                    let (locals, clauses) = Expr::transform_clauses(clauses, locals, slot);
+                    Expr::DictComprehension(
+                        (
+                            Expr::transform_in_comprehension(key, &locals),
+                            Expr::transform_in_comprehension(value, &locals),
+                        ),
+                        clauses,
+                    )
+                }
                 n @ Expr::DictComprehension(..)
                 | n @ Expr::ListComprehension(..)
                 | n @ Expr::SetComprehension(..)
-                | n @ Expr::ComprehensionCompiled(..) => {
-                    // TODO: access parent scope variables by index, not by name.
-                    n
-                }
+                | n @ Expr::ComprehensionCompiled(..) => n,
             },
         })
     }
 
+    /// Transform an expression evaluated in the scope of a comprehension of a `def` body,
+    /// where `locals` are the function variables not shadowed by the comprehension.
+    fn transform_in_comprehension(expr: AstExpr, locals: &HashMap<String, usize>) -> AstExpr {
+        Expr::transform_locals(expr, locals, Expr::FunctionSlot, true)
+    }
+
+    /// Transform the clauses of a comprehension of a `def` body, returning the function
+    /// variables not shadowed by the comprehension along with the clauses.
+    ///
+    /// The iterable of the first clause is evaluated in the enclosing scope, so it is
+    /// transformed with `slot`, like the expression containing the comprehension.
+    fn transform_clauses(
+        clauses: Vec<AstClause>,
+        locals: &HashMap<String, usize>,
+        slot: fn(usize, AstString) -> Expr,
+    ) -> (HashMap<String, usize>, Vec<AstClause>) {
+        let mut bound = HashMap::new();
+        for c in &clauses {
+            if let Clause::For(ref var, _) = c.node {
+                Expr::collect_locals_from_assign_expr(var, &mut bound);
+            }
+        }
+        let visible: HashMap<String, usize> = locals
+            .iter()
+            .filter(|(name, _)| !bound.contains_key(*name))
+            .map(|(name, &index)| (name.clone(), index))
+            .collect();
+        let clauses = clauses
+            .into_iter()
+            .enumerate()
+            .map(|(i, c)| Spanned {
+                span: c.span,
+                node: match c.node {
+                    Clause::For(var, over) if i == 0 => {
+                        Clause::For(var, Expr::transform_locals(over, locals, slot, true))
+                    }
+                    Clause::For(var, over) => {
+                        Clause::For(var, Expr::transform_in_comprehension(over, &visible))
+                    }
+                    Clause::If(cond) => {
+                        Clause::If(Expr::transform_in_comprehension(cond, &visible))
+                    }
+                },
+            })
+            .collect();
+        (visible, clauses)
+    }
+
     pub(crate) fn compile(expr: AstExpr) -> Result<AstExpr, Diagnostic> {
         Ok(Box::new(Spanned {
             span: expr.span,
@@ -401,73 +693,131 @@ impl Expr {
                     ComprehensionCompiled::new_dict(key, value, clauses)?,
                 ),
                 Expr::ComprehensionCompiled(..) => unreachable!(),
+                Expr::Lambda(params, body) => {
+                    let span = body.span;
+                    let name = Spanned {
+                        span: expr.span,
+                        node: "lambda".to_owned(),
+                    };
+                    let suite = Statement::Return(Some(body)).to_ast(span);
+                    Expr::LambdaCompiled(DefCompiled::new(name, params, suite)?)
+                }
+                Expr::LambdaCompiled(..) => unreachable!(),
                 e @ Expr::Slot(..)
+                | e @ Expr::FunctionSlot(..)
                 | e @ Expr::Identifier(..)
                 | e @ Expr::StringLiteral(..)
-                | e @ Expr::IntLiteral(..) => e,
+                | e @ Expr::BytesLiteral(..)
+                | e @ Expr::IntLiteral(..)
+                | e @ Expr::BigIntLiteral(..) => e,
             },
         }))
     }
 }
 
-#[doc(hidden)]
+/// Clause of a comprehension.
 #[derive(Debug, Clone)]
 pub enum Clause {
+    /// `for x in a`: the target and the iterated expression.
     For(AstExpr, AstExpr),
+    /// `if x`
     If(AstExpr),
 }
 to_ast_trait!(Clause, AstClause);
 
-#[doc(hidden)]
+/// Binary operator.
 #[derive(Debug, Clone, Copy)]
 pub enum BinOp {
+    /// `or`
     Or,
+    /// `and`
     And,
+    /// `==`
     EqualsTo,
+    /// `!=`
     Different,
+    /// `<`
     LowerThan,
+    /// `>`
     GreaterThan,
+    /// `<=`
     LowerOrEqual,
+    /// `>=`
     GreaterOrEqual,
+    /// `in`
     In,
+    /// `not in`
     NotIn,
+    /// `-`
     Substraction,
+    /// `+`
     Addition,
+    /// `*`
     Multiplication,
+    /// `%`
     Percent,
+    /// `/`
     Division,
+    /// `//`
     FloorDivision,
+    /// `|`
     Pipe,
 }
 
-#[doc(hidden)]
+/// Assignment operator, `=` or an augmented assignment like `+=`.
 #[derive(Debug, Clone, Copy)]
 pub enum AssignOp {
+    /// `=`
     Assign,
+    /// `+=`
     Increment,
+    /// `-=`
     Decrement,
+    /// `*=`
     Multiplier,
+    /// `/=`
     Divider,
+    /// `//=`
     FloorDivider,
+    /// `%=`
     Percent,
 }
 
-#[doc(hidden)]
+/// A statement, a whole file being a `Statements`.
+///
+/// The variants hidden from the documentation are only produced when preparing the
+/// evaluation, never by [`parse`](crate::syntax::parse), and may change in any release.
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub enum Statement {
+    /// `break`
     Break,
+    /// `continue`
     Continue,
+    /// `pass`
     Pass,
+    /// `return` or `return x`
     Return(Option<AstExpr>),
+    /// An expression used as a statement, e.g. a call.
     Expression(AstExpr),
+    /// `a = x` or an augmented assignment like `a += x`.
     Assign(AstExpr, AssignOp, AstExpr),
+    /// A sequence of statements, e.g. a block.
     Statements(Vec<AstStatement>),
+    /// `if condition:` without `else`.
     If(AstExpr, AstStatement),
+    /// `if condition:` with an `else`, an `elif` being an `if` in the `else` block.
     IfElse(AstExpr, AstStatement, AstStatement),
+    /// `for x in a:`: the target, the iterated expression and the body.
     For(AstExpr, AstExpr, AstStatement),
+    /// `while condition:`
+    While(AstExpr, AstStatement),
+    /// `def f(x):`: the name, the parameters and the body.
     Def(AstString, Vec<AstParameter>, AstStatement),
     /// Post-processed `def` statement
+    #[doc(hidden)]
     DefCompiled(DefCompiled),
+    /// `load("module", "a", b = "c")`: the module and the pairs of local and loaded names.
     Load(AstString, Vec<(AstString, AstString)>),
 }
 to_ast_trait!(Statement, AstStatement, Box);
@@ -488,11 +838,20 @@ macro_rules! test_param_name {
 }
 
 impl Statement {
+    #[doc(hidden)]
     pub fn check_def(
         name: AstString,
         parameters: Vec<AstParameter>,
         stmts: AstStatement,
     ) -> Result<Statement, lalrpop_util::ParseError<u64, lexer::Token, lexer::LexerError>> {
+        Statement::check_parameters(&parameters)?;
+        Ok(Statement::Def(name, parameters, stmts))
+    }
+
+    /// Validate the order and names of `def` or `lambda` parameters
+    fn check_parameters(
+        parameters: &[AstParameter],
+    ) -> Result<(), lalrpop_util::ParseError<u64, lexer::Token, lexer::LexerError>> {
         {
             let mut stage = 0;
             let mut argset = HashSet::new();
@@ -556,7 +915,7 @@ impl Statement {
                 }
             }
         }
-        Ok(Statement::Def(name, parameters, stmts))
+        Ok(())
     }
 
     /// Validate `break` and `continue` is only used inside loops
@@ -593,7 +952,7 @@ impl Statement {
                 }
                 Ok(())
             }
-            Statement::For(..) => {
+            Statement::For(..) | Statement::While(..) => {
                 // No need to check loop body, because `break` and `continue`
                 // are valid anywhere in loop body.
                 Ok(())
@@ -609,6 +968,86 @@ impl Statement {
         }
     }
 
+    /// Validate that only the language features enabled in `dialect` are used
+    fn validate_dialect(
+        stmt: &AstStatement,
+        dialect: Dialect,
+        top_level: bool,
+    ) -> Result<(), Diagnostic> {
+        let check_top_level = |feature| {
+            if top_level && !dialect.allow_toplevel_statements {
+                Err(feature_not_allowed(
+                    stmt.span,
+                    feature,
+                    "allow_toplevel_statements",
+                ))
+            } else {
+                Ok(())
+            }
+        };
+        match stmt.node {
+            Statement::Def(_, ref params, ref suite) => {
+                if !dialect.allow_def {
+                    return Err(feature_not_allowed(
+                        stmt.span,
+                        "`def` statements",
+                        "allow_def",
+                    ));
+                }
+                Parameter::validate_dialect(params, dialect)?;
+                Statement::validate_dialect(suite, dialect, false)
+            }
+            Statement::DefCompiled(..) => unreachable!(),
+            Statement::If(ref cond, ref then_block) => {
+                check_top_level("`if` statements outside of a `def`")?;
+                Expr::validate_dialect(cond, dialect)?;
+                Statement::validate_dialect(then_block, dialect, false)
+            }
+            Statement::IfElse(ref cond, ref then_block, ref else_block) => {
+                check_top_level("`if` statements outside of a `def`")?;
+                Expr::validate_dialect(cond, dialect)?;
+                Statement::validate_dialect(then_block, dialect, false)?;
+                Statement::validate_dialect(else_block, dialect, false)
+            }
+            Statement::For(ref var, ref over, ref body) => {
+                check_top_level("`for` loops outside of a `def`")?;
+                Expr::validate_dialect(var, dialect)?;
+                Expr::validate_dialect(over, dialect)?;
+                Statement::validate_dialect(body, dialect, false)
+            }
+            Statement::While(ref cond, ref body) => {
+                if !dialect.allow_while {
+                    return Err(feature_not_allowed(
+                        stmt.span,
+                        "`while` loops",
+                        "allow_while",
+                    ));
+                }
+                check_top_level("`while` loops outside of a `def`")?;
+                Expr::validate_dialect(cond, dialect)?;
+                Statement::validate_dialect(body, dialect, false)
+            }
+            Statement::Statements(ref stmts) => {
+                for stmt in stmts {
+                    Statement::validate_dialect(stmt, dialect, top_level)?;
+                }
+                Ok(())
+            }
+            Statement::Return(Some(ref e)) | Statement::Expression(ref e) => {
+                Expr::validate_dialect(e, dialect)
+            }
+            Statement::Assign(ref left, _, ref right) => {
+                Expr::validate_dialect(left, dialect)?;
+                Expr::validate_dialect(right, dialect)
+            }
+            Statement::Return(None)
+            | Statement::Break
+            | Statement::Continue
+            | Statement::Pass
+            | Statement::Load(..) => Ok(()),
+        }
+    }
+
     pub(crate) fn compile(stmt: AstStatement) -> Result<AstStatement, Diagnostic> {
         Ok(Box::new(Spanned {
             span: stmt.span,
@@ -622,12 +1061,15 @@ impl Statement {
                     Expr::compile(over)?,
                     Statement::compile(body)?,
                 ),
+                Statement::While(cond, body) => {
+                    Statement::While(Expr::compile(cond)?, Statement::compile(body)?)
+                }
                 Statement::Return(expr) => Statement::Return(expr.map(Expr::compile).transpose()?),
                 Statement::If(cond, then_block) => {
-                    Statement::If(cond, Statement::compile(then_block)?)
+                    Statement::If(Expr::compile(cond)?, Statement::compile(then_block)?)
                 }
                 Statement::IfElse(conf, then_block, else_block) => Statement::IfElse(
-                    conf,
+                    Expr::compile(conf)?,
                     Statement::compile(then_block)?,
                     Statement::compile(else_block)?,
                 ),
@@ -649,11 +1091,17 @@ impl Statement {
         }))
     }
 
+    /// Check the constraints which are not enforced by the grammar.
+    pub(crate) fn validate_mod(stmt: &AstStatement, dialect: Dialect) -> Result<(), Diagnostic> {
+        Statement::validate_break_continue(stmt)?;
+        Statement::validate_dialect(stmt, dialect, true)
+    }
+
     pub(crate) fn compile_mod(
         stmt: AstStatement,
-        _dialect: Dialect,
+        dialect: Dialect,
     ) -> Result<AstStatement, Diagnostic> {
-        Statement::validate_break_continue(&stmt)?;
+        Statement::validate_mod(&stmt, dialect)?;
         let stmt = Statement::compile(stmt)?;
         Ok(stmt)
     }
@@ -688,7 +1136,7 @@ impl Display for AssignOp {
         match *self {
             AssignOp::Assign => f.write_str(" = "),
             AssignOp::Increment => f.write_str(" += "),
-            AssignOp::Decrement => f.write_str(" += "),
+            AssignOp::Decrement => f.write_str(" -= "),
             AssignOp::Multiplier => f.write_str(" *= "),
             AssignOp::Divider => f.write_str(" /= "),
             AssignOp::FloorDivider => f.write_str(" //= "),
@@ -732,6 +1180,22 @@ fn fmt_string_literal(f: &mut Formatter<'_>, s: &str) -> fmt::Result {
     f.write_str("\"")
 }
 
+fn fmt_bytes_literal(f: &mut Formatter<'_>, b: &[u8]) -> fmt::Result {
+    f.write_str("b\"")?;
+    for &c in b {
+        match c {
+            b'\n' => f.write_str("\\n")?,
+            b'\t' => f.write_str("\\t")?,
+            b'\r' => f.write_str("\\r")?,
+            b'"' => f.write_str("\\\"")?,
+            b'\\' => f.write_str("\\\\")?,
+            0x20..=0x7e => write!(f, "{}", c as char)?,
+            c => write!(f, "\\x{:02x}", c)?,
+        }
+    }
+    f.write_str("\"")
+}
+
 impl Display for Expr {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match *self {
@@ -775,22 +1239,24 @@ impl Display for Expr {
             }
             Expr::ArrayIndirection(ref e, ref i) => write!(f, "{}[{}]", e.node, i.node),
             Expr::Slice(ref e, ref i1, ref i2, ref i3) => {
-                write!(f, "{}[]", e.node)?;
+                write!(f, "{}[", e.node)?;
                 if let Some(ref x) = i1 {
-                    write!(f, "{}:", x.node)?
-                } else {
-                    f.write_str(":")?
+                    x.node.fmt(f)?
                 }
+                f.write_str(":")?;
                 if let Some(ref x) = i2 {
                     x.node.fmt(f)?
                 }
                 if let Some(ref x) = i3 {
                     write!(f, ":{}", x.node)?
                 }
-                Ok(())
+                f.write_str("]")
+            }
+            Expr::Identifier(ref s) | Expr::Slot(_, ref s) | Expr::FunctionSlot(_, ref s) => {
+                s.node.fmt(f)
             }
-            Expr::Identifier(ref s) | Expr::Slot(_, ref s) => s.node.fmt(f),
             Expr::IntLiteral(ref i) => i.node.fmt(f),
+            Expr::BigIntLiteral(ref i) => i.node.fmt(f),
             Expr::Not(ref e) => write!(f, "(not {})", e.node),
             Expr::Minus(ref e) => write!(f, "-{}", e.node),
             Expr::Plus(ref e) => write!(f, "+{}", e.node),
@@ -813,23 +1279,50 @@ impl Display for Expr {
                 comma_separated_fmt(f, v, |x, f| write!(f, "{}: {}", x.0.node, x.1.node), false)?;
                 f.write_str("}")
             }
+            // Clauses are not comma separated, each one starts with a space.
             Expr::ListComprehension(ref e, ref v) => {
                 write!(f, "[{}", e.node)?;
-                comma_separated_fmt(f, v, |x, f| x.node.fmt(f), false)?;
+                for x in v {
+                    x.node.fmt(f)?;
+                }
                 f.write_str("]")
             }
             Expr::SetComprehension(ref e, ref v) => {
                 write!(f, "{{{}", e.node)?;
-                comma_separated_fmt(f, v, |x, f| x.node.fmt(f), false)?;
-                f.write_str("}}")
+                for x in v {
+                    x.node.fmt(f)?;
+                }
+                f.write_str("}")
             }
             Expr::DictComprehension((ref k, ref v), ref c) => {
                 write!(f, "{{{}: {}", k.node, v.node)?;
-                comma_separated_fmt(f, c, |x, f| x.node.fmt(f), false)?;
-                f.write_str("}}")
+                for x in c {
+                    x.node.fmt(f)?;
+                }
+                f.write_str("}")
             }
             Expr::ComprehensionCompiled(ref c) => fmt::Display::fmt(&c.to_raw(), f),
+            Expr::Lambda(ref params, ref body) => {
+                f.write_str("(lambda")?;
+                if !params.is_empty() {
+                    f.write_str(" ")?;
+                }
+                comma_separated_fmt(f, params, |x, f| x.node.fmt(f), false)?;
+                write!(f, ": {})", body.node)
+            }
+            Expr::LambdaCompiled(ref d) => {
+                f.write_str("(lambda")?;
+                if !d.params.is_empty() {
+                    f.write_str(" ")?;
+                }
+                comma_separated_fmt(f, &d.params, |x, f| x.node.fmt(f), false)?;
+                match d.suite.node {
+                    Statement::Return(Some(ref body)) => write!(f, ": {})", body.node),
+                    _ => unreachable!("lambda body is a single return statement"),
+                }
+            }
             Expr::StringLiteral(ref s) => fmt_string_literal(f, &s.node),
+            Expr::BytesLiteral(ref b) => fmt_bytes_literal(f, &b.node),
         }
     }
 }
@@ -897,6 +1390,10 @@ impl Statement {
                 writeln!(f, "{}for {} in {}:", tab, bind.node, coll.node)?;
                 suite.node.fmt_with_tab(f, tab + "  ")
             }
+            Statement::While(ref cond, ref suite) => {
+                writeln!(f, "{}while {}:", tab, cond.node)?;
+                suite.node.fmt_with_tab(f, tab + "  ")
+            }
             Statement::Def(ref name, ref params, ref suite)
             | Statement::DefCompiled(DefCompiled {
                 ref name,
@@ -912,6 +1409,7 @@ impl Statement {
             Statement::Load(ref filename, ref v) => {
                 write!(f, "{}load(", tab)?;
                 fmt_string_literal(f, &filename.node)?;
+                f.write_str(", ")?;
                 comma_separated_fmt(
                     f,
                     v,
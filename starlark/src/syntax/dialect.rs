@@ -12,11 +12,61 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-/// Starlark language dialect.
-#[derive(Copy, Debug, Clone)]
-pub enum Dialect {
-    // Build file dialect which is used to interpret Bazel's BUILD files
-    Build,
-    // Full Starlark language that is available in Bazel's .bzl files
-    Bzl,
+/// Starlark language dialect: which language features are accepted on top of the core
+/// language.
+///
+/// [`Dialect::Bzl`], the default, is the full language available in Bazel's .bzl files and
+/// [`Dialect::Build`] the restricted one used for Bazel's BUILD files. Other dialects can be
+/// derived from them, e.g.:
+///
+/// ```
+/// # use starlark::syntax::dialect::Dialect;
+/// let dialect = Dialect {
+///     allow_while: true,
+///     allow_recursion: true,
+///     ..Dialect::Bzl
+/// };
+/// ```
+///
+/// Using a disabled feature is reported when parsing, except for recursion which can only
+/// be detected during the evaluation.
+#[derive(Copy, Debug, Clone, PartialEq, Eq)]
+pub struct Dialect {
+    /// Allow `def` statements.
+    pub allow_def: bool,
+    /// Allow `if`, `for` and `while` statements outside of a `def`.
+    pub allow_toplevel_statements: bool,
+    /// Allow a function to call itself, directly or not.
+    pub allow_recursion: bool,
+    /// Allow `while` loops.
+    pub allow_while: bool,
+    /// Allow `lambda` expressions.
+    pub allow_lambda: bool,
+    /// Allow set literals and set comprehensions, e.g. `{1, 2}`.
+    pub allow_set_literals: bool,
+}
+
+#[allow(non_upper_case_globals)]
+impl Dialect {
+    /// Build file dialect which is used to interpret Bazel's BUILD files: no `def`.
+    pub const Build: Dialect = Dialect {
+        allow_def: false,
+        ..Dialect::Bzl
+    };
+
+    /// Full Starlark language that is available in Bazel's .bzl files.
+    pub const Bzl: Dialect = Dialect {
+        allow_def: true,
+        allow_toplevel_statements: false,
+        allow_recursion: false,
+        allow_while: false,
+        allow_lambda: false,
+        allow_set_literals: true,
+    };
+}
+
+impl Default for Dialect {
+    fn default() -> Self {
+        Dialect::Bzl
+    }
 }
@@ -16,7 +16,7 @@ use crate::syntax::grammar::StarlarkParser;
 use std::sync::{Arc, Mutex};
 use crate::syntax::ast::Statement;
 use crate::syntax::dialect::Dialect;
-use crate::syntax::parser::parse_file;
+use crate::syntax::parser::{parse, parse_file};
 use crate::syntax::errors::SyntaxError;
 use codemap;
 use codemap_diagnostic;
@@ -101,6 +101,11 @@ fn test_top_level_def() {
     assert_eq!(unwrap_parse!("def t():\n\n  pass"), "def t():\n  pass\n");
 }
 
+#[test]
+fn test_top_level_pass() {
+    assert_eq!(unwrap_parse!("pass\na = 1; pass"), "pass\na = 1\npass\n");
+}
+
 #[test]
 fn test_top_level_def_with_docstring() {
     assert_eq!(
@@ -123,6 +128,27 @@ fn test_ifelse() {
     );
 }
 
+#[test]
+fn test_while() {
+    assert_eq!(
+        unwrap_parse!("def d():\n  while a:\n    break"),
+        "def d():\n  while a:\n    break\n"
+    );
+}
+
+#[test]
+fn test_lambda() {
+    assert_eq!(unwrap_parse!("f = lambda: 1"), "f = (lambda: 1)\n");
+    assert_eq!(
+        unwrap_parse!("f(lambda x, y = 1, *a: x + y if x else y)"),
+        "f((lambda x, y = 1, *a: ((x + y) if x else y)))\n"
+    );
+    assert_eq!(
+        unwrap_parse!("[lambda: lambda x: x]"),
+        "[(lambda: (lambda x: x))]\n"
+    );
+}
+
 #[test]
 fn test_kwargs_passing() {
     assert_eq!(
@@ -146,6 +172,68 @@ fn test_tuples() {
     assert_eq!(unwrap_parse!("a = ()"), "a = ()\n");
 }
 
+#[test]
+fn test_trailing_commas() {
+    assert_eq!(unwrap_parse!("a = (1, 2,)"), "a = (1, 2)\n");
+    assert_eq!(unwrap_parse!("a = [1, 2,]"), "a = [1, 2]\n");
+    assert_eq!(unwrap_parse!("a = {1: 2, 3: 4,}"), "a = {1: 2, 3: 4}\n");
+    assert_eq!(unwrap_parse!("a = {1, 2,}"), "a = {1, 2}\n");
+    assert_eq!(unwrap_parse!("f(x, y = 1,)"), "f(x, y = 1)\n");
+    assert_eq!(unwrap_parse!("f(*a, **b,)"), "f(*a, **b)\n");
+    assert_eq!(unwrap_parse!("a[1, 2,]"), "a[(1, 2)]\n");
+    assert_eq!(
+        unwrap_parse!("def f(a, b = 1, *c, **d,):\n  pass"),
+        "def f(a, b = 1, *c, **d):\n  pass\n"
+    );
+    assert_eq!(
+        unwrap_parse!("load(\"a.bzl\", \"b\", c = \"d\",)"),
+        "load(\"a.bzl\", b = \"b\", c = \"d\")\n"
+    );
+}
+
+#[test]
+fn test_semicolons() {
+    assert_eq!(unwrap_parse!("a = 1; b = 2"), "a = 1\nb = 2\n");
+    assert_eq!(
+        unwrap_parse!("a = 1; b = 2;\nc = 3;"),
+        "a = 1\nb = 2\nc = 3\n"
+    );
+    assert_eq!(
+        unwrap_parse!("def f():\n  a = 1; return a"),
+        "def f():\n  a = 1\n  return a\n"
+    );
+    assert_eq!(
+        unwrap_parse!("def f():\n  if a: b; c\n"),
+        "def f():\n  if a:\n    b\n    c\n"
+    );
+}
+
+#[test]
+fn test_semicolons_build_file() {
+    let map = Arc::new(Mutex::new(codemap::CodeMap::new()));
+    let ast = parse(&map, "BUILD", "a = 1; b = 2;\nf(a,)\n", Dialect::Build).unwrap();
+    assert_eq!("a = 1\nb = 2\nf(a)\n", format!("{}", ast.node));
+}
+
+#[test]
+fn test_misplaced_commas_and_semicolons() {
+    let map = Arc::new(Mutex::new(codemap::CodeMap::new()));
+    for content in &[
+        "f(,)",
+        "a = [,]",
+        "a = (,)",
+        "a = 1;;",
+        ";",
+        "def f(,):\n  pass",
+    ] {
+        assert!(
+            parse(&map, "a.bzl", content, Dialect::Bzl).is_err(),
+            "{}",
+            content
+        );
+    }
+}
+
 #[test]
 fn test_return() {
     assert_eq!(
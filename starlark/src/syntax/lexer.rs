@@ -13,6 +13,8 @@
 // limitations under the License.
 
 use super::errors::SyntaxError;
+#[cfg(feature = "bigint")]
+use crate::values::bigint;
 use codemap::Span;
 use codemap_diagnostic::{Diagnostic, Level, SpanLabel, SpanStyle};
 use std::char;
@@ -110,6 +112,8 @@ pub enum Token {
     Pass,     // "pass" keyword
     Elif,     // "elif" keyword
     Return,   // "return" keyword
+    While,    // "while" keyword
+    Lambda,   // "lambda" keyword
     // Symbols
     Comma,            // ','
     Semicolon,        // ';'
@@ -144,10 +148,12 @@ pub enum Token {
     ClosingCurlyBracket, // '}'
     ClosingParenthesis,  // ')'
 
-    Reserved(String),      // One of the reserved keywords
-    Identifier(String),    // An identifier
-    IntegerLiteral(i64),   // An integer literal (123, 0x1, 0b1011, 0755, ...)
-    StringLiteral(String), // A string literal
+    Reserved(String),          // One of the reserved keywords
+    Identifier(String),        // An identifier
+    IntegerLiteral(i64),       // An integer literal (123, 0x1, 0b1011, 0755, ...)
+    BigIntegerLiteral(String), // An integer literal overflowing i64, in base 10 (bigint feature)
+    StringLiteral(String),     // A string literal
+    BytesLiteral(Vec<u8>),     // A bytes literal (b"...")
 }
 
 impl fmt::Display for Token {
@@ -171,6 +177,8 @@ impl fmt::Display for Token {
             Token::Pass => write!(f, "keyword 'pass'"),
             Token::Elif => write!(f, "keyword 'elif'"),
             Token::Return => write!(f, "keyword 'return'"),
+            Token::While => write!(f, "keyword 'while'"),
+            Token::Lambda => write!(f, "keyword 'lambda'"),
             Token::Comma => write!(f, "symbol ','"),
             Token::Semicolon => write!(f, "symbol ';'"),
             Token::Colon => write!(f, "symbol ':'"),
@@ -205,7 +213,11 @@ impl fmt::Display for Token {
             Token::Reserved(ref s) => write!(f, "reserved keyword '{}'", s),
             Token::Identifier(ref s) => write!(f, "identifier '{}'", s),
             Token::IntegerLiteral(ref i) => write!(f, "integer literal '{}'", i),
+            Token::BigIntegerLiteral(ref i) => write!(f, "integer literal '{}'", i),
             Token::StringLiteral(ref s) => write!(f, "string literal '{}'", s),
+            Token::BytesLiteral(ref b) => {
+                write!(f, "bytes literal '{}'", String::from_utf8_lossy(b))
+            }
         }
     }
 }
@@ -493,8 +505,10 @@ impl Lexer {
             "pass" => Token::Pass,
             "elif" => Token::Elif,
             "return" => Token::Return,
+            "while" => Token::While,
+            "lambda" => Token::Lambda,
             "as" | "import" | "assert" | "is" | "class" | "nonlocal" | "del" | "raise"
-            | "except" | "try" | "finally" | "while" | "from" | "with" | "global" | "yield" => {
+            | "except" | "try" | "finally" | "from" | "with" | "global" | "yield" => {
                 Token::Reserved(identifier.to_owned())
             }
             _ => Token::Identifier(identifier.to_owned()),
@@ -636,25 +650,30 @@ impl Lexer {
         self.consume_identifier_queue("")
     }
 
-    fn consume_int_r(&mut self, radix: u32) -> Result<i64, ()> {
+    fn consume_digits(&mut self, radix: u32) -> String {
         let mut number = String::new();
         while self.peek_char().is_digit(radix) {
             number.push(self.next_char());
         }
-        let val = i64::from_str_radix(&number, radix);
-        if val.is_err() {
-            Err(())
-        } else {
-            Ok(val.unwrap())
-        }
+        number
+    }
+
+    fn consume_int_r(&mut self, radix: u32) -> Result<i64, ()> {
+        let number = self.consume_digits(radix);
+        i64::from_str_radix(&number, radix).map_err(|_| ())
     }
 
     fn consume_int_radix(&mut self, radix: u32) -> Option<<Self as Iterator>::Item> {
-        let val = self.consume_int_r(radix);
-        if val.is_err() {
-            self.invalid()
-        } else {
-            self.end(Token::IntegerLiteral(val.unwrap()))
+        let number = self.consume_digits(radix);
+        match i64::from_str_radix(&number, radix) {
+            Ok(i) => self.end(Token::IntegerLiteral(i)),
+            #[cfg(feature = "bigint")]
+            Err(..) if !number.is_empty() => {
+                // The digits are all valid, so the literal only overflowed.
+                let big = bigint::parse(&number, radix).unwrap();
+                self.end(Token::BigIntegerLiteral(big.to_string()))
+            }
+            Err(..) => self.invalid(),
         }
     }
 
@@ -770,9 +789,37 @@ impl Lexer {
         }
     }
 
-    fn consume_string(&mut self, raw: bool) -> Option<<Self as Iterator>::Item> {
+    /// Consume a `\xhh` escape sequence of a bytes literal, which has exactly two
+    /// hexadecimal digits.
+    fn consume_hex_byte_escape(&mut self) -> Result<u8, LexerError> {
+        let (pos, _) = self.pop().unwrap();
+        self.pop();
+        let mut byte = 0;
+        for _ in 0..2 {
+            match self.peek_char().to_digit(16) {
+                Some(d) => {
+                    self.pop();
+                    byte = byte * 16 + d as u8;
+                }
+                None => {
+                    let p = self.end_pos();
+                    return Err(LexerError::InvalidEscapeSequence(pos, p.1));
+                }
+            }
+        }
+        Ok(byte)
+    }
+
+    /// Consume a string literal, or a bytes literal if `bytes` is set, in which case the
+    /// characters are UTF-8 encoded except for octal and hexadecimal escapes, which denote
+    /// a single byte (e.g. `b"\xff"` is one byte, `"\xff"` is two).
+    fn consume_string(&mut self, raw: bool, bytes: bool) -> Option<<Self as Iterator>::Item> {
+        fn push(res: &mut Vec<u8>, c: char) {
+            res.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+        }
+
         self.begin();
-        let mut res = String::new();
+        let mut res = Vec::new();
         let quote = self.next_char();
         let mut triple = false;
         if self.peek_char() == quote {
@@ -781,7 +828,7 @@ impl Lexer {
                 self.next_char();
                 triple = true;
             } else {
-                return self.end(Token::StringLiteral(res));
+                return self.end(Self::string_token(res, bytes));
             }
         }
         loop {
@@ -791,13 +838,27 @@ impl Lexer {
                         self.pop();
                         if self.peek_char() == quote {
                             self.pop();
-                            res.push(quote);
+                            push(&mut res, quote);
                         } else {
-                            res.push('\\');
+                            push(&mut res, '\\');
+                        }
+                    } else if bytes && self.input[self.pos_bytes + 1..].starts_with('x') {
+                        match self.consume_hex_byte_escape() {
+                            Ok(x) => res.push(x),
+                            Err(c) => return Some(Err(c)),
                         }
                     } else {
+                        let octal = bytes && self.input[self.pos_bytes + 1..].starts_with('0');
+                        let pos = self.peek().unwrap().0;
                         match self.consume_escape_sequence(triple) {
-                            Ok(Some(x)) => res.push(x),
+                            Ok(Some(x)) if octal => {
+                                if u32::from(x) > 0xff {
+                                    let p = self.end_pos();
+                                    return Some(Err(LexerError::InvalidEscapeSequence(pos, p.1)));
+                                }
+                                res.push(x as u8)
+                            }
+                            Ok(Some(x)) => push(&mut res, x),
                             Ok(None) => {}
                             Err(c) => return Some(Result::Err(c)),
                         }
@@ -805,7 +866,7 @@ impl Lexer {
                 }
                 '\n' | '\r' | '\u{2028}' | '\u{2029}' => {
                     if triple {
-                        res.push(self.next_char());
+                        push(&mut res, self.next_char());
                     } else {
                         let p = self.end_pos();
                         return Some(Err(LexerError::UnfinishedStringLiteral(p.0, p.1)));
@@ -822,11 +883,11 @@ impl Lexer {
                             if self.next_char() == quote {
                                 break;
                             } else {
-                                res.push(quote);
-                                res.push(quote);
+                                push(&mut res, quote);
+                                push(&mut res, quote);
                             }
                         } else {
-                            res.push(quote);
+                            push(&mut res, quote);
                         }
                     } else {
                         break;
@@ -834,11 +895,20 @@ impl Lexer {
                 }
                 x => {
                     self.pop();
-                    res.push(x);
+                    push(&mut res, x);
                 }
             }
         }
-        self.end(Token::StringLiteral(res))
+        self.end(Self::string_token(res, bytes))
+    }
+
+    fn string_token(res: Vec<u8>, bytes: bool) -> Token {
+        if bytes {
+            Token::BytesLiteral(res)
+        } else {
+            // Only complete characters were pushed.
+            Token::StringLiteral(String::from_utf8(res).unwrap())
+        }
     }
 
     fn consume_token(&mut self) -> Option<<Self as Iterator>::Item> {
@@ -856,14 +926,17 @@ impl Lexer {
         match self.peek_char() {
             '\0' => None,
             '\n' | '\r' | '\u{2028}' | '\u{2029}' => self.consume_nl(),
-            '\'' | '"' => self.consume_string(false),
-            'r' => {
-                self.pop();
-                let p = self.peek_char();
-                if p == '\'' || p == '"' {
-                    self.consume_string(true)
-                } else {
-                    self.consume_identifier_queue("r")
+            '\'' | '"' => self.consume_string(false, false),
+            'r' | 'b' => {
+                // String prefixes: `r` for raw strings, `b` for bytes, in any order.
+                let first = self.next_char();
+                let mut prefix = first.to_string();
+                if self.peek_char() == if first == 'r' { 'b' } else { 'r' } {
+                    prefix.push(self.next_char());
+                }
+                match self.peek_char() {
+                    '\'' | '"' => self.consume_string(prefix.contains('r'), prefix.contains('b')),
+                    _ => self.consume_identifier_queue(&prefix),
                 }
             }
             '0'..='9' => self.consume_int(),
@@ -1076,6 +1149,30 @@ mod tests {
         assert_eq!(vec![0o755, 0o755, 0o755], get_result("0o755 0O755 0755"));
     }
 
+    #[cfg(feature = "bigint")]
+    #[test]
+    fn test_big_int_lit() {
+        let get_result = |s: &'static str| -> Vec<String> {
+            collect_result(s)
+                .iter()
+                .filter_map(|v| match v {
+                    Token::BigIntegerLiteral(r) => Some(r.clone()),
+                    Token::IntegerLiteral(r) => Some(r.to_string()),
+                    Token::Newline => None,
+                    _ => panic!("{:?} is not a integer literal", v),
+                })
+                .collect()
+        };
+        assert_eq!(
+            vec!["9223372036854775807", "9223372036854775808"],
+            get_result("9223372036854775807 9223372036854775808")
+        );
+        assert_eq!(
+            vec!["18446744073709551615", "18446744073709551616"],
+            get_result("0xffffffffffffffff 0o2000000000000000000000")
+        );
+    }
+
     #[test]
     fn test_indentation() {
         let r = collect_result(
@@ -1169,7 +1266,8 @@ mod tests {
     #[test]
     fn test_keywords() {
         let r = collect_result(
-            "and else load break for not not  in continue if or def in pass elif return",
+            "and else load break for not not  in continue if or def in pass elif return while \
+             lambda",
         );
         assert_eq!(
             &[
@@ -1188,6 +1286,8 @@ mod tests {
                 Token::Pass,
                 Token::Elif,
                 Token::Return,
+                Token::While,
+                Token::Lambda,
                 Token::Newline,
             ],
             &r[..]
@@ -1232,7 +1332,7 @@ mod tests {
     fn test_reserved() {
         let r = collect_result(
             "as import assert is class nonlocal del raise except try finally \
-             while from with global yield",
+             from with global yield",
         );
         assert_eq!(
             &[
@@ -1247,7 +1347,6 @@ mod tests {
                 Token::Reserved("except".to_owned()),
                 Token::Reserved("try".to_owned()),
                 Token::Reserved("finally".to_owned()),
-                Token::Reserved("while".to_owned()),
                 Token::Reserved("from".to_owned()),
                 Token::Reserved("with".to_owned()),
                 Token::Reserved("global".to_owned()),
@@ -1350,6 +1449,40 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_bytes_lit() {
+        let r =
+            collect_result("b'' b\"a\" b'\\xff\\x01a' b'\\0101' b'\u{e9}' rb'\\x00' br\"\\n\" bx");
+        assert_eq!(
+            &[
+                Token::BytesLiteral(Vec::new()),
+                Token::BytesLiteral(b"a".to_vec()),
+                Token::BytesLiteral(b"\xff\x01a".to_vec()),
+                Token::BytesLiteral(b"A".to_vec()),
+                Token::BytesLiteral(b"\xc3\xa9".to_vec()),
+                Token::BytesLiteral(b"\\x00".to_vec()),
+                Token::BytesLiteral(b"\\n".to_vec()),
+                Token::Identifier("bx".to_owned()),
+                Token::Newline,
+            ],
+            &r[..]
+        );
+        // Hexadecimal escapes have exactly two digits, octal escapes must fit in a byte.
+        assert_eq!(
+            super::Lexer::new("b'\\x1'").next().unwrap(),
+            Err(super::LexerError::InvalidEscapeSequence(2, 5))
+        );
+        assert_eq!(
+            super::Lexer::new("b'\\0777'").next().unwrap(),
+            Err(super::LexerError::InvalidEscapeSequence(2, 7))
+        );
+        // Escapes of strings still denote code points.
+        assert_eq!(
+            collect_result("'\\xff'"),
+            vec![Token::StringLiteral("\u{ff}".to_owned()), Token::Newline]
+        );
+    }
+
     #[test]
     fn test_simple_example() {
         let r = collect_result(
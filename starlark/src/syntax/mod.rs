@@ -21,7 +21,6 @@ pub mod errors;
 #[macro_use]
 mod testutil;
 
-#[doc(hidden)]
 pub mod ast;
 pub mod dialect;
 #[doc(hidden)]
@@ -40,5 +39,8 @@ mod grammar_tests {
     ));
 }
 
+pub mod module;
 #[doc(hidden)]
 pub mod parser;
+
+pub use crate::syntax::module::{parse, AstModule};
@@ -0,0 +1,194 @@
+// Copyright 2019 The Starlark in Rust Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Parsing of a Starlark file to its AST without evaluating it, e.g. for linters or
+//! refactoring tools.
+
+use crate::syntax::ast::AstStatement;
+use crate::syntax::dialect::Dialect;
+use crate::syntax::parser::parse_unprepared;
+use codemap::{CodeMap, Span, SpanLoc};
+use codemap_diagnostic::Diagnostic;
+use std::sync::{Arc, Mutex};
+
+/// A parsed Starlark file: its AST and the code map its spans refer to.
+///
+/// The AST is the one of the source code, as the parser produced it: it has not been
+/// prepared for the evaluation, e.g. `def` statements are
+/// [`Statement::Def`](crate::syntax::ast::Statement::Def).
+#[derive(Debug, Clone)]
+pub struct AstModule {
+    codemap: Arc<Mutex<CodeMap>>,
+    statement: AstStatement,
+}
+
+impl AstModule {
+    /// Parse `content` in `dialect`, adding the file to `codemap`, so that the spans of
+    /// the AST and of the parse errors can be resolved with `codemap` (e.g. to print the
+    /// errors with `codemap_diagnostic::Emitter`).
+    pub fn parse_with_codemap(
+        codemap: &Arc<Mutex<CodeMap>>,
+        filename: &str,
+        content: &str,
+        dialect: Dialect,
+    ) -> Result<AstModule, Diagnostic> {
+        Ok(AstModule {
+            codemap: codemap.clone(),
+            statement: parse_unprepared(codemap, filename, content, dialect)?,
+        })
+    }
+
+    /// The statements of the file, a
+    /// [`Statement::Statements`](crate::syntax::ast::Statement::Statements).
+    pub fn statement(&self) -> &AstStatement {
+        &self.statement
+    }
+
+    /// The code map containing the file.
+    pub fn codemap(&self) -> &Arc<Mutex<CodeMap>> {
+        &self.codemap
+    }
+
+    /// The file, lines and columns of a span of the AST. Lines and columns start at 0.
+    pub fn look_up_span(&self, span: Span) -> SpanLoc {
+        self.codemap.lock().unwrap().look_up_span(span)
+    }
+
+    /// The source code of a span of the AST.
+    pub fn source(&self, span: Span) -> String {
+        let codemap = self.codemap.lock().unwrap();
+        codemap.find_file(span.low()).source_slice(span).to_owned()
+    }
+}
+
+/// Parse a Starlark file in the default [`Dialect`].
+///
+/// Use [`AstModule::parse_with_codemap`] to choose the dialect or to resolve the spans of
+/// the errors.
+///
+/// # Examples
+///
+/// ```
+/// # use starlark::syntax::ast::Statement;
+/// # use starlark::syntax::parse;
+/// let module = parse("a.star", "x = 1\ny = x + 1\n").unwrap();
+/// match module.statement().node {
+///     Statement::Statements(ref statements) => {
+///         assert_eq!(2, statements.len());
+///         // Lines start at 0.
+///         assert_eq!(1, module.look_up_span(statements[1].span).begin.line);
+///     }
+///     _ => unreachable!(),
+/// }
+/// ```
+pub fn parse(filename: &str, content: &str) -> Result<AstModule, Diagnostic> {
+    AstModule::parse_with_codemap(
+        &Arc::new(Mutex::new(CodeMap::new())),
+        filename,
+        content,
+        Dialect::default(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::syntax::ast::{BinOp, Expr, Statement};
+
+    #[test]
+    fn round_trip() {
+        let content = "load(\"lib.star\", \"f\")\n\
+                       def g(a, b = 1, *args, **kwargs):\n  \
+                       if a:\n    return [x * b for x in args if x]\n  \
+                       return f(a, **kwargs)\n\
+                       y = g(1, 2, 3)[1:]\n\
+                       y -= {k: v for k, v in {}.items()}[::2]\n";
+        let module = parse("a.star", content).unwrap();
+        let printed = module.statement().node.to_string();
+        assert_eq!(
+            "load(\"lib.star\", f = \"f\")\n\
+             def g(a, b = 1, *args, **kwargs):\n  \
+             if a:\n    return [(x * b) for x in args if x]\n  \
+             return f(a, **kwargs)\n\
+             y = g(1, 2, 3)[1:]\n\
+             y -= {k: v for (k, v) in {}.items()}[::2]\n",
+            printed
+        );
+        let reparsed = parse("b.star", &printed).unwrap();
+        assert_eq!(printed, reparsed.statement().node.to_string());
+        // The AST is not prepared for the evaluation.
+        match module.statement().node {
+            Statement::Statements(ref statements) => match statements[1].node {
+                Statement::Def(ref name, ..) => assert_eq!("g", name.node),
+                ref s => panic!("unexpected statement {:?}", s),
+            },
+            ref s => panic!("unexpected statement {:?}", s),
+        }
+    }
+
+    #[test]
+    fn identifier_span() {
+        let module = parse("a.star", "x = 1\ndef f(a):\n  return a + x\n").unwrap();
+        let statements = match module.statement().node {
+            Statement::Statements(ref statements) => statements,
+            ref s => panic!("unexpected statement {:?}", s),
+        };
+        let body = match statements[1].node {
+            Statement::Def(_, _, ref body) => body,
+            ref s => panic!("unexpected statement {:?}", s),
+        };
+        let x = match body.node {
+            Statement::Statements(ref body) => &body[0],
+            ref s => panic!("unexpected statement {:?}", s),
+        };
+        let x = match x.node {
+            Statement::Return(Some(ref e)) => match e.node {
+                Expr::Op(BinOp::Addition, _, ref x) => x,
+                ref e => panic!("unexpected expression {:?}", e),
+            },
+            ref s => panic!("unexpected statement {:?}", s),
+        };
+        match x.node {
+            Expr::Identifier(ref name) => assert_eq!("x", name.node),
+            ref e => panic!("unexpected expression {:?}", e),
+        }
+        let loc = module.look_up_span(x.span);
+        assert_eq!("a.star", loc.file.name());
+        assert_eq!((2, 13), (loc.begin.line, loc.begin.column));
+        assert_eq!((2, 14), (loc.end.line, loc.end.column));
+        assert_eq!("x", module.source(x.span));
+    }
+
+    #[test]
+    fn errors() {
+        let codemap = Arc::new(Mutex::new(CodeMap::new()));
+        let err = AstModule::parse_with_codemap(&codemap, "a.star", "x = (1\n", Dialect::Bzl)
+            .unwrap_err();
+        let loc = codemap.lock().unwrap().look_up_span(err.spans[0].span);
+        assert_eq!("a.star", loc.file.name());
+        // Features not allowed in the dialect are reported.
+        assert!(parse("a.star", "while True:\n  pass\n").is_err());
+        assert!(AstModule::parse_with_codemap(
+            &codemap,
+            "b.star",
+            "def f():\n  while True:\n    break\n",
+            Dialect {
+                allow_while: true,
+                ..Dialect::Bzl
+            }
+        )
+        .is_ok());
+        assert!(parse("a.star", "break\n").is_err());
+    }
+}
@@ -15,7 +15,7 @@
 use super::ast::AstStatement;
 use super::dialect::Dialect;
 use super::errors::SyntaxError;
-use super::grammar::{BuildFileParser, StarlarkParser};
+use super::grammar::StarlarkParser;
 use super::lexer::{Lexer, LexerError, LexerIntoIter, LexerItem, Token};
 use codemap::{CodeMap, Span};
 use codemap_diagnostic::{Diagnostic, Level, SpanLabel, SpanStyle};
@@ -172,6 +172,18 @@ pub fn parse_lexer<T1: Iterator<Item = LexerItem>, T2: LexerIntoIter<T1>>(
     content: &str,
     dialect: Dialect,
     lexer: T2,
+) -> Result<AstStatement, Diagnostic> {
+    let v = parse_grammar(map, filename, content, lexer)?;
+    Statement::compile_mod(v, dialect)
+}
+
+/// Add `content` to the code map and run the grammar on it, without the checks which are
+/// not enforced by the grammar.
+fn parse_grammar<T1: Iterator<Item = LexerItem>, T2: LexerIntoIter<T1>>(
+    map: &Arc<Mutex<CodeMap>>,
+    filename: &str,
+    content: &str,
+    lexer: T2,
 ) -> Result<AstStatement, Diagnostic> {
     let filespan = {
         map.lock()
@@ -179,15 +191,22 @@ pub fn parse_lexer<T1: Iterator<Item = LexerItem>, T2: LexerIntoIter<T1>>(
             .add_file(filename.to_string(), content.to_string())
             .span
     };
-    match {
-        match dialect {
-            Dialect::Build => BuildFileParser::new().parse(content, filespan, lexer),
-            Dialect::Bzl => StarlarkParser::new().parse(content, filespan, lexer),
-        }
-    } {
-        Result::Ok(v) => Ok(Statement::compile_mod(v, dialect)?),
-        Result::Err(p) => Result::Err(p.to_diagnostic(filespan)),
-    }
+    StarlarkParser::new()
+        .parse(content, filespan, lexer)
+        .map_err(|p| p.to_diagnostic(filespan))
+}
+
+/// Parse a file like [`parse`] but without preparing it for the evaluation, i.e. the
+/// AST is the one of the source code.
+pub(crate) fn parse_unprepared(
+    map: &Arc<Mutex<CodeMap>>,
+    filename: &str,
+    content: &str,
+    dialect: Dialect,
+) -> Result<AstStatement, Diagnostic> {
+    let v = parse_grammar(map, filename, content, Lexer::new(content))?;
+    Statement::validate_mod(&v, dialect)?;
+    Ok(v)
 }
 
 /// Parse a build file (if build is true) or a starlark file provided as a content.
@@ -0,0 +1,121 @@
+// Copyright 2019 The Starlark in Rust Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Helpers to write unit tests of Starlark code and native functions.
+//!
+//! ```
+//! # #[macro_use] extern crate starlark;
+//! # use starlark::testing::setup_test_env;
+//! # use starlark::values::error::NOT_SUPPORTED_ERROR_CODE;
+//! # fn main() {
+//! let env = setup_test_env();
+//! eval_in_env!(env, "x = [1, 2]");
+//! assert_starlark_eq!(env, "len(x)", 2);
+//! assert_starlark_eq!(env, "x + [3]", vec![1, 2, 3]);
+//! assert_starlark_error!(env, "len(1)", NOT_SUPPORTED_ERROR_CODE);
+//! # }
+//! ```
+
+use crate::environment::{Environment, TypeValues};
+use crate::eval::noload;
+use crate::stdlib::global_environment_with_extensions;
+use crate::syntax::dialect::Dialect;
+use crate::values::Value;
+use codemap::CodeMap;
+use codemap_diagnostic::Diagnostic;
+use std::sync::{Arc, Mutex};
+
+/// A new environment for a test, child of the
+/// [global environment](crate::stdlib::global_environment_with_extensions).
+pub fn setup_test_env() -> Environment {
+    global_environment_with_extensions().freeze().child("test")
+}
+
+/// Evaluate `content` in `env` with the [`Bzl`](Dialect::Bzl) dialect, resolving the type
+/// values (e.g. `list.append`) in `env` and its parents.
+pub fn eval_in_env(env: &Environment, content: &str) -> Result<Value, Diagnostic> {
+    noload::eval(
+        &Arc::new(Mutex::new(CodeMap::new())),
+        "<test>",
+        content,
+        Dialect::Bzl,
+        &mut env.clone(),
+        TypeValues::new(env.clone()),
+    )
+}
+
+/// Evaluate a Starlark snippet in an environment and return the value of its last
+/// statement, panicking if the evaluation fails.
+#[macro_export]
+macro_rules! eval_in_env {
+    ($env:expr, $content:expr) => {
+        match $crate::testing::eval_in_env(&$env, $content) {
+            Ok(v) => v,
+            Err(d) => panic!("evaluation of {:?} failed: {}", $content, d.message),
+        }
+    };
+}
+
+/// Assert that a Starlark snippet evaluates to the expected value, which can be anything
+/// convertible into a [`Value`](crate::values::Value).
+#[macro_export]
+macro_rules! assert_starlark_eq {
+    ($env:expr, $content:expr, $expected:expr) => {
+        assert_eq!(
+            $crate::values::Value::from($expected),
+            $crate::eval_in_env!($env, $content),
+            "{}",
+            $content
+        );
+    };
+}
+
+/// Assert that the evaluation of a Starlark snippet fails with the given error code.
+#[macro_export]
+macro_rules! assert_starlark_error {
+    ($env:expr, $content:expr, $code:expr) => {
+        match $crate::testing::eval_in_env(&$env, $content) {
+            Ok(v) => panic!(
+                "evaluation of {:?} returned {} instead of failing with {}",
+                $content,
+                v.to_repr(),
+                $code
+            ),
+            Err(d) => assert_eq!(Some($code), d.code.as_deref(), "{}", d.message),
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::values::error::NOT_SUPPORTED_ERROR_CODE;
+
+    #[test]
+    fn environment_is_kept() {
+        let env = setup_test_env();
+        eval_in_env!(env, "def f(x): return x * 2");
+        assert_starlark_eq!(env, "f(2)", 4);
+        assert_starlark_eq!(env, "f('a')", "aa");
+        // Extensions are available.
+        assert_starlark_eq!(env, "struct(a = 1).a", 1);
+        assert_starlark_error!(env, "f(None)", NOT_SUPPORTED_ERROR_CODE);
+    }
+
+    #[test]
+    #[should_panic(expected = "returned 1 instead of failing")]
+    fn unexpected_success() {
+        assert_starlark_error!(setup_test_env(), "1", NOT_SUPPORTED_ERROR_CODE);
+    }
+}
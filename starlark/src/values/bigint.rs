@@ -0,0 +1,281 @@
+// Copyright 2019 The Starlark in Rust Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Arbitrary precision integers, enabled by the `bigint` feature.
+//!
+//! Integers are still stored as `i64` whenever they fit, a [`BigInt`] is only created when
+//! the result of an arithmetic operation overflows. Both are of type `int`, and since a
+//! `BigInt` value never fits in an `i64` the two representations never compare equal.
+
+use crate::values::error::ValueError;
+use crate::values::frozen::Thaw;
+use crate::values::*;
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_traits::{ToPrimitive, Zero};
+use std::cmp::Ordering;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::iter;
+use std::sync::Arc;
+
+impl TypedValue for BigInt {
+    type Holder = Immutable<BigInt>;
+    const TYPE: &'static str = "int";
+
+    fn python_type_name(&self) -> &'static str {
+        "int"
+    }
+
+    fn values_for_descendant_check_and_freeze<'a>(
+        &'a self,
+    ) -> Box<dyn Iterator<Item = Value> + 'a> {
+        Box::new(iter::empty())
+    }
+
+    fn to_str(&self) -> String {
+        self.to_string()
+    }
+    fn to_repr(&self) -> String {
+        self.to_string()
+    }
+    fn to_json(&self) -> Result<String, ValueError> {
+        Ok(self.to_string())
+    }
+    fn share(&self) -> Result<FrozenValue, ValueError> {
+        Ok(FrozenValue::new(self.clone()))
+    }
+    // Without the `arbitrary_precision` feature of serde_json, a JSON number is at most
+    // an `u64`.
+    #[cfg(feature = "json")]
+    fn to_json_value(&self) -> Result<serde_json::Value, ValueError> {
+        match self.to_u64() {
+            Some(u) => Ok(serde_json::Value::from(u)),
+            None => Err(ValueError::IntegerOverflow),
+        }
+    }
+    fn to_int(&self) -> Result<i64, ValueError> {
+        Err(ValueError::IntegerOverflow)
+    }
+    fn to_bool(&self) -> bool {
+        // Zero is stored as an `i64`.
+        true
+    }
+    fn get_hash(&self) -> Result<u64, ValueError> {
+        let mut s = DefaultHasher::new();
+        self.hash(&mut s);
+        Ok(s.finish())
+    }
+    fn equals(&self, other: &BigInt) -> Result<bool, ValueError> {
+        Ok(self == other)
+    }
+    fn compare(&self, other: &BigInt) -> Result<Ordering, ValueError> {
+        Ok(self.cmp(other))
+    }
+}
+
+/// Convert to an `int` value, stored as an `i64` if it fits.
+impl From<BigInt> for Value {
+    fn from(b: BigInt) -> Value {
+        match b.to_i64() {
+            Some(i) => Value::new(i),
+            None => Value::new(b),
+        }
+    }
+}
+
+/// The value of an `int`, `None` for other types.
+fn to_bigint(v: &Value) -> Option<BigInt> {
+    match v.downcast_ref::<i64>() {
+        Some(i) => Some(BigInt::from(*i)),
+        None => v.downcast_ref::<BigInt>().map(|b| b.clone()),
+    }
+}
+
+/// Parse the digits of an integer in the given radix, without sign, prefix or separator.
+pub(crate) fn parse(digits: &str, radix: u32) -> Option<BigInt> {
+    if digits.is_empty() || !digits.chars().all(|c| c.is_digit(radix)) {
+        return None;
+    }
+    BigInt::parse_bytes(digits.as_bytes(), radix)
+}
+
+/// Retry a failed unary operation on an `int` in arbitrary precision.
+pub(crate) fn promote_unary<F>(result: ValueResult, a: &Value, op: F) -> ValueResult
+where
+    F: FnOnce(BigInt) -> BigInt,
+{
+    match (result, to_bigint(a)) {
+        (Err(..), Some(a)) => Ok(Value::from(op(a))),
+        (result, _) => result,
+    }
+}
+
+/// Retry a failed binary operation between two `int` values in arbitrary precision, i.e.
+/// if either it overflowed or one of the operands is a `BigInt`.
+pub(crate) fn promote<F>(result: ValueResult, a: &Value, b: &Value, op: F) -> ValueResult
+where
+    F: FnOnce(BigInt, BigInt) -> Result<BigInt, ValueError>,
+{
+    match result {
+        Err(e) => match (to_bigint(a), to_bigint(b)) {
+            (Some(a), Some(b)) => op(a, b).map(Value::from),
+            _ => Err(e),
+        },
+        result => result,
+    }
+}
+
+/// Like [`promote`] for comparisons.
+pub(crate) fn promote_compare(
+    result: Result<Ordering, ValueError>,
+    a: &Value,
+    b: &Value,
+) -> Result<Ordering, ValueError> {
+    match result {
+        Err(e) => match (to_bigint(a), to_bigint(b)) {
+            (Some(a), Some(b)) => Ok(a.cmp(&b)),
+            _ => Err(e),
+        },
+        result => result,
+    }
+}
+
+/// The remainder of the floored division, like for `i64`.
+pub(crate) fn percent(a: BigInt, b: BigInt) -> Result<BigInt, ValueError> {
+    if b.is_zero() {
+        return Err(ValueError::DivisionByZero);
+    }
+    Ok(a.mod_floor(&b))
+}
+
+pub(crate) fn floor_div(a: BigInt, b: BigInt) -> Result<BigInt, ValueError> {
+    if b.is_zero() {
+        return Err(ValueError::DivisionByZero);
+    }
+    Ok(a.div_floor(&b))
+}
+
+impl Thaw for BigInt {
+    fn thaw(self: Arc<Self>) -> Value {
+        Value::new_shared(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stdlib::starlark_default;
+
+    fn big(s: &str) -> Value {
+        Value::from(s.parse::<BigInt>().unwrap())
+    }
+
+    #[test]
+    fn test_normalized() {
+        assert_eq!(
+            Some(3),
+            Value::from(BigInt::from(3))
+                .downcast_ref::<i64>()
+                .map(|i| *i)
+        );
+        assert_eq!("int", big("9223372036854775808").get_type());
+        assert!(big("9223372036854775808")
+            .downcast_ref::<BigInt>()
+            .is_some());
+        assert!(big("9223372036854775808").to_int().is_err());
+    }
+
+    #[test]
+    fn test_promote_on_overflow() {
+        let max = Value::new(i64::max_value());
+        let min = Value::new(i64::min_value());
+        assert_eq!(big("9223372036854775808"), max.add(Value::new(1)).unwrap());
+        assert_eq!(big("-9223372036854775809"), min.sub(Value::new(1)).unwrap());
+        assert_eq!(big("9223372036854775808"), min.minus().unwrap());
+        assert_eq!(
+            big("18446744073709551616"),
+            Value::new(1 << 32).mul(Value::new(1 << 32)).unwrap()
+        );
+        assert_eq!(
+            big("9223372036854775808"),
+            min.floor_div(Value::new(-1)).unwrap()
+        );
+        assert_eq!(Value::new(0), min.percent(Value::new(-1)).unwrap());
+    }
+
+    #[test]
+    fn test_bigint_arithmetic() {
+        let b = big("100000000000000000000");
+        assert_eq!(big("100000000000000000001"), b.add(Value::new(1)).unwrap());
+        assert_eq!(
+            big("100000000000000000001"),
+            Value::new(1).add(b.clone()).unwrap()
+        );
+        // Results which fit are stored as `i64` again.
+        assert_eq!(Value::new(0), b.sub(b.clone()).unwrap());
+        assert_eq!(
+            Value::new(-7),
+            b.floor_div(big("-15000000000000000000")).unwrap()
+        );
+        assert_eq!(
+            big("-5000000000000000000"),
+            b.percent(big("-15000000000000000000")).unwrap()
+        );
+        assert_eq!(Value::new(3), Value::new(3).percent(b.clone()).unwrap());
+        assert!(b.floor_div(Value::new(0)).is_err());
+        assert!(b.add(Value::from("a")).is_err());
+        assert_eq!(Ordering::Less, Value::new(1).compare(&b).unwrap());
+        assert_eq!(Ordering::Greater, b.compare(&Value::new(1)).unwrap());
+        assert!(!b.equals(&Value::new(1)).unwrap());
+    }
+
+    #[test]
+    fn test_starlark() {
+        assert!(starlark_default(
+            r#"
+x = 9223372036854775807 + 1
+(str(x) == "9223372036854775808" and type(x) == "int" and x - 1 == 9223372036854775807
+ and x * x // x == x and -x < 0 and {x: 1}[9223372036854775807 + 1] == 1)"#
+        )
+        .unwrap());
+    }
+
+    #[test]
+    fn test_literals() {
+        assert!(starlark_default(
+            r#"
+x = 123456789012345678901234567890
+(str(x) == "123456789012345678901234567890" and x // 100000000000000000000000000000 == 1
+ and 0x10000000000000000 == 18446744073709551615 + 1
+ and -9223372036854775808 == -9223372036854775807 - 1)"#
+        )
+        .unwrap());
+    }
+
+    #[test]
+    fn test_int() {
+        assert!(starlark_default(
+            r#"
+x = 123456789012345678901234567890
+(int("123456789012345678901234567890") == x and int("+123456789012345678901234567890") == x
+ and int("-0x10000000000000000") == -18446744073709551616
+ and int("-8000000000000000", 16) == -9223372036854775807 - 1
+ and int(x) == x)"#
+        )
+        .unwrap());
+        assert!(starlark_default("int('123456789012345678901234567890a')").is_err());
+        assert!(starlark_default("int('1_000000000000000000000')").is_err());
+    }
+}
@@ -30,6 +30,10 @@ impl TypedValue for bool {
     type Holder = Immutable<Self>;
     const TYPE: &'static str = "bool";
 
+    fn python_type_name(&self) -> &'static str {
+        "bool"
+    }
+
     fn new_value(self) -> Value {
         Value(ValueInner::Bool(ValueHolder::new(self)))
     }
@@ -41,6 +45,16 @@ impl TypedValue for bool {
             "False".to_owned()
         }
     }
+    fn to_json(&self) -> Result<String, ValueError> {
+        Ok(self.to_string())
+    }
+    fn share(&self) -> Result<FrozenValue, ValueError> {
+        Ok(FrozenValue::from(*self))
+    }
+    #[cfg(feature = "json")]
+    fn to_json_value(&self) -> Result<serde_json::Value, ValueError> {
+        Ok(serde_json::Value::Bool(*self))
+    }
     fn to_int(&self) -> Result<i64, ValueError> {
         Ok(if *self { 1 } else { 0 })
     }
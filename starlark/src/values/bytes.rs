@@ -0,0 +1,204 @@
+// Copyright 2019 The Starlark in Rust Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Define the bytes type for Starlark, an immutable sequence of bytes.
+
+use crate::values::error::ValueError;
+use crate::values::frozen::Thaw;
+use crate::values::*;
+use std::cmp::Ordering;
+use std::collections::hash_map::DefaultHasher;
+use std::fmt::Write;
+use std::hash::{Hash, Hasher};
+use std::iter;
+use std::sync::Arc;
+
+/// A starlark bytes value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bytes {
+    content: Vec<u8>,
+}
+
+impl Bytes {
+    pub fn new(content: Vec<u8>) -> Bytes {
+        Bytes { content }
+    }
+
+    /// The content of this value.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.content
+    }
+}
+
+impl From<Bytes> for Value {
+    fn from(b: Bytes) -> Value {
+        Value::new(b)
+    }
+}
+
+impl TypedValue for Bytes {
+    type Holder = Immutable<Bytes>;
+    const TYPE: &'static str = "bytes";
+
+    fn python_type_name(&self) -> &'static str {
+        "bytes"
+    }
+
+    fn values_for_descendant_check_and_freeze<'a>(
+        &'a self,
+    ) -> Box<dyn Iterator<Item = Value> + 'a> {
+        Box::new(iter::empty())
+    }
+
+    /// The content decoded as UTF-8, invalid sequences are replaced with U+FFFD.
+    fn to_str(&self) -> String {
+        String::from_utf8_lossy(&self.content).into_owned()
+    }
+
+    fn to_repr(&self) -> String {
+        let mut r = String::with_capacity(self.content.len() + 3);
+        r.push_str("b\"");
+        for &b in &self.content {
+            match b {
+                b'"' => r.push_str("\\\""),
+                b'\\' => r.push_str("\\\\"),
+                b'\n' => r.push_str("\\n"),
+                b'\r' => r.push_str("\\r"),
+                b'\t' => r.push_str("\\t"),
+                0x20..=0x7e => r.push(b as char),
+                _ => write!(r, "\\x{:02x}", b).unwrap(),
+            }
+        }
+        r.push('"');
+        r
+    }
+
+    fn share(&self) -> Result<FrozenValue, ValueError> {
+        Ok(FrozenValue::new(self.clone()))
+    }
+
+    fn to_bool(&self) -> bool {
+        !self.content.is_empty()
+    }
+
+    fn get_hash(&self) -> Result<u64, ValueError> {
+        let mut s = DefaultHasher::new();
+        self.content.hash(&mut s);
+        Ok(s.finish())
+    }
+
+    fn equals(&self, other: &Bytes) -> Result<bool, ValueError> {
+        Ok(self.content == other.content)
+    }
+
+    fn compare(&self, other: &Bytes) -> Result<Ordering, ValueError> {
+        Ok(self.content.cmp(&other.content))
+    }
+
+    fn length(&self) -> Result<i64, ValueError> {
+        Ok(self.content.len() as i64)
+    }
+
+    /// The byte at `index`, as an `int`.
+    fn at(&self, index: Value) -> ValueResult {
+        let i = index.convert_index(self.content.len() as i64)? as usize;
+        Ok(Value::new(i64::from(self.content[i])))
+    }
+
+    fn slice(
+        &self,
+        start: Option<Value>,
+        stop: Option<Value>,
+        stride: Option<Value>,
+    ) -> ValueResult {
+        let (start, stop, stride) =
+            Value::convert_slice_indices(self.content.len() as i64, start, stop, stride)?;
+        let mut content = Vec::new();
+        let mut i = start;
+        while (stride > 0 && i < stop) || (stride < 0 && i > stop) {
+            content.push(self.content[i as usize]);
+            i += stride;
+        }
+        Ok(Value::new(Bytes::new(content)))
+    }
+
+    /// Whether `other`, a byte value or a `bytes`, is in this value.
+    fn is_in(&self, other: &Value) -> Result<bool, ValueError> {
+        if let Some(other) = other.downcast_ref::<Bytes>() {
+            let needle = other.as_bytes();
+            return Ok(needle.is_empty()
+                || self
+                    .content
+                    .windows(needle.len())
+                    .any(|window| window == needle));
+        }
+        match other.downcast_ref::<i64>() {
+            Some(b) if (0..=255).contains(&*b) => Ok(self.content.contains(&(*b as u8))),
+            Some(_) => Err(ValueError::IncorrectParameterType),
+            None => Err(ValueError::OperationNotSupported {
+                op: "in".to_owned(),
+                left: other.get_type().to_owned(),
+                right: Some(Self::TYPE.to_owned()),
+            }),
+        }
+    }
+
+    /// Concatenate `other` to this value.
+    fn add(&self, other: &Bytes) -> Result<Bytes, ValueError> {
+        let mut content = Vec::with_capacity(self.content.len() + other.content.len());
+        content.extend_from_slice(&self.content);
+        content.extend_from_slice(&other.content);
+        Ok(Bytes::new(content))
+    }
+}
+
+impl Thaw for Bytes {
+    fn thaw(self: Arc<Self>) -> Value {
+        Value::new_shared(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repr() {
+        assert_eq!(
+            "b\"ab\\\"\\\\\\n\\x00\\xff\"",
+            Value::new(Bytes::new(b"ab\"\\\n\x00\xff".to_vec())).to_repr()
+        );
+        assert_eq!("b\"\"", Value::new(Bytes::new(Vec::new())).to_repr());
+    }
+
+    #[test]
+    fn test_str() {
+        assert_eq!(
+            "h\u{e9}\u{fffd}",
+            Value::new(Bytes::new(b"h\xc3\xa9\xff".to_vec())).to_str()
+        );
+    }
+
+    #[test]
+    fn test_value_semantics() {
+        let a = Value::new(Bytes::new(b"ab".to_vec()));
+        let b = Value::new(Bytes::new(b"ab".to_vec()));
+        let c = Value::new(Bytes::new(b"b".to_vec()));
+        assert_eq!(2, a.length().unwrap());
+        assert!(a.equals(&b).unwrap());
+        assert_eq!(a.get_hash().unwrap(), b.get_hash().unwrap());
+        assert_eq!(Ordering::Less, a.compare(&c).unwrap());
+        assert!(!Value::new(Bytes::new(Vec::new())).to_bool());
+    }
+}
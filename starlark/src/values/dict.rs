@@ -14,25 +14,28 @@
 
 //! Module define the Starlark type Dictionary
 use crate::values::error::ValueError;
+use crate::values::frozen::Thaw;
 use crate::values::hashed_value::HashedValue;
 use crate::values::iter::TypedIterable;
 use crate::values::none::NoneType;
+use crate::values::OrderedMap;
 use crate::values::*;
-use linked_hash_map::LinkedHashMap; // To preserve insertion order
 use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::hash::Hash;
+use std::iter::FromIterator;
+use std::sync::Arc;
 
 /// The Dictionary type
 #[derive(Default)]
 pub struct Dictionary {
-    content: LinkedHashMap<HashedValue, Value>,
+    content: OrderedMap<HashedValue, Value>,
 }
 
 impl Dictionary {
     pub fn new_typed() -> Dictionary {
         Dictionary {
-            content: LinkedHashMap::new(),
+            content: OrderedMap::new(),
         }
     }
 
@@ -40,7 +43,15 @@ impl Dictionary {
         Value::new(Dictionary::new_typed())
     }
 
-    pub fn get_content(&self) -> &LinkedHashMap<HashedValue, Value> {
+    /// An empty dictionary with room for `capacity` entries without reallocating.
+    pub fn with_capacity(capacity: usize) -> Value {
+        Value::new(Dictionary {
+            content: OrderedMap::with_capacity(capacity),
+        })
+    }
+
+    /// The entries of the dictionary, in insertion order.
+    pub fn get_content(&self) -> &OrderedMap<HashedValue, Value> {
         &self.content
     }
 
@@ -57,7 +68,7 @@ impl Dictionary {
     }
 
     pub fn pop_front(&mut self) -> Option<(HashedValue, Value)> {
-        self.content.pop_front()
+        self.content.shift_remove_index(0)
     }
 
     pub fn items(&self) -> Vec<(Value, Value)> {
@@ -84,7 +95,7 @@ impl Dictionary {
     }
 
     pub fn remove_hashed(&mut self, key: &HashedValue) -> Option<Value> {
-        self.content.remove(key)
+        self.content.shift_remove(key)
     }
 }
 
@@ -95,7 +106,7 @@ impl<T1: Into<Value> + Hash + Eq + Clone, T2: Into<Value> + Eq + Clone> TryFrom<
 
     fn try_from(a: HashMap<T1, T2>) -> Result<Dictionary, ValueError> {
         let mut result = Dictionary {
-            content: LinkedHashMap::new(),
+            content: OrderedMap::with_capacity(a.len()),
         };
         for (k, v) in a.iter() {
             result
@@ -106,14 +117,14 @@ impl<T1: Into<Value> + Hash + Eq + Clone, T2: Into<Value> + Eq + Clone> TryFrom<
     }
 }
 
-impl<T1: Into<Value> + Hash + Eq + Clone, T2: Into<Value> + Eq + Clone>
-    TryFrom<LinkedHashMap<T1, T2>> for Dictionary
+impl<T1: Into<Value> + Hash + Eq + Clone, T2: Into<Value> + Eq + Clone> TryFrom<OrderedMap<T1, T2>>
+    for Dictionary
 {
     type Error = ValueError;
 
-    fn try_from(a: LinkedHashMap<T1, T2>) -> Result<Dictionary, ValueError> {
+    fn try_from(a: OrderedMap<T1, T2>) -> Result<Dictionary, ValueError> {
         let mut result = Dictionary {
-            content: LinkedHashMap::new(),
+            content: OrderedMap::with_capacity(a.len()),
         };
         for (k, v) in a.iter() {
             result
@@ -124,6 +135,24 @@ impl<T1: Into<Value> + Hash + Eq + Clone, T2: Into<Value> + Eq + Clone>
     }
 }
 
+/// Collect `(key, value)` pairs into a dictionary with string keys, e.g.
+/// `vec![("a", 1)].into_iter().collect::<Dictionary>()`. A repeated key replaces the value
+/// of the first one, which keeps its position.
+///
+/// Since a pair is also converted to a tuple value, collecting pairs into a [`Value`] builds
+/// a list of tuples: wrap the dictionary with [`Value::new`] instead.
+impl<K: Into<String>, V: Into<Value>> FromIterator<(K, V)> for Dictionary {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Dictionary {
+        let iter = iter.into_iter();
+        let mut content = OrderedMap::with_capacity(iter.size_hint().0);
+        for (k, v) in iter {
+            // Strings are always hashable.
+            content.insert(HashedValue::new(Value::from(k.into())).unwrap(), v.into());
+        }
+        Dictionary { content }
+    }
+}
+
 /// Define the Dictionary type
 impl TypedValue for Dictionary {
     type Holder = Mutable<Dictionary>;
@@ -153,8 +182,59 @@ impl TypedValue for Dictionary {
                 })
         )
     }
+    fn to_python_repr(&self) -> String {
+        let content: Vec<String> = self
+            .content
+            .iter()
+            .map(|(k, v)| format!("{}: {}", k.get_value().to_python_repr(), v.to_python_repr()))
+            .collect();
+        format!("{{{}}}", content.join(", "))
+    }
+    fn to_json(&self) -> Result<String, ValueError> {
+        let content = self
+            .content
+            .iter()
+            .map(|(k, v)| {
+                let k = k.get_value();
+                if k.get_type() != "string" {
+                    return Err(ValueError::TypeNotX {
+                        object_type: k.get_type().to_owned(),
+                        op: "a valid JSON object key".to_owned(),
+                    });
+                }
+                Ok(format!("{}:{}", k.to_json()?, v.to_json()?))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(format!("{{{}}}", content.join(",")))
+    }
+
+    fn share(&self) -> Result<FrozenValue, ValueError> {
+        let mut content = Vec::with_capacity(self.content.len());
+        for (k, v) in &self.content {
+            content.push((k.get_value().share()?, v.share()?));
+        }
+        Ok(FrozenValue::new(FrozenDictionary(content)))
+    }
+    #[cfg(feature = "json")]
+    fn to_json_value(&self) -> Result<serde_json::Value, ValueError> {
+        let mut object = serde_json::Map::new();
+        for (k, v) in &self.content {
+            let k = k.get_value();
+            if k.get_type() != "string" {
+                return Err(ValueError::TypeNotX {
+                    object_type: k.get_type().to_owned(),
+                    op: "a valid JSON object key".to_owned(),
+                });
+            }
+            object.insert(k.to_str(), v.to_json_value()?);
+        }
+        Ok(serde_json::Value::Object(object))
+    }
 
     const TYPE: &'static str = "dict";
+    fn python_type_name(&self) -> &'static str {
+        "dict"
+    }
     fn to_bool(&self) -> bool {
         !self.content.is_empty()
     }
@@ -212,7 +292,7 @@ impl TypedValue for Dictionary {
 
     fn add(&self, other: &Dictionary) -> Result<Dictionary, ValueError> {
         let mut result = Dictionary {
-            content: LinkedHashMap::new(),
+            content: OrderedMap::new(),
         };
         for (k, v) in &self.content {
             result.content.insert(k.clone(), v.clone());
@@ -240,23 +320,38 @@ impl<T1: Into<Value> + Eq + Hash + Clone, T2: Into<Value> + Eq + Clone> TryFrom<
     }
 }
 
-impl<T1: Into<Value> + Eq + Hash + Clone, T2: Into<Value> + Eq + Clone>
-    TryFrom<LinkedHashMap<T1, T2>> for Value
+impl<T1: Into<Value> + Eq + Hash + Clone, T2: Into<Value> + Eq + Clone> TryFrom<OrderedMap<T1, T2>>
+    for Value
 {
     type Error = ValueError;
 
-    fn try_from(a: LinkedHashMap<T1, T2>) -> Result<Value, ValueError> {
+    fn try_from(a: OrderedMap<T1, T2>) -> Result<Value, ValueError> {
         Ok(Value::new(dict::Dictionary::try_from(a)?))
     }
 }
 
+/// A shared dictionary, see [`Value::share`].
+struct FrozenDictionary(Vec<(FrozenValue, FrozenValue)>);
+
+impl Thaw for FrozenDictionary {
+    fn thaw(self: Arc<Self>) -> Value {
+        let mut content = OrderedMap::with_capacity(self.0.len());
+        for (k, v) in &self.0 {
+            // The key was hashable when it was shared.
+            content.insert(HashedValue::new(k.to_value()).unwrap(), v.to_value());
+        }
+        Value::new(Dictionary { content })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::stdlib::starlark_default;
 
     #[test]
     fn test_mutate_dict() {
-        let mut map = LinkedHashMap::<HashedValue, Value>::new();
+        let mut map = OrderedMap::<HashedValue, Value>::new();
         map.insert(HashedValue::new(Value::from(1)).unwrap(), Value::from(2));
         map.insert(HashedValue::new(Value::from(2)).unwrap(), Value::from(4));
         let mut d = Value::try_from(map).unwrap();
@@ -267,9 +362,47 @@ mod tests {
         assert_eq!("{1: 2, 2: 3, (3, 4): 5}", d.to_str());
     }
 
+    #[test]
+    fn test_from_iter() {
+        let d: Dictionary = vec![("a", 1), ("b", 2), ("a", 3)].into_iter().collect();
+        assert_eq!("{\"a\": 3, \"b\": 2}", Value::new(d).to_repr());
+        let d: Dictionary = (0..3).map(|i| (i.to_string(), vec![i])).collect();
+        assert_eq!(
+            "{\"0\": [0], \"1\": [1], \"2\": [2]}",
+            Value::new(d).to_repr()
+        );
+        let d: Dictionary = std::iter::empty::<(String, Value)>().collect();
+        assert_eq!("{}", Value::new(d).to_repr());
+    }
+
+    #[test]
+    fn test_iter_keys() {
+        let mut map = OrderedMap::<HashedValue, Value>::new();
+        for i in 0..10000 {
+            map.insert(
+                HashedValue::new(Value::from(i.to_string())).unwrap(),
+                Value::from(i),
+            );
+        }
+        let d = Value::try_from(map).unwrap();
+        let keys = d.iter_keys().unwrap();
+        let content = d.downcast_ref::<Dictionary>().unwrap();
+        let mut count = 0;
+        // Keys are yielded in insertion order, and are the dict keys themselves.
+        for (k, (expected, _)) in keys.iter().zip(content.get_content().iter()) {
+            assert_eq!(k.data_ptr(), expected.get_value().data_ptr());
+            assert_eq!(Value::from(count.to_string()), k);
+            count += 1;
+        }
+        assert_eq!(10000, count);
+
+        assert!(Value::from(vec![1, 2]).iter_keys().is_none());
+        assert!(Value::from(1).iter_keys().is_none());
+    }
+
     #[test]
     fn test_is_descendant() {
-        let mut map = LinkedHashMap::<HashedValue, Value>::new();
+        let mut map = OrderedMap::<HashedValue, Value>::new();
         map.insert(HashedValue::new(Value::from(1)).unwrap(), Value::from(2));
         map.insert(HashedValue::new(Value::from(2)).unwrap(), Value::from(4));
         let v1 = Value::try_from(map.clone()).unwrap();
@@ -289,4 +422,21 @@ mod tests {
         assert!(!v1.is_descendant_value(&v2));
         assert!(!v1.is_descendant_value(&v3));
     }
+
+    #[test]
+    fn test_key_hashability() {
+        for key in &["None", "True", "1", "'a'", "(1, 'a', (None,))"] {
+            let content = format!("d = {{{}: 1}}\nd[{}] == 1", key, key);
+            assert!(starlark_default(&content).unwrap(), "{}", key);
+        }
+        for key in &["[]", "{}", "set([1])", "(1, [])", "range(3)", "len"] {
+            let err = starlark_default(&format!("{{{}: 1}}", key)).unwrap_err();
+            assert_eq!(
+                Some(error::NOT_HASHABLE_VALUE_ERROR_CODE.to_owned()),
+                err.code,
+                "{}",
+                key
+            );
+        }
+    }
 }
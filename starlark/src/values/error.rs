@@ -14,11 +14,15 @@
 
 //! Module define the common engine error.
 
+use crate::eval::call_stack::Traceback;
+use crate::eval::source_map::SourceMap;
 use crate::syntax::errors::SyntaxError;
 use crate::values::string::interpolation::StringInterpolationError;
 use crate::values::*;
 use codemap::Span;
 use codemap_diagnostic::{Diagnostic, SpanLabel, SpanStyle};
+use std::error;
+use std::fmt;
 
 // TODO: move that code in some common error code list?
 // CV prefix = Critical Value expression
@@ -40,6 +44,13 @@ pub const INTEGER_OVERFLOW_ERROR_CODE: &str = "CV16";
 pub const INTERPOLATION_UNEXPECTED_EOF_CLOSING_PAREN: &str = "CV17";
 pub const INTERPOLATION_UNEXPECTED_EOF_PERCENT: &str = "CV18";
 pub const INTERPOLATION_UNKNOWN_SPECIFIER: &str = "CV19";
+pub const INTERRUPTED_ERROR_CODE: &str = "CV20";
+pub const CALL_DEPTH_EXCEEDED_ERROR_CODE: &str = "CV21";
+pub const EVALUATION_BUDGET_EXCEEDED_ERROR_CODE: &str = "CV22";
+pub const NO_ATTR_ERROR_CODE: &str = "CV24";
+pub const NOT_FROZEN_ERROR_CODE: &str = "CV25";
+// CR prefix = Critical Runtime
+pub const USER_FAILURE_ERROR_CODE: &str = "CR99";
 
 /// Error that can be returned by function from the `TypedValue` trait,
 #[derive(Clone, Debug)]
@@ -58,10 +69,26 @@ pub enum ValueError {
     IntegerOverflow,
     /// Trying to modify an immutable value.
     CannotMutateImmutableValue,
+    /// Trying to modify a value frozen with the globals of the given module, e.g. a value
+    /// loaded from that module.
+    CannotMutateFrozenValue { module: String },
     /// Trying to apply incorrect parameter type, e.g. for slicing.
     IncorrectParameterType,
     /// Trying to apply incorrect parameter type, e.g. for slicing.
     IncorrectParameterTypeNamed(&'static str),
+    /// A parameter value of type `got` could not be converted to the `expected` type.
+    IncorrectParameterTypeNamedWithGot {
+        param: String,
+        expected: &'static str,
+        got: String,
+    },
+    /// The element at `path` (e.g. `[1]`) of a container value, of type `got`, could not be
+    /// converted to the `expected` type.
+    IncorrectElementType {
+        path: String,
+        expected: &'static str,
+        got: String,
+    },
     /// Trying to access an index outside of the value range,
     IndexOutOfBound(i64),
     /// The value is not hashable but was requested for a hash structure (e.g. dictionary).
@@ -72,6 +99,9 @@ pub enum ValueError {
     Runtime(RuntimeError),
     /// Wrapper around diagnosed errors to be bubbled up.
     DiagnosedError(Diagnostic),
+    /// Wrapper around diagnosed errors raised inside a function, with the functions being
+    /// executed when they were raised.
+    TracedError(Diagnostic, Traceback),
     /// String interpolation errors
     StringInterpolation(StringInterpolationError),
     /// Too many recursion in internal operation
@@ -80,8 +110,33 @@ pub enum ValueError {
     UnsupportedRecursiveDataStructure,
     /// It is not allowed to mutate a structure during iteration.
     MutationDuringIteration,
+    /// The evaluation was cancelled through its interruption flag, with the innermost frames
+    /// of the call stack at that time, see
+    /// [`EvaluationOptions::interrupt_flag`](crate::eval::options::EvaluationOptions::interrupt_flag)
+    /// and [`CancellationToken`](crate::eval::options::CancellationToken).
+    Interrupted { call_stack: String },
+    /// A function call would exceed the maximum call depth of the evaluation, with the
+    /// innermost frames of the call stack, see
+    /// [`EvaluationOptions::max_call_depth`](crate::eval::options::EvaluationOptions::max_call_depth).
+    CallDepthExceeded {
+        max_depth: usize,
+        call_stack: String,
+    },
+    /// The evaluation ran out of its step budget after the given number of steps, see
+    /// [`EvaluationOptions::max_steps`](crate::eval::options::EvaluationOptions::max_steps).
+    EvaluationBudgetExceeded { steps: u64 },
+    /// A value with named fields, e.g. a `struct`, has no field with the given name.
+    NoAttrLeft { attribute: String, left: String },
     /// A type was used which isn't supported with the current feature set. Wraps the type name.
     TypeNotSupported(String),
+    /// A value of the given type is mutable where a frozen value is required, see
+    /// [`Value::check_frozen`].
+    NotFrozenError(String),
+    /// Failure raised by the user code with `fail()`, with the call stack at the time of
+    /// the failure, formatted by [`CallStack::print_with_newline_before`].
+    ///
+    /// [`CallStack::print_with_newline_before`]: crate::eval::call_stack::CallStack::print_with_newline_before
+    UserFailure { message: String, call_stack: String },
 }
 
 /// A simpler error format to return as a ValueError
@@ -92,6 +147,59 @@ pub struct RuntimeError {
     pub label: String,
 }
 
+impl RuntimeError {
+    /// Display this error as raised at `span` with its source line, like
+    /// [`SourceMap::display`] displays an evaluation error.
+    pub fn display_with_source_map(&self, span: Span, source_map: &SourceMap) -> String {
+        source_map.display(&self.clone().to_diagnostic(span))
+    }
+
+    /// Prefix the message of this error with `context`, e.g. the name of the operation
+    /// which failed.
+    pub fn with_context(self, context: impl Into<String>) -> RuntimeError {
+        RuntimeError {
+            message: format!("{}: {}", context.into(), self.message),
+            ..self
+        }
+    }
+}
+
+/// Annotation of the error of a result, e.g. a [`ValueResult`](crate::values::ValueResult),
+/// before propagating it with `?`.
+///
+/// ```
+/// # use starlark::values::error::{ValueError, ValueErrorContext};
+/// # use starlark::values::ValueResult;
+/// fn parse() -> ValueResult {
+///     Err(ValueError::IntegerOverflow)
+/// }
+/// fn load() -> ValueResult {
+///     Ok(parse().map_err_context("while parsing the header")?)
+/// }
+/// assert_eq!(
+///     "while parsing the header: Integer overflow",
+///     load().unwrap_err().to_string()
+/// );
+/// ```
+pub trait ValueErrorContext<T> {
+    /// Wrap the error, if any, with [`ValueError::with_context`].
+    fn map_err_context(self, context: impl Into<String>) -> Result<T, ValueError>;
+
+    /// Prefix the error, if any, with the name of the native function being called, e.g.
+    /// for the result of [`FunctionArg::into_normal`](crate::values::function::FunctionArg::into_normal).
+    fn with_function_context(self, function: &str) -> Result<T, ValueError>;
+}
+
+impl<T> ValueErrorContext<T> for Result<T, ValueError> {
+    fn map_err_context(self, context: impl Into<String>) -> Result<T, ValueError> {
+        self.map_err(|e| e.with_context(context))
+    }
+
+    fn with_function_context(self, function: &str) -> Result<T, ValueError> {
+        self.map_err_context(format!("{}()", function))
+    }
+}
+
 impl<T: Into<RuntimeError>> SyntaxError for T {
     fn to_diagnostic(self, file_span: Span) -> Diagnostic {
         ValueError::Runtime(self.into()).to_diagnostic(file_span)
@@ -110,130 +218,274 @@ impl From<StringInterpolationError> for ValueError {
     }
 }
 
+impl ValueError {
+    /// Prefix the message of this error with `context`, keeping its code and label.
+    ///
+    /// The error is converted to a [`RuntimeError`] with [`RuntimeError::with_context`],
+    /// except for already diagnosed errors, which are annotated in place, and errors
+    /// aborting the evaluation (e.g. [`ValueError::Interrupted`]), which are returned
+    /// unchanged so that they are still recognized as such.
+    pub fn with_context(self, context: impl Into<String>) -> ValueError {
+        match self {
+            ValueError::TooManyRecursionLevel
+            | ValueError::Interrupted { .. }
+            | ValueError::CallDepthExceeded { .. }
+            | ValueError::EvaluationBudgetExceeded { .. } => self,
+            ValueError::DiagnosedError(mut d) => {
+                d.message = format!("{}: {}", context.into(), d.message);
+                ValueError::DiagnosedError(d)
+            }
+            ValueError::TracedError(mut d, traceback) => {
+                d.message = format!("{}: {}", context.into(), d.message);
+                ValueError::TracedError(d, traceback)
+            }
+            e => ValueError::Runtime(
+                RuntimeError {
+                    code: e.code(),
+                    message: e.message(),
+                    label: e.label(),
+                }
+                .with_context(context),
+            ),
+        }
+    }
+
+    /// The long explanation of the error, as put in the diagnostic message.
+    fn message(&self) -> String {
+        match self {
+            ValueError::Runtime(ref e) => e.message.clone(),
+            ValueError::OperationNotSupported {
+                ref op,
+                ref left,
+                right: Some(ref right),
+            } => format!("Cannot {} types {} and {}", op, left, right),
+            ValueError::OperationNotSupported {
+                ref op,
+                ref left,
+                right: None,
+            } => format!("Cannot {} on type {}", op, left),
+            ValueError::TypeNotX {
+                ref object_type,
+                ref op,
+            } => format!("The type '{}' is not {}", object_type, op),
+            ValueError::DivisionByZero => "Cannot divide by zero".to_owned(),
+            ValueError::IntegerOverflow => "Integer overflow".to_owned(),
+            ValueError::CannotMutateImmutableValue => "Immutable".to_owned(),
+            ValueError::CannotMutateFrozenValue { ref module } => format!(
+                "Cannot mutate a frozen value defined in module '{}'",
+                module
+            ),
+            ValueError::IncorrectParameterType => "Type of parameters mismatch".to_owned(),
+            ValueError::IncorrectParameterTypeNamed(name) => {
+                format!("Type of parameters {} mismatch", name)
+            }
+            ValueError::IncorrectParameterTypeNamedWithGot {
+                ref param,
+                expected,
+                ref got,
+            } => format!(
+                "Type of parameters {} mismatch, expected {}, got {}",
+                param, expected, got
+            ),
+            ValueError::IncorrectElementType {
+                ref path,
+                expected,
+                ref got,
+            } => format!(
+                "Type of element {} mismatch, expected {}, got {}",
+                path, expected, got
+            ),
+            ValueError::IndexOutOfBound(ref b) => {
+                format!("Index {} is out of bound", b)
+            }
+            ValueError::NotHashableValue => "Value is not hashable".to_owned(),
+            ValueError::KeyNotFound(ref k) => format!("Key '{}' was not found", k),
+            ValueError::TooManyRecursionLevel => "Too many recursion levels".to_owned(),
+            ValueError::UnsupportedRecursiveDataStructure => concat!(
+                "This operation create a recursive data structure. Recursive data",
+                "structure are disallowed because infinite loops are disallowed in Starlark."
+            )
+            .to_owned(),
+            ValueError::MutationDuringIteration => {
+                "This operation mutate an iterable for an iterator is borrowed.".to_owned()
+            }
+            ValueError::Interrupted { ref call_stack } if call_stack.is_empty() => {
+                "Evaluation was interrupted".to_owned()
+            }
+            ValueError::Interrupted { ref call_stack } => {
+                format!("Evaluation was interrupted, innermost calls:{}", call_stack)
+            }
+            ValueError::CallDepthExceeded {
+                max_depth,
+                ref call_stack,
+            } => format!(
+                "Maximum call depth of {} exceeded, innermost calls:{}",
+                max_depth, call_stack
+            ),
+            ValueError::EvaluationBudgetExceeded { steps } => {
+                format!("Evaluation budget exceeded after {} steps", steps)
+            }
+            ValueError::NoAttrLeft {
+                ref attribute,
+                ref left,
+            } => format!("{} has no field or method '{}'", left, attribute),
+            ValueError::TypeNotSupported(ref t) => format!(
+                "Type `{}` is not supported. Perhaps you need to enable some crate feature?",
+                t
+            ),
+            ValueError::NotFrozenError(ref t) => {
+                format!("Value of type '{}' is mutable, expected a frozen value", t)
+            }
+            ValueError::UserFailure {
+                ref message,
+                ref call_stack,
+            } => format!("fail(): {}{}", message, call_stack),
+            ValueError::DiagnosedError(ref d) | ValueError::TracedError(ref d, _) => {
+                d.message.clone()
+            }
+            ValueError::StringInterpolation(ref e) => e.to_string(),
+        }
+    }
+
+    /// The short explanation of the error, as put in the label of the diagnostic span.
+    fn label(&self) -> String {
+        match self {
+            ValueError::Runtime(ref e) => e.label.clone(),
+            ValueError::OperationNotSupported {
+                ref op,
+                ref left,
+                right: Some(ref right),
+            } => format!("{} not supported for types {} and {}", op, left, right),
+            ValueError::OperationNotSupported {
+                ref op,
+                ref left,
+                right: None,
+            } => format!("{} not supported for type {}", op, left),
+            ValueError::TypeNotX {
+                ref object_type,
+                ref op,
+            } => format!("The type '{}' is not {}", object_type, op),
+            ValueError::DivisionByZero => "Division by zero".to_owned(),
+            ValueError::IntegerOverflow => "Integer overflow".to_owned(),
+            ValueError::CannotMutateImmutableValue => "Immutable".to_owned(),
+            ValueError::CannotMutateFrozenValue { .. } => "Immutable".to_owned(),
+            ValueError::IncorrectParameterType => "Type of parameters mismatch".to_owned(),
+            ValueError::IncorrectParameterTypeNamed(_) => "Type of parameters mismatch".to_owned(),
+            ValueError::IncorrectParameterTypeNamedWithGot {
+                expected, ref got, ..
+            }
+            | ValueError::IncorrectElementType {
+                expected, ref got, ..
+            } => format!("Expected {}, got {}", expected, got),
+            ValueError::IndexOutOfBound(..) => "Index out of bound".to_owned(),
+            ValueError::NotHashableValue => "Value is not hashable".to_owned(),
+            ValueError::KeyNotFound(..) => "Key not found".to_owned(),
+            ValueError::TooManyRecursionLevel => "Too many recursion".to_owned(),
+            ValueError::UnsupportedRecursiveDataStructure => {
+                "Unsupported recursive data structure".to_owned()
+            }
+            ValueError::MutationDuringIteration => {
+                "Cannot mutate an iterable while iterating".to_owned()
+            }
+            ValueError::Interrupted { .. } => "Interrupted".to_owned(),
+            ValueError::CallDepthExceeded { .. } => "Maximum call depth exceeded".to_owned(),
+            ValueError::EvaluationBudgetExceeded { .. } => "Evaluation budget exceeded".to_owned(),
+            ValueError::NoAttrLeft { .. } => "No such attribute".to_owned(),
+            ValueError::TypeNotSupported(ref t) => {
+                format!("Attempt to construct unsupported type ({})", t)
+            }
+            ValueError::NotFrozenError(..) => "Not frozen".to_owned(),
+            ValueError::UserFailure { ref message, .. } => message.clone(),
+            ValueError::DiagnosedError(ref d) | ValueError::TracedError(ref d, _) => d
+                .spans
+                .first()
+                .and_then(|s| s.label.clone())
+                .unwrap_or_default(),
+            ValueError::StringInterpolation(ref e) => e.describe().0,
+        }
+    }
+
+    /// The error code of the diagnostic.
+    fn code(&self) -> &'static str {
+        match self {
+            ValueError::OperationNotSupported { .. } | ValueError::TypeNotSupported(..) => {
+                NOT_SUPPORTED_ERROR_CODE
+            }
+            ValueError::TypeNotX { .. } => NOT_SUPPORTED_ERROR_CODE,
+            ValueError::DivisionByZero => DIVISION_BY_ZERO_ERROR_CODE,
+            ValueError::IntegerOverflow => INTEGER_OVERFLOW_ERROR_CODE,
+            ValueError::CannotMutateImmutableValue | ValueError::CannotMutateFrozenValue { .. } => {
+                IMMUTABLE_ERROR_CODE
+            }
+            ValueError::IncorrectParameterType
+            | ValueError::IncorrectParameterTypeNamed(..)
+            | ValueError::IncorrectParameterTypeNamedWithGot { .. }
+            | ValueError::IncorrectElementType { .. } => INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+            ValueError::IndexOutOfBound(..) => OUT_OF_BOUND_ERROR_CODE,
+            ValueError::NotHashableValue => NOT_HASHABLE_VALUE_ERROR_CODE,
+            ValueError::KeyNotFound(..) => KEY_NOT_FOUND_ERROR_CODE,
+            ValueError::Runtime(e) => e.code,
+            ValueError::TooManyRecursionLevel => TOO_MANY_RECURSION_LEVEL_ERROR_CODE,
+            ValueError::UnsupportedRecursiveDataStructure => {
+                UNSUPPORTED_RECURSIVE_DATA_STRUCTURE_ERROR_CODE
+            }
+            ValueError::MutationDuringIteration => CANNOT_MUTATE_DURING_ITERATION_ERROR_CODE,
+            ValueError::Interrupted { .. } => INTERRUPTED_ERROR_CODE,
+            ValueError::CallDepthExceeded { .. } => CALL_DEPTH_EXCEEDED_ERROR_CODE,
+            ValueError::EvaluationBudgetExceeded { .. } => EVALUATION_BUDGET_EXCEEDED_ERROR_CODE,
+            ValueError::NoAttrLeft { .. } => NO_ATTR_ERROR_CODE,
+            ValueError::NotFrozenError(..) => NOT_FROZEN_ERROR_CODE,
+            ValueError::UserFailure { .. } => USER_FAILURE_ERROR_CODE,
+            ValueError::StringInterpolation(ref e) => e.describe().2,
+            // Not static, handled by the callers
+            ValueError::DiagnosedError(..) | ValueError::TracedError(..) => unreachable!(),
+        }
+    }
+}
+
+impl fmt::Display for ValueError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.message())
+    }
+}
+
+impl error::Error for ValueError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            ValueError::Runtime(ref e) => Some(e),
+            ValueError::StringInterpolation(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl error::Error for RuntimeError {}
+
 impl SyntaxError for ValueError {
     fn to_diagnostic(self, file_span: Span) -> Diagnostic {
         match self {
             ValueError::DiagnosedError(d) => d,
+            ValueError::TracedError(mut d, traceback) => {
+                traceback.add_to(&mut d);
+                d
+            }
             ValueError::StringInterpolation(e) => e.to_diagnostic(file_span),
             _ => {
                 let sl = SpanLabel {
                     span: file_span,
                     style: SpanStyle::Primary,
-                    label: Some(match self {
-                        ValueError::Runtime(ref e) => e.label.clone(),
-                        ValueError::OperationNotSupported {
-                            ref op,
-                            ref left,
-                            right: Some(ref right),
-                        } => format!("{} not supported for types {} and {}", op, left, right),
-                        ValueError::OperationNotSupported {
-                            ref op,
-                            ref left,
-                            right: None,
-                        } => format!("{} not supported for type {}", op, left),
-                        ValueError::TypeNotX {
-                            ref object_type,
-                            ref op,
-                        } => format!("The type '{}' is not {}", object_type, op),
-                        ValueError::DivisionByZero => "Division by zero".to_owned(),
-                        ValueError::IntegerOverflow => "Integer overflow".to_owned(),
-                        ValueError::CannotMutateImmutableValue => "Immutable".to_owned(),
-                        ValueError::IncorrectParameterType => {
-                            "Type of parameters mismatch".to_owned()
-                        }
-                        ValueError::IncorrectParameterTypeNamed(_) => {
-                            "Type of parameters mismatch".to_owned()
-                        }
-                        ValueError::IndexOutOfBound(..) => "Index out of bound".to_owned(),
-                        ValueError::NotHashableValue => "Value is not hashable".to_owned(),
-                        ValueError::KeyNotFound(..) => "Key not found".to_owned(),
-                        ValueError::TooManyRecursionLevel => "Too many recursion".to_owned(),
-                        ValueError::UnsupportedRecursiveDataStructure => {
-                            "Unsupported recursive data structure".to_owned()
-                        }
-                        ValueError::MutationDuringIteration => {
-                            "Cannot mutate an iterable while iterating".to_owned()
-                        }
-                        ValueError::TypeNotSupported(ref t) => {
-                            format!("Attempt to construct unsupported type ({})", t)
-                        }
-                        // handled above
-                        ValueError::DiagnosedError(..) | ValueError::StringInterpolation(..) => unreachable!(),
-                    }),
+                    label: Some(self.label()),
                 };
                 Diagnostic {
                     level: Level::Error,
-                    message: match self {
-                        ValueError::Runtime(ref e) => e.message.clone(),
-                        ValueError::OperationNotSupported {
-                            ref op,
-                            ref left,
-                            right: Some(ref right),
-                        } => format!("Cannot {} types {} and {}", op, left, right),
-                        ValueError::OperationNotSupported {
-                            ref op,
-                            ref left,
-                            right: None,
-                        } => format!("Cannot {} on type {}", op, left),
-                        ValueError::TypeNotX {
-                            ref object_type,
-                            ref op,
-                        } => format!("The type '{}' is not {}", object_type, op),
-                        ValueError::DivisionByZero => "Cannot divide by zero".to_owned(),
-                        ValueError::IntegerOverflow => "Integer overflow".to_owned(),
-                        ValueError::CannotMutateImmutableValue => "Immutable".to_owned(),
-                        ValueError::IncorrectParameterType => {
-                            "Type of parameters mismatch".to_owned()
-                        }
-                        ValueError::IncorrectParameterTypeNamed(name) => {
-                            format!("Type of parameters {} mismatch", name)
-                        }
-                        ValueError::IndexOutOfBound(ref b) => {
-                            format!("Index {} is out of bound", b)
-                        }
-                        ValueError::NotHashableValue => "Value is not hashable".to_owned(),
-                        ValueError::KeyNotFound(ref k) => format!("Key '{}' was not found", k),
-                        ValueError::TooManyRecursionLevel => "Too many recursion levels".to_owned(),
-                        ValueError::UnsupportedRecursiveDataStructure => concat!(
-                            "This operation create a recursive data structure. Recursive data",
-                            "structure are disallowed because infinite loops are disallowed in Starlark."
-                        ).to_owned(),
-                        ValueError::MutationDuringIteration => {
-                            "This operation mutate an iterable for an iterator is borrowed.".to_owned()
-                        }
-                        ValueError::TypeNotSupported(ref t) => {
-                            format!("Type `{}` is not supported. Perhaps you need to enable some crate feature?", t)
-                        }
-                        // handled above
-                        ValueError::DiagnosedError(..) | ValueError::StringInterpolation(..) => unreachable!(),
-                    },
-                    code: Some(
-                        match self {
-                            ValueError::OperationNotSupported { .. } | ValueError::TypeNotSupported(..) => NOT_SUPPORTED_ERROR_CODE,
-                            ValueError::TypeNotX { .. } => NOT_SUPPORTED_ERROR_CODE,
-                            ValueError::DivisionByZero => DIVISION_BY_ZERO_ERROR_CODE,
-                            ValueError::IntegerOverflow => INTEGER_OVERFLOW_ERROR_CODE,
-                            ValueError::CannotMutateImmutableValue => IMMUTABLE_ERROR_CODE,
-                            ValueError::IncorrectParameterType | ValueError::IncorrectParameterTypeNamed(..) => {
-                                INCORRECT_PARAMETER_TYPE_ERROR_CODE
-                            }
-                            ValueError::IndexOutOfBound(..) => OUT_OF_BOUND_ERROR_CODE,
-                            ValueError::NotHashableValue => NOT_HASHABLE_VALUE_ERROR_CODE,
-                            ValueError::KeyNotFound(..) => KEY_NOT_FOUND_ERROR_CODE,
-                            ValueError::Runtime(e) => e.code,
-                            ValueError::TooManyRecursionLevel => {
-                                TOO_MANY_RECURSION_LEVEL_ERROR_CODE
-                            }
-                            ValueError::UnsupportedRecursiveDataStructure => {
-                                UNSUPPORTED_RECURSIVE_DATA_STRUCTURE_ERROR_CODE
-                            }
-                            ValueError::MutationDuringIteration => {
-                                CANNOT_MUTATE_DURING_ITERATION_ERROR_CODE
-                            }
-                            // handled above
-                            ValueError::DiagnosedError(..) | ValueError::StringInterpolation(..) => unreachable!(),
-                        }.to_owned(),
-                    ),
+                    message: self.message(),
+                    code: Some(self.code().to_owned()),
                     spans: vec![sl],
                 }
             }
@@ -250,9 +502,120 @@ impl PartialEq for ValueError {
                 &ValueError::OperationNotSupported { op: ref x, .. },
                 &ValueError::OperationNotSupported { op: ref y, .. },
             ) if x == y => true,
+            (
+                &ValueError::CannotMutateFrozenValue { module: ref x },
+                &ValueError::CannotMutateFrozenValue { module: ref y },
+            ) if x == y => true,
             (&ValueError::IndexOutOfBound(x), &ValueError::IndexOutOfBound(y)) if x == y => true,
             (&ValueError::IntegerOverflow, &ValueError::IntegerOverflow) => true,
+            (&ValueError::NotHashableValue, &ValueError::NotHashableValue) => true,
+            (&ValueError::Interrupted { .. }, &ValueError::Interrupted { .. }) => true,
+            (ValueError::NotFrozenError(x), ValueError::NotFrozenError(y)) if x == y => true,
             _ => false,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::values::function::FunctionArg;
+    use std::error::Error;
+
+    #[test]
+    fn test_display() {
+        assert_eq!(
+            "Cannot divide by zero",
+            ValueError::DivisionByZero.to_string()
+        );
+        assert_eq!(
+            "Index 3 is out of bound",
+            ValueError::IndexOutOfBound(3).to_string()
+        );
+        let e = RuntimeError {
+            code: "XX00",
+            message: "some message".to_owned(),
+            label: "some label".to_owned(),
+        };
+        assert_eq!("some message", e.to_string());
+        assert_eq!("some message", ValueError::Runtime(e).to_string());
+    }
+
+    #[test]
+    fn test_source() {
+        assert!(ValueError::IntegerOverflow.source().is_none());
+        let e = ValueError::Runtime(RuntimeError {
+            code: "XX00",
+            message: "inner".to_owned(),
+            label: "inner".to_owned(),
+        });
+        assert_eq!("inner", e.source().unwrap().to_string());
+        let e = ValueError::StringInterpolation(StringInterpolationError::ValueNotChar);
+        assert_eq!(e.to_string(), e.source().unwrap().to_string());
+    }
+
+    #[test]
+    fn test_into_boxed_error() {
+        fn value_error() -> Result<(), Box<dyn Error>> {
+            Err(ValueError::DivisionByZero)?
+        }
+        fn runtime_error() -> Result<(), Box<dyn Error + Send + Sync>> {
+            Err(RuntimeError {
+                code: "XX00",
+                message: "message".to_owned(),
+                label: "label".to_owned(),
+            })?
+        }
+        assert_eq!(
+            "Cannot divide by zero",
+            value_error().unwrap_err().to_string()
+        );
+        assert_eq!("message", runtime_error().unwrap_err().to_string());
+    }
+
+    #[test]
+    fn test_with_context() {
+        fn divide() -> ValueResult {
+            Err(ValueError::DivisionByZero)
+        }
+        fn average() -> ValueResult {
+            let v = divide().map_err_context("average of []")?;
+            Ok(v)
+        }
+        fn report() -> ValueResult {
+            Ok(average().map_err_context("report")?)
+        }
+
+        let e = report().unwrap_err();
+        assert_eq!(
+            "report: average of []: Cannot divide by zero",
+            e.to_string()
+        );
+        assert_eq!(DIVISION_BY_ZERO_ERROR_CODE, e.code());
+        assert_eq!("Division by zero", e.label());
+
+        // Errors aborting the evaluation are kept as is.
+        let interrupted = ValueError::Interrupted {
+            call_stack: String::new(),
+        };
+        assert_eq!(
+            interrupted,
+            Err::<(), _>(interrupted.clone())
+                .map_err_context("report")
+                .unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_with_function_context() {
+        let e = FunctionArg::Normal(Value::from("a"))
+            .into_normal::<i64>("x")
+            .with_function_context("range")
+            .unwrap_err();
+        assert_eq!(
+            "range(): Type of parameters x mismatch, expected int, got string",
+            e.to_string()
+        );
+        assert_eq!(INCORRECT_PARAMETER_TYPE_ERROR_CODE, e.code());
+    }
+}
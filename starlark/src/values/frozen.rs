@@ -0,0 +1,204 @@
+// Copyright 2019 The Starlark in Rust Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Frozen values which can be sent to other threads, see [`Value::share`].
+
+use crate::environment::frozen::with_thaw_scope;
+use crate::values::none::NoneType;
+use crate::values::Value;
+use std::fmt;
+use std::sync::Arc;
+
+/// The content of a [`FrozenValue`], from which a new [`Value`] is created in each thread.
+///
+/// [`TypedValue::share`](crate::values::TypedValue::share) returns a `FrozenValue` of a type
+/// implementing this trait: the type itself for plain data like `string`, whose values all
+/// point to the shared content (see [`Value::new_shared`]), or a copy of a container with its
+/// content shared as `FrozenValue`s.
+pub trait Thaw: Send + Sync + 'static {
+    /// Create a `Value` equal to the value which was shared, whose content is created with
+    /// [`FrozenValue::to_value`]. It does not need to be frozen, the caller freezes it.
+    fn thaw(self: Arc<Self>) -> Value;
+}
+
+#[derive(Clone)]
+enum FrozenValueInner {
+    None,
+    Bool(bool),
+    Int(i64),
+    Other(Arc<dyn Thaw>),
+}
+
+/// A frozen [`Value`] which is `Send + Sync`, returned by [`Value::share`].
+///
+/// The reference counting and interior mutability of a `Value` are not thread-safe, while
+/// a `FrozenValue` is deeply immutable and cheap to clone. It can thus be computed once,
+/// e.g. for the bindings of a [`FrozenEnvironment`](crate::environment::FrozenEnvironment),
+/// and read by several threads, which convert it back to a frozen `Value` with
+/// [`to_value`](FrozenValue::to_value).
+///
+/// # Examples
+///
+/// ```
+/// # use starlark::values::Value;
+/// let mut value = Value::from(vec![Value::from(1), Value::from("a")]);
+/// value.freeze();
+/// let frozen = value.share().unwrap();
+/// let other_thread = std::thread::spawn(move || frozen.to_value().to_repr());
+/// assert_eq!("[1, \"a\"]", other_thread.join().unwrap());
+/// ```
+#[derive(Clone)]
+pub struct FrozenValue(FrozenValueInner);
+
+impl FrozenValue {
+    /// Create a `FrozenValue` from its content.
+    pub fn new<T: Thaw>(t: T) -> FrozenValue {
+        FrozenValue(FrozenValueInner::Other(Arc::new(t)))
+    }
+
+    /// The frozen `None`.
+    pub fn none() -> FrozenValue {
+        FrozenValue(FrozenValueInner::None)
+    }
+
+    /// Create a new frozen `Value` equal to the value which was shared.
+    ///
+    /// `None`, booleans and integers are stored inline, while strings, bytes and the code
+    /// of functions are shared with the other threads, so converting them does not copy them.
+    /// Containers are rebuilt from their converted elements: a thread which reads a large
+    /// container many times should convert it once.
+    pub fn to_value(&self) -> Value {
+        match &self.0 {
+            FrozenValueInner::None => Value::new(NoneType::None),
+            FrozenValueInner::Bool(b) => Value::new(*b),
+            FrozenValueInner::Int(i) => Value::new(*i),
+            FrozenValueInner::Other(content) => with_thaw_scope(|outermost| {
+                let mut value = content.clone().thaw();
+                // Freezing is recursive, so freeze the outermost value only.
+                if outermost {
+                    value.freeze();
+                }
+                value
+            }),
+        }
+    }
+
+    /// Create a new mutable `Value` equal to the value which was shared, e.g. for a
+    /// default value which each call may mutate.
+    pub(crate) fn to_mutable_value(&self) -> Value {
+        match &self.0 {
+            FrozenValueInner::Other(content) => with_thaw_scope(|_| content.clone().thaw()),
+            _ => self.to_value(),
+        }
+    }
+}
+
+impl From<bool> for FrozenValue {
+    fn from(b: bool) -> FrozenValue {
+        FrozenValue(FrozenValueInner::Bool(b))
+    }
+}
+
+impl From<i64> for FrozenValue {
+    fn from(i: i64) -> FrozenValue {
+        FrozenValue(FrozenValueInner::Int(i))
+    }
+}
+
+impl fmt::Debug for FrozenValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "FrozenValue({})", self.to_value().to_repr())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::values::error::ValueError;
+    use crate::values::tuple::Tuple;
+    use crate::values::{Immutable, TypedValue};
+    use std::iter;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn frozen_value_is_send_sync() {
+        assert_send_sync::<FrozenValue>();
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut value = Value::from(vec![
+            Value::new(Tuple::new(vec![Value::from(1), Value::from(true)])),
+            Value::new(NoneType::None),
+            Value::from("abc"),
+        ]);
+        value.freeze();
+        let frozen = value.share().unwrap();
+        let thawed = std::thread::spawn(move || frozen.to_value().to_repr())
+            .join()
+            .unwrap();
+        assert_eq!(value.to_repr(), thawed);
+
+        // The values are frozen again.
+        let mut thawed = value.share().unwrap().to_value();
+        assert!(thawed.check_frozen().is_ok());
+        assert!(thawed.set_at(Value::from(0), Value::from(1)).is_err());
+    }
+
+    #[test]
+    fn strings_are_not_copied() {
+        let frozen = Value::from("a long enough string").share().unwrap();
+        let (a, b) = (frozen.to_value(), frozen.to_value());
+        assert_eq!(a.data_ptr(), b.data_ptr());
+        assert_eq!(
+            "a long enough string",
+            a.into_typed::<String>().unwrap().as_str()
+        );
+    }
+
+    #[test]
+    fn mutable_values_are_not_shared() {
+        let value = Value::from(vec![Value::from(1)]);
+        match value.share() {
+            Err(ValueError::NotFrozenError(t)) => assert_eq!("list", t),
+            r => panic!("unexpected result {:?}", r),
+        }
+        let tuple = Value::new(Tuple::new(vec![value]));
+        assert!(tuple.share().is_err());
+    }
+
+    #[test]
+    fn unshareable_type() {
+        struct Opaque;
+
+        impl TypedValue for Opaque {
+            type Holder = Immutable<Opaque>;
+            const TYPE: &'static str = "opaque";
+
+            fn values_for_descendant_check_and_freeze<'a>(
+                &'a self,
+            ) -> Box<dyn Iterator<Item = Value> + 'a> {
+                Box::new(iter::empty())
+            }
+        }
+
+        let mut value = Value::from(vec![Value::new(Opaque)]);
+        value.freeze();
+        match value.share() {
+            Err(ValueError::TypeNotX { object_type, .. }) => assert_eq!("opaque", object_type),
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+}
@@ -14,12 +14,22 @@
 
 //! Function as a TypedValue
 use super::*;
+use crate::environment::Environment;
+use crate::eval::noload;
+use crate::eval::options::LineHandler;
 use crate::stdlib::macros::param::TryParamConvertFromValue;
+use crate::syntax::dialect::Dialect;
 use crate::values::error::RuntimeError;
+use crate::values::frozen::Thaw;
 use crate::values::none::NoneType;
+use codemap::CodeMap;
+use codemap_diagnostic::Diagnostic;
+use std::collections::{HashMap, HashSet};
 use std::convert::TryInto;
 use std::iter;
 use std::mem;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Mutex};
 use std::vec;
 
 #[derive(Debug, Clone)]
@@ -28,10 +38,125 @@ pub enum FunctionParameter {
     Normal(String),
     Optional(String),
     WithDefaultValue(String, Value),
+    /// Default value given as a Starlark expression, with its value, copied for each call.
+    WithStarlarkDefault(String, &'static str, FrozenValue),
     ArgsArray(String),
     KWArgsDict(String),
 }
 
+impl FunctionParameter {
+    /// A parameter whose default value is the Starlark expression `literal`, e.g. `"[]"`.
+    ///
+    /// The expression is evaluated once, and each call which does not pass the parameter
+    /// gets a mutable copy of its value, so that a mutable default is not shared between
+    /// calls.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the expression does not evaluate, to report it when the function is
+    /// registered. See [`try_with_starlark_default`](Self::try_with_starlark_default).
+    pub fn with_starlark_default(name: String, literal: &'static str) -> FunctionParameter {
+        match FunctionParameter::try_with_starlark_default(name.clone(), literal) {
+            Ok(parameter) => parameter,
+            Err(e) => panic!(
+                "invalid default value {} for parameter {}: {}",
+                literal, name, e
+            ),
+        }
+    }
+
+    /// Like [`with_starlark_default`](Self::with_starlark_default), but returns the error
+    /// of the evaluation of `literal` instead of panicking.
+    pub fn try_with_starlark_default(
+        name: String,
+        literal: &'static str,
+    ) -> Result<FunctionParameter, ValueError> {
+        let mut value = eval_starlark_default(literal).map_err(ValueError::DiagnosedError)?;
+        value.freeze();
+        Ok(FunctionParameter::WithStarlarkDefault(
+            name,
+            literal,
+            value.share()?,
+        ))
+    }
+
+    /// Share the default value of the parameter, see [`Value::share`].
+    pub(crate) fn share(&self) -> Result<FrozenFunctionParameter, ValueError> {
+        Ok(match self {
+            FunctionParameter::Normal(name) => FrozenFunctionParameter::Normal(name.clone()),
+            FunctionParameter::Optional(name) => FrozenFunctionParameter::Optional(name.clone()),
+            FunctionParameter::WithDefaultValue(name, value) => {
+                FrozenFunctionParameter::WithDefaultValue(name.clone(), value.share()?)
+            }
+            FunctionParameter::WithStarlarkDefault(name, literal, value) => {
+                FrozenFunctionParameter::WithStarlarkDefault(name.clone(), literal, value.clone())
+            }
+            FunctionParameter::ArgsArray(name) => FrozenFunctionParameter::ArgsArray(name.clone()),
+            FunctionParameter::KWArgsDict(name) => {
+                FrozenFunctionParameter::KWArgsDict(name.clone())
+            }
+        })
+    }
+
+    /// The name of the parameter, starting with `$` for a positional-only parameter.
+    fn name(&self) -> &str {
+        match self {
+            FunctionParameter::Normal(name)
+            | FunctionParameter::Optional(name)
+            | FunctionParameter::WithDefaultValue(name, _)
+            | FunctionParameter::WithStarlarkDefault(name, ..)
+            | FunctionParameter::ArgsArray(name)
+            | FunctionParameter::KWArgsDict(name) => name,
+        }
+    }
+}
+
+/// A [`FunctionParameter`] whose default value is shared, see [`Value::share`].
+pub(crate) enum FrozenFunctionParameter {
+    Normal(String),
+    Optional(String),
+    WithDefaultValue(String, FrozenValue),
+    WithStarlarkDefault(String, &'static str, FrozenValue),
+    ArgsArray(String),
+    KWArgsDict(String),
+}
+
+impl FrozenFunctionParameter {
+    pub(crate) fn to_parameter(&self) -> FunctionParameter {
+        match self {
+            FrozenFunctionParameter::Normal(name) => FunctionParameter::Normal(name.clone()),
+            FrozenFunctionParameter::Optional(name) => FunctionParameter::Optional(name.clone()),
+            FrozenFunctionParameter::WithDefaultValue(name, value) => {
+                FunctionParameter::WithDefaultValue(name.clone(), value.to_value())
+            }
+            FrozenFunctionParameter::WithStarlarkDefault(name, literal, value) => {
+                FunctionParameter::WithStarlarkDefault(name.clone(), literal, value.clone())
+            }
+            FrozenFunctionParameter::ArgsArray(name) => FunctionParameter::ArgsArray(name.clone()),
+            FrozenFunctionParameter::KWArgsDict(name) => {
+                FunctionParameter::KWArgsDict(name.clone())
+            }
+        }
+    }
+}
+
+/// Evaluate a default value given as a Starlark expression, in an environment which only
+/// defines `None`, `True` and `False`.
+fn eval_starlark_default(literal: &str) -> Result<Value, Diagnostic> {
+    let env = Environment::new("default");
+    env.set("None", Value::new(NoneType::None)).unwrap();
+    env.set("True", Value::new(true)).unwrap();
+    env.set("False", Value::new(false)).unwrap();
+    noload::eval(
+        &Arc::new(Mutex::new(CodeMap::new())),
+        "<default>",
+        literal,
+        Dialect::Bzl,
+        &mut env.clone(),
+        TypeValues::new(env),
+    )
+}
+
 #[derive(Debug, Clone)]
 #[doc(hidden)]
 pub enum FunctionType {
@@ -44,18 +169,34 @@ pub enum FunctionArg {
     Normal(Value),
     Optional(Option<Value>),
     ArgsArray(Vec<Value>),
-    KWArgsDict(LinkedHashMap<String, Value>),
+    KWArgsDict(OrderedMap<String, Value>),
 }
 
 impl FunctionArg {
+    /// The types of the values of this argument, for the argument trace.
+    fn describe(&self) -> String {
+        let types =
+            |values: &mut dyn Iterator<Item = String>| values.collect::<Vec<_>>().join(", ");
+        match self {
+            FunctionArg::Normal(v) | FunctionArg::Optional(Some(v)) => v.get_type().to_owned(),
+            FunctionArg::Optional(None) => "nothing".to_owned(),
+            FunctionArg::ArgsArray(v) => format!(
+                "({})",
+                types(&mut v.iter().map(|x| x.get_type().to_owned()))
+            ),
+            FunctionArg::KWArgsDict(d) => format!(
+                "{{{}}}",
+                types(&mut d.iter().map(|(k, v)| format!("{}: {}", k, v.get_type())))
+            ),
+        }
+    }
+
     pub fn into_normal<T: TryParamConvertFromValue>(
         self,
         param_name: &'static str,
     ) -> Result<T, ValueError> {
         match self {
-            FunctionArg::Normal(v) => {
-                T::try_from(v).map_err(|_| ValueError::IncorrectParameterTypeNamed(param_name))
-            }
+            FunctionArg::Normal(v) => try_param_from(v, param_name),
             _ => Err(ValueError::IncorrectParameterType),
         }
     }
@@ -65,11 +206,7 @@ impl FunctionArg {
         param_name: &'static str,
     ) -> Result<Option<T>, ValueError> {
         match self {
-            FunctionArg::Optional(Some(v)) => {
-                Ok(Some(T::try_from(v).map_err(|_| {
-                    ValueError::IncorrectParameterTypeNamed(param_name)
-                })?))
-            }
+            FunctionArg::Optional(Some(v)) => Ok(Some(try_param_from(v, param_name)?)),
             FunctionArg::Optional(None) => Ok(None),
             _ => Err(ValueError::IncorrectParameterType),
         }
@@ -80,11 +217,10 @@ impl FunctionArg {
         param_name: &'static str,
     ) -> Result<Vec<T>, ValueError> {
         match self {
-            FunctionArg::ArgsArray(v) => Ok(v
+            FunctionArg::ArgsArray(v) => v
                 .into_iter()
-                .map(T::try_from)
-                .collect::<Result<Vec<T>, _>>()
-                .map_err(|_| ValueError::IncorrectParameterTypeNamed(param_name))?),
+                .map(|v| try_param_from(v, param_name))
+                .collect(),
             _ => Err(ValueError::IncorrectParameterType),
         }
     }
@@ -92,16 +228,12 @@ impl FunctionArg {
     pub fn into_kw_args_dict<T: TryParamConvertFromValue>(
         self,
         param_name: &'static str,
-    ) -> Result<LinkedHashMap<String, T>, ValueError> {
+    ) -> Result<OrderedMap<String, T>, ValueError> {
         match self {
             FunctionArg::KWArgsDict(dict) => Ok({
-                let mut r = LinkedHashMap::new();
+                let mut r = OrderedMap::new();
                 for (k, v) in dict {
-                    r.insert(
-                        k,
-                        T::try_from(v)
-                            .map_err(|_| ValueError::IncorrectParameterTypeNamed(param_name))?,
-                    );
+                    r.insert(k, try_param_from(v, param_name)?);
                 }
                 r
             }),
@@ -110,6 +242,31 @@ impl FunctionArg {
     }
 }
 
+/// Convert the value of the parameter `param_name`, with an error naming the type received
+/// and, when an element of the value failed to convert, the position of that element.
+fn try_param_from<T: TryParamConvertFromValue>(
+    v: Value,
+    param_name: &'static str,
+) -> Result<T, ValueError> {
+    let got = v.get_type();
+    T::try_from(v).map_err(|e| match e {
+        ValueError::IncorrectElementType {
+            path,
+            expected,
+            got,
+        } => ValueError::IncorrectParameterTypeNamedWithGot {
+            param: format!("{}{}", param_name, path),
+            expected,
+            got,
+        },
+        _ => ValueError::IncorrectParameterTypeNamedWithGot {
+            param: param_name.to_owned(),
+            expected: T::EXPECTED_TYPE,
+            got: got.to_owned(),
+        },
+    })
+}
+
 impl From<FunctionArg> for Value {
     fn from(a: FunctionArg) -> Value {
         match a {
@@ -141,6 +298,52 @@ pub struct NativeFunction {
     function: fn(&CallStack, TypeValues, ParameterParser) -> ValueResult,
     signature: Vec<FunctionParameter>,
     function_type: FunctionType,
+    /// Description of the parameters, by name without the `$` of positional-only ones.
+    parameter_docs: Arc<HashMap<String, String>>,
+    deprecation: Option<Deprecation>,
+}
+
+/// The deprecation of a native function, declared in [`starlark_module!`](crate::starlark_module)
+/// with a `#[starlark(deprecated_in = "2.0", note = "use bar() instead")]` attribute.
+///
+/// Calls to a deprecated function are reported to the
+/// [warning handler](crate::eval::options::EvaluationOptions::on_warning) of the
+/// evaluation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Deprecation {
+    /// The version in which the function was deprecated.
+    pub version: String,
+    /// How to upgrade, e.g. `use bar() instead`.
+    pub note: Option<String>,
+}
+
+impl Deprecation {
+    /// The warning reported for a call to the function `name`.
+    fn warning(&self, name: &str) -> String {
+        match self.note {
+            Some(ref note) => format!("{}() was deprecated in {}; {}", name, self.version, note),
+            None => format!("{}() was deprecated in {}", name, self.version),
+        }
+    }
+}
+
+/// Documentation of the parameters of a native function, see
+/// [`TypeValues::get_signature_help`](crate::environment::TypeValues::get_signature_help).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SignatureHelp {
+    /// The function and its parameters, e.g. `split(this, sep, maxsplit)`.
+    pub label: String,
+    pub parameters: Vec<ParameterHelp>,
+}
+
+/// A parameter of a [`SignatureHelp`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParameterHelp {
+    /// The name of the parameter, without the `$` prefix of positional-only parameters.
+    pub name: String,
+    /// The description of the parameter, given by a `@param name: description` line in the
+    /// doc comment of the function.
+    pub documentation: Option<String>,
 }
 
 // Wrapper for method that have been affected the self object
@@ -157,6 +360,8 @@ const ARGS_NOT_ITERABLE_ERROR_CODE: &str = "CF02";
 const KWARGS_NOT_MAPPABLE_ERROR_CODE: &str = "CF03";
 // Not an error: const KWARGS_KEY_IDENT_ERROR_CODE: &str = "CF04";
 const EXTRA_PARAMETER_ERROR_CODE: &str = "CF05";
+const WRONG_NUMBER_OF_PARAMETERS_ERROR_CODE: &str = "CF06";
+const NATIVE_FUNCTION_PANICKED_ERROR_CODE: &str = "CF07";
 
 #[derive(Debug, Clone)]
 pub enum FunctionError {
@@ -169,6 +374,27 @@ pub enum FunctionError {
     ArgsArrayIsNotIterable,
     KWArgsDictIsNotMappable,
     ExtraParameter,
+    UnexpectedKwargs {
+        names: Vec<String>,
+        function_type: FunctionType,
+        signature: Vec<FunctionParameter>,
+    },
+    WrongNumberOfParameters {
+        expected: usize,
+        got: usize,
+        function_type: FunctionType,
+        signature: Vec<FunctionParameter>,
+    },
+    /// A native function panicked, see
+    /// [`Environment::set_catch_native_panics`](crate::environment::Environment::set_catch_native_panics).
+    NativeFunctionPanicked {
+        function_type: FunctionType,
+        /// The message of the panic.
+        message: String,
+        /// The call stack, formatted by
+        /// [`CallStack::print_with_newline_before`](crate::eval::call_stack::CallStack::print_with_newline_before).
+        call_stack: String,
+    },
 }
 
 impl Into<RuntimeError> for FunctionError {
@@ -179,7 +405,13 @@ impl Into<RuntimeError> for FunctionError {
                 FunctionError::ArgsValueIsNotString => WRONG_ARGS_IDENT_ERROR_CODE,
                 FunctionError::ArgsArrayIsNotIterable => ARGS_NOT_ITERABLE_ERROR_CODE,
                 FunctionError::KWArgsDictIsNotMappable => KWARGS_NOT_MAPPABLE_ERROR_CODE,
-                FunctionError::ExtraParameter => EXTRA_PARAMETER_ERROR_CODE,
+                FunctionError::ExtraParameter | FunctionError::UnexpectedKwargs { .. } => {
+                    EXTRA_PARAMETER_ERROR_CODE
+                }
+                FunctionError::WrongNumberOfParameters { .. } => {
+                    WRONG_NUMBER_OF_PARAMETERS_ERROR_CODE
+                }
+                FunctionError::NativeFunctionPanicked { .. } => NATIVE_FUNCTION_PANICKED_ERROR_CODE,
             },
             label: match self {
                 FunctionError::NotEnoughParameter { .. } => {
@@ -188,7 +420,15 @@ impl Into<RuntimeError> for FunctionError {
                 FunctionError::ArgsValueIsNotString => "not an identifier for *args".to_owned(),
                 FunctionError::ArgsArrayIsNotIterable => "*args is not iterable".to_owned(),
                 FunctionError::KWArgsDictIsNotMappable => "**kwargs is not mappable".to_owned(),
-                FunctionError::ExtraParameter => "Extraneous parameter in function call".to_owned(),
+                FunctionError::ExtraParameter | FunctionError::UnexpectedKwargs { .. } => {
+                    "Extraneous parameter in function call".to_owned()
+                }
+                FunctionError::WrongNumberOfParameters { .. } => {
+                    "Wrong number of parameters in function call".to_owned()
+                }
+                FunctionError::NativeFunctionPanicked { .. } => {
+                    "Native function panicked".to_owned()
+                }
             },
             message: match self {
                 FunctionError::NotEnoughParameter {
@@ -212,6 +452,37 @@ impl Into<RuntimeError> for FunctionError {
                 FunctionError::ExtraParameter => {
                     "Extraneous parameter passed to function call".to_owned()
                 }
+                FunctionError::UnexpectedKwargs {
+                    names,
+                    function_type,
+                    signature,
+                } => format!(
+                    "Unexpected keyword argument{} {} for call to {}",
+                    if names.len() == 1 { "" } else { "s" },
+                    names.join(", "),
+                    repr(&function_type, &signature)
+                ),
+                FunctionError::WrongNumberOfParameters {
+                    expected,
+                    got,
+                    function_type,
+                    signature,
+                } => format!(
+                    "Expected {} parameters but got {} for call to {}",
+                    expected,
+                    got,
+                    repr(&function_type, &signature)
+                ),
+                FunctionError::NativeFunctionPanicked {
+                    function_type,
+                    message,
+                    call_stack,
+                } => format!(
+                    "Native function {} panicked: {}, call stack:{}",
+                    function_type.to_str(),
+                    message,
+                    call_stack
+                ),
             },
         }
     }
@@ -223,18 +494,122 @@ impl From<FunctionError> for ValueError {
     }
 }
 
+/// Check that a native function signature can be parsed by `ParameterParser`,
+/// returning a description of the problem otherwise.
+fn check_signature(signature: &[FunctionParameter]) -> Result<(), String> {
+    let mut names = HashSet::new();
+    let mut seen_default = false;
+    let mut seen_args = false;
+    let mut seen_kwargs = false;
+    for p in signature {
+        let name = p.name();
+        if seen_kwargs {
+            return Err(format!("parameter {} after **kwargs", name));
+        }
+        if !names.insert(name.trim_start_matches('$')) {
+            return Err(format!("duplicate parameter {}", name));
+        }
+        match p {
+            // Parameters after *args can only be given by name, so they may lack a default.
+            FunctionParameter::Normal(..) if seen_default && !seen_args => {
+                return Err(format!(
+                    "parameter {} without default after a default",
+                    name
+                ));
+            }
+            FunctionParameter::Normal(..) => {}
+            FunctionParameter::Optional(..)
+            | FunctionParameter::WithDefaultValue(..)
+            | FunctionParameter::WithStarlarkDefault(..) => seen_default = true,
+            FunctionParameter::ArgsArray(..) if seen_args => {
+                return Err(format!("second *args parameter {}", name));
+            }
+            FunctionParameter::ArgsArray(..) => seen_args = true,
+            FunctionParameter::KWArgsDict(..) => seen_kwargs = true,
+        }
+    }
+    Ok(())
+}
+
+/// Extract the descriptions of parameters from the `@param name: description` lines of a
+/// doc comment.
+fn parse_parameter_docs(doc: &[&str]) -> HashMap<String, String> {
+    doc.iter()
+        .filter_map(|line| {
+            let (name, description) = line.trim().strip_prefix("@param ")?.split_once(':')?;
+            Some((name.trim().to_owned(), description.trim().to_owned()))
+        })
+        .collect()
+}
+
 impl NativeFunction {
     pub fn new(
         name: String,
         function: fn(&CallStack, TypeValues, ParameterParser) -> ValueResult,
         signature: Vec<FunctionParameter>,
     ) -> Value {
+        NativeFunction::new_documented(name, function, signature, &[], None)
+    }
+
+    /// Same as [`new`](NativeFunction::new), with the lines of the doc comment of the
+    /// function, in which parameters are described by `@param name: description` lines,
+    /// and its deprecation if any.
+    pub fn new_documented(
+        name: String,
+        function: fn(&CallStack, TypeValues, ParameterParser) -> ValueResult,
+        signature: Vec<FunctionParameter>,
+        doc: &[&str],
+        deprecation: Option<Deprecation>,
+    ) -> Value {
+        let parameter_docs = Arc::new(parse_parameter_docs(doc));
+        // Catch malformed signatures when the function is registered rather than on call.
+        if cfg!(debug_assertions) {
+            if let Err(e) = check_signature(&signature) {
+                panic!("invalid signature for native function {}: {}", name, e);
+            }
+            for documented in parameter_docs.keys() {
+                if !signature
+                    .iter()
+                    .any(|p| p.name().trim_start_matches('$') == documented)
+                {
+                    panic!(
+                        "documented parameter {} is not a parameter of native function {}",
+                        documented, name
+                    );
+                }
+            }
+        }
         Value::new(NativeFunction {
             function,
             signature,
             function_type: FunctionType::Native(name),
+            parameter_docs,
+            deprecation,
         })
     }
+
+    /// The deprecation of this function, if it is deprecated.
+    pub fn deprecation(&self) -> Option<&Deprecation> {
+        self.deprecation.as_ref()
+    }
+
+    /// The parameters of this function and their documentation.
+    pub fn signature_help(&self) -> SignatureHelp {
+        SignatureHelp {
+            label: to_str(&self.function_type, &self.signature),
+            parameters: self
+                .signature
+                .iter()
+                .map(|p| {
+                    let name = p.name().trim_start_matches('$');
+                    ParameterHelp {
+                        name: name.to_owned(),
+                        documentation: self.parameter_docs.get(name).cloned(),
+                    }
+                })
+                .collect(),
+        }
+    }
 }
 
 impl WrappedMethod {
@@ -271,6 +646,9 @@ pub(crate) fn repr(function_type: &FunctionType, signature: &[FunctionParameter]
                 FunctionParameter::WithDefaultValue(ref name, ref value) => {
                     format!("{} = {}", name, value.to_repr())
                 }
+                FunctionParameter::WithStarlarkDefault(ref name, literal, _) => {
+                    format!("{} = {}", name, literal)
+                }
                 FunctionParameter::ArgsArray(ref name) => format!("*{}", name),
                 FunctionParameter::KWArgsDict(ref name) => format!("**{}", name),
             }
@@ -289,6 +667,9 @@ pub(crate) fn to_str(function_type: &FunctionType, signature: &[FunctionParamete
                 FunctionParameter::WithDefaultValue(ref name, ref value) => {
                     format!("{} = {}", name, value.to_repr())
                 }
+                FunctionParameter::WithStarlarkDefault(ref name, literal, _) => {
+                    format!("{} = {}", name, literal)
+                }
                 FunctionParameter::ArgsArray(ref name) => format!("*{}", name),
                 FunctionParameter::KWArgsDict(ref name) => format!("**{}", name),
             }
@@ -304,7 +685,10 @@ pub struct ParameterParser<'a> {
     index: usize,
     function_type: &'a FunctionType,
     positional: vec::IntoIter<Value>,
-    kwargs: LinkedHashMap<String, Value>,
+    kwargs: OrderedMap<String, Value>,
+    // keyword arguments for parameters already given by position
+    duplicated: Vec<String>,
+    trace: Option<LineHandler>,
 }
 
 impl<'a> ParameterParser<'a> {
@@ -312,7 +696,7 @@ impl<'a> ParameterParser<'a> {
         signature: &'a [FunctionParameter],
         function_type: &'a FunctionType,
         positional: Vec<Value>,
-        named: LinkedHashMap<String, Value>,
+        named: OrderedMap<String, Value>,
         args: Option<Value>,
         kwargs_arg: Option<Value>,
     ) -> Result<ParameterParser<'a>, ValueError> {
@@ -328,18 +712,20 @@ impl<'a> ParameterParser<'a> {
         // Collect kwargs
         let mut kwargs = named;
         if let Some(x) = kwargs_arg {
+            if let Ok(len) = x.length() {
+                kwargs.reserve(len as usize);
+            }
             match x.iter() {
                 Ok(y) => {
                     for n in &y {
-                        if n.get_type() == "string" {
-                            let k = n.to_str();
-                            if let Ok(v) = x.at(n) {
-                                kwargs.insert(k, v);
-                            } else {
-                                return Err(FunctionError::KWArgsDictIsNotMappable.into());
-                            }
+                        let k = match n.as_str() {
+                            Some(k) => k.to_owned(),
+                            None => return Err(FunctionError::ArgsValueIsNotString.into()),
+                        };
+                        if let Ok(v) = x.at(n) {
+                            kwargs.insert(k, v);
                         } else {
-                            return Err(FunctionError::ArgsValueIsNotString.into());
+                            return Err(FunctionError::KWArgsDictIsNotMappable.into());
                         }
                     }
                 }
@@ -353,14 +739,26 @@ impl<'a> ParameterParser<'a> {
             function_type,
             positional,
             kwargs,
+            duplicated: Vec::new(),
+            trace: None,
         })
     }
 
+    /// The next positional argument, for parameter `name`, which must then not also be
+    /// given by keyword, even if the remaining keywords go to a `**kwargs` parameter.
+    fn next_positional(&mut self, name: &str) -> Option<Value> {
+        let x = self.positional.next()?;
+        if self.kwargs.shift_remove(name).is_some() {
+            self.duplicated.push(name.to_owned());
+        }
+        Some(x)
+    }
+
     pub fn next_normal(&mut self, name: &str) -> Result<Value, ValueError> {
-        if let Some(x) = self.positional.next() {
+        if let Some(x) = self.next_positional(name) {
             self.index += 1;
             Ok(x)
-        } else if let Some(ref r) = self.kwargs.remove(name) {
+        } else if let Some(ref r) = self.kwargs.shift_remove(name) {
             self.index += 1;
             Ok(r.clone())
         } else {
@@ -375,9 +773,9 @@ impl<'a> ParameterParser<'a> {
 
     pub fn next_optional(&mut self, name: &str) -> Option<Value> {
         self.index += 1;
-        if let Some(x) = self.positional.next() {
+        if let Some(x) = self.next_positional(name) {
             Some(x)
-        } else if let Some(ref r) = self.kwargs.remove(name) {
+        } else if let Some(ref r) = self.kwargs.shift_remove(name) {
             Some(r.clone())
         } else {
             None
@@ -394,19 +792,90 @@ impl<'a> ParameterParser<'a> {
         mem::replace(&mut self.positional, Vec::new().into_iter()).collect()
     }
 
-    pub fn next_kwargs_dict(&mut self) -> LinkedHashMap<String, Value> {
+    pub fn next_kwargs_dict(&mut self) -> OrderedMap<String, Value> {
         self.index += 1;
         mem::replace(&mut self.kwargs, Default::default())
     }
 
     pub fn check_no_more_args(&mut self) -> Result<(), ValueError> {
-        if self.positional.next().is_some() || !self.kwargs.is_empty() {
-            return Err(FunctionError::ExtraParameter.into());
+        let extra_positional = self.positional.next().is_some();
+        if extra_positional || !self.kwargs.is_empty() || !self.duplicated.is_empty() {
+            if let Some(ref trace) = self.trace {
+                trace.emit(&format!(
+                    "{}: extraneous parameters",
+                    self.function_type.to_str()
+                ));
+            }
+            if extra_positional {
+                return Err(FunctionError::ExtraParameter.into());
+            }
+            let mut names = mem::take(&mut self.duplicated);
+            names.extend(self.kwargs.keys().cloned());
+            return Err(FunctionError::UnexpectedKwargs {
+                names,
+                function_type: self.function_type.clone(),
+                signature: self.signature.to_owned(),
+            }
+            .into());
         }
         debug_assert_eq!(self.index, self.signature.len());
         Ok(())
     }
 
+    /// Check that exactly `n` parameters, positional or named, were passed, before
+    /// parsing them.
+    ///
+    /// A shortcut for native functions with a fixed number of parameters, which would
+    /// otherwise only report a missing or extraneous parameter after parsing the others.
+    pub fn expect_exactly(&self, n: usize) -> Result<(), ValueError> {
+        let got = self.positional.len() + self.kwargs.len();
+        if got != n {
+            return Err(FunctionError::WrongNumberOfParameters {
+                expected: n,
+                got,
+                function_type: self.function_type.clone(),
+                signature: self.signature.to_owned(),
+            }
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Parse the next argument with [`next_arg`](ParameterParser::next_arg) and convert it
+    /// with `convert`, reporting the conversion to the argument trace of the evaluation if
+    /// any, see
+    /// [`EvaluationOptions::trace_arguments`](crate::eval::options::EvaluationOptions::trace_arguments).
+    ///
+    /// This function is only called from macros
+    pub fn next_arg_into<T, F>(&mut self, convert: F) -> Result<T, ValueError>
+    where
+        F: FnOnce(FunctionArg) -> Result<T, ValueError>,
+    {
+        let trace = match self.trace {
+            None => return convert(self.next_arg()?),
+            Some(ref trace) => trace.clone(),
+        };
+        let name = format!(
+            "{}({})",
+            self.function_type.to_str(),
+            self.signature[self.index].name().trim_start_matches('$')
+        );
+        let arg = match self.next_arg() {
+            Ok(arg) => arg,
+            Err(e) => {
+                trace.emit(&format!("{}: missing", name));
+                return Err(e);
+            }
+        };
+        let received = arg.describe();
+        let result = convert(arg);
+        match result {
+            Ok(..) => trace.emit(&format!("{}: received {}, accepted", name, received)),
+            Err(ref e) => trace.emit(&format!("{}: received {}, rejected: {}", name, received, e)),
+        }
+        result
+    }
+
     /// This function is only called from macros
     pub fn next_arg(&mut self) -> Result<FunctionArg, ValueError> {
         // Macros call this function exactly once for each signature item.
@@ -420,6 +889,12 @@ impl<'a> ParameterParser<'a> {
             FunctionParameter::WithDefaultValue(ref name, ref value) => {
                 FunctionArg::Normal(self.next_with_default_value(name, value))
             }
+            FunctionParameter::WithStarlarkDefault(ref name, _, ref value) => {
+                FunctionArg::Normal(match self.next_optional(name) {
+                    Some(v) => v,
+                    None => value.to_mutable_value(),
+                })
+            }
             FunctionParameter::ArgsArray(..) => FunctionArg::ArgsArray(self.next_args_array()),
             FunctionParameter::KWArgsDict(..) => FunctionArg::KWArgsDict(self.next_kwargs_dict()),
         })
@@ -443,6 +918,20 @@ impl TypedValue for NativeFunction {
         repr(&self.function_type, &self.signature)
     }
 
+    fn share(&self) -> Result<FrozenValue, ValueError> {
+        Ok(FrozenValue::new(FrozenNativeFunction {
+            function: self.function,
+            signature: self
+                .signature
+                .iter()
+                .map(FunctionParameter::share)
+                .collect::<Result<_, _>>()?,
+            function_type: self.function_type.clone(),
+            parameter_docs: self.parameter_docs.clone(),
+            deprecation: self.deprecation.clone(),
+        }))
+    }
+
     const TYPE: &'static str = "function";
 
     fn call(
@@ -450,11 +939,11 @@ impl TypedValue for NativeFunction {
         call_stack: &CallStack,
         type_values: TypeValues,
         positional: Vec<Value>,
-        named: LinkedHashMap<String, Value>,
+        named: OrderedMap<String, Value>,
         args: Option<Value>,
         kwargs: Option<Value>,
     ) -> ValueResult {
-        let parser = ParameterParser::new(
+        let mut parser = ParameterParser::new(
             &self.signature,
             &self.function_type,
             positional,
@@ -462,8 +951,33 @@ impl TypedValue for NativeFunction {
             args,
             kwargs,
         )?;
+        parser.trace = call_stack.argument_trace().cloned();
+        if let Some(ref deprecation) = self.deprecation {
+            call_stack.warn(&deprecation.warning(&self.function_type.to_str()));
+        }
 
-        (self.function)(call_stack, type_values, parser)
+        if !type_values.catch_native_panics() {
+            return (self.function)(call_stack, type_values, parser);
+        }
+        let function = self.function;
+        panic::catch_unwind(AssertUnwindSafe(|| {
+            function(call_stack, type_values, parser)
+        }))
+        .unwrap_or_else(|payload| {
+            let message = if let Some(s) = payload.downcast_ref::<&str>() {
+                (*s).to_owned()
+            } else if let Some(s) = payload.downcast_ref::<String>() {
+                s.clone()
+            } else {
+                "<non-string payload>".to_owned()
+            };
+            Err(FunctionError::NativeFunctionPanicked {
+                function_type: self.function_type.clone(),
+                message,
+                call_stack: call_stack.print_with_newline_before().to_string(),
+            }
+            .into())
+        })
     }
 }
 
@@ -486,6 +1000,13 @@ impl TypedValue for WrappedMethod {
     fn to_repr(&self) -> String {
         self.method.to_repr()
     }
+
+    fn share(&self) -> Result<FrozenValue, ValueError> {
+        Ok(FrozenValue::new(FrozenWrappedMethod {
+            method: self.method.share()?,
+            self_obj: self.self_obj.share()?,
+        }))
+    }
     const TYPE: &'static str = "function";
 
     fn call(
@@ -493,7 +1014,7 @@ impl TypedValue for WrappedMethod {
         call_stack: &CallStack,
         type_values: TypeValues,
         positional: Vec<Value>,
-        named: LinkedHashMap<String, Value>,
+        named: OrderedMap<String, Value>,
         args: Option<Value>,
         kwargs: Option<Value>,
     ) -> ValueResult {
@@ -507,3 +1028,278 @@ impl TypedValue for WrappedMethod {
             .call(call_stack, type_values, positional, named, args, kwargs)
     }
 }
+
+/// A shared [`NativeFunction`], see [`Value::share`].
+struct FrozenNativeFunction {
+    function: fn(&CallStack, TypeValues, ParameterParser) -> ValueResult,
+    signature: Vec<FrozenFunctionParameter>,
+    function_type: FunctionType,
+    parameter_docs: Arc<HashMap<String, String>>,
+    deprecation: Option<Deprecation>,
+}
+
+impl Thaw for FrozenNativeFunction {
+    fn thaw(self: Arc<Self>) -> Value {
+        Value::new(NativeFunction {
+            function: self.function,
+            signature: self
+                .signature
+                .iter()
+                .map(FrozenFunctionParameter::to_parameter)
+                .collect(),
+            function_type: self.function_type.clone(),
+            parameter_docs: self.parameter_docs.clone(),
+            deprecation: self.deprecation.clone(),
+        })
+    }
+}
+
+/// A shared [`WrappedMethod`], see [`Value::share`].
+struct FrozenWrappedMethod {
+    method: FrozenValue,
+    self_obj: FrozenValue,
+}
+
+impl Thaw for FrozenWrappedMethod {
+    fn thaw(self: Arc<Self>) -> Value {
+        WrappedMethod::new(self.self_obj.to_value(), self.method.to_value())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::Environment;
+    use crate::stdlib::global_environment_with_extensions;
+    use crate::testing::eval_in_env;
+
+    fn normal(name: &str) -> FunctionParameter {
+        FunctionParameter::Normal(name.to_owned())
+    }
+
+    fn default(name: &str) -> FunctionParameter {
+        FunctionParameter::WithDefaultValue(name.to_owned(), Value::from(1))
+    }
+
+    fn nop(_: &CallStack, _: TypeValues, _: ParameterParser) -> ValueResult {
+        Ok(Value::from(NoneType::None))
+    }
+
+    fn boom(_: &CallStack, _: TypeValues, _: ParameterParser) -> ValueResult {
+        panic!("boom")
+    }
+
+    #[test]
+    fn valid_signatures() {
+        assert!(check_signature(&[]).is_ok());
+        assert!(check_signature(&[
+            normal("$a"),
+            normal("b"),
+            FunctionParameter::Optional("c".to_owned()),
+            default("d"),
+            FunctionParameter::ArgsArray("args".to_owned()),
+            normal("e"),
+            FunctionParameter::KWArgsDict("kwargs".to_owned()),
+        ])
+        .is_ok());
+    }
+
+    #[test]
+    fn invalid_signatures() {
+        assert!(check_signature(&[normal("a"), normal("$a")]).is_err());
+        assert!(check_signature(&[default("a"), normal("b")]).is_err());
+        assert!(check_signature(&[
+            FunctionParameter::KWArgsDict("kwargs".to_owned()),
+            normal("a"),
+        ])
+        .is_err());
+        assert!(check_signature(&[
+            FunctionParameter::ArgsArray("a".to_owned()),
+            FunctionParameter::ArgsArray("b".to_owned()),
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn starlark_default() {
+        assert!(FunctionParameter::try_with_starlark_default("a".to_owned(), "[").is_err());
+        assert!(FunctionParameter::try_with_starlark_default("a".to_owned(), "len").is_err());
+
+        let function_type = FunctionType::Native("f".to_owned());
+        let parameter =
+            FunctionParameter::try_with_starlark_default("a".to_owned(), "[[1], {'b': None}]");
+        let signature = [parameter.unwrap()];
+        let default = || {
+            let named = OrderedMap::new();
+            let mut parser =
+                ParameterParser::new(&signature, &function_type, Vec::new(), named, None, None)
+                    .unwrap();
+            match parser.next_arg().unwrap() {
+                FunctionArg::Normal(v) => v,
+                arg => panic!("unexpected argument {:?}", arg),
+            }
+        };
+        // Each call gets a copy whose content is mutable too.
+        let first = default();
+        first
+            .at(Value::new(0))
+            .unwrap()
+            .set_at(Value::new(0), Value::new(2))
+            .unwrap();
+        assert_eq!("[[2], {\"b\": None}]", first.to_repr());
+        assert_eq!("[[1], {\"b\": None}]", default().to_repr());
+    }
+
+    #[test]
+    fn expect_exactly() {
+        let function_type = FunctionType::Native("f".to_owned());
+        let signature = [normal("a"), normal("b")];
+        let parser = |positional: Vec<Value>, named: &[(&str, Value)]| {
+            let named = named
+                .iter()
+                .map(|(k, v)| ((*k).to_owned(), v.clone()))
+                .collect();
+            ParameterParser::new(&signature, &function_type, positional, named, None, None).unwrap()
+        };
+        let message = |r: Result<(), ValueError>| match r {
+            Err(ValueError::Runtime(e)) => {
+                assert_eq!(WRONG_NUMBER_OF_PARAMETERS_ERROR_CODE, e.code);
+                e.message
+            }
+            r => panic!("unexpected result {:?}", r),
+        };
+
+        assert!(parser(vec![Value::new(1), Value::new(2)], &[])
+            .expect_exactly(2)
+            .is_ok());
+        assert!(parser(vec![Value::new(1)], &[("b", Value::new(2))])
+            .expect_exactly(2)
+            .is_ok());
+        assert_eq!(
+            "Expected 2 parameters but got 1 for call to <native function f>(a, b)",
+            message(parser(vec![Value::new(1)], &[]).expect_exactly(2))
+        );
+        assert_eq!(
+            "Expected 2 parameters but got 3 for call to <native function f>(a, b)",
+            message(
+                parser(vec![Value::new(1), Value::new(2)], &[("c", Value::new(3))])
+                    .expect_exactly(2)
+            )
+        );
+    }
+
+    #[test]
+    fn unexpected_kwargs() {
+        let function_type = FunctionType::Native("f".to_owned());
+        let parse =
+            |signature: &[FunctionParameter], positional: Vec<Value>, named: &[(&str, Value)]| {
+                let named = named
+                    .iter()
+                    .map(|(k, v)| ((*k).to_owned(), v.clone()))
+                    .collect();
+                let mut parser =
+                    ParameterParser::new(signature, &function_type, positional, named, None, None)
+                        .unwrap();
+                for _ in signature {
+                    parser.next_arg()?;
+                }
+                parser.check_no_more_args()
+            };
+        let message = |r: Result<(), ValueError>| match r {
+            Err(ValueError::Runtime(e)) => {
+                assert_eq!(EXTRA_PARAMETER_ERROR_CODE, e.code);
+                e.message
+            }
+            r => panic!("unexpected result {:?}", r),
+        };
+
+        let signature = [normal("a"), default("b")];
+        assert!(parse(&signature, vec![Value::new(1)], &[("b", Value::new(2))]).is_ok());
+        assert_eq!(
+            "Unexpected keyword arguments c, d for call to <native function f>(a, b = 1)",
+            message(parse(
+                &signature,
+                vec![Value::new(1)],
+                &[("c", Value::new(3)), ("d", Value::new(4))]
+            ))
+        );
+        assert_eq!(
+            "Unexpected keyword argument a for call to <native function f>(a, b = 1)",
+            message(parse(
+                &signature,
+                vec![Value::new(1)],
+                &[("a", Value::new(2))]
+            ))
+        );
+
+        // Keywords for parameters given by position do not go to **kwargs.
+        let signature = [
+            normal("a"),
+            normal("$b"),
+            FunctionParameter::KWArgsDict("kwargs".to_owned()),
+        ];
+        assert!(parse(
+            &signature,
+            vec![Value::new(1), Value::new(2)],
+            &[("b", Value::new(3)), ("c", Value::new(4))]
+        )
+        .is_ok());
+        assert_eq!(
+            "Unexpected keyword argument a for call to <native function f>(a, $b, **kwargs)",
+            message(parse(
+                &signature,
+                vec![Value::new(1), Value::new(2)],
+                &[("a", Value::new(3)), ("c", Value::new(4))]
+            ))
+        );
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "invalid signature for native function f: duplicate parameter")]
+    fn invalid_signature_panics_on_registration() {
+        NativeFunction::new("f".to_owned(), nop, vec![normal("a"), normal("a")]);
+    }
+
+    #[test]
+    fn native_function_panics() {
+        let env = global_environment_with_extensions();
+        env.set(
+            "boom",
+            NativeFunction::new("boom".to_owned(), boom, Vec::new()),
+        )
+        .unwrap();
+        env.freeze();
+        let env = env.child("test");
+        let d = eval_in_env(&env, "boom()").unwrap_err();
+        assert_eq!(Some(NATIVE_FUNCTION_PANICKED_ERROR_CODE.to_owned()), d.code);
+        assert!(
+            d.message
+                .starts_with("Native function boom panicked: boom, call stack:"),
+            "{}",
+            d.message
+        );
+
+        // The error can be caught by the script and evaluation still works.
+        assert_starlark_eq!(
+            env,
+            "def f():\n  boom()\nassert_fails(f, 'panicked')\nlen([1, 2])",
+            2
+        );
+    }
+
+    #[test]
+    fn native_function_panics_not_caught() {
+        let env = Environment::new("root");
+        env.set(
+            "boom",
+            NativeFunction::new("boom".to_owned(), boom, Vec::new()),
+        )
+        .unwrap();
+        env.set_catch_native_panics(false);
+        env.freeze();
+        let env = env.child("test");
+        let r = panic::catch_unwind(AssertUnwindSafe(|| eval_in_env(&env, "boom()")));
+        assert!(r.is_err());
+    }
+}
@@ -78,11 +78,136 @@ where
     }
 }
 
+/// What arithmetic operations on `int` values do when their result does not fit in an
+/// `i64`, see [`Environment::set_integer_overflow_mode`].
+///
+/// With the `bigint` feature, these results are converted to big integers instead.
+///
+/// [`Environment::set_integer_overflow_mode`]: crate::environment::Environment::set_integer_overflow_mode
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IntegerOverflowMode {
+    /// Fail with [`ValueError::IntegerOverflow`], the default.
+    Error,
+    /// Wrap around the bounds of `i64`, e.g. `9223372036854775807 + 1 == -9223372036854775808`.
+    Wrap,
+    /// Clamp to the bounds of `i64`, e.g. `9223372036854775807 + 1 == 9223372036854775807`.
+    Saturate,
+}
+
+impl IntegerOverflowMode {
+    /// `-value`, handling overflows according to this mode.
+    pub(crate) fn minus(self, value: &Value) -> ValueResult {
+        match (self, value.minus()) {
+            (IntegerOverflowMode::Error, result) => result,
+            (_, Err(ValueError::IntegerOverflow)) if value.downcast_ref::<i64>().is_some() => {
+                let value = value.to_int()?;
+                Ok(Value::new(match self {
+                    IntegerOverflowMode::Wrap => value.wrapping_neg(),
+                    _ => value.saturating_neg(),
+                }))
+            }
+            (_, result) => result,
+        }
+    }
+
+    /// `left + right`, handling overflows according to this mode.
+    pub(crate) fn add(self, left: &Value, right: Value) -> ValueResult {
+        self.bin_op(
+            left,
+            right,
+            Value::add,
+            i64::wrapping_add,
+            i64::saturating_add,
+        )
+    }
+
+    /// `left - right`, handling overflows according to this mode.
+    pub(crate) fn sub(self, left: &Value, right: Value) -> ValueResult {
+        self.bin_op(
+            left,
+            right,
+            Value::sub,
+            i64::wrapping_sub,
+            i64::saturating_sub,
+        )
+    }
+
+    /// `left * right`, handling overflows according to this mode.
+    pub(crate) fn mul(self, left: &Value, right: Value) -> ValueResult {
+        self.bin_op(
+            left,
+            right,
+            Value::mul,
+            i64::wrapping_mul,
+            i64::saturating_mul,
+        )
+    }
+
+    /// `left // right`, handling overflows according to this mode.
+    pub(crate) fn floor_div(self, left: &Value, right: Value) -> ValueResult {
+        // Only `i64::min_value() // -1` overflows, for which the floored and the truncated
+        // divisions are the same.
+        self.bin_op(
+            left,
+            right,
+            Value::floor_div,
+            i64::wrapping_div,
+            i64::saturating_div,
+        )
+    }
+
+    /// `left % right`, handling overflows according to this mode.
+    pub(crate) fn percent(self, left: &Value, right: Value) -> ValueResult {
+        // `i64::min_value() % -1` is `0` rather than an overflow, so a remainder of `int`
+        // values never overflows in any mode.
+        self.bin_op(
+            left,
+            right,
+            Value::percent,
+            i64::wrapping_rem,
+            i64::wrapping_rem,
+        )
+    }
+
+    /// Apply the `checked` operation, recomputing its result with `wrapping` or `saturating`
+    /// if it overflowed.
+    fn bin_op(
+        self,
+        left: &Value,
+        right: Value,
+        checked: fn(&Value, Value) -> ValueResult,
+        wrapping: fn(i64, i64) -> i64,
+        saturating: fn(i64, i64) -> i64,
+    ) -> ValueResult {
+        if self == IntegerOverflowMode::Error {
+            return checked(left, right);
+        }
+        match checked(left, right.clone()) {
+            Err(ValueError::IntegerOverflow)
+                if left.downcast_ref::<i64>().is_some()
+                    && right.downcast_ref::<i64>().is_some() =>
+            {
+                let (left, right) = (left.to_int()?, right.to_int()?);
+                Ok(Value::new(if self == IntegerOverflowMode::Wrap {
+                    wrapping(left, right)
+                } else {
+                    saturating(left, right)
+                }))
+            }
+            result => result,
+        }
+    }
+}
+
 /// Define the int type
 impl TypedValue for i64 {
     type Holder = Immutable<Self>;
     const TYPE: &'static str = "int";
 
+    fn python_type_name(&self) -> &'static str {
+        "int"
+    }
+
     fn new_value(self) -> Value {
         Value(ValueInner::Int(ValueHolder::new(self)))
     }
@@ -96,6 +221,16 @@ impl TypedValue for i64 {
     fn to_repr(&self) -> String {
         format!("{}", self)
     }
+    fn to_json(&self) -> Result<String, ValueError> {
+        Ok(self.to_string())
+    }
+    fn share(&self) -> Result<FrozenValue, ValueError> {
+        Ok(FrozenValue::from(*self))
+    }
+    #[cfg(feature = "json")]
+    fn to_json_value(&self) -> Result<serde_json::Value, ValueError> {
+        Ok(serde_json::Value::from(*self))
+    }
     fn to_int(&self) -> Result<i64, ValueError> {
         Ok(*self)
     }
@@ -186,4 +321,81 @@ mod test {
         assert_eq!(2, int_op!(5.percent(3)));
         assert_eq!(3, int_op!(7.div(2))); // 7.div(2) = 7 / 2 = 3
     }
+
+    // With the `bigint` feature, overflowing results are promoted instead.
+    #[cfg(not(feature = "bigint"))]
+    mod overflow {
+        use super::super::IntegerOverflowMode;
+        use crate::testing::setup_test_env;
+        use crate::values::error::{DIVISION_BY_ZERO_ERROR_CODE, INTEGER_OVERFLOW_ERROR_CODE};
+
+        const OVERFLOWS: &str = "MAX = 9223372036854775807
+MIN = -MAX - 1
+def sqr(x):
+    return x * x
+";
+
+        #[test]
+        fn test_overflow_error() {
+            let env = setup_test_env();
+            assert_eq!(IntegerOverflowMode::Error, env.integer_overflow_mode());
+            eval_in_env!(env, OVERFLOWS);
+            for content in &[
+                "MAX + 1",
+                "MIN - 1",
+                "-MIN",
+                "sqr(MAX)",
+                "MIN // -1",
+                "x = MAX\nx += 1",
+            ] {
+                assert_starlark_error!(env, content, INTEGER_OVERFLOW_ERROR_CODE);
+            }
+            assert_starlark_eq!(env, "MAX - 1 + 1", i64::MAX);
+            assert_starlark_eq!(env, "MIN % -1", 0);
+        }
+
+        #[test]
+        fn test_overflow_wrap() {
+            let env = setup_test_env();
+            env.set_integer_overflow_mode(IntegerOverflowMode::Wrap);
+            eval_in_env!(env, OVERFLOWS);
+            assert_starlark_eq!(env, "MAX + 1", i64::MIN);
+            assert_starlark_eq!(env, "MIN - 1", i64::MAX);
+            assert_starlark_eq!(env, "-MIN", i64::MIN);
+            assert_starlark_eq!(env, "sqr(MAX)", 1);
+            assert_starlark_eq!(env, "MIN // -1", i64::MIN);
+            assert_starlark_eq!(env, "x = MAX\nx += 2\nx", i64::MIN + 1);
+            assert_starlark_eq!(env, "MIN % -1", 0);
+            assert_starlark_eq!(env, "x = MIN\nx %= -1\nx", 0);
+            // Errors other than overflows are kept.
+            assert_starlark_error!(env, "MAX // 0", DIVISION_BY_ZERO_ERROR_CODE);
+        }
+
+        #[test]
+        fn test_overflow_saturate() {
+            let env = setup_test_env();
+            env.set_integer_overflow_mode(IntegerOverflowMode::Saturate);
+            eval_in_env!(env, OVERFLOWS);
+            assert_starlark_eq!(env, "MAX + 1", i64::MAX);
+            assert_starlark_eq!(env, "MIN - 1", i64::MIN);
+            assert_starlark_eq!(env, "-MIN", i64::MAX);
+            assert_starlark_eq!(env, "sqr(MAX)", i64::MAX);
+            assert_starlark_eq!(env, "sqr(MIN + 1) - sqr(MIN)", 0);
+            assert_starlark_eq!(env, "MIN // -1", i64::MAX);
+            assert_starlark_eq!(env, "x = MIN\nx *= 2\nx", i64::MIN);
+            assert_starlark_eq!(env, "MIN % -1", 0);
+            assert_starlark_eq!(env, "MIN % MAX", -1 + i64::MAX);
+        }
+
+        #[test]
+        fn test_overflow_mode_inherited() {
+            let env = setup_test_env();
+            env.set_integer_overflow_mode(IntegerOverflowMode::Saturate);
+            let child = env.child("child");
+            assert_eq!(IntegerOverflowMode::Saturate, child.integer_overflow_mode());
+            child.set_integer_overflow_mode(IntegerOverflowMode::Wrap);
+            assert_eq!(IntegerOverflowMode::Saturate, env.integer_overflow_mode());
+            assert_starlark_eq!(child, "9223372036854775807 + 1", i64::MIN);
+        }
+    }
 }
@@ -15,9 +15,12 @@
 //! Define the list type of Starlark
 use crate::stdlib::list::LIST_REMOVE_ELEMENT_NOT_FOUND_ERROR_CODE;
 use crate::values::error::{RuntimeError, ValueError};
+use crate::values::frozen::Thaw;
 use crate::values::iter::TypedIterable;
 use crate::values::*;
 use std::cmp::Ordering;
+use std::iter::FromIterator;
+use std::sync::Arc;
 
 #[derive(Clone, Default)]
 pub struct List {
@@ -38,6 +41,21 @@ impl<T: Into<Value>> From<Vec<T>> for Value {
     }
 }
 
+impl<T: Into<Value>> FromIterator<T> for List {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> List {
+        List {
+            content: iter.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+/// Collect an iterator into a list value, e.g. `(0..10).collect::<Value>()`.
+impl<T: Into<Value>> FromIterator<T> for Value {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Value {
+        Value::new(iter.into_iter().collect::<List>())
+    }
+}
+
 impl List {
     pub fn new() -> Value {
         Value::new(List {
@@ -134,8 +152,41 @@ impl TypedValue for List {
                 },)
         )
     }
+    fn to_python_repr(&self) -> String {
+        let content: Vec<String> = self.content.iter().map(Value::to_python_repr).collect();
+        format!("[{}]", content.join(", "))
+    }
+    fn to_json(&self) -> Result<String, ValueError> {
+        let content = self
+            .content
+            .iter()
+            .map(Value::to_json)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(format!("[{}]", content.join(",")))
+    }
+
+    fn share(&self) -> Result<FrozenValue, ValueError> {
+        let content = self
+            .content
+            .iter()
+            .map(Value::share)
+            .collect::<Result<_, _>>()?;
+        Ok(FrozenValue::new(FrozenList(content)))
+    }
+    #[cfg(feature = "json")]
+    fn to_json_value(&self) -> Result<serde_json::Value, ValueError> {
+        Ok(serde_json::Value::Array(
+            self.content
+                .iter()
+                .map(Value::to_json_value)
+                .collect::<Result<_, _>>()?,
+        ))
+    }
 
     const TYPE: &'static str = "list";
+    fn python_type_name(&self) -> &'static str {
+        "list"
+    }
     fn to_bool(&self) -> bool {
         !self.content.is_empty()
     }
@@ -301,6 +352,17 @@ impl TypedIterable for List {
     }
 }
 
+/// A shared list, see [`Value::share`].
+struct FrozenList(Vec<FrozenValue>);
+
+impl Thaw for FrozenList {
+    fn thaw(self: Arc<Self>) -> Value {
+        Value::new(List {
+            content: self.0.iter().map(FrozenValue::to_value).collect(),
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -316,6 +378,20 @@ mod tests {
         assert_eq!("[]", Value::from(Vec::<i64>::new()).to_str());
     }
 
+    #[test]
+    fn test_from_iter() {
+        let v: Value = (0..5).collect();
+        assert_eq!("list", v.get_type());
+        assert_eq!(Value::from(vec![0, 1, 2, 3, 4]), v);
+        let v: Value = (1..4).map(|i| Value::from(i * i)).collect();
+        assert_eq!("[1, 4, 9]", v.to_repr());
+        let v: Value = vec!["a", "b"].into_iter().collect();
+        assert_eq!("[\"a\", \"b\"]", v.to_repr());
+        let v: Value = [1i64, 2, 3].iter().copied().collect();
+        assert_eq!(Value::from(vec![1, 2, 3]), v);
+        assert_eq!("[]", (0..0).collect::<Value>().to_repr());
+    }
+
     #[test]
     fn test_mutate_list() {
         let mut v = Value::from(vec![1, 2, 3]);
@@ -340,6 +416,21 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_add_empty_copies() {
+        let x = Value::from(vec![1, 2]);
+        for mut r in vec![
+            Value::from(Vec::<i64>::new()).add(x.clone()).unwrap(),
+            x.add(Value::from(Vec::<i64>::new())).unwrap(),
+        ] {
+            assert_eq!(r, x);
+            r.set_at(Value::from(0), Value::from(3)).unwrap();
+            assert_eq!(x.to_str(), "[1, 2]");
+        }
+        // [1] + (2,) is an error
+        assert!(Value::from(vec![1]).add(Value::from((2,))).is_err());
+    }
+
     #[test]
     fn test_value_alias() {
         let v1 = Value::from(vec![1, 2, 3]);
@@ -349,6 +440,58 @@ mod tests {
         assert_eq!(v1.to_str(), "[1, 2, 4]");
     }
 
+    #[test]
+    fn test_to_json_cyclic() {
+        // a = [1, [a]]
+        let a = Value::from(vec![1]);
+        let b = Value::from(vec![a.clone()]);
+        a.downcast_mut::<List>().unwrap().unwrap().content.push(b);
+        match a.to_json() {
+            Err(ValueError::UnsupportedRecursiveDataStructure) => {}
+            r => panic!("unexpected result {:?}", r),
+        }
+        // The values visited by the failed encoding are no longer in progress.
+        assert_eq!(
+            "[1,[2]]",
+            Value::from(vec![Value::from(1), Value::from(vec![2])])
+                .to_json()
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_equals_cyclic() {
+        fn push(list: &Value, item: &Value) {
+            list.downcast_mut::<List>()
+                .unwrap()
+                .unwrap()
+                .content
+                .push(item.clone());
+        }
+
+        // a = [1, b], b = [2, a]
+        let a = Value::from(vec![1]);
+        let b = Value::from(vec![2]);
+        push(&a, &b);
+        push(&b, &a);
+        // c = [1, d], d = [2, c]
+        let c = Value::from(vec![1]);
+        let d = Value::from(vec![2]);
+        push(&c, &d);
+        push(&d, &c);
+        // e = [1, f], f = [3, e]
+        let e = Value::from(vec![1]);
+        let f = Value::from(vec![3]);
+        push(&e, &f);
+        push(&f, &e);
+
+        assert!(a.equals(&a).unwrap());
+        assert!(a.equals(&c).unwrap());
+        assert!(b.equals(&d).unwrap());
+        assert!(!a.equals(&b).unwrap());
+        assert!(!a.equals(&e).unwrap());
+    }
+
     #[test]
     fn test_is_descendant() {
         let v1 = Value::from(vec![1, 2, 3]);
@@ -366,4 +509,25 @@ mod tests {
         assert!(!v1.is_descendant_value(&v2));
         assert!(!v1.is_descendant_value(&v3));
     }
+
+    #[test]
+    fn test_collected_capacity() {
+        use crate::testing::setup_test_env;
+
+        for content in &[
+            "list(range(1000))",
+            "[x for x in range(1000)]",
+            "[x for x in list(range(1000))]",
+        ] {
+            let v = eval_in_env!(setup_test_env(), content);
+            assert_eq!(Ok(1000), v.length());
+            // The size of the iterable is known in advance, no space is wasted.
+            assert_eq!(
+                1000,
+                v.downcast_ref::<List>().unwrap().content.capacity(),
+                "{}",
+                content
+            );
+        }
+    }
 }
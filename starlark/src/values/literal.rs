@@ -0,0 +1,240 @@
+// Copyright 2019 The Starlark in Rust Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Conversion of values to Starlark source code.
+
+use crate::environment::{Environment, TypeValues};
+use crate::eval::noload;
+use crate::stdlib::global_environment_with_extensions;
+use crate::syntax::dialect::Dialect;
+use crate::values::Value;
+use codemap::CodeMap;
+use std::error;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+/// Error returned by [`Value::to_starlark_literal`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum LiteralError {
+    /// Values of that type, e.g. functions, have no literal.
+    NotRepresentable(String),
+    /// The representation of the value does not evaluate back to an equal value, e.g.
+    /// because it contains a value of a native type whose representation is not valid
+    /// Starlark.
+    NotRoundTrippable(String),
+}
+
+impl fmt::Display for LiteralError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LiteralError::NotRepresentable(t) => {
+                write!(f, "Values of type '{}' have no Starlark literal", t)
+            }
+            LiteralError::NotRoundTrippable(repr) => {
+                write!(f, "{} does not evaluate back to an equal value", repr)
+            }
+        }
+    }
+}
+
+impl error::Error for LiteralError {}
+
+thread_local! {
+    /// Environment in which the literals are evaluated back, which provides the `set`,
+    /// `struct` and `range` functions.
+    static LITERAL_ENV: Environment = {
+        let env = global_environment_with_extensions();
+        env.freeze();
+        env
+    };
+}
+
+/// Append the literal of `value` to `literal`, failing on the first value without one.
+fn write_literal(value: &Value, literal: &mut String) -> Result<(), LiteralError> {
+    let not_representable = |_| LiteralError::NotRepresentable(value.get_type().to_owned());
+    let write_items = |literal: &mut String, items: Vec<Value>| -> Result<(), LiteralError> {
+        for (i, item) in items.iter().enumerate() {
+            if i != 0 {
+                literal.push_str(", ");
+            }
+            write_literal(item, literal)?;
+        }
+        Ok(())
+    };
+    match value.get_type() {
+        "function" => return Err(LiteralError::NotRepresentable("function".to_owned())),
+        "list" => {
+            literal.push('[');
+            write_items(
+                literal,
+                value.iter().map_err(not_representable)?.iter().collect(),
+            )?;
+            literal.push(']');
+        }
+        "tuple" => {
+            let items: Vec<Value> = value.iter().map_err(not_representable)?.iter().collect();
+            let single = items.len() == 1;
+            literal.push('(');
+            write_items(literal, items)?;
+            literal.push_str(if single { ",)" } else { ")" });
+        }
+        "set" => {
+            literal.push_str("set([");
+            write_items(
+                literal,
+                value.iter().map_err(not_representable)?.iter().collect(),
+            )?;
+            literal.push_str("])");
+        }
+        "dict" => {
+            literal.push('{');
+            let keys: Vec<Value> = value.iter().map_err(not_representable)?.iter().collect();
+            for (i, key) in keys.iter().enumerate() {
+                if i != 0 {
+                    literal.push_str(", ");
+                }
+                write_literal(key, literal)?;
+                literal.push_str(": ");
+                write_literal(&value.at(key.clone()).map_err(not_representable)?, literal)?;
+            }
+            literal.push('}');
+        }
+        "struct" => {
+            literal.push_str("struct(");
+            for (i, field) in value
+                .dir_attr()
+                .map_err(not_representable)?
+                .iter()
+                .enumerate()
+            {
+                if i != 0 {
+                    literal.push_str(", ");
+                }
+                literal.push_str(field);
+                literal.push_str(" = ");
+                write_literal(&value.get_attr(field).map_err(not_representable)?, literal)?;
+            }
+            literal.push(')');
+        }
+        _ => literal.push_str(&value.to_python_repr()),
+    }
+    Ok(())
+}
+
+impl Value {
+    /// A Starlark expression which evaluates to a value equal to this one.
+    ///
+    /// Unlike [`to_repr`](Value::to_repr), the result is guaranteed to be valid Starlark:
+    /// it is checked by evaluating it in the
+    /// [global environment with extensions](crate::stdlib::global_environment_with_extensions),
+    /// so values of a type without a literal there, e.g. a native type, are reported as
+    /// [`NotRoundTrippable`](LiteralError::NotRoundTrippable). Sets are written as
+    /// `set([...])`, since `{}` is the empty dict and set literals may be disabled.
+    ///
+    /// ```
+    /// # use starlark::values::Value;
+    /// assert_eq!(
+    ///     "(\"a\\n\", 1)",
+    ///     Value::from(("a\n", 1)).to_starlark_literal().unwrap()
+    /// );
+    /// ```
+    pub fn to_starlark_literal(&self) -> Result<String, LiteralError> {
+        let mut literal = String::new();
+        write_literal(self, &mut literal)?;
+        let evaluated = LITERAL_ENV.with(|env| {
+            noload::eval(
+                &Arc::new(Mutex::new(CodeMap::new())),
+                "<literal>",
+                &literal,
+                Dialect::Bzl,
+                &mut env.child("literal"),
+                TypeValues::new(env.clone()),
+            )
+        });
+        match evaluated {
+            Ok(ref v) if v.get_type() == self.get_type() && v.equals(self) == Ok(true) => {
+                Ok(literal)
+            }
+            _ => Err(LiteralError::NotRoundTrippable(literal)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::setup_test_env;
+
+    fn eval(content: &str) -> Value {
+        eval_in_env!(setup_test_env(), content)
+    }
+
+    fn to_literal(content: &str) -> Result<String, LiteralError> {
+        eval(content).to_starlark_literal()
+    }
+
+    #[test]
+    fn round_trip() {
+        for content in &[
+            "None",
+            "True",
+            "-12",
+            "9223372036854775807",
+            r#""quote \" backslash \\ tab \t newline \n nul \x00 del \x7f 世界""#,
+            "'it\\'s'",
+            "[]",
+            "[1, [2, 'x'], ()]",
+            "(1,)",
+            "(None, (True, 'x'))",
+            "{}",
+            "{1: 'a', 'b': [2], (3, 4): {}}",
+            "{1, 'a', (2, 3)}",
+            "set()",
+            "[set([1]), {'a': set()}]",
+            "range(1, 10, 2)",
+            "struct(a = 1, b = struct(c = ['d']))",
+        ] {
+            let value = eval(content);
+            let literal = value.to_starlark_literal().unwrap();
+            assert!(eval(&literal).equals(&value).unwrap(), "{}", literal);
+        }
+    }
+
+    #[test]
+    fn not_representable() {
+        assert_eq!(
+            Err(LiteralError::NotRepresentable("function".to_owned())),
+            to_literal("len")
+        );
+        assert_eq!(
+            Err(LiteralError::NotRepresentable("function".to_owned())),
+            to_literal("def f():\n  pass\nf")
+        );
+        for content in &["[1, len]", "{'a': (len,)}", "struct(f = len)"] {
+            assert_eq!(
+                Err(LiteralError::NotRepresentable("function".to_owned())),
+                to_literal(content)
+            );
+        }
+    }
+
+    #[test]
+    fn set_literal() {
+        assert_eq!(Ok("set([])".to_owned()), to_literal("set()"));
+        assert_eq!(
+            Ok("set([1, (2,)])".to_owned()),
+            to_literal("set([1, (2,)])")
+        );
+    }
+}
@@ -84,19 +84,25 @@
 use crate::environment::TypeValues;
 use crate::eval::call_stack;
 use crate::eval::call_stack::CallStack;
-use crate::values::error::ValueError;
+use crate::values::error::{ValueError, ValueErrorContext};
+pub use crate::values::frozen::FrozenValue;
 use crate::values::iter::{FakeTypedIterable, RefIterable, TypedIterable};
 use codemap_diagnostic::Level;
-use linked_hash_map::LinkedHashMap;
 use std::any::Any;
 use std::cell::{RefCell, RefMut};
 use std::cmp::Ordering;
+use std::collections::HashSet;
 use std::fmt;
+use std::hash::{Hash, Hasher};
 use std::marker;
 use std::rc::Rc;
+use std::sync::Arc;
 
 /// ValueInner wraps the actual value or a memory pointer
 /// to the actual value for complex type.
+///
+/// `None`, booleans and integers are stored inline, so creating them never allocates. The
+/// empty string and the one-character ASCII strings are interned instead.
 #[derive(Clone)]
 enum ValueInner {
     None(ValueHolder<NoneType>),
@@ -113,12 +119,39 @@ pub struct Value(ValueInner);
 
 pub type ValueResult = Result<Value, ValueError>;
 
+/// Map iterating in insertion order, used for dictionaries and named arguments.
+///
+/// Updating the value of a key keeps its position, removing a key must use
+/// `shift_remove` to keep the order of the other keys.
+pub type OrderedMap<K, V> = indexmap::IndexMap<K, V>;
+
 impl Value {
     /// Create a new `Value` from a static value.
     pub fn new<T: TypedValue>(t: T) -> Value {
         t.new_value()
     }
 
+    /// Create a new frozen `Value`, e.g. for the constants of an environment.
+    ///
+    /// Values of immutable types like `int` or `string` are always frozen, but an immutable
+    /// container like a `tuple` can still hold mutable values, which are frozen too.
+    pub fn new_frozen<T: TypedValue>(t: T) -> Value {
+        let mut value = t.new_value();
+        value.freeze();
+        value
+    }
+
+    /// Create a new `Value` from content shared with other threads, without copying it.
+    ///
+    /// This is how [`Thaw::thaw`](crate::values::frozen::Thaw::thaw) converts the content
+    /// of an immutable type like `string` back to a `Value`.
+    pub fn new_shared<T: TypedValue<Holder = Immutable<T>>>(t: Arc<T>) -> Value {
+        Value(ValueInner::Other(Rc::new(ValueHolder::<T> {
+            mutability: ImmutableMutability,
+            content: ImmutableCell::shared(t),
+        })))
+    }
+
     fn value_holder(&self) -> &(dyn ValueHolderDyn + 'static) {
         match &self.0 {
             ValueInner::None(n) => n,
@@ -160,6 +193,17 @@ impl Value {
     pub fn function_id(&self) -> FunctionId {
         self.value_holder().function_id()
     }
+
+    /// Number of `Value`s sharing the same underlying object, to debug leaks.
+    ///
+    /// `None`, booleans and integers are not shared, so this is always 1 for them.
+    #[cfg(feature = "debug-refcount")]
+    pub fn strong_count(&self) -> usize {
+        match &self.0 {
+            ValueInner::Other(rc) => Rc::strong_count(rc),
+            _ => 1,
+        }
+    }
 }
 
 pub trait Mutability {
@@ -245,6 +289,62 @@ impl PartialEq for DataPtr {
     }
 }
 
+impl Hash for DataPtr {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state)
+    }
+}
+
+thread_local!(
+    static EQUALS_IN_PROGRESS: RefCell<HashSet<(DataPtr, DataPtr)>> = RefCell::new(HashSet::new())
+);
+
+/// Pair of mutable values being compared for equality, removed from the in-progress
+/// set on drop.
+struct EqualsGuard(DataPtr, DataPtr);
+
+impl EqualsGuard {
+    /// Mark the pair as being compared, returns `None` if it was already in progress,
+    /// i.e. if we are comparing the descendants of a self-referential value.
+    fn enter(a: DataPtr, b: DataPtr) -> Option<EqualsGuard> {
+        if EQUALS_IN_PROGRESS.with(|s| s.borrow_mut().insert((a, b))) {
+            Some(EqualsGuard(a, b))
+        } else {
+            None
+        }
+    }
+}
+
+impl Drop for EqualsGuard {
+    fn drop(&mut self) {
+        EQUALS_IN_PROGRESS.with(|s| s.borrow_mut().remove(&(self.0, self.1)));
+    }
+}
+
+thread_local!(static VISIT_IN_PROGRESS: RefCell<HashSet<DataPtr>> = RefCell::new(HashSet::new()));
+
+/// Mutable value being visited by a recursive conversion (e.g. to JSON), removed from the
+/// in-progress set on drop.
+pub(crate) struct VisitGuard(DataPtr);
+
+impl VisitGuard {
+    /// Mark the value as being visited, fails if it already was, i.e. if the value
+    /// contains itself.
+    pub(crate) fn enter(p: DataPtr) -> Result<VisitGuard, ValueError> {
+        if VISIT_IN_PROGRESS.with(|s| s.borrow_mut().insert(p)) {
+            Ok(VisitGuard(p))
+        } else {
+            Err(ValueError::UnsupportedRecursiveDataStructure)
+        }
+    }
+}
+
+impl Drop for VisitGuard {
+    fn drop(&mut self) {
+        VISIT_IN_PROGRESS.with(|s| s.borrow_mut().remove(&self.0));
+    }
+}
+
 /// Function identity to detect recursion.
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct FunctionId(pub DataPtr);
@@ -265,6 +365,10 @@ impl<T: TypedValue> ValueHolderDyn for ValueHolder<T> {
         RefOrRef::map(self.content.borrow(), |v| v as &dyn Any)
     }
 
+    fn into_any(self: Rc<Self>) -> Rc<dyn Any> {
+        self
+    }
+
     fn data_ptr(&self) -> DataPtr {
         DataPtr::from(self.content.as_ptr())
     }
@@ -278,6 +382,7 @@ impl<T: TypedValue> ValueHolderDyn for ValueHolder<T> {
 
     /// Freezes the current value.
     fn freeze(&self) {
+        let was_mutable = self.mutability.get() == IterableMutability::Mutable;
         self.mutability.freeze();
         for mut value in self
             .content
@@ -286,6 +391,9 @@ impl<T: TypedValue> ValueHolderDyn for ValueHolder<T> {
         {
             value.freeze();
         }
+        if was_mutable {
+            self.content.borrow_mut().freeze();
+        }
     }
 
     /// Freezes the current value for iterating over.
@@ -298,6 +406,28 @@ impl<T: TypedValue> ValueHolderDyn for ValueHolder<T> {
         self.mutability.unfreeze_for_iteration();
     }
 
+    fn check_frozen(&self) -> Result<(), ValueError> {
+        if self.mutability.get() != IterableMutability::Immutable {
+            return Err(ValueError::NotFrozenError(T::TYPE.to_owned()));
+        }
+        for value in self
+            .content
+            .borrow()
+            .values_for_descendant_check_and_freeze()
+        {
+            value.check_frozen()?;
+        }
+        Ok(())
+    }
+
+    fn share(&self) -> Result<FrozenValue, ValueError> {
+        if self.mutability.get() != IterableMutability::Immutable {
+            return Err(ValueError::NotFrozenError(T::TYPE.to_owned()));
+        }
+        let _stack_depth_guard = call_stack::try_inc()?;
+        self.content.borrow().share()
+    }
+
     fn to_str(&self) -> String {
         self.content.borrow().to_str()
     }
@@ -306,6 +436,35 @@ impl<T: TypedValue> ValueHolderDyn for ValueHolder<T> {
         self.content.borrow().to_repr()
     }
 
+    fn to_python_repr(&self) -> String {
+        self.content.borrow().to_python_repr()
+    }
+
+    fn python_type_name(&self) -> &'static str {
+        self.content.borrow().python_type_name()
+    }
+
+    fn to_json(&self) -> Result<String, ValueError> {
+        let _stack_depth_guard = call_stack::try_inc()?;
+        let _visit_guard = if T::Holder::MUTABLE {
+            Some(VisitGuard::enter(self.data_ptr())?)
+        } else {
+            None
+        };
+        self.content.borrow().to_json()
+    }
+
+    #[cfg(feature = "json")]
+    fn to_json_value(&self) -> Result<serde_json::Value, ValueError> {
+        let _stack_depth_guard = call_stack::try_inc()?;
+        let _visit_guard = if T::Holder::MUTABLE {
+            Some(VisitGuard::enter(self.data_ptr())?)
+        } else {
+            None
+        };
+        self.content.borrow().to_json_value()
+    }
+
     fn get_type(&self) -> &'static str {
         T::TYPE
     }
@@ -340,6 +499,18 @@ impl<T: TypedValue> ValueHolderDyn for ValueHolder<T> {
     fn equals(&self, other: &Value) -> Result<bool, ValueError> {
         let _stack_depth_guard = call_stack::try_inc()?;
 
+        // Only a mutable value can be made to contain itself, so cycles are detected on
+        // those only. A pair already being compared is assumed equal: if it is not, the
+        // outer comparison of that pair will find the difference.
+        let _equals_guard = if T::Holder::MUTABLE {
+            match EqualsGuard::enter(self.data_ptr(), other.data_ptr()) {
+                Some(guard) => Some(guard),
+                None => return Ok(true),
+            }
+        } else {
+            None
+        };
+
         match other.downcast_ref::<T>() {
             Some(other) => self.content.borrow().equals(&*other),
             None => Ok(false),
@@ -364,7 +535,7 @@ impl<T: TypedValue> ValueHolderDyn for ValueHolder<T> {
         call_stack: &CallStack,
         type_values: TypeValues,
         positional: Vec<Value>,
-        named: LinkedHashMap<String, Value>,
+        named: OrderedMap<String, Value>,
         args: Option<Value>,
         kwargs: Option<Value>,
     ) -> ValueResult {
@@ -457,7 +628,11 @@ impl<T: TypedValue> ValueHolderDyn for ValueHolder<T> {
     fn add(&self, other: Value) -> Result<Value, ValueError> {
         match other.downcast_ref::<T>() {
             Some(other) => self.content.borrow().add(&*other).map(Value::new),
-            None => Err(ValueError::IncorrectParameterType),
+            None => Err(ValueError::OperationNotSupported {
+                op: "+".to_owned(),
+                left: T::TYPE.to_owned(),
+                right: Some(other.get_type().to_owned()),
+            }),
         }
     }
 
@@ -500,6 +675,9 @@ trait ValueHolderDyn {
     /// This function panics if value is mutably borrowed.
     fn as_any_ref(&self) -> RefOrRef<'_, dyn Any>;
 
+    /// The `ValueHolder` itself, to take ownership of its content.
+    fn into_any(self: Rc<Self>) -> Rc<dyn Any>;
+
     /// Pointer to `TypedValue` object, used for cycle checks.
     fn data_ptr(&self) -> DataPtr;
 
@@ -512,10 +690,23 @@ trait ValueHolderDyn {
 
     fn unfreeze_for_iteration(&self);
 
+    fn check_frozen(&self) -> Result<(), ValueError>;
+
+    fn share(&self) -> Result<FrozenValue, ValueError>;
+
     fn to_str(&self) -> String;
 
     fn to_repr(&self) -> String;
 
+    fn to_python_repr(&self) -> String;
+
+    fn python_type_name(&self) -> &'static str;
+
+    fn to_json(&self) -> Result<String, ValueError>;
+
+    #[cfg(feature = "json")]
+    fn to_json_value(&self) -> Result<serde_json::Value, ValueError>;
+
     fn get_type(&self) -> &'static str;
 
     fn to_bool(&self) -> bool;
@@ -534,7 +725,7 @@ trait ValueHolderDyn {
         call_stack: &CallStack,
         type_values: TypeValues,
         positional: Vec<Value>,
-        named: LinkedHashMap<String, Value>,
+        named: OrderedMap<String, Value>,
         args: Option<Value>,
         kwargs: Option<Value>,
     ) -> ValueResult;
@@ -622,6 +813,17 @@ pub trait TypedValue: Sized + 'static {
     fn values_for_descendant_check_and_freeze<'a>(&'a self)
         -> Box<dyn Iterator<Item = Value> + 'a>;
 
+    /// Called when a value of a mutable type is frozen, e.g. to switch to a more compact
+    /// representation.
+    ///
+    /// The hook runs once, when the value goes from mutable to frozen, after the values
+    /// returned by
+    /// [`values_for_descendant_check_and_freeze`](TypedValue::values_for_descendant_check_and_freeze)
+    /// were frozen. A value reachable from its own descendants is already marked frozen
+    /// when their hooks run. It is never called for immutable types, which are frozen from
+    /// their creation.
+    fn freeze(&mut self) {}
+
     /// Return function id to detect recursion.
     ///
     /// If `None` is returned, object id is used.
@@ -639,6 +841,63 @@ pub trait TypedValue: Sized + 'static {
         format!("<{}>", Self::TYPE)
     }
 
+    /// Return a Python expression evaluating to an equivalent Python object, for embedders
+    /// passing values to a Python interpreter.
+    ///
+    /// Starlark and Python literals mostly agree, so this defaults to [`to_repr`], which is
+    /// also what is produced for types without a Python counterpart. Containers should
+    /// convert their content with [`Value::to_python_repr`].
+    ///
+    /// [`to_repr`]: TypedValue::to_repr
+    fn to_python_repr(&self) -> String {
+        self.to_repr()
+    }
+
+    /// Return the name of the Python type of the object [`to_python_repr`] evaluates to,
+    /// e.g. `"str"` for Starlark strings.
+    ///
+    /// Defaults to `"object"`, for types without a Python counterpart.
+    ///
+    /// [`to_python_repr`]: TypedValue::to_python_repr
+    fn python_type_name(&self) -> &'static str {
+        "object"
+    }
+
+    /// Return the JSON encoding of self, as returned by the `json.encode()` function.
+    ///
+    /// Containers should encode their content with [`Value::to_json`], which rejects
+    /// values containing themselves.
+    fn to_json(&self) -> Result<String, ValueError> {
+        Err(ValueError::TypeNotX {
+            object_type: Self::TYPE.to_owned(),
+            op: "JSON serializable".to_owned(),
+        })
+    }
+
+    /// Return the JSON value of self, for types which can be encoded by
+    /// [`to_json`](TypedValue::to_json).
+    ///
+    /// Containers should convert their content with [`Value::to_json_value`], which
+    /// rejects values containing themselves.
+    #[cfg(feature = "json")]
+    fn to_json_value(&self) -> Result<serde_json::Value, ValueError> {
+        Err(ValueError::TypeNotX {
+            object_type: Self::TYPE.to_owned(),
+            op: "JSON serializable".to_owned(),
+        })
+    }
+
+    /// Return a copy of self which can be sent to other threads, see [`Value::share`].
+    ///
+    /// Containers should share their content with [`Value::share`], which checks that it
+    /// is frozen.
+    fn share(&self) -> Result<FrozenValue, ValueError> {
+        Err(ValueError::TypeNotX {
+            object_type: Self::TYPE.to_owned(),
+            op: "shareable between threads".to_owned(),
+        })
+    }
+
     /// Convert self to a Boolean truth value, as returned by the bool() function.
     fn to_bool(&self) -> bool {
         // Return `true` by default, because this is default when implementing
@@ -657,7 +916,16 @@ pub trait TypedValue: Sized + 'static {
     }
 
     /// Return a hash code for self, as returned by the hash() function, or
-    /// OperationNotSupported if there is no hash for this value (e.g. list).
+    /// NotHashableValue if there is no hash for this value (e.g. list).
+    ///
+    /// Of the builtin types, `NoneType`, `bool`, `int` and `string` are hashable, as well as
+    /// `tuple` when all its elements are hashable. `list`, `dict`, `set`, `range` and
+    /// `function` are not.
+    ///
+    /// The hash must not change when the value is frozen, since a value hashed as a dict
+    /// key is frozen along with the dict. Types which can be mutated should thus not be
+    /// hashable, even once frozen, and immutable containers should only hash their
+    /// content.
     fn get_hash(&self) -> Result<u64, ValueError> {
         Err(ValueError::NotHashableValue)
     }
@@ -714,7 +982,7 @@ pub trait TypedValue: Sized + 'static {
         _call_stack: &CallStack,
         _type_values: TypeValues,
         _positional: Vec<Value>,
-        _named: LinkedHashMap<String, Value>,
+        _named: OrderedMap<String, Value>,
         _args: Option<Value>,
         _kwargs: Option<Value>,
     ) -> ValueResult {
@@ -845,7 +1113,8 @@ pub trait TypedValue: Sized + 'static {
     }
 
     /// Set the attribute named `attribute` of the current value to `new_value` (e.g.
-    /// `a.attribute = new_value`).
+    /// `a.attribute = new_value`). An augmented assignment like `a.attribute += 1` reads
+    /// the attribute with [`get_attr`](TypedValue::get_attr) then sets it.
     ///
     /// This method should error with `ValueError::CannotMutateImmutableValue` if the value was
     /// frozen or the attribute is immutable (but with `ValueError::OperationNotSupported`
@@ -869,9 +1138,14 @@ pub trait TypedValue: Sized + 'static {
         })
     }
 
-    /// Tell wether `other` is in the current value, if it is a container.
+    /// Tell wether `other` is in the current value, if it is a container, as used by the `in`
+    /// and `not in` operators.
+    ///
+    /// The default implementation compares `other` with each value of [`iter`], and returns an
+    /// error `ValueError::OperationNotSupported` if the value is not iterable. Containers
+    /// can override it with a faster lookup, e.g. by key for a dictionary.
     ///
-    /// Non container value should return an error `ValueError::OperationNotSupported`.
+    /// [`iter`]: TypedValue::iter
     ///
     /// # Examples
     ///
@@ -886,11 +1160,22 @@ pub trait TypedValue: Sized + 'static {
     /// assert!(!Value::from("abc").is_in(&Value::from("z")).unwrap().to_bool());
     /// ```
     fn is_in(&self, other: &Value) -> Result<bool, ValueError> {
-        Err(ValueError::OperationNotSupported {
-            op: "in".to_owned(),
-            left: other.get_type().to_owned(),
-            right: Some(Self::TYPE.to_owned()),
-        })
+        let iterable = match self.iter() {
+            Ok(iterable) => iterable,
+            Err(..) => {
+                return Err(ValueError::OperationNotSupported {
+                    op: "in".to_owned(),
+                    left: other.get_type().to_owned(),
+                    right: Some(Self::TYPE.to_owned()),
+                })
+            }
+        };
+        for x in iterable.to_iter() {
+            if x.equals(other)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
     }
 
     /// Apply the `+` unary operator to the current value.
@@ -1077,12 +1362,53 @@ impl Value {
     pub fn unfreeze_for_iteration(&mut self) {
         self.value_holder().unfreeze_for_iteration()
     }
+    /// Check that this value and the values it contains are frozen, e.g. before keeping
+    /// it beyond the evaluation which created it.
+    ///
+    /// Values of immutable types are frozen, but an immutable container like a `tuple`
+    /// passes the check only if its content does too. The error is a
+    /// [`ValueError::NotFrozenError`] with the type of the first value found mutable.
+    pub fn check_frozen(&self) -> Result<(), ValueError> {
+        self.value_holder().check_frozen()
+    }
+    /// Return a copy of this frozen value which is `Send + Sync`, e.g. to evaluate scripts
+    /// on several threads against the same constants. See [`FrozenValue`].
+    ///
+    /// The error is a [`ValueError::NotFrozenError`] if the value or one of the values it
+    /// contains is mutable, or a [`ValueError::TypeNotX`] if its type cannot be shared.
+    pub fn share(&self) -> Result<FrozenValue, ValueError> {
+        self.value_holder().share()
+    }
     pub fn to_str(&self) -> String {
         self.value_holder().to_str()
     }
     pub fn to_repr(&self) -> String {
         self.value_holder().to_repr()
     }
+    pub fn to_python_repr(&self) -> String {
+        self.value_holder().to_python_repr()
+    }
+    pub fn python_type_name(&self) -> &'static str {
+        self.value_holder().python_type_name()
+    }
+    pub fn to_json(&self) -> Result<String, ValueError> {
+        self.value_holder().to_json()
+    }
+    /// The JSON value of this value, e.g. to be further processed by `serde_json` aware
+    /// code rather than encoded right away, with the keys of objects sorted.
+    #[cfg(feature = "json")]
+    pub fn to_json_value(&self) -> Result<serde_json::Value, ValueError> {
+        self.value_holder().to_json_value()
+    }
+    /// The UTF-8 encoding of this value as a `bytes` value: of the string content for
+    /// strings, of [`to_repr`](Value::to_repr) for other types.
+    pub fn to_bytes_value(&self) -> Result<Value, ValueError> {
+        let s = match self.as_str() {
+            Some(s) => s.to_owned(),
+            None => self.to_repr(),
+        };
+        Ok(Value::new(bytes::Bytes::new(s.into_bytes())))
+    }
     pub fn get_type(&self) -> &'static str {
         self.value_holder().get_type()
     }
@@ -1099,7 +1425,14 @@ impl Value {
         self.value_holder().equals(other)
     }
     pub fn compare(&self, other: &Value) -> Result<Ordering, ValueError> {
-        self.value_holder().compare(other)
+        #[cfg(feature = "bigint")]
+        {
+            bigint::promote_compare(self.value_holder().compare(other), self, other)
+        }
+        #[cfg(not(feature = "bigint"))]
+        {
+            self.value_holder().compare(other)
+        }
     }
 
     pub fn is_descendant(&self, other: DataPtr) -> bool {
@@ -1111,7 +1444,7 @@ impl Value {
         call_stack: &CallStack,
         type_values: TypeValues,
         positional: Vec<Value>,
-        named: LinkedHashMap<String, Value>,
+        named: OrderedMap<String, Value>,
         args: Option<Value>,
         kwargs: Option<Value>,
     ) -> ValueResult {
@@ -1119,6 +1452,44 @@ impl Value {
             .call(call_stack, type_values, positional, named, args, kwargs)
     }
 
+    /// Call the method `name` of this value, i.e. evaluate `self.name(*positional, **named)`.
+    ///
+    /// The method is looked up like the `.` operator does: first in the type values, then
+    /// amongst the attributes of the value, e.g. a function stored in a `struct`. A missing
+    /// method fails with [`ValueError::NoAttrLeft`].
+    pub fn call_method(
+        &self,
+        name: &str,
+        type_values: &TypeValues,
+        call_stack: &CallStack,
+        positional: Vec<Value>,
+        named: OrderedMap<String, Value>,
+    ) -> ValueResult {
+        let (function, positional) = match type_values.get_type_value(self, name) {
+            // Insert self so the method see the object it is acting on
+            Some(method) if method.get_type() == "function" => (
+                method,
+                std::iter::once(self.clone()).chain(positional).collect(),
+            ),
+            Some(attr) => (attr, positional),
+            None if self.has_attr(name).unwrap_or(false) => (self.get_attr(name)?, positional),
+            None => {
+                return Err(ValueError::NoAttrLeft {
+                    attribute: name.to_owned(),
+                    left: self.get_type().to_owned(),
+                })
+            }
+        };
+        function.call(
+            call_stack,
+            type_values.clone(),
+            positional,
+            named,
+            None,
+            None,
+        )
+    }
+
     pub fn at(&self, index: Value) -> ValueResult {
         self.value_holder().at(index)
     }
@@ -1137,6 +1508,24 @@ impl Value {
     pub fn iter<'a>(&'a self) -> Result<RefIterable<'a>, ValueError> {
         self.value_holder().iter()
     }
+
+    /// Iterate over this value if it is iterable (e.g. a list, a dict or a range), or return
+    /// `None` otherwise, like for strings and numbers.
+    ///
+    /// The value is borrowed for as long as the returned iterable lives, like with
+    /// [`iter`](Value::iter).
+    pub fn iter_values(&self) -> Option<RefIterable<'_>> {
+        self.iter().ok()
+    }
+
+    /// Iterate over the keys of a dict, or return `None` if this value is not a dict.
+    ///
+    /// Unlike `dict.keys()`, no list is allocated: the dict is borrowed for as long as
+    /// the returned iterable lives, so it must not be mutated meanwhile.
+    pub fn iter_keys(&self) -> Option<RefIterable<'_>> {
+        self.downcast_ref::<dict::Dictionary>()
+            .map(|d| RefIterable::new(RefOrRef::map(d, |d| d as &dyn TypedIterable)))
+    }
     pub fn length(&self) -> Result<i64, ValueError> {
         self.value_holder().length()
     }
@@ -1146,6 +1535,25 @@ impl Value {
     pub fn has_attr(&self, attribute: &str) -> Result<bool, ValueError> {
         self.value_holder().has_attr(attribute)
     }
+    /// Get the value of the path of attributes `path`, e.g. `["a", "b"]` for `self.a.b`,
+    /// resolving the methods of types in `type_values` like the `.` operator does.
+    ///
+    /// The error of a missing attribute is prefixed with the path up to that attribute.
+    pub fn get_attr_chain(&self, path: &[&str], type_values: &TypeValues) -> ValueResult {
+        let mut value = self.clone();
+        for (i, attribute) in path.iter().enumerate() {
+            value = match type_values.get_type_value(&value, attribute) {
+                Some(method) if method.get_type() == "function" => {
+                    function::WrappedMethod::new(value, method)
+                }
+                Some(v) => v,
+                None => value
+                    .get_attr(attribute)
+                    .map_err_context(format!(".{}", path[..=i].join(".")))?,
+            };
+        }
+        Ok(value)
+    }
     pub fn set_attr(&mut self, attribute: &str, new_value: Value) -> Result<(), ValueError> {
         self.value_holder().set_attr(attribute, new_value)
     }
@@ -1159,25 +1567,80 @@ impl Value {
         self.value_holder().plus()
     }
     pub fn minus(&self) -> ValueResult {
-        self.value_holder().minus()
+        #[cfg(feature = "bigint")]
+        {
+            bigint::promote_unary(self.value_holder().minus(), self, |a| -a)
+        }
+        #[cfg(not(feature = "bigint"))]
+        {
+            self.value_holder().minus()
+        }
     }
     pub fn add(&self, other: Value) -> ValueResult {
-        self.value_holder().add(other)
+        #[cfg(feature = "bigint")]
+        {
+            let result = self.value_holder().add(other.clone());
+            bigint::promote(result, self, &other, |a, b| Ok(a + b))
+        }
+        #[cfg(not(feature = "bigint"))]
+        {
+            self.value_holder().add(other)
+        }
     }
     pub fn sub(&self, other: Value) -> ValueResult {
-        self.value_holder().sub(other)
+        #[cfg(feature = "bigint")]
+        {
+            let result = self.value_holder().sub(other.clone());
+            bigint::promote(result, self, &other, |a, b| Ok(a - b))
+        }
+        #[cfg(not(feature = "bigint"))]
+        {
+            self.value_holder().sub(other)
+        }
     }
     pub fn mul(&self, other: Value) -> ValueResult {
-        self.value_holder().mul(other)
+        #[cfg(feature = "bigint")]
+        {
+            let result = self.value_holder().mul(other.clone());
+            bigint::promote(result, self, &other, |a, b| Ok(a * b))
+        }
+        #[cfg(not(feature = "bigint"))]
+        {
+            self.value_holder().mul(other)
+        }
     }
     pub fn percent(&self, other: Value) -> ValueResult {
-        self.value_holder().percent(other)
+        #[cfg(feature = "bigint")]
+        {
+            let result = self.value_holder().percent(other.clone());
+            bigint::promote(result, self, &other, bigint::percent)
+        }
+        #[cfg(not(feature = "bigint"))]
+        {
+            self.value_holder().percent(other)
+        }
     }
     pub fn div(&self, other: Value) -> ValueResult {
-        self.value_holder().div(other)
+        #[cfg(feature = "bigint")]
+        {
+            let result = self.value_holder().div(other.clone());
+            bigint::promote(result, self, &other, bigint::floor_div)
+        }
+        #[cfg(not(feature = "bigint"))]
+        {
+            self.value_holder().div(other)
+        }
     }
     pub fn floor_div(&self, other: Value) -> ValueResult {
-        self.value_holder().floor_div(other)
+        #[cfg(feature = "bigint")]
+        {
+            let result = self.value_holder().floor_div(other.clone());
+            bigint::promote(result, self, &other, bigint::floor_div)
+        }
+        #[cfg(not(feature = "bigint"))]
+        {
+            self.value_holder().floor_div(other)
+        }
     }
     pub fn pipe(&self, other: Value) -> ValueResult {
         self.value_holder().pipe(other)
@@ -1197,6 +1660,23 @@ impl PartialEq for Value {
 }
 impl Eq for Value {}
 
+/// Hash with [`get_hash`](Value::get_hash), so that a `Value` can be used as the key of a
+/// Rust `HashMap`.
+///
+/// # Panics
+///
+/// Hashing an unhashable value, e.g. a `list`, panics. Use
+/// [`HashedValue::new`](crate::values::hashed_value::HashedValue::new) to handle that case
+/// as an error instead.
+impl Hash for Value {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match self.get_hash() {
+            Ok(hash) => state.write_u64(hash),
+            Err(e) => panic!("cannot hash a value of type {}: {}", self.get_type(), e),
+        }
+    }
+}
+
 impl dyn ValueHolderDyn {
     // To be calleds by convert_slice_indices only
     fn convert_index_aux(
@@ -1353,6 +1833,19 @@ impl Value {
         }
     }
 
+    /// Get the content of a string value without copying it, or `None` if the value is not
+    /// a string.
+    ///
+    /// Strings are immutable, so unlike [`downcast_ref`](Value::downcast_ref) no borrow is
+    /// taken: the returned `&str` is valid for as long as this `Value` is, whether the value
+    /// is frozen or not.
+    pub fn as_str(&self) -> Option<&str> {
+        match self.downcast_ref::<String>()? {
+            RefOrRef::Ptr(s) => Some(s.as_str()),
+            RefOrRef::Borrowed(..) => unreachable!("strings are immutable"),
+        }
+    }
+
     /// Get a mutable reference to underlying data or `None`
     /// if contained object has different type than requested.
     ///
@@ -1373,29 +1866,69 @@ impl Value {
         })
     }
 
+    /// Take the underlying data, or an error if the contained object has a different type
+    /// than requested.
+    ///
+    /// The data is moved out of the value if it has no other owner, otherwise it is cloned.
+    ///
+    /// This function panics if the `Value` is mutably borrowed.
+    pub fn into_typed<T: TypedValue + Clone>(self) -> Result<T, ValueError> {
+        let object_type = self.get_type();
+        let mismatch = || ValueError::TypeNotX {
+            object_type: object_type.to_owned(),
+            op: format!("of type {}", T::TYPE),
+        };
+        match self.0 {
+            ValueInner::Other(rc) => match rc.into_any().downcast::<ValueHolder<T>>() {
+                Ok(holder) => match Rc::try_unwrap(holder) {
+                    Ok(holder) => match holder.content.into_inner() {
+                        Ok(content) => Ok(content),
+                        Err(content) => Ok(content.borrow().clone()),
+                    },
+                    Err(holder) => Ok(holder.content.borrow().clone()),
+                },
+                Err(..) => Err(mismatch()),
+            },
+            _ => match self.downcast_ref::<T>() {
+                Some(v) => Ok(v.clone()),
+                None => Err(mismatch()),
+            },
+        }
+    }
+
     pub fn convert_index(&self, len: i64) -> Result<i64, ValueError> {
         self.value_holder().convert_index(len)
     }
 }
 
 // Submodules
+#[cfg(feature = "bigint")]
+pub mod bigint;
 pub mod boolean;
+pub mod bytes;
 pub mod dict;
 pub mod error;
+pub mod frozen;
 pub mod function;
 pub mod hashed_value;
 pub mod int;
 pub mod iter;
 pub mod list;
+pub mod literal;
 pub mod mutability;
 pub mod none;
+pub mod pretty;
 pub mod range;
 pub mod string;
+#[cfg(feature = "toml")]
+pub mod toml;
 pub mod tuple;
+#[cfg(feature = "yaml")]
+pub mod yaml;
 
 use crate::values::mutability::{
-    ImmutableCell, ImmutableMutability, MutabilityCell, MutableMutability, RefCellOrImmutable,
-    RefOrRef,
+    ImmutableCell, ImmutableMutability, IterableMutability, MutabilityCell, MutableMutability,
+    RefCellOrImmutable, RefOrRef,
 };
 use crate::values::none::NoneType;
 
@@ -1477,8 +2010,524 @@ mod tests {
         assert_eq!(two.compare(&one), Ok(Greater));
     }
 
+    #[cfg(feature = "debug-refcount")]
+    #[test]
+    fn strong_count() {
+        let v = Value::from(vec![1, 2]);
+        assert_eq!(1, v.strong_count());
+        let clones: Vec<Value> = (0..3).map(|_| v.clone()).collect();
+        assert_eq!(4, v.strong_count());
+        let list = Value::from(vec![v.clone()]);
+        assert_eq!(5, v.strong_count());
+        drop(clones);
+        assert_eq!(2, v.strong_count());
+        drop(list);
+        assert_eq!(1, v.strong_count());
+
+        let i = Value::from(1);
+        let _j = i.clone();
+        assert_eq!(1, i.strong_count());
+    }
+
+    #[test]
+    fn to_bytes_value() {
+        fn encode(v: Value) -> Vec<u8> {
+            let b = v.to_bytes_value().unwrap();
+            assert_eq!("bytes", b.get_type());
+            let content = b
+                .downcast_ref::<bytes::Bytes>()
+                .unwrap()
+                .as_bytes()
+                .to_vec();
+            content
+        }
+
+        assert_eq!(b"abc".to_vec(), encode(Value::from("abc")));
+        assert_eq!(
+            vec![0x68, 0xc3, 0xa9, 0xe2, 0x82, 0xac, 0xf0, 0x9f, 0x98, 0x80],
+            encode(Value::from("h\u{e9}\u{20ac}\u{1f600}"))
+        );
+        assert_eq!(
+            b"[1, \"a\"]".to_vec(),
+            encode(Value::from(vec![Value::from(1), Value::from("a")]))
+        );
+        assert_eq!(b"None".to_vec(), encode(Value::new(NoneType::None)));
+    }
+
+    #[test]
+    fn new_frozen() {
+        let mut list = Value::new_frozen(list::List::from(vec![1, 2]));
+        assert!(list.set_at(Value::from(0), Value::from(3)).is_err());
+        assert!(list.downcast_mut::<list::List>().is_err());
+
+        let tuple = Value::new_frozen(tuple::Tuple::new(vec![Value::from(vec![1])]));
+        let mut nested = tuple.at(Value::from(0)).unwrap();
+        assert!(nested.set_at(Value::from(0), Value::from(3)).is_err());
+        assert_eq!("([1],)", tuple.to_repr());
+
+        assert_eq!(Value::from("a"), Value::new_frozen("a".to_owned()));
+    }
+
+    #[test]
+    fn check_frozen() {
+        assert!(Value::from(1).check_frozen().is_ok());
+        assert!(Value::from("a").check_frozen().is_ok());
+        assert!(Value::from((1, "a")).check_frozen().is_ok());
+        assert!(Value::new_frozen(list::List::from(vec![1, 2]))
+            .check_frozen()
+            .is_ok());
+
+        let mut list = Value::from(vec![1, 2]);
+        let err = list.check_frozen().unwrap_err();
+        assert_eq!(ValueError::NotFrozenError("list".to_owned()), err);
+        assert!(err.to_string().contains("'list'"), "{}", err);
+        // The content of an immutable container is checked too.
+        let tuple = Value::new(tuple::Tuple::new(vec![list.clone()]));
+        assert_eq!(
+            Err(ValueError::NotFrozenError("list".to_owned())),
+            tuple.check_frozen()
+        );
+        list.freeze();
+        assert!(tuple.check_frozen().is_ok());
+
+        // A value frozen for iteration only is still mutable.
+        let mut dict = dict::Dictionary::new();
+        dict.freeze_for_iteration();
+        assert_eq!(
+            Err(ValueError::NotFrozenError("dict".to_owned())),
+            dict.check_frozen()
+        );
+        dict.unfreeze_for_iteration();
+    }
+
+    #[test]
+    fn hash_map_key() {
+        use std::collections::HashMap;
+
+        let mut map = HashMap::new();
+        map.insert(Value::from("a"), 1);
+        map.insert(Value::from(1), 2);
+        map.insert(Value::from((1, "b")), 3);
+        map.insert(Value::new(NoneType::None), 4);
+        assert_eq!(Some(&1), map.get(&Value::from("a")));
+        assert_eq!(Some(&2), map.get(&Value::from(1)));
+        assert_eq!(Some(&3), map.get(&Value::from((1, "b"))));
+        assert_eq!(Some(&4), map.get(&Value::new(NoneType::None)));
+        assert_eq!(None, map.get(&Value::from("1")));
+        assert_eq!(None, map.get(&Value::from((1, "c"))));
+        map.insert(Value::from((1, "b")), 5);
+        assert_eq!(4, map.len());
+        assert_eq!(Some(&5), map.get(&Value::from((1, "b"))));
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot hash a value of type list")]
+    fn hash_unhashable_panics() {
+        let mut set = HashSet::new();
+        set.insert(Value::from(vec![1]));
+    }
+
+    #[test]
+    fn hash_stable_across_freeze() {
+        let mut list = Value::from(vec![Value::from("a"), Value::from(1)]);
+        let mut tuple = Value::new(tuple::Tuple::new(
+            list.iter().unwrap().iter().collect::<Vec<_>>(),
+        ));
+        let mut nested = Value::from((tuple.clone(), "b", NoneType::None, true));
+        let mut dict = dict::Dictionary::new();
+
+        let tuple_hash = tuple.get_hash().unwrap();
+        let nested_hash = nested.get_hash().unwrap();
+        assert!(list.get_hash().is_err());
+        assert!(dict.get_hash().is_err());
+
+        for v in &mut [&mut list, &mut tuple, &mut nested, &mut dict] {
+            v.freeze();
+        }
+
+        assert_eq!(tuple_hash, tuple.get_hash().unwrap());
+        assert_eq!(nested_hash, nested.get_hash().unwrap());
+        assert!(list.get_hash().is_err());
+        assert!(dict.get_hash().is_err());
+    }
+
+    #[test]
+    fn call_method() {
+        let env = crate::stdlib::global_environment();
+        let type_values = TypeValues::new(env);
+        let call_stack = CallStack::default();
+        let r = Value::from("a,b")
+            .call_method(
+                "split",
+                &type_values,
+                &call_stack,
+                vec![Value::from(",")],
+                OrderedMap::new(),
+            )
+            .unwrap();
+        assert_eq!(Value::from(vec!["a", "b"]), r);
+
+        let mut named = OrderedMap::new();
+        named.insert("b".to_owned(), Value::from(2));
+        let r = Value::from("{} {b}")
+            .call_method(
+                "format",
+                &type_values,
+                &call_stack,
+                vec![Value::from(1)],
+                named,
+            )
+            .unwrap();
+        assert_eq!(Value::from("1 2"), r);
+
+        for (value, method, left) in [
+            (Value::from(1), "split", "int"),
+            (Value::from("a"), "nope", "string"),
+        ] {
+            match value.call_method(
+                method,
+                &type_values,
+                &call_stack,
+                Vec::new(),
+                OrderedMap::new(),
+            ) {
+                Err(ValueError::NoAttrLeft {
+                    attribute,
+                    left: type_name,
+                }) => assert_eq!((method, left), (attribute.as_str(), type_name.as_str())),
+                r => panic!("unexpected result: {:?}", r.map(|v| v.to_repr())),
+            }
+        }
+    }
+
+    #[test]
+    fn into_typed() {
+        use std::cell::Cell;
+
+        thread_local!(static CLONES: Cell<usize> = const { Cell::new(0) });
+
+        #[derive(Debug, PartialEq)]
+        struct Counted(String);
+
+        impl Clone for Counted {
+            fn clone(&self) -> Counted {
+                CLONES.with(|c| c.set(c.get() + 1));
+                Counted(self.0.clone())
+            }
+        }
+
+        impl TypedValue for Counted {
+            type Holder = Mutable<Counted>;
+            const TYPE: &'static str = "Counted";
+
+            fn values_for_descendant_check_and_freeze<'a>(
+                &'a self,
+            ) -> Box<dyn Iterator<Item = Value> + 'a> {
+                Box::new(iter::empty())
+            }
+        }
+
+        let clones = || CLONES.with(Cell::get);
+
+        // The only owner, the content is moved.
+        let v = Value::new(Counted("a".to_owned()));
+        assert_eq!(Ok(Counted("a".to_owned())), v.into_typed::<Counted>());
+        assert_eq!(0, clones());
+
+        // Shared, the content is cloned and the other owner is untouched.
+        let v = Value::new(Counted("b".to_owned()));
+        let other = v.clone();
+        assert_eq!(Ok(Counted("b".to_owned())), v.into_typed::<Counted>());
+        assert_eq!(1, clones());
+        assert_eq!("b", other.downcast_ref::<Counted>().unwrap().0);
+
+        assert_eq!(Ok(3), Value::new(3).into_typed::<i64>());
+        match Value::new(3).into_typed::<Counted>() {
+            Err(ValueError::TypeNotX { object_type, op }) => {
+                assert_eq!(
+                    ("int", "of type Counted"),
+                    (object_type.as_str(), op.as_str())
+                )
+            }
+            r => panic!("unexpected result: {:?}", r),
+        }
+        assert!(other.into_typed::<String>().is_err());
+    }
+
+    #[test]
+    fn iter_values() {
+        let collect = |v: Value| v.iter_values().map(|i| i.iter().collect::<Vec<_>>());
+
+        assert_eq!(
+            Some(vec![Value::from(1), Value::from(2)]),
+            collect(Value::from(vec![1, 2]))
+        );
+        let mut dict = dict::Dictionary::new();
+        dict.set_at(Value::from("k"), Value::from(1)).unwrap();
+        assert_eq!(Some(vec![Value::from("k")]), collect(dict));
+        assert_eq!(
+            Some(vec![Value::from(0), Value::from(1)]),
+            collect(Value::new(range::Range::new(
+                0,
+                2,
+                std::num::NonZeroI64::new(1).unwrap()
+            )))
+        );
+
+        // Strings are not iterable in Starlark.
+        assert!(Value::from("ab").iter_values().is_none());
+        assert!(Value::from(1).iter_values().is_none());
+        assert!(Value::from(true).iter_values().is_none());
+        let f = function::NativeFunction::new(
+            "f".to_owned(),
+            |_, _, _| Ok(Value::new(NoneType::None)),
+            Vec::new(),
+        );
+        assert!(f.iter_values().is_none());
+    }
+
+    #[test]
+    fn freeze_hook() {
+        #[derive(Debug)]
+        struct Recorder {
+            children: Vec<Value>,
+            freezes: usize,
+            children_frozen: bool,
+        }
+
+        impl TypedValue for Recorder {
+            type Holder = Mutable<Recorder>;
+            const TYPE: &'static str = "Recorder";
+
+            fn values_for_descendant_check_and_freeze<'a>(
+                &'a self,
+            ) -> Box<dyn Iterator<Item = Value> + 'a> {
+                Box::new(self.children.iter().cloned())
+            }
+
+            fn freeze(&mut self) {
+                self.freezes += 1;
+                self.children_frozen = self.children.iter().all(|v| v.check_frozen().is_ok());
+            }
+        }
+
+        let mut recorder = Value::new(Recorder {
+            children: vec![Value::from(vec![1]), Value::from(vec![2])],
+            freezes: 0,
+            children_frozen: false,
+        });
+        let mut list = Value::from(vec![recorder.clone()]);
+        list.freeze();
+        list.freeze();
+        recorder.freeze();
+
+        let recorder = recorder.downcast_ref::<Recorder>().unwrap();
+        assert_eq!(1, recorder.freezes);
+        assert!(recorder.children_frozen);
+    }
+
+    #[test]
+    fn to_python_repr() {
+        let mut dict = dict::Dictionary::new();
+        dict.set_at(
+            Value::from("k"),
+            Value::from(vec![Value::new(NoneType::None)]),
+        )
+        .unwrap();
+        dict.set_at(Value::from(false), Value::from((1,))).unwrap();
+        let v = Value::new(tuple::Tuple::new(vec![
+            Value::new(NoneType::None),
+            Value::from(true),
+            Value::from(-3),
+            Value::from("a\u{1}\"b"),
+            Value::from(Vec::<i64>::new()),
+            dict,
+            Value::from(()),
+            Value::new(range::Range::new(
+                0,
+                3,
+                std::num::NonZeroI64::new(1).unwrap(),
+            )),
+        ]));
+        assert_eq!(
+            r#"(None, True, -3, "a\x01\"b", [], {"k": [None], False: (1,)}, (), range(3))"#,
+            v.to_python_repr()
+        );
+    }
+
+    #[test]
+    fn python_type_name() {
+        struct Custom;
+
+        impl TypedValue for Custom {
+            type Holder = Immutable<Custom>;
+            const TYPE: &'static str = "custom";
+
+            fn values_for_descendant_check_and_freeze<'a>(
+                &'a self,
+            ) -> Box<dyn Iterator<Item = Value> + 'a> {
+                Box::new(iter::empty())
+            }
+        }
+
+        let cases = vec![
+            ("int", Value::from(1)),
+            ("str", Value::from("a")),
+            ("bytes", Value::new(bytes::Bytes::new(vec![]))),
+            ("list", Value::from(vec![1])),
+            ("tuple", Value::from((1,))),
+            ("dict", dict::Dictionary::new()),
+            (
+                "set",
+                crate::linked_hash_set::value::Set::from(vec![1]).unwrap(),
+            ),
+            ("bool", Value::from(true)),
+            ("NoneType", Value::new(NoneType::None)),
+            (
+                "range",
+                Value::new(range::Range::new(
+                    0,
+                    1,
+                    std::num::NonZeroI64::new(1).unwrap(),
+                )),
+            ),
+            ("object", Value::new(Custom)),
+        ];
+        for (expected, v) in cases {
+            assert_eq!(expected, v.python_type_name(), "{}", v.get_type());
+        }
+    }
+
+    #[test]
+    fn is_in_custom_type() {
+        use crate::environment::Environment;
+        use crate::eval::noload::eval;
+        use crate::syntax::dialect::Dialect;
+        use codemap::CodeMap;
+        use std::sync::{Arc, Mutex};
+
+        /// Contains the even integers.
+        struct Evens;
+
+        impl TypedValue for Evens {
+            type Holder = Immutable<Evens>;
+            const TYPE: &'static str = "Evens";
+
+            fn values_for_descendant_check_and_freeze<'a>(
+                &'a self,
+            ) -> Box<dyn Iterator<Item = Value> + 'a> {
+                Box::new(iter::empty())
+            }
+
+            fn is_in(&self, other: &Value) -> Result<bool, ValueError> {
+                Ok(other.to_int()? % 2 == 0)
+            }
+        }
+
+        /// Only iterable, relies on the default `is_in`.
+        struct Digits;
+
+        impl TypedIterable for Digits {
+            fn to_iter<'a>(&'a self) -> Box<dyn Iterator<Item = Value> + 'a> {
+                Box::new((0..10).map(Value::new))
+            }
+        }
+
+        impl TypedValue for Digits {
+            type Holder = Immutable<Digits>;
+            const TYPE: &'static str = "Digits";
+
+            fn values_for_descendant_check_and_freeze<'a>(
+                &'a self,
+            ) -> Box<dyn Iterator<Item = Value> + 'a> {
+                Box::new(iter::empty())
+            }
+
+            fn iter(&self) -> Result<&dyn TypedIterable, ValueError> {
+                Ok(self)
+            }
+        }
+
+        let mut env = Environment::new("test");
+        env.set("evens", Value::new(Evens)).unwrap();
+        env.set("digits", Value::new(Digits)).unwrap();
+        let r = eval(
+            &Arc::new(Mutex::new(CodeMap::new())),
+            "<test>",
+            "(2 in evens, 3 not in evens, 9 in digits, 10 not in digits, 'a' in digits)",
+            Dialect::Bzl,
+            &mut env,
+            TypeValues::new(Environment::new("empty")),
+        )
+        .unwrap();
+        assert_eq!("(True, True, True, True, False)", r.to_repr());
+
+        assert!(Value::new(Evens).is_in(&Value::from("a")).is_err());
+        match Value::new(1).is_in(&Value::new(1)) {
+            Err(ValueError::OperationNotSupported { op, .. }) => assert_eq!("in", op),
+            r => panic!("unexpected result: {:?}", r),
+        }
+    }
+
     #[test]
     fn compare_between_different_types() {
         assert!(Value::new(1).compare(&Value::new(false)).is_err());
     }
+
+    #[test]
+    fn get_attr_chain() {
+        use crate::testing::setup_test_env;
+
+        let env = setup_test_env();
+        let type_values = TypeValues::new(env.clone());
+        let value = eval_in_env!(env, "struct(a = struct(b = struct(c = 1)), d = {'e': 2})");
+        let c = value
+            .get_attr_chain(&["a", "b", "c"], &type_values)
+            .unwrap();
+        assert_eq!(Value::new(1), c);
+        assert_eq!(value, value.get_attr_chain(&[], &type_values).unwrap());
+        // Methods are bound to their object.
+        let get = value.get_attr_chain(&["d", "get"], &type_values).unwrap();
+        assert_eq!("function", get.get_type());
+
+        match value.get_attr_chain(&["a", "x", "c"], &type_values) {
+            Err(ValueError::Runtime(e)) => {
+                assert!(e.message.starts_with(".a.x: "), "{}", e.message);
+                assert!(
+                    e.message.contains("no field or method 'x'"),
+                    "{}",
+                    e.message
+                );
+            }
+            r => panic!("unexpected result: {:?}", r),
+        }
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn to_json_value() {
+        use crate::testing::setup_test_env;
+        use serde_json::json;
+
+        let env = setup_test_env();
+        let value = eval_in_env!(
+            env,
+            "{'b': [1, True, None], 'a': (struct(x = 'y'), {}), 'c': 'multi\\nline'}"
+        );
+        assert_eq!(
+            json!({"a": [{"x": "y"}, {}], "b": [1, true, null], "c": "multi\nline"}),
+            value.to_json_value().unwrap()
+        );
+        // Same content as the JSON encoding.
+        assert_eq!(
+            value.to_json_value().unwrap(),
+            serde_json::from_str::<serde_json::Value>(&value.to_json().unwrap()).unwrap()
+        );
+        for content in &["{1: 2}", "[range(3)]", "len"] {
+            match eval_in_env!(env, content).to_json_value() {
+                Err(ValueError::TypeNotX { .. }) => {}
+                r => panic!("unexpected result for {}: {:?}", content, r),
+            }
+        }
+    }
 }
@@ -18,6 +18,7 @@ use crate::values::ValueError;
 use std::cell::{BorrowError, Cell, Ref, RefCell, RefMut};
 use std::fmt;
 use std::ops::Deref;
+use std::sync::Arc;
 
 /// A helper enum for defining the level of mutability of an iterable.
 #[derive(PartialEq, Eq, Hash, Debug, Copy, Clone)]
@@ -79,11 +80,35 @@ pub trait RefCellOrImmutable {
     fn try_borrow(&self) -> Result<RefOrRef<Self::Content>, BorrowError>;
     fn borrow_mut(&self) -> RefMut<'_, Self::Content>;
     fn as_ptr(&self) -> *const Self::Content;
+    /// The content, or the container itself if the content is shared with other threads.
+    fn into_inner(self) -> Result<Self::Content, Self>
+    where
+        Self: Sized;
 }
 
-/// Container for immutable data
+/// Container for immutable data, which may be shared with other threads (see
+/// [`Value::new_shared`](crate::values::Value::new_shared)).
+#[derive(Debug, Clone)]
+pub struct ImmutableCell<T>(ImmutableContent<T>);
+
 #[derive(Debug, Clone)]
-pub struct ImmutableCell<T>(T);
+enum ImmutableContent<T> {
+    Owned(T),
+    Shared(Arc<T>),
+}
+
+impl<T> ImmutableCell<T> {
+    pub(crate) fn shared(value: Arc<T>) -> Self {
+        ImmutableCell(ImmutableContent::Shared(value))
+    }
+
+    fn get(&self) -> &T {
+        match &self.0 {
+            ImmutableContent::Owned(value) => value,
+            ImmutableContent::Shared(value) => value,
+        }
+    }
+}
 
 impl<T> RefCellOrImmutable for RefCell<T> {
     type Content = T;
@@ -107,21 +132,25 @@ impl<T> RefCellOrImmutable for RefCell<T> {
     fn as_ptr(&self) -> *const T {
         RefCell::as_ptr(self)
     }
+
+    fn into_inner(self) -> Result<T, Self> {
+        Ok(RefCell::into_inner(self))
+    }
 }
 
 impl<T> RefCellOrImmutable for ImmutableCell<T> {
     type Content = T;
 
     fn new(value: T) -> Self {
-        ImmutableCell(value)
+        ImmutableCell(ImmutableContent::Owned(value))
     }
 
     fn borrow(&self) -> RefOrRef<T> {
-        RefOrRef::Ptr(&self.0)
+        RefOrRef::Ptr(self.get())
     }
 
     fn try_borrow(&self) -> Result<RefOrRef<T>, BorrowError> {
-        Ok(RefOrRef::Ptr(&self.0))
+        Ok(RefOrRef::Ptr(self.get()))
     }
 
     fn borrow_mut(&self) -> RefMut<Self::Content> {
@@ -129,7 +158,14 @@ impl<T> RefCellOrImmutable for ImmutableCell<T> {
     }
 
     fn as_ptr(&self) -> *const T {
-        &self.0 as *const T
+        self.get() as *const T
+    }
+
+    fn into_inner(self) -> Result<T, Self> {
+        match self.0 {
+            ImmutableContent::Owned(value) => Ok(value),
+            ImmutableContent::Shared(value) => Err(ImmutableCell(ImmutableContent::Shared(value))),
+        }
     }
 }
 
@@ -30,6 +30,10 @@ impl TypedValue for NoneType {
     type Holder = Immutable<Self>;
     const TYPE: &'static str = "NoneType";
 
+    fn python_type_name(&self) -> &'static str {
+        "NoneType"
+    }
+
     fn new_value(self) -> Value {
         Value(ValueInner::None(ValueHolder::new(self)))
     }
@@ -50,6 +54,16 @@ impl TypedValue for NoneType {
     fn to_repr(&self) -> String {
         "None".to_owned()
     }
+    fn to_json(&self) -> Result<String, ValueError> {
+        Ok("null".to_owned())
+    }
+    fn share(&self) -> Result<FrozenValue, ValueError> {
+        Ok(FrozenValue::none())
+    }
+    #[cfg(feature = "json")]
+    fn to_json_value(&self) -> Result<serde_json::Value, ValueError> {
+        Ok(serde_json::Value::Null)
+    }
     fn to_bool(&self) -> bool {
         false
     }
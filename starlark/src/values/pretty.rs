@@ -0,0 +1,191 @@
+// Copyright 2019 The Starlark in Rust Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Indented, multi-line representation of values.
+
+use crate::values::{Value, VisitGuard};
+use std::cmp::Ordering;
+
+/// Order of dictionary keys: by value when comparable, by type and representation
+/// otherwise, so that the order does not depend on the insertion order.
+fn compare_keys(a: &Value, b: &Value) -> Ordering {
+    match a.compare(b) {
+        Ok(ordering) => ordering,
+        Err(..) => (a.get_type(), a.to_repr()).cmp(&(b.get_type(), b.to_repr())),
+    }
+}
+
+fn write_pretty(v: &Value, indent: usize, sort_keys: bool, level: usize, out: &mut String) {
+    let (open, close) = match v.get_type() {
+        "list" => ("[", "]"),
+        "tuple" => ("(", ")"),
+        "dict" => ("{", "}"),
+        _ => {
+            out.push_str(&v.to_repr());
+            return;
+        }
+    };
+    let keys: Vec<Value> = match v.iter() {
+        Ok(iter) => iter.iter().collect(),
+        Err(..) => Vec::new(),
+    };
+    if keys.is_empty() {
+        out.push_str(&v.to_repr());
+        return;
+    }
+    // A value containing itself is elided, like Python does.
+    let _visit_guard = match VisitGuard::enter(v.data_ptr()) {
+        Ok(guard) => guard,
+        Err(..) => {
+            out.push_str(open);
+            out.push_str("...");
+            out.push_str(close);
+            return;
+        }
+    };
+
+    let margin = " ".repeat(indent * (level + 1));
+    out.push_str(open);
+    out.push('\n');
+    if close == "}" {
+        let mut keys = keys;
+        if sort_keys {
+            keys.sort_by(compare_keys);
+        }
+        for k in keys {
+            out.push_str(&margin);
+            write_pretty(&k, indent, sort_keys, level + 1, out);
+            out.push_str(": ");
+            write_pretty(&v.at(k).unwrap(), indent, sort_keys, level + 1, out);
+            out.push_str(",\n");
+        }
+    } else {
+        for x in keys {
+            out.push_str(&margin);
+            write_pretty(&x, indent, sort_keys, level + 1, out);
+            out.push_str(",\n");
+        }
+    }
+    out.push_str(&" ".repeat(indent * level));
+    out.push_str(close);
+}
+
+impl Value {
+    /// A representation of this value with one element of a non-empty list, tuple or
+    /// dictionary per line, indented by `indent` spaces per nesting level.
+    ///
+    /// Elements are followed by a comma, so that one-element tuples keep their meaning.
+    /// Other values are formatted with [`to_repr`](Value::to_repr), and a container
+    /// containing itself is represented as `[...]` (resp. `(...)`, `{...}`) where it
+    /// appears again.
+    ///
+    /// ```
+    /// # use starlark::values::Value;
+    /// let v = Value::from(vec![Value::from(1), Value::from(("a", 2))]);
+    /// assert_eq!("[\n  1,\n  (\n    \"a\",\n    2,\n  ),\n]", v.to_pretty(2));
+    /// ```
+    pub fn to_pretty(&self, indent: usize) -> String {
+        let mut out = String::new();
+        write_pretty(self, indent, false, 0, &mut out);
+        out
+    }
+
+    /// Same as [`to_pretty`](Value::to_pretty) but with the keys of dictionaries sorted,
+    /// so that the result does not depend on the insertion order.
+    ///
+    /// Keys are sorted by value, keys which cannot be compared (e.g. of different types)
+    /// by type name then representation.
+    pub fn to_pretty_sorted(&self, indent: usize) -> String {
+        let mut out = String::new();
+        write_pretty(self, indent, true, 0, &mut out);
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::values::dict::Dictionary;
+    use crate::values::none::NoneType;
+    use crate::values::Value;
+
+    fn nested() -> Value {
+        let mut inner = Dictionary::new();
+        inner
+            .set_at(Value::from("z"), Value::from(vec![1, 2]))
+            .unwrap();
+        inner.set_at(Value::from("y"), Value::from((3,))).unwrap();
+        inner.set_at(Value::from("x"), Dictionary::new()).unwrap();
+        let mut dict = Dictionary::new();
+        dict.set_at(Value::from("name"), Value::from("lib"))
+            .unwrap();
+        dict.set_at(Value::from("deps"), Value::from(Vec::<Value>::new()))
+            .unwrap();
+        dict.set_at(Value::from("attrs"), inner).unwrap();
+        dict.set_at(Value::from(1), Value::new(NoneType::None))
+            .unwrap();
+        dict
+    }
+
+    #[test]
+    fn indent_2() {
+        assert_eq!(
+            r#"{
+  "name": "lib",
+  "deps": [],
+  "attrs": {
+    "z": [
+      1,
+      2,
+    ],
+    "y": (
+      3,
+    ),
+    "x": {},
+  },
+  1: None,
+}"#,
+            nested().to_pretty(2)
+        );
+    }
+
+    #[test]
+    fn indent_4_sorted() {
+        assert_eq!(
+            r#"{
+    1: None,
+    "attrs": {
+        "x": {},
+        "y": (
+            3,
+        ),
+        "z": [
+            1,
+            2,
+        ],
+    },
+    "deps": [],
+    "name": "lib",
+}"#,
+            nested().to_pretty_sorted(4)
+        );
+    }
+
+    #[test]
+    fn scalars() {
+        assert_eq!("1", Value::from(1).to_pretty(2));
+        assert_eq!("\"a\\nb\"", Value::from("a\nb").to_pretty(2));
+        assert_eq!("None", Value::new(NoneType::None).to_pretty(4));
+        assert_eq!("[\n1,\n]", Value::from(vec![1]).to_pretty(0));
+    }
+}
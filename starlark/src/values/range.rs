@@ -14,9 +14,12 @@
 
 //! `range()` builtin implementation
 
+use crate::values::frozen::Thaw;
 use crate::values::iter::TypedIterable;
-use crate::values::{Immutable, TypedValue, Value, ValueError};
+use crate::values::{FrozenValue, Immutable, TypedValue, Value, ValueError};
+use std::convert::TryFrom;
 use std::num::NonZeroI64;
+use std::sync::Arc;
 use std::{iter, mem};
 
 /// Representation of `range()` type.
@@ -47,11 +50,24 @@ impl Iterator for RangeIterator {
         let new_start = self.0.start.saturating_add(self.0.step.get());
         Some(Value::new(mem::replace(&mut self.0.start, new_start)))
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        // The remaining range may be too long for `usize` (or even `i64`), in which case
+        // it cannot be collected anyway.
+        match self.0.length().map(usize::try_from) {
+            Ok(Ok(len)) => (len, Some(len)),
+            _ => (0, None),
+        }
+    }
 }
 
 impl TypedValue for Range {
     const TYPE: &'static str = "range";
 
+    fn python_type_name(&self) -> &'static str {
+        "range"
+    }
+
     fn to_str(&self) -> String {
         self.to_repr()
     }
@@ -66,6 +82,10 @@ impl TypedValue for Range {
         }
     }
 
+    fn share(&self) -> Result<FrozenValue, ValueError> {
+        Ok(FrozenValue::new(self.clone()))
+    }
+
     fn to_bool(&self) -> bool {
         (self.start < self.stop && self.step.get() > 0)
             || (self.start > self.stop && self.step.get() < 0)
@@ -218,8 +238,15 @@ impl TypedIterable for Range {
     }
 }
 
+impl Thaw for Range {
+    fn thaw(self: Arc<Self>) -> Value {
+        Value::new_shared(self)
+    }
+}
+
 #[cfg(test)]
 mod test {
+    use crate::values::iter::TypedIterable;
     use crate::values::range::Range;
     use crate::values::{TypedValue, ValueError};
     use std::i64;
@@ -271,6 +298,23 @@ mod test {
         assert_eq!(Ok(1), range(4, 14, 10).length());
     }
 
+    #[test]
+    fn size_hint() {
+        assert_eq!((0, Some(0)), range_stop(0).to_iter().size_hint());
+        assert_eq!((5, Some(5)), range(9, 0, -2).to_iter().size_hint());
+        let r = range(0, 10, 3);
+        let mut it = r.to_iter();
+        assert_eq!((4, Some(4)), it.size_hint());
+        it.next();
+        assert_eq!((3, Some(3)), it.size_hint());
+        assert_eq!(
+            (0, None),
+            range_start_stop(i64::min_value(), i64::max_value())
+                .to_iter()
+                .size_hint()
+        );
+    }
+
     #[test]
     fn eq() {
         assert_eq!(range_stop(0), range(2, 1, 3));
@@ -20,6 +20,8 @@ use crate::values::Value;
 use codemap::Span;
 use codemap_diagnostic::{Diagnostic, Level, SpanLabel, SpanStyle};
 use std::convert::TryFrom;
+use std::error;
+use std::fmt;
 use std::iter;
 
 /// Operator `%` format or evaluation errors
@@ -29,21 +31,24 @@ pub enum StringInterpolationError {
     UnexpectedEOFClosingParen,
     /// `%` must be followed by specifier.
     UnexpectedEOFPercent,
-    /// `%z` where `z` is unknown specifier.
-    UnknownSpecifier(char),
+    /// `%z` where `z` is unknown specifier, at the given directive index.
+    UnknownSpecifier(char, usize),
     /// Trying to interpolate with %c an integer that is not in the UTF-8 range.
     ValueNotInUTFRange(i64),
-    /// Interpolation parameter is too big for the format string.
-    TooManyParameters,
-    /// Interpolation parameter is too small for the format string.
-    NotEnoughParameters,
+    /// Interpolation parameter is too big for the format string,
+    /// which only has the given number of directives.
+    TooManyParameters(usize),
+    /// Interpolation parameter is too small for the format string,
+    /// no argument is left for the directive at the given index.
+    NotEnoughParameters(usize),
     /// Value for `%s` is required to be a char
     ValueNotChar,
 }
 
-impl SyntaxError for StringInterpolationError {
-    fn to_diagnostic(self, file_span: Span) -> Diagnostic {
-        let (label, message, code) = match self {
+impl StringInterpolationError {
+    /// Label, message and error code of that error.
+    pub(crate) fn describe(&self) -> (String, String, &'static str) {
+        match *self {
             StringInterpolationError::UnexpectedEOFClosingParen => (
                 "Unexpected EOF in format string when looking for closing paren".to_owned(),
                 "Could not found ')' when parsing '%(name)f' expression".to_owned(),
@@ -59,14 +64,16 @@ impl SyntaxError for StringInterpolationError {
                 .to_owned(),
                 INTERPOLATION_UNEXPECTED_EOF_PERCENT,
             ),
-            StringInterpolationError::UnknownSpecifier(c) => (
+            StringInterpolationError::UnknownSpecifier(c, i) => (
                 format!("Unknown format string specifier '{}'", c.escape_default()),
-                concat!(
-                    "Interpolation string format is incorrect:",
-                    " '%' must be followed by an optional name and a specifier ",
-                    "('s', 'r', 'd', 'i', 'o', 'x', 'X', 'c') or '%'",
-                )
-                .to_owned(),
+                format!(
+                    concat!(
+                        "Interpolation string format is incorrect at directive #{}:",
+                        " '%' must be followed by an optional name and a specifier ",
+                        "('s', 'r', 'd', 'i', 'o', 'x', 'X', 'c') or '%'",
+                    ),
+                    i
+                ),
                 INTERPOLATION_UNKNOWN_SPECIFIER,
             ),
             StringInterpolationError::ValueNotInUTFRange(ref c) => (
@@ -80,14 +87,20 @@ impl SyntaxError for StringInterpolationError {
                 ),
                 INTERPOLATION_OUT_OF_UTF8_RANGE_ERROR_CODE,
             ),
-            StringInterpolationError::TooManyParameters => (
-                "Too many arguments for format string".to_owned(),
+            StringInterpolationError::TooManyParameters(n) => (
                 "Too many arguments for format string".to_owned(),
+                format!(
+                    "Too many arguments for format string: it only has {} directive(s)",
+                    n
+                ),
                 INTERPOLATION_TOO_MANY_PARAMS_ERROR_CODE,
             ),
-            StringInterpolationError::NotEnoughParameters => (
-                "Not enough arguments for format string".to_owned(),
+            StringInterpolationError::NotEnoughParameters(i) => (
                 "Not enough arguments for format string".to_owned(),
+                format!(
+                    "Not enough arguments for format string: no argument left for directive #{}",
+                    i
+                ),
                 INTERPOLATION_NOT_ENOUGH_PARAMS_ERROR_CODE,
             ),
             StringInterpolationError::ValueNotChar => (
@@ -95,7 +108,21 @@ impl SyntaxError for StringInterpolationError {
                 "'%c' formatter requires a single-character string".to_owned(),
                 INTERPOLATION_VALUE_IS_NOT_CHAR_ERROR_CODE,
             ),
-        };
+        }
+    }
+}
+
+impl fmt::Display for StringInterpolationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.describe().1)
+    }
+}
+
+impl error::Error for StringInterpolationError {}
+
+impl SyntaxError for StringInterpolationError {
+    fn to_diagnostic(self, file_span: Span) -> Diagnostic {
+        let (label, message, code) = self.describe();
         let sl = SpanLabel {
             span: file_span,
             style: SpanStyle::Primary,
@@ -290,7 +317,13 @@ impl ArgsFormat {
                             ArgFormat::Percent
                         }
                     },
-                    c => return Err(StringInterpolationError::UnknownSpecifier(c).into()),
+                    c => {
+                        return Err(StringInterpolationError::UnknownSpecifier(
+                            c,
+                            result.parameters.len(),
+                        )
+                        .into())
+                    }
                 };
                 match named_or_positional {
                     NamedOrPositional::Positional => {
@@ -311,7 +344,11 @@ impl ArgsFormat {
     pub fn format(self, other: Value) -> Result<String, ValueError> {
         let mut r = self.init;
         let other_iter;
-        let mut arg_iter: Box<dyn Iterator<Item = Value>> = if self.positional_count > 1 {
+        let mut arg_iter: Box<dyn Iterator<Item = Value>> = if self.positional_count > 1
+            || (self.positional_count == 1 && other.get_type() == "tuple")
+        {
+            // A tuple always provides the arguments, even for a single directive,
+            // so `"%s" % (1,)` is `"1"` and not `"(1,)"`.
             other_iter = Some(other.iter()?);
             other_iter.as_ref().unwrap().iter()
         } else if self.positional_count == 1 {
@@ -324,11 +361,12 @@ impl ArgsFormat {
             other_iter = Some(other.iter()?);
             other_iter.as_ref().unwrap().iter()
         };
-        for (named_or_positional, format, tail) in self.parameters {
+        let directive_count = self.parameters.len();
+        for (i, (named_or_positional, format, tail)) in self.parameters.into_iter().enumerate() {
             let arg = match named_or_positional {
                 NamedOrPositional::Positional => match arg_iter.next() {
                     Some(a) => a,
-                    None => return Err(StringInterpolationError::NotEnoughParameters.into()),
+                    None => return Err(StringInterpolationError::NotEnoughParameters(i).into()),
                 },
                 NamedOrPositional::Named(name) => other.at(Value::new(name))?,
             };
@@ -337,7 +375,7 @@ impl ArgsFormat {
         }
 
         if arg_iter.next().is_some() {
-            return Err(StringInterpolationError::TooManyParameters.into());
+            return Err(StringInterpolationError::TooManyParameters(directive_count).into());
         }
 
         Ok(r)
@@ -346,6 +384,8 @@ impl ArgsFormat {
 
 #[cfg(test)]
 mod test {
+    use super::StringInterpolationError;
+    use crate::values::error::ValueError;
     use crate::values::Value;
     use std::collections::HashMap;
     use std::convert::TryFrom;
@@ -396,4 +436,44 @@ mod test {
             Value::from("{\"a\": 1}1")
         );
     }
+
+    #[test]
+    fn test_string_interpolation_tuple() {
+        assert_eq!(
+            Value::from("%s").percent(Value::from((1,))).unwrap(),
+            Value::from("1")
+        );
+        assert_eq!(
+            Value::from("%r").percent(Value::from(("a",))).unwrap(),
+            Value::from("\"a\"")
+        );
+        assert_eq!(
+            Value::from("%s").percent(Value::from(vec![1])).unwrap(),
+            Value::from("[1]")
+        );
+        assert!(Value::from("%s").percent(Value::from((1, 2))).is_err());
+    }
+
+    #[test]
+    fn test_string_interpolation_errors() {
+        match Value::from("%d %d %d").percent(Value::from((1, 2))) {
+            Err(ValueError::StringInterpolation(
+                StringInterpolationError::NotEnoughParameters(2),
+            )) => (),
+            _ => panic!("expected not enough parameters at directive #2"),
+        }
+        match Value::from("%d %s").percent(Value::from((1, 2, 3))) {
+            Err(ValueError::StringInterpolation(StringInterpolationError::TooManyParameters(
+                2,
+            ))) => (),
+            _ => panic!("expected too many parameters"),
+        }
+        match Value::from("%d %s %z").percent(Value::from((1, 2, 3))) {
+            Err(ValueError::StringInterpolation(StringInterpolationError::UnknownSpecifier(
+                'z',
+                2,
+            ))) => (),
+            _ => panic!("expected unknown specifier at directive #2"),
+        }
+    }
 }
@@ -14,19 +14,52 @@
 
 //! Define the string type for Starlark.
 use crate::values::error::ValueError;
+use crate::values::frozen::Thaw;
 use crate::values::string::interpolation::ArgsFormat;
 use crate::values::*;
 use std;
 use std::cmp::Ordering;
 use std::collections::hash_map::DefaultHasher;
+use std::fmt;
 use std::hash::{Hash, Hasher};
+use std::rc::Rc;
+use std::sync::Arc;
 
 pub mod interpolation;
 use std::iter;
 
+thread_local!(
+    // The empty string and the one-character ASCII strings, which are frequently created
+    // e.g. by indexing or splitting strings: all the equal values share one allocation.
+    static INTERNED: Vec<Value> = iter::once(String::new())
+        .chain((0..128u8).map(|c| (c as char).to_string()))
+        .map(|s| Value(ValueInner::Other(Rc::new(ValueHolder::new(s)))))
+        .collect()
+);
+
+/// Index of `s` in `INTERNED`, if interned.
+fn interned_index(s: &str) -> Option<usize> {
+    match s.as_bytes() {
+        [] => Some(0),
+        // A one-byte UTF-8 string is an ASCII character.
+        [c] => Some(*c as usize + 1),
+        _ => None,
+    }
+}
+
 impl TypedValue for String {
     type Holder = Immutable<String>;
 
+    fn new_value(self) -> Value {
+        let interned =
+            interned_index(&self).and_then(|i| INTERNED.try_with(|values| values[i].clone()).ok());
+        match interned {
+            Some(v) => v,
+            // Not interned, or the thread-local values were already destroyed.
+            None => Value(ValueInner::Other(Rc::new(ValueHolder::new(self)))),
+        }
+    }
+
     fn values_for_descendant_check_and_freeze<'a>(
         &'a self,
     ) -> Box<dyn Iterator<Item = Value> + 'a> {
@@ -44,8 +77,52 @@ impl TypedValue for String {
                 .fold("".to_string(), |accum, s| accum + &s)
         )
     }
+    fn to_python_repr(&self) -> String {
+        let mut r = String::with_capacity(self.len() + 2);
+        r.push('"');
+        for c in self.chars() {
+            match c {
+                '"' => r.push_str("\\\""),
+                '\\' => r.push_str("\\\\"),
+                '\n' => r.push_str("\\n"),
+                '\r' => r.push_str("\\r"),
+                '\t' => r.push_str("\\t"),
+                c if c < ' ' || c == '\x7f' => r.push_str(&format!("\\x{:02x}", c as u32)),
+                c => r.push(c),
+            }
+        }
+        r.push('"');
+        r
+    }
+    fn to_json(&self) -> Result<String, ValueError> {
+        let mut r = String::with_capacity(self.len() + 2);
+        r.push('"');
+        for c in self.chars() {
+            match c {
+                '"' => r.push_str("\\\""),
+                '\\' => r.push_str("\\\\"),
+                '\n' => r.push_str("\\n"),
+                '\r' => r.push_str("\\r"),
+                '\t' => r.push_str("\\t"),
+                c if c < ' ' => r.push_str(&format!("\\u{:04x}", c as u32)),
+                c => r.push(c),
+            }
+        }
+        r.push('"');
+        Ok(r)
+    }
+    fn share(&self) -> Result<FrozenValue, ValueError> {
+        Ok(FrozenValue::new(self.clone()))
+    }
+    #[cfg(feature = "json")]
+    fn to_json_value(&self) -> Result<serde_json::Value, ValueError> {
+        Ok(serde_json::Value::String(self.clone()))
+    }
 
     const TYPE: &'static str = "string";
+    fn python_type_name(&self) -> &'static str {
+        "str"
+    }
     fn to_bool(&self) -> bool {
         !self.is_empty()
     }
@@ -61,11 +138,15 @@ impl TypedValue for String {
     }
 
     fn compare(&self, other: &String) -> Result<Ordering, ValueError> {
-        Ok(self.cmp(&other.to_str()))
+        // Byte-wise comparison of UTF-8 strings is the same as comparing
+        // them by code points.
+        Ok(self.as_str().cmp(other.as_str()))
     }
 
+    // Strings are indexed by code points, like `len`, so indexing or slicing
+    // never splits a multi-byte UTF-8 sequence.
     fn at(&self, index: Value) -> ValueResult {
-        let i = index.convert_index(self.len() as i64)? as usize;
+        let i = index.convert_index(self.chars().count() as i64)? as usize;
         Ok(Value::new(self.chars().nth(i).unwrap().to_string()))
     }
 
@@ -74,10 +155,9 @@ impl TypedValue for String {
     }
 
     fn is_in(&self, other: &Value) -> Result<bool, ValueError> {
-        if other.get_type() == "string" {
-            Ok(self.contains(&other.to_str()))
-        } else {
-            Err(ValueError::IncorrectParameterType)
+        match other.as_str() {
+            Some(other) => Ok(self.contains(other)),
+            None => Err(ValueError::IncorrectParameterType),
         }
     }
 
@@ -88,7 +168,7 @@ impl TypedValue for String {
         stride: Option<Value>,
     ) -> ValueResult {
         let (start, stop, stride) =
-            Value::convert_slice_indices(self.len() as i64, start, stop, stride)?;
+            Value::convert_slice_indices(self.chars().count() as i64, start, stop, stride)?;
         let (low, take, astride) = if stride < 0 {
             (stop + 1, start - stop, -stride)
         } else {
@@ -224,9 +304,91 @@ impl<'a> From<&'a str> for Value {
     }
 }
 
+impl Value {
+    /// Create a string `Value` from the [`Display`](std::fmt::Display) output of `val`.
+    ///
+    /// The output is written directly into the string stored by the value, this is thus a
+    /// shorthand for `Value::from(format!("{}", val))`, which also accepts the result of
+    /// `format_args!`:
+    ///
+    /// ```
+    /// # use starlark::values::Value;
+    /// assert_eq!("1 + 2", Value::from_display(format_args!("{} + {}", 1, 2)).to_str());
+    /// ```
+    pub fn from_display<T: std::fmt::Display>(val: T) -> Value {
+        let mut s = String::new();
+        fmt::write(&mut s, format_args!("{}", val)).unwrap();
+        Value::new(s)
+    }
+}
+
+impl Thaw for String {
+    fn thaw(self: Arc<Self>) -> Value {
+        Value::new_shared(self)
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::super::Value;
+    use super::super::{Value, ValueHolder, ValueInner};
+    use crate::testing::setup_test_env;
+    use crate::values::error::ValueError;
+    use std::cmp::Ordering;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_interned() {
+        for s in &["", "a", "\0", "\x7f"] {
+            let (a, b) = (Value::from(*s), Value::new(s.to_string()));
+            assert_eq!(a.data_ptr(), b.data_ptr(), "{:?}", s);
+            // Values equal to an interned string but allocated separately behave the same.
+            let other = Value(ValueInner::Other(Rc::new(ValueHolder::new(s.to_string()))));
+            assert_ne!(a.data_ptr(), other.data_ptr());
+            assert_eq!(a, other);
+            assert_eq!(a.get_hash().unwrap(), other.get_hash().unwrap());
+            assert_eq!(Ordering::Equal, a.compare(&other).unwrap());
+            assert_eq!(s.to_string(), other.to_str());
+        }
+        for s in &["ab", "\u{e9}", "\u{80}"] {
+            assert_ne!(Value::from(*s).data_ptr(), Value::from(*s).data_ptr());
+        }
+    }
+
+    #[test]
+    fn test_interned_immutable() {
+        let env = setup_test_env();
+        eval_in_env!(env, "e = ''\nd = {e: 1, 'a': 2}\ns = e\ns += 'x'");
+        assert_starlark_eq!(env, "e", "");
+        assert_starlark_eq!(env, "s", "x");
+        assert_starlark_eq!(env, "d['abc'[0:0]] + d['abc'[0]]", 3);
+        let child = env.freeze().child("child");
+        assert_starlark_eq!(child, "{'': 1}[''] + len(e)", 1);
+        assert_starlark_eq!(
+            child,
+            "[c for c in 'aba'.split_codepoints()]",
+            vec!["a", "b", "a"]
+        );
+    }
+
+    #[test]
+    fn test_from_display() {
+        struct Point(i64, i64);
+
+        impl std::fmt::Display for Point {
+            fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                write!(f, "({}, {})", self.0, self.1)
+            }
+        }
+
+        let v = Value::from_display(42);
+        assert_eq!("string", v.get_type());
+        assert_eq!("42", v.to_str());
+        assert_eq!(Value::from("(1, -2)"), Value::from_display(Point(1, -2)));
+        assert_eq!(
+            Value::from("x = (3, 4)"),
+            Value::from_display(format_args!("x = {}", Point(3, 4)))
+        );
+    }
 
     #[test]
     fn test_to_repr() {
@@ -234,11 +396,98 @@ mod tests {
         assert_eq!("\"Hello, 世界\"", Value::from("Hello, 世界").to_repr());
     }
 
+    #[test]
+    fn test_as_str() {
+        let mut v = Value::from("abc");
+        assert_eq!(Some("abc"), v.as_str());
+        // No copy is made.
+        let p = v.as_str().unwrap().as_ptr();
+        assert_eq!(p, v.clone().as_str().unwrap().as_ptr());
+        v.freeze();
+        assert_eq!(Some(p), v.as_str().map(str::as_ptr));
+        assert_eq!(None, Value::from(1).as_str());
+        assert_eq!(None, Value::from(vec!["abc"]).as_str());
+    }
+
+    #[test]
+    fn test_to_python_repr() {
+        assert_eq!(r#""\t\n'\"\\""#, Value::from("\t\n'\"\\").to_python_repr());
+        assert_eq!(
+            r#""\x00\x1b\x7f""#,
+            Value::from("\0\x1b\x7f").to_python_repr()
+        );
+        assert_eq!(
+            "\"Hello, 世界\"",
+            Value::from("Hello, 世界").to_python_repr()
+        );
+    }
+
     #[test]
     fn test_string_len() {
         assert_eq!(1, Value::from("😿").length().unwrap())
     }
 
+    #[test]
+    fn test_string_at() {
+        // "a😿b"[1] == "😿"
+        assert_eq!(
+            Value::from("a😿b").at(Value::from(1)).unwrap(),
+            Value::from("😿")
+        );
+        // "a😿b"[-1] == "b"
+        assert_eq!(
+            Value::from("a😿b").at(Value::from(-1)).unwrap(),
+            Value::from("b")
+        );
+        // "e" followed by U+0301 COMBINING ACUTE ACCENT is two code points
+        assert_eq!(2, Value::from("e\u{301}").length().unwrap());
+        assert_eq!(
+            Value::from("e\u{301}").at(Value::from(1)).unwrap(),
+            Value::from("\u{301}")
+        );
+        // Out of range indices are errors, even if they are valid byte offsets
+        assert_eq!(
+            Value::from("世界").at(Value::from(2)),
+            Err(ValueError::IndexOutOfBound(2))
+        );
+        assert_eq!(
+            Value::from("世界").at(Value::from(-3)),
+            Err(ValueError::IndexOutOfBound(-1))
+        );
+    }
+
+    #[test]
+    fn test_slice_multibyte_string() {
+        // "世界😿"[1:] == "界😿"
+        assert_eq!(
+            Value::from("世界😿")
+                .slice(Some(Value::from(1)), None, None)
+                .unwrap(),
+            Value::from("界😿")
+        );
+        // "世界😿"[-1:] == "😿"
+        assert_eq!(
+            Value::from("世界😿")
+                .slice(Some(Value::from(-1)), None, None)
+                .unwrap(),
+            Value::from("😿")
+        );
+        // "世界😿"[::-1] == "😿界世"
+        assert_eq!(
+            Value::from("世界😿")
+                .slice(None, None, Some(Value::from(-1)))
+                .unwrap(),
+            Value::from("😿界世")
+        );
+        // Out of range slice indices are clamped
+        assert_eq!(
+            Value::from("世界")
+                .slice(Some(Value::from(1)), Some(Value::from(10)), None)
+                .unwrap(),
+            Value::from("界")
+        );
+    }
+
     #[test]
     fn test_arithmetic_on_string() {
         // "abc" + "def" = "abcdef"
@@ -246,6 +495,15 @@ mod tests {
             Value::from("abc").add(Value::from("def")).unwrap(),
             Value::from("abcdef")
         );
+        // "" + "abc" == "abc" + "" == "abc"
+        assert_eq!(
+            Value::from("").add(Value::from("abc")).unwrap(),
+            Value::from("abc")
+        );
+        assert_eq!(
+            Value::from("abc").add(Value::from("")).unwrap(),
+            Value::from("abc")
+        );
         // "abc" * 3 == "abcabcabc"
         assert_eq!(
             Value::from("abc").mul(Value::from(3)).unwrap(),
@@ -293,6 +551,25 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_string_compare() {
+        use std::cmp::Ordering;
+
+        let cmp = |a: &str, b: &str| Value::from(a).compare(&Value::from(b)).unwrap();
+        assert_eq!(Ordering::Equal, cmp("abc", "abc"));
+        assert_eq!(Ordering::Less, cmp("abc", "abd"));
+        assert_eq!(Ordering::Less, cmp("", "a"));
+        // A prefix is smaller
+        assert_eq!(Ordering::Less, cmp("ab", "abc"));
+        assert_eq!(Ordering::Greater, cmp("abc", "ab"));
+        // Upper case letters have smaller code points
+        assert_eq!(Ordering::Less, cmp("Z", "a"));
+        // U+00E9 < U+4E16 < U+1F63F
+        assert_eq!(Ordering::Less, cmp("é", "世"));
+        assert_eq!(Ordering::Less, cmp("世", "😿"));
+        assert_eq!(Ordering::Greater, cmp("😿", "z"));
+    }
+
     #[test]
     fn test_string_is_in() {
         // "a" in "abc" == True
@@ -0,0 +1,199 @@
+// Copyright 2019 The Starlark in Rust Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Conversion of values from and to TOML, enabled by the `toml` feature.
+//!
+//! | Starlark               | TOML    |
+//! |------------------------|---------|
+//! | `bool`                 | boolean |
+//! | `int`                  | integer |
+//! | `string`               | string  |
+//! | `list`                 | array   |
+//! | `dict` of string keys  | table   |
+//!
+//! Other values cannot be converted to TOML. There is no Starlark type for TOML floats and
+//! date-times, they cannot be converted from TOML.
+
+use crate::eval::call_stack;
+use crate::values::dict::Dictionary;
+use crate::values::error::{RuntimeError, ValueError};
+use crate::values::Value;
+use toml::value::{Table, Value as Toml};
+
+/// Code of the errors of conversions from or to TOML.
+pub const TOML_ERROR_CODE: &str = "VC00";
+
+fn toml_error(e: impl ToString) -> ValueError {
+    RuntimeError {
+        code: TOML_ERROR_CODE,
+        message: e.to_string(),
+        label: "Invalid TOML".to_owned(),
+    }
+    .into()
+}
+
+fn to_toml_value(v: &Value) -> Result<Toml, ValueError> {
+    let _stack_depth_guard = call_stack::try_inc()?;
+    Ok(match v.get_type() {
+        "bool" => Toml::Boolean(v.to_bool()),
+        "int" => Toml::Integer(v.to_int()?),
+        "string" => Toml::String(v.to_str()),
+        "list" => Toml::Array(
+            v.iter()?
+                .iter()
+                .map(|x| to_toml_value(&x))
+                .collect::<Result<_, _>>()?,
+        ),
+        "dict" => {
+            let mut table = Table::new();
+            for k in &v.iter()? {
+                if k.get_type() != "string" {
+                    return Err(ValueError::TypeNotX {
+                        object_type: k.get_type().to_owned(),
+                        op: "a valid TOML key".to_owned(),
+                    });
+                }
+                table.insert(k.to_str(), to_toml_value(&v.at(k)?)?);
+            }
+            Toml::Table(table)
+        }
+        t => {
+            return Err(ValueError::TypeNotX {
+                object_type: t.to_owned(),
+                op: "TOML serializable".to_owned(),
+            })
+        }
+    })
+}
+
+fn from_toml_value(t: Toml) -> Result<Value, ValueError> {
+    Ok(match t {
+        Toml::Boolean(b) => Value::new(b),
+        Toml::Integer(i) => Value::new(i),
+        Toml::String(s) => Value::new(s),
+        Toml::Array(a) => Value::from(
+            a.into_iter()
+                .map(from_toml_value)
+                .collect::<Result<Vec<_>, _>>()?,
+        ),
+        Toml::Table(table) => {
+            let mut dict = Dictionary::new();
+            for (k, v) in table {
+                dict.set_at(Value::new(k), from_toml_value(v)?)?;
+            }
+            dict
+        }
+        Toml::Float(..) => return Err(ValueError::TypeNotSupported("float".to_owned())),
+        Toml::Datetime(..) => return Err(ValueError::TypeNotSupported("datetime".to_owned())),
+    })
+}
+
+impl Value {
+    /// Encode this value as TOML: a TOML document for a `dict`, a single TOML value
+    /// otherwise.
+    ///
+    /// ```
+    /// # use starlark::values::Value;
+    /// # use starlark::values::dict::Dictionary;
+    /// let mut dict = Dictionary::new();
+    /// dict.set_at(Value::from("name"), Value::from("x")).unwrap();
+    /// dict.set_at(Value::from("deps"), Value::from(vec!["y", "z"])).unwrap();
+    /// assert_eq!("name = \"x\"\ndeps = [\"y\", \"z\"]\n", dict.to_toml().unwrap());
+    /// assert_eq!(dict, Value::from_toml(&dict.to_toml().unwrap()).unwrap());
+    /// ```
+    pub fn to_toml(&self) -> Result<String, ValueError> {
+        match to_toml_value(self)? {
+            t @ Toml::Table(..) => toml::to_string(&t).map_err(toml_error),
+            t => Ok(t.to_string()),
+        }
+    }
+
+    /// Decode TOML into a value: either a document, decoded as a `dict`, or a single TOML
+    /// value as encoded by [`to_toml`](Value::to_toml).
+    pub fn from_toml(s: &str) -> Result<Value, ValueError> {
+        match s.parse::<Toml>() {
+            Ok(t) => from_toml_value(t),
+            Err(e) => match format!("value = {}", s).parse::<Toml>() {
+                Ok(Toml::Table(mut table)) if table.len() == 1 => {
+                    from_toml_value(table.remove("value").unwrap())
+                }
+                _ => Err(toml_error(e)),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::setup_test_env;
+
+    fn eval(content: &str) -> Value {
+        eval_in_env!(setup_test_env(), content)
+    }
+
+    #[test]
+    fn round_trip() {
+        for content in &[
+            "True",
+            "False",
+            "-12",
+            "9223372036854775807",
+            r#""quote \" backslash \\ newline \n 世界""#,
+            "[]",
+            "[1, 2, 3]",
+            "[[1, 2], ['a', 'b']]",
+            "{}",
+            "{'a': 1, 'b': 'c'}",
+            "{'a': [1, 2], 'b': {'c': True, 'd': {'e': []}}, 'f': 'g'}",
+            "{'servers': [{'name': 'a', 'port': 80}, {'name': 'b', 'port': 81}]}",
+        ] {
+            let value = eval(content);
+            let toml = value.to_toml().unwrap();
+            assert_eq!(value, Value::from_toml(&toml).unwrap(), "{}", toml);
+        }
+    }
+
+    #[test]
+    fn to_toml() {
+        assert_eq!("1", Value::new(1).to_toml().unwrap());
+        assert_eq!(
+            "a = 1\n\n[b]\nc = \"d\"\n",
+            eval("{'a': 1, 'b': {'c': 'd'}}").to_toml().unwrap()
+        );
+        for content in &["None", "(1,)", "{1: 2}", "{'a': None}", "[len]", "range(3)"] {
+            match eval(content).to_toml() {
+                Err(ValueError::TypeNotX { .. }) => {}
+                r => panic!("unexpected result for {}: {:?}", content, r),
+            }
+        }
+    }
+
+    #[test]
+    fn from_toml() {
+        assert_eq!(
+            eval("{'a': {'b': 1, 'c': [True]}}"),
+            Value::from_toml("[a]\nb = 1\nc = [true]\n").unwrap()
+        );
+        assert_eq!(Value::from("x"), Value::from_toml("'x'").unwrap());
+        match Value::from_toml("a = 1.5") {
+            Err(ValueError::TypeNotSupported(t)) => assert_eq!("float", t),
+            r => panic!("unexpected result {:?}", r),
+        }
+        match Value::from_toml("a = ") {
+            Err(ValueError::Runtime(e)) => assert_eq!(TOML_ERROR_CODE, e.code),
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+}
@@ -14,11 +14,13 @@
 
 //! Define the tuple type for Starlark.
 use crate::values::error::ValueError;
+use crate::values::frozen::Thaw;
 use crate::values::iter::TypedIterable;
 use crate::values::*;
 use std::cmp::Ordering;
 use std::collections::hash_map::DefaultHasher;
 use std::hash::Hasher;
+use std::sync::Arc;
 
 /// A starlark tuple
 #[derive(Debug, Clone)]
@@ -277,7 +279,44 @@ impl TypedValue for Tuple {
             if self.content.len() == 1 { "," } else { "" }
         )
     }
+    fn to_python_repr(&self) -> String {
+        let content: Vec<String> = self.content.iter().map(Value::to_python_repr).collect();
+        if content.len() == 1 {
+            format!("({},)", content[0])
+        } else {
+            format!("({})", content.join(", "))
+        }
+    }
+    fn to_json(&self) -> Result<String, ValueError> {
+        let content = self
+            .content
+            .iter()
+            .map(Value::to_json)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(format!("[{}]", content.join(",")))
+    }
+
+    fn share(&self) -> Result<FrozenValue, ValueError> {
+        let content = self
+            .content
+            .iter()
+            .map(Value::share)
+            .collect::<Result<_, _>>()?;
+        Ok(FrozenValue::new(FrozenTuple(content)))
+    }
+    #[cfg(feature = "json")]
+    fn to_json_value(&self) -> Result<serde_json::Value, ValueError> {
+        Ok(serde_json::Value::Array(
+            self.content
+                .iter()
+                .map(Value::to_json_value)
+                .collect::<Result<_, _>>()?,
+        ))
+    }
     const TYPE: &'static str = "tuple";
+    fn python_type_name(&self) -> &'static str {
+        "tuple"
+    }
     fn to_bool(&self) -> bool {
         !self.content.is_empty()
     }
@@ -481,6 +520,17 @@ from_tuple!(Tuple, T1, T2, T3, T4, T5, T6, T7, T8);
 from_tuple!(Tuple, T1, T2, T3, T4, T5, T6, T7, T8, T9);
 from_tuple!(Tuple, T1, T2, T3, T4, T5, T6, T7, T8, T9, T10);
 
+/// A shared tuple, see [`Value::share`].
+struct FrozenTuple(Vec<FrozenValue>);
+
+impl Thaw for FrozenTuple {
+    fn thaw(self: Arc<Self>) -> Value {
+        Value::new(Tuple::new(
+            self.0.iter().map(FrozenValue::to_value).collect(),
+        ))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -500,6 +550,32 @@ mod tests {
             Value::from((1, 2, 3)).add(Value::from((2, 3))).unwrap(),
             Value::from((1, 2, 3, 2, 3))
         );
+        // (1,) + (2,) == (1, 2)
+        assert_eq!(
+            Value::from((1,)).add(Value::from((2,))).unwrap(),
+            Value::from((1, 2))
+        );
+        // () + (1,) == (1,) + () == (1,)
+        assert_eq!(
+            Value::from(()).add(Value::from((1,))).unwrap(),
+            Value::from((1,))
+        );
+        assert_eq!(
+            Value::from((1,)).add(Value::from(())).unwrap(),
+            Value::from((1,))
+        );
+        // (1,) + [2] is an error
+        match Value::from((1,)).add(Value::from(vec![2])) {
+            Err(ValueError::OperationNotSupported { op, left, right }) => assert_eq!(
+                ("+", "tuple", Some("list")),
+                (
+                    op.as_str(),
+                    left.as_str(),
+                    right.as_ref().map(String::as_str)
+                )
+            ),
+            r => panic!("unexpected result: {:?}", r),
+        }
         // (1, 2, 3) * 3 == (1, 2, 3, 1, 2, 3, 1, 2, 3)
         assert_eq!(
             Value::from((1, 2, 3)).mul(Value::from(3)).unwrap(),
@@ -507,6 +583,45 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_compare() {
+        let cmp = |a: Value, b: Value| a.compare(&b).unwrap();
+        assert_eq!(Ordering::Equal, cmp(Value::from(()), Value::from(())));
+        assert_eq!(
+            Ordering::Equal,
+            cmp(Value::from((1, "a")), Value::from((1, "a")))
+        );
+        assert_eq!(
+            Ordering::Less,
+            cmp(Value::from((1, 2)), Value::from((1, 3)))
+        );
+        assert_eq!(
+            Ordering::Greater,
+            cmp(Value::from((2,)), Value::from((1, 3)))
+        );
+        // A prefix is smaller
+        assert_eq!(Ordering::Less, cmp(Value::from(()), Value::from((1,))));
+        assert_eq!(
+            Ordering::Less,
+            cmp(Value::from((1, 2)), Value::from((1, 2, 0)))
+        );
+        assert_eq!(
+            Ordering::Greater,
+            cmp(Value::from((1, 2, 0)), Value::from((1, 2)))
+        );
+        // Elements are compared with their own ordering
+        assert_eq!(
+            Ordering::Less,
+            cmp(Value::from(("a", 9)), Value::from(("b", 0)))
+        );
+        assert_eq!(
+            Ordering::Less,
+            cmp(Value::from(((1, "z"), 2)), Value::from(((1, "zz"), 0)))
+        );
+        // Elements of different types are not comparable
+        assert!(Value::from((1,)).compare(&Value::from(("a",))).is_err());
+    }
+
     #[test]
     fn test_is_descendant() {
         let v1 = Value::from((1, 2, 3));
@@ -0,0 +1,206 @@
+// Copyright 2019 The Starlark in Rust Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Conversion of values from and to YAML, enabled by the `yaml` feature.
+//!
+//! | Starlark          | YAML                |
+//! |-------------------|---------------------|
+//! | `None`            | null                |
+//! | `bool`            | boolean             |
+//! | `int`             | integer             |
+//! | `string`          | string              |
+//! | `list`, `tuple`   | sequence            |
+//! | `dict`            | mapping             |
+//!
+//! Other values cannot be converted to YAML. Explicitly typed nodes (`!!int`, `!!bool`,
+//! `!!null`...) are decoded with their tag type, and aliases are replaced by the node of their
+//! anchor. There is no Starlark type for YAML floats, they cannot be converted from YAML.
+
+use crate::eval::call_stack;
+use crate::values::dict::Dictionary;
+use crate::values::error::{RuntimeError, ValueError};
+use crate::values::none::NoneType;
+use crate::values::Value;
+use serde_yaml::{Mapping, Value as Yaml};
+
+/// Code of the errors of conversions from or to YAML.
+pub const YAML_ERROR_CODE: &str = "VC01";
+
+fn yaml_error(e: impl ToString) -> ValueError {
+    RuntimeError {
+        code: YAML_ERROR_CODE,
+        message: e.to_string(),
+        label: "Invalid YAML".to_owned(),
+    }
+    .into()
+}
+
+fn to_yaml_value(v: &Value) -> Result<Yaml, ValueError> {
+    let _stack_depth_guard = call_stack::try_inc()?;
+    Ok(match v.get_type() {
+        "NoneType" => Yaml::Null,
+        "bool" => Yaml::Bool(v.to_bool()),
+        "int" => Yaml::Number(v.to_int()?.into()),
+        "string" => Yaml::String(v.to_str()),
+        "list" | "tuple" => Yaml::Sequence(
+            v.iter()?
+                .iter()
+                .map(|x| to_yaml_value(&x))
+                .collect::<Result<_, _>>()?,
+        ),
+        "dict" => {
+            let mut mapping = Mapping::new();
+            for k in &v.iter()? {
+                mapping.insert(to_yaml_value(&k)?, to_yaml_value(&v.at(k)?)?);
+            }
+            Yaml::Mapping(mapping)
+        }
+        t => {
+            return Err(ValueError::TypeNotX {
+                object_type: t.to_owned(),
+                op: "YAML serializable".to_owned(),
+            })
+        }
+    })
+}
+
+fn from_yaml_value(y: Yaml) -> Result<Value, ValueError> {
+    let _stack_depth_guard = call_stack::try_inc()?;
+    Ok(match y {
+        Yaml::Null => Value::new(NoneType::None),
+        Yaml::Bool(b) => Value::new(b),
+        Yaml::Number(ref n) if n.is_f64() => {
+            return Err(ValueError::TypeNotSupported("float".to_owned()))
+        }
+        Yaml::Number(n) => match n.as_i64() {
+            Some(i) => Value::new(i),
+            None => return Err(ValueError::IntegerOverflow),
+        },
+        Yaml::String(s) => Value::new(s),
+        Yaml::Sequence(s) => Value::from(
+            s.into_iter()
+                .map(from_yaml_value)
+                .collect::<Result<Vec<_>, _>>()?,
+        ),
+        Yaml::Mapping(mapping) => {
+            let mut dict = Dictionary::new();
+            for (k, v) in mapping {
+                dict.set_at(from_yaml_value(k)?, from_yaml_value(v)?)?;
+            }
+            dict
+        }
+    })
+}
+
+impl Value {
+    /// Encode this value as a YAML document.
+    ///
+    /// ```
+    /// # use starlark::values::Value;
+    /// # use starlark::values::dict::Dictionary;
+    /// let mut dict = Dictionary::new();
+    /// dict.set_at(Value::from("name"), Value::from("x")).unwrap();
+    /// dict.set_at(Value::from("deps"), Value::from(vec!["y", "z"])).unwrap();
+    /// assert_eq!("---\nname: x\ndeps:\n  - y\n  - z\n", dict.to_yaml().unwrap());
+    /// assert_eq!(dict, Value::from_yaml(&dict.to_yaml().unwrap()).unwrap());
+    /// ```
+    pub fn to_yaml(&self) -> Result<String, ValueError> {
+        serde_yaml::to_string(&to_yaml_value(self)?).map_err(yaml_error)
+    }
+
+    /// Decode a YAML document into a value.
+    pub fn from_yaml(s: &str) -> Result<Value, ValueError> {
+        from_yaml_value(serde_yaml::from_str(s).map_err(yaml_error)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::setup_test_env;
+
+    fn eval(content: &str) -> Value {
+        eval_in_env!(setup_test_env(), content)
+    }
+
+    #[test]
+    fn round_trip() {
+        for content in &[
+            "None",
+            "True",
+            "False",
+            "-12",
+            "9223372036854775807",
+            r#""quote \" backslash \\ newline \n 世界""#,
+            "'true'",
+            "'1'",
+            "''",
+            "[]",
+            "[1, 2, 3]",
+            "[[1, 2], ['a', None]]",
+            "{}",
+            "{'a': 1, 2: 'b', True: None}",
+            "{'a': [1, 2], 'b': {'c': True, 'd': {'e': []}}, 'f': 'multi\\nline'}",
+            "{'servers': [{'name': 'a', 'port': 80}, {'name': 'b', 'port': 81}]}",
+        ] {
+            let value = eval(content);
+            let yaml = value.to_yaml().unwrap();
+            assert_eq!(value, Value::from_yaml(&yaml).unwrap(), "{}", yaml);
+        }
+    }
+
+    #[test]
+    fn to_yaml() {
+        assert_eq!("---\n1\n", Value::new(1).to_yaml().unwrap());
+        assert_eq!("---\n- 1\n- a\n", eval("(1, 'a')").to_yaml().unwrap());
+        for content in &["[len]", "{'a': range(3)}"] {
+            match eval(content).to_yaml() {
+                Err(ValueError::TypeNotX { .. }) => {}
+                r => panic!("unexpected result for {}: {:?}", content, r),
+            }
+        }
+    }
+
+    #[test]
+    fn from_yaml() {
+        assert_eq!(
+            eval("{'a': {'b': 1, 'c': [True, None]}, 'd': 'x\\ny\\n'}"),
+            Value::from_yaml("a:\n  b: 1\n  c: [true, ~]\nd: |\n  x\n  y\n").unwrap()
+        );
+        // Explicit tags
+        assert_eq!(
+            eval("[12, True, None, '12']"),
+            Value::from_yaml("[!!int '12', !!bool 'true', !!null ~, !!str 12]").unwrap()
+        );
+        // Aliases are expanded
+        assert_eq!(
+            eval("{'base': {'port': 80}, 'server': {'port': 80}}"),
+            Value::from_yaml("base: &base\n  port: 80\nserver: *base\n").unwrap()
+        );
+        for content in &["a: 1.5", "!!float 1"] {
+            match Value::from_yaml(content) {
+                Err(ValueError::TypeNotSupported(t)) => assert_eq!("float", t),
+                r => panic!("unexpected result for {}: {:?}", content, r),
+            }
+        }
+        match Value::from_yaml("[1, 2]: a") {
+            Err(ValueError::NotHashableValue) => {}
+            r => panic!("unexpected result {:?}", r),
+        }
+        match Value::from_yaml("a: [") {
+            Err(ValueError::Runtime(e)) => assert_eq!(YAML_ERROR_CODE, e.code),
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+}